@@ -78,6 +78,7 @@ interface IssueCounts {
   reasoning_leak: number;
   tool_args_invalid: number;
   tool_call_duplicate_id: number;
+  secret_scrubbed: number;
 }
 
 interface StageDiffResponse {
@@ -187,6 +188,34 @@ interface TurnDetail {
   role?: string;
 }
 
+// Hit returned by /debug/search
+interface SearchHit {
+  conversation_id: string;
+  turn_id: string;
+  started_at_ms: number;
+  model_id: string;
+  tool_names: string[];
+  issue_kinds: string[];
+  user_query?: string;
+  snippet?: string;
+}
+
+// Split "tool:apply_patch issue:ToolArgsRepaired foo.rs" into /debug/search params
+function parseSearchInput(input: string): Record<string, string> {
+  const params: Record<string, string> = {};
+  const text: string[] = [];
+  for (const token of input.trim().split(/\s+/)) {
+    const match = token.match(/^(tool|model|issue):(.+)$/);
+    if (match) {
+      params[match[1]] = match[2];
+    } else if (token) {
+      text.push(token);
+    }
+  }
+  if (text.length > 0) params.q = text.join(' ');
+  return params;
+}
+
 function TagsDisplay({ tags, deltas }: { tags?: ParsedTag[]; deltas?: TagDelta[] }) {
   const [expandedTags, setExpandedTags] = useState<Set<string>>(new Set());
   const [viewMode, setViewMode] = useState<'all' | 'changes-only'>('all');
@@ -545,6 +574,8 @@ function App() {
   const [selectedTurn, setSelectedTurn] = useState<string | null>(null);
   const [turnDetail, setTurnDetail] = useState<TurnDetail | null>(null);
   const [filterText, setFilterText] = useState<string>('');
+  const [searchText, setSearchText] = useState<string>('');
+  const [searchHits, setSearchHits] = useState<SearchHit[] | null>(null);
   const [selectedToolCall, setSelectedToolCall] = useState<ToolCallIndex | null>(null);
   // eslint-disable-next-line @typescript-eslint/no-unused-vars
   const [selectedStage, setSelectedStage] = useState<Stage | null>(null);
@@ -632,6 +663,33 @@ function App() {
     }
   };
 
+  const runSearch = async () => {
+    if (!searchText.trim()) {
+      setSearchHits(null);
+      return;
+    }
+    try {
+      const res = await axios.get(`${API_BASE}/search`, { params: parseSearchInput(searchText) });
+      setSearchHits(res.data);
+    } catch (err) {
+      console.error("Failed to search", err);
+      setSearchHits([]);
+    }
+  };
+
+  const openSearchHit = async (hit: SearchHit) => {
+    await selectConversation(hit.conversation_id);
+    setSelectedTurn(hit.turn_id);
+    setSelectedToolCall(null);
+    setSelectedStage(null);
+    try {
+      const res = await axios.get(`${API_BASE}/conversation/${hit.conversation_id}/turn/${hit.turn_id}`);
+      setTurnDetail(res.data);
+    } catch (err) {
+      console.error("Failed to fetch turn", err);
+    }
+  };
+
   const selectConversation = async (cid: string) => {
     setSelectedCid(cid);
     setSelectedTurn(null);
@@ -776,6 +834,46 @@ function App() {
             </div>
             <span className="text-xs bg-slate-800 px-2 py-1 rounded text-slate-400">v0.2.0</span>
           </div>
+          <div className="p-3 border-b border-slate-800">
+            <div className="relative">
+              <Search size={14} className="absolute left-2.5 top-2.5 text-slate-500" />
+              <input
+                type="text"
+                placeholder="Search turns (tool: model: issue:)"
+                value={searchText}
+                onChange={(e) => {
+                  setSearchText(e.target.value);
+                  if (!e.target.value.trim()) setSearchHits(null);
+                }}
+                onKeyDown={(e) => { if (e.key === 'Enter') void runSearch(); }}
+                className="w-full pl-8 pr-3 py-2 bg-slate-800 border border-slate-700 rounded-lg text-xs text-slate-200 placeholder-slate-500 focus:outline-none focus:border-emerald-500 focus:ring-1 focus:ring-emerald-500 transition-all"
+              />
+            </div>
+          </div>
+          {searchHits !== null ? (
+          <div className="flex-1 overflow-y-auto p-3 space-y-2">
+            <div className="text-[10px] uppercase tracking-wider text-slate-500 font-bold">{searchHits.length} matching turns</div>
+            {searchHits.map(hit => (
+              <button
+                key={`${hit.conversation_id}/${hit.turn_id}`}
+                onClick={() => void openSearchHit(hit)}
+                className={`w-full text-left p-3 rounded-lg transition-all border ${selectedTurn === hit.turn_id ? 'bg-emerald-900/20 border-emerald-500/50' : 'bg-slate-800/50 hover:bg-slate-800 border-slate-700/50 hover:border-slate-600'}`}
+              >
+                <div className="flex justify-between items-center mb-1">
+                  <span className="font-mono text-xs font-bold text-slate-200">{hit.conversation_id.slice(0, 8)}/{hit.turn_id.slice(0, 8)}</span>
+                  <span className="text-[10px] text-slate-500 font-mono">{new Date(hit.started_at_ms).toLocaleString()}</span>
+                </div>
+                <div className="text-[10px] text-slate-400 font-mono truncate" title={hit.model_id}>{hit.model_id}</div>
+                {hit.tool_names.length > 0 && (
+                  <div className="text-[10px] text-amber-400/80 font-mono truncate">{hit.tool_names.join(', ')}</div>
+                )}
+                {(hit.snippet || hit.user_query) && (
+                  <div className="text-[11px] text-slate-300 mt-1 line-clamp-2 break-all">{hit.snippet || hit.user_query}</div>
+                )}
+              </button>
+            ))}
+          </div>
+          ) : (
           <div className="flex-1 overflow-y-auto p-3 space-y-2">
             {conversations.map(conv => (
               <button
@@ -800,6 +898,7 @@ function App() {
               </button>
             ))}
           </div>
+          )}
         </Panel>
         
         <PanelResizeHandle className="w-1 bg-slate-800 hover:bg-emerald-500 transition-colors cursor-col-resize z-10" />
@@ -863,6 +962,7 @@ function App() {
                                 {turn.issues.tag_unregistered > 0 && <span className="w-1.5 h-1.5 rounded-full bg-orange-400" title="Unregistered Tag"></span>}
                                 {turn.issues.rescue_used > 0 && <span className="w-1.5 h-1.5 rounded-full bg-amber-500" title="Rescue Used"></span>}
                                 {turn.issues.tool_call_duplicate_id > 0 && <span className="w-1.5 h-1.5 rounded-full bg-pink-500" title="Duplicate Tool Call ID"></span>}
+                                {turn.issues.secret_scrubbed > 0 && <span className="w-1.5 h-1.5 rounded-full bg-sky-500" title="Secret Scrubbed"></span>}
                               </div>
                             )}
                           </div>
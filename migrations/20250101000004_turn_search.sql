@@ -0,0 +1,16 @@
+-- Full-text index over captured debug bundle turns (see src/search_index.rs).
+-- Identifier and timestamp columns are stored but not tokenized; `issue_kinds` and
+-- `tool_names` are space-separated lists used both for matching and exact filters.
+CREATE VIRTUAL TABLE IF NOT EXISTS turn_search USING fts5(
+    conversation_id UNINDEXED,
+    turn_id UNINDEXED,
+    started_at_ms UNINDEXED,
+    model,
+    issue_kinds,
+    tool_names,
+    user_query,
+    tool_args,
+    tool_results,
+    issues,
+    tokenize = "unicode61 tokenchars '_'"
+);
@@ -0,0 +1,43 @@
+-- Index for the content-addressed debug bundle store (`--bundle-store cas`, see
+-- src/bundle_store.rs). Object bytes live under `<capture dir>/cas/objects`.
+CREATE TABLE IF NOT EXISTS bundle_conversations (
+    conversation_id TEXT PRIMARY KEY NOT NULL,
+    summary_json TEXT NOT NULL,
+    last_updated_ms INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS bundle_turns (
+    conversation_id TEXT NOT NULL,
+    turn_id TEXT NOT NULL,
+    detail_json TEXT NOT NULL,
+    started_at_ms INTEGER NOT NULL,
+    PRIMARY KEY (conversation_id, turn_id)
+);
+
+-- One row per named blob; `sha256` is the root object (NULL while still being appended to).
+CREATE TABLE IF NOT EXISTS bundle_blobs (
+    conversation_id TEXT NOT NULL,
+    turn_id TEXT NOT NULL,
+    blob_id TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    sha256 TEXT,
+    chunked INTEGER NOT NULL DEFAULT 0,
+    size_bytes INTEGER NOT NULL,
+    PRIMARY KEY (conversation_id, turn_id, blob_id)
+);
+
+CREATE TABLE IF NOT EXISTS bundle_objects (
+    sha256 TEXT PRIMARY KEY NOT NULL,
+    size_bytes INTEGER NOT NULL
+);
+
+-- Every object a blob needs (its root plus split-out chunks); unreferenced objects are collected.
+CREATE TABLE IF NOT EXISTS bundle_blob_refs (
+    conversation_id TEXT NOT NULL,
+    turn_id TEXT NOT NULL,
+    blob_id TEXT NOT NULL,
+    sha256 TEXT NOT NULL,
+    PRIMARY KEY (conversation_id, turn_id, blob_id, sha256)
+);
+
+CREATE INDEX IF NOT EXISTS idx_bundle_blob_refs_sha256 ON bundle_blob_refs(sha256);
@@ -0,0 +1,576 @@
+//! Optional at-rest encryption for captured prompts and tool outputs.
+//!
+//! When a key is configured (`PARALLAX_ENCRYPTION_KEY` or `--encryption-key-file`), debug
+//! bundles (blobs, turn details, conversation summaries, flight recorder reports) and the
+//! sensitive SQLite columns (`conversation_states.state_json`, `tool_signatures.signature`
+//! and `thought_signature`) are sealed with AES-256-GCM. Reads accept both sealed and
+//! plaintext data, so enabling encryption needs no migration; `parallax rotate-key`
+//! re-seals everything under a new key (or back to plaintext).
+//!
+//! Storage code only sees a pool or a path, so the keyring is installed once per process
+//! with [`install`] and used through [`seal`]/[`open`] and their string variants.
+
+use crate::types::{ParallaxError, Result};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// Leading bytes of every sealed frame.
+const FRAME_MAGIC: &[u8; 4] = b"PXE1";
+const KEY_ID_LEN: usize = 4;
+const NONCE_LEN: usize = 12;
+const FRAME_HEADER_LEN: usize = FRAME_MAGIC.len() + KEY_ID_LEN + NONCE_LEN + 4;
+/// AES-GCM authentication tag at the end of every frame's ciphertext.
+const TAG_LEN: usize = 16;
+/// Prefix of sealed values stored in text columns.
+const TEXT_PREFIX: &str = "pxe1:";
+
+/// A key loaded from the environment or a key file.
+pub struct Key {
+    id: [u8; KEY_ID_LEN],
+    cipher: Aes256Gcm,
+}
+
+impl Key {
+    /// Derives the AES key from arbitrary key material (e.g. `openssl rand -base64 32`).
+    pub fn from_material(material: &str) -> Result<Self> {
+        let material = material.trim();
+        if material.len() < 16 {
+            return Err(ParallaxError::Config(
+                "encryption key material must be at least 16 characters".to_string(),
+            )
+            .into());
+        }
+        let key_bytes = Sha256::digest(material.as_bytes());
+        let fingerprint = Sha256::digest([b"parallax-key-id\0".as_slice(), &key_bytes].concat());
+        let mut id = [0u8; KEY_ID_LEN];
+        id.copy_from_slice(&fingerprint[..KEY_ID_LEN]);
+        let cipher = match Aes256Gcm::new_from_slice(&key_bytes) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(ParallaxError::Config(format!("invalid encryption key: {}", e)).into())
+            }
+        };
+        Ok(Self { id, cipher })
+    }
+
+    /// Short fingerprint identifying the key in sealed data and log output.
+    pub fn id(&self) -> String {
+        self.id.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+/// The active key, if any. Without one, [`Keyring::seal`] passes data through unchanged.
+#[derive(Default)]
+pub struct Keyring {
+    key: Option<Key>,
+}
+
+impl Keyring {
+    pub fn plaintext() -> Self {
+        Self { key: None }
+    }
+
+    pub fn with_key(key: Key) -> Self {
+        Self { key: Some(key) }
+    }
+
+    /// Key from `key_file` if given, else from `env_var`; plaintext when neither is set.
+    pub fn load(key_file: Option<&Path>, env_var: &str) -> Result<Self> {
+        let material = match key_file {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(m) => m,
+                Err(e) => {
+                    return Err(ParallaxError::Config(format!(
+                        "cannot read encryption key file {}: {}",
+                        path.display(),
+                        e
+                    ))
+                    .into())
+                }
+            },
+            None => match std::env::var(env_var) {
+                Ok(v) if !v.trim().is_empty() => v,
+                _ => return Ok(Self::plaintext()),
+            },
+        };
+        Ok(Self::with_key(Key::from_material(&material)?))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub fn key_id(&self) -> Option<String> {
+        self.key.as_ref().map(Key::id)
+    }
+
+    /// One sealed frame for `plaintext`. Frames can be concatenated (appended blobs).
+    pub fn seal(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
+        let key = match &self.key {
+            Some(k) => k,
+            None => return Ok(plaintext.to_vec()),
+        };
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = match key.cipher.encrypt(&nonce, plaintext) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(ParallaxError::Internal(
+                    format!("encryption failed: {}", e),
+                    tracing_error::SpanTrace::capture(),
+                )
+                .into())
+            }
+        };
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + ciphertext.len());
+        frame.extend_from_slice(FRAME_MAGIC);
+        frame.extend_from_slice(&key.id);
+        frame.extend_from_slice(&nonce);
+        frame.extend_from_slice(&(ciphertext.len() as u32).to_be_bytes());
+        frame.extend_from_slice(&ciphertext);
+        Ok(frame)
+    }
+
+    /// Plaintext of `data`: a sequence of sealed frames, or data that was never sealed.
+    pub fn open(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let frames = match frames(data) {
+            Some(f) => f,
+            None => return Ok(data.to_vec()),
+        };
+        let mut plaintext = Vec::with_capacity(data.len());
+        for frame in frames {
+            let key = match &self.key {
+                Some(k) if k.id == frame.key_id => k,
+                _ => {
+                    let sealed_with: String =
+                        frame.key_id.iter().map(|b| format!("{:02x}", b)).collect();
+                    return Err(ParallaxError::Config(format!(
+                        "data is encrypted with key {} but the configured key is {}",
+                        sealed_with,
+                        self.key_id().unwrap_or_else(|| "(none)".to_string())
+                    ))
+                    .into());
+                }
+            };
+            match key
+                .cipher
+                .decrypt(Nonce::from_slice(frame.nonce), frame.ciphertext)
+            {
+                Ok(p) => plaintext.extend_from_slice(&p),
+                Err(_) => return Err(corrupt("authentication failed")),
+            }
+        }
+        Ok(plaintext)
+    }
+
+    /// Sealed form of a text column value (`pxe1:<base64>`), or the value itself.
+    pub fn seal_str(&self, plaintext: &str) -> Result<String> {
+        if !self.is_enabled() {
+            return Ok(plaintext.to_string());
+        }
+        let frame = self.seal(plaintext.as_bytes())?;
+        Ok(format!(
+            "{}{}",
+            TEXT_PREFIX,
+            base64::engine::general_purpose::STANDARD.encode(frame)
+        ))
+    }
+
+    pub fn open_str(&self, value: &str) -> Result<String> {
+        let encoded = match value.strip_prefix(TEXT_PREFIX) {
+            Some(e) => e,
+            None => return Ok(value.to_string()),
+        };
+        let frame = match base64::engine::general_purpose::STANDARD.decode(encoded) {
+            Ok(f) if is_sealed(&f) => f,
+            // Text that merely starts with the prefix
+            _ => return Ok(value.to_string()),
+        };
+        match String::from_utf8(self.open(&frame)?) {
+            Ok(s) => Ok(s),
+            Err(_) => Err(corrupt("sealed text is not UTF-8")),
+        }
+    }
+}
+
+/// One sealed frame: magic, key id, nonce, ciphertext length, ciphertext (with its tag).
+struct Frame<'a> {
+    key_id: &'a [u8],
+    nonce: &'a [u8],
+    ciphertext: &'a [u8],
+}
+
+/// Splits `data` into sealed frames; `None` unless every byte belongs to a well-formed frame
+/// (magic, a length that covers at least the authentication tag and ends within the data),
+/// so plaintext that happens to start with the magic is not mistaken for sealed data.
+fn frames(data: &[u8]) -> Option<Vec<Frame<'_>>> {
+    if data.is_empty() {
+        return None;
+    }
+    let mut frames = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest.len() < FRAME_HEADER_LEN || !rest.starts_with(FRAME_MAGIC) {
+            return None;
+        }
+        let mut len_bytes = [0u8; 4];
+        len_bytes.copy_from_slice(&rest[FRAME_HEADER_LEN - 4..FRAME_HEADER_LEN]);
+        let len = u32::from_be_bytes(len_bytes) as usize;
+        let end = FRAME_HEADER_LEN + len;
+        if len < TAG_LEN || rest.len() < end {
+            return None;
+        }
+        frames.push(Frame {
+            key_id: &rest[FRAME_MAGIC.len()..FRAME_MAGIC.len() + KEY_ID_LEN],
+            nonce: &rest[FRAME_MAGIC.len() + KEY_ID_LEN..FRAME_HEADER_LEN - 4],
+            ciphertext: &rest[FRAME_HEADER_LEN..end],
+        });
+        rest = &rest[end..];
+    }
+    Some(frames)
+}
+
+fn is_sealed(data: &[u8]) -> bool {
+    frames(data).is_some()
+}
+
+fn corrupt(reason: &str) -> crate::types::ObservedError {
+    ParallaxError::Internal(
+        format!("encrypted data is corrupt: {}", reason),
+        tracing_error::SpanTrace::capture(),
+    )
+    .into()
+}
+
+static KEYRING: RwLock<Option<Arc<Keyring>>> = RwLock::new(None);
+
+/// Makes `keyring` the process-wide key used by storage code. Call once at startup.
+pub fn install(keyring: Keyring) {
+    if let Some(id) = keyring.key_id() {
+        tracing::info!("At-rest encryption enabled (key {})", id);
+    }
+    match KEYRING.write() {
+        Ok(mut slot) => *slot = Some(Arc::new(keyring)),
+        Err(poisoned) => *poisoned.into_inner() = Some(Arc::new(keyring)),
+    }
+}
+
+/// The installed keyring (plaintext until [`install`] is called).
+pub fn keyring() -> Arc<Keyring> {
+    let slot = match KEYRING.read() {
+        Ok(slot) => slot,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    match slot.as_ref() {
+        Some(k) => k.clone(),
+        None => Arc::new(Keyring::plaintext()),
+    }
+}
+
+pub fn is_enabled() -> bool {
+    keyring().is_enabled()
+}
+
+pub fn seal(plaintext: &[u8]) -> Result<Vec<u8>> {
+    keyring().seal(plaintext)
+}
+
+pub fn open(data: &[u8]) -> Result<Vec<u8>> {
+    keyring().open(data)
+}
+
+pub fn seal_str(plaintext: &str) -> Result<String> {
+    keyring().seal_str(plaintext)
+}
+
+pub fn open_str(value: &str) -> Result<String> {
+    keyring().open_str(value)
+}
+
+// --- Key rotation ---
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct RotateKeyArgs {
+    /// File holding the new key material.
+    #[arg(long)]
+    pub new_key_file: Option<std::path::PathBuf>,
+    /// Environment variable holding the new key material (used without `--new-key-file`).
+    #[arg(long, default_value = crate::constants::ENCRYPTION_KEY_NEW_ENV)]
+    pub new_key_env: String,
+    /// Rewrite everything as plaintext instead of sealing it under a new key.
+    #[arg(long, default_value_t = false)]
+    pub decrypt: bool,
+}
+
+/// What a rotation rewrote.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RotationReport {
+    pub db_values: u64,
+    pub files: u64,
+    /// `turn_search` rows dropped; the index is rebuilt under the new key on the next start.
+    pub search_rows: u64,
+}
+
+/// `(table, key column, sealed columns)` rewritten by a rotation.
+const SEALED_COLUMNS: [(&str, &str, &[&str]); 4] = [
+    ("conversation_states", "id", &["state_json"]),
+    ("tool_signatures", "id", &["signature", "thought_signature"]),
+    ("bundle_conversations", "conversation_id", &["summary_json"]),
+    ("bundle_turns", "rowid", &["detail_json"]),
+];
+
+/// Re-seals every encrypted (or plaintext) value under `new`: the sensitive database
+/// columns and every file under `capture_dir`. Values already sealed under `new` are
+/// skipped, so an interrupted rotation can simply be run again.
+///
+/// The search index holds free text only while encryption is off, so it is cleared and
+/// backfilled from the re-sealed bundles on the next start, under the new key's policy.
+pub async fn rotate(
+    db: &crate::db::DbPool,
+    capture_dir: &Path,
+    old: &Keyring,
+    new: &Keyring,
+) -> Result<RotationReport> {
+    let mut report = RotationReport {
+        search_rows: sqlx::query("DELETE FROM turn_search")
+            .execute(db)
+            .await?
+            .rows_affected(),
+        ..RotationReport::default()
+    };
+
+    for (table, key_column, columns) in SEALED_COLUMNS {
+        for column in columns {
+            let rows = sqlx::query(&format!(
+                "SELECT {key}, {col} FROM {table} WHERE {col} IS NOT NULL",
+                key = key_column,
+                col = column,
+                table = table
+            ))
+            .fetch_all(db)
+            .await?;
+            let mut tx = db.begin().await?;
+            for row in rows {
+                let value: String = row.get(1);
+                let plaintext = match old.open_str(&value) {
+                    Ok(p) => p,
+                    // Already rewritten by an interrupted earlier rotation.
+                    Err(_) if new.is_enabled() && new.open_str(&value).is_ok() => continue,
+                    Err(e) => return Err(e),
+                };
+                let resealed = new.seal_str(&plaintext)?;
+                let update = format!(
+                    "UPDATE {table} SET {col} = ? WHERE {key} = ?",
+                    key = key_column,
+                    col = column,
+                    table = table
+                );
+                let query = sqlx::query(&update).bind(resealed);
+                let query = if key_column == "rowid" {
+                    query.bind(row.get::<i64, _>(0))
+                } else {
+                    query.bind(row.get::<String, _>(0))
+                };
+                query.execute(&mut *tx).await?;
+                report.db_values += 1;
+            }
+            tx.commit().await?;
+        }
+    }
+
+    let mut stack = vec![capture_dir.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(rd) => rd,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                stack.push(entry.path());
+            } else if file_type.is_file() {
+                let path = entry.path();
+                let data = tokio::fs::read(&path).await?;
+                let plaintext = match old.open(&data) {
+                    Ok(p) => p,
+                    Err(_) if new.is_enabled() && new.open(&data).is_ok() => continue,
+                    Err(e) => return Err(e),
+                };
+                let tmp = path.with_extension(format!("rotate-{}", uuid::Uuid::new_v4()));
+                tokio::fs::write(&tmp, new.seal(&plaintext)?).await?;
+                tokio::fs::rename(&tmp, &path).await?;
+                report.files += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyring(material: &str) -> Keyring {
+        match Key::from_material(material) {
+            Ok(k) => Keyring::with_key(k),
+            Err(e) => panic!("key: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_frames_round_trip_and_concatenate() {
+        let ring = keyring("correct horse battery staple");
+        let mut data = match ring.seal(b"{\"line\": 1}\n") {
+            Ok(d) => d,
+            Err(e) => panic!("seal: {}", e),
+        };
+        assert!(!data.windows(4).any(|w| w == b"line"));
+        match ring.seal(b"{\"line\": 2}\n") {
+            Ok(more) => data.extend_from_slice(&more),
+            Err(e) => panic!("seal: {}", e),
+        }
+        match ring.open(&data) {
+            Ok(p) => assert_eq!(p, b"{\"line\": 1}\n{\"line\": 2}\n"),
+            Err(e) => panic!("open: {}", e),
+        }
+
+        // Plaintext written before encryption was enabled stays readable.
+        match ring.open(b"{\"legacy\": true}") {
+            Ok(p) => assert_eq!(p, b"{\"legacy\": true}"),
+            Err(e) => panic!("open plaintext: {}", e),
+        }
+        // ...including plaintext that merely starts with the frame magic or text prefix.
+        for legacy in [&b"PXE1 release notes"[..], &b"PXE1\0\0\0\0"[..]] {
+            match ring.open(legacy) {
+                Ok(p) => assert_eq!(p, legacy),
+                Err(e) => panic!("open plaintext: {}", e),
+            }
+        }
+        match ring.open_str("pxe1: a note about the format") {
+            Ok(p) => assert_eq!(p, "pxe1: a note about the format"),
+            Err(e) => panic!("open_str plaintext: {}", e),
+        }
+    }
+
+    #[test]
+    fn test_wrong_or_missing_key_and_tampering_are_rejected() {
+        let ring = keyring("correct horse battery staple");
+        let sealed = match ring.seal_str("secret prompt") {
+            Ok(s) => s,
+            Err(e) => panic!("seal_str: {}", e),
+        };
+        assert!(sealed.starts_with(TEXT_PREFIX));
+        match ring.open_str(&sealed) {
+            Ok(p) => assert_eq!(p, "secret prompt"),
+            Err(e) => panic!("open_str: {}", e),
+        }
+
+        assert!(keyring("another key entirely!").open_str(&sealed).is_err());
+        assert!(Keyring::plaintext().open_str(&sealed).is_err());
+
+        let mut frame = match ring.seal(b"payload") {
+            Ok(f) => f,
+            Err(e) => panic!("seal: {}", e),
+        };
+        if let Some(last) = frame.last_mut() {
+            *last ^= 1;
+        }
+        assert!(ring.open(&frame).is_err());
+        match Key::from_material("short") {
+            Err(e) => assert!(matches!(e.inner, ParallaxError::Config(_))),
+            Ok(_) => panic!("short key material was accepted"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rotate_reseals_database_and_files() {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let db = match crate::db::init_db(dir.path().join("p.db")).await {
+            Ok(db) => db,
+            Err(e) => panic!("init_db: {}", e),
+        };
+        let old = keyring("the old key material");
+        let new = keyring("the new key material");
+
+        let sealed = match old.seal_str("{\"history\": []}") {
+            Ok(s) => s,
+            Err(e) => panic!("seal_str: {}", e),
+        };
+        if let Err(e) =
+            sqlx::query("INSERT INTO conversation_states (id, state_json) VALUES (?, ?)")
+                .bind("c1")
+                .bind(&sealed)
+                .execute(&db)
+                .await
+        {
+            panic!("insert: {}", e);
+        }
+        if let Err(e) = sqlx::query(
+            "INSERT INTO turn_search (conversation_id, turn_id, user_query) VALUES ('c1', 't1', 'secret')",
+        )
+        .execute(&db)
+        .await
+        {
+            panic!("insert: {}", e);
+        }
+        let blob = dir
+            .path()
+            .join("capture/conversations/c1/turns/t1/blobs/final.json");
+        if let Some(parent) = blob.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                panic!("mkdir: {}", e);
+            }
+        }
+        match old.seal(b"{\"content\": \"hi\"}") {
+            Ok(bytes) => {
+                if let Err(e) = std::fs::write(&blob, bytes) {
+                    panic!("write: {}", e);
+                }
+            }
+            Err(e) => panic!("seal: {}", e),
+        }
+
+        let report = match rotate(&db, &dir.path().join("capture"), &old, &new).await {
+            Ok(r) => r,
+            Err(e) => panic!("rotate: {}", e),
+        };
+        assert_eq!(
+            report,
+            RotationReport {
+                db_values: 1,
+                files: 1,
+                search_rows: 1
+            }
+        );
+
+        let stored: String = match sqlx::query("SELECT state_json FROM conversation_states")
+            .fetch_one(&db)
+            .await
+        {
+            Ok(row) => row.get(0),
+            Err(e) => panic!("select: {}", e),
+        };
+        assert!(old.open_str(&stored).is_err());
+        match new.open_str(&stored) {
+            Ok(s) => assert_eq!(s, "{\"history\": []}"),
+            Err(e) => panic!("open_str: {}", e),
+        }
+        let bytes = match std::fs::read(&blob) {
+            Ok(b) => b,
+            Err(e) => panic!("read: {}", e),
+        };
+        match new.open(&bytes) {
+            Ok(p) => assert_eq!(p, b"{\"content\": \"hi\"}"),
+            Err(e) => panic!("open: {}", e),
+        }
+    }
+}
@@ -0,0 +1,1195 @@
+//! Storage backends for debug bundles.
+//!
+//! [`BundleManager`](crate::debug_bundle::BundleManager) decides *what* a bundle contains;
+//! a [`BundleStore`] decides where it lives. Two backends exist:
+//!
+//! * [`FileStore`] keeps the original loose-file layout
+//!   (`conversations/<cid>/{conversation.json, turns/<tid>/{turn.json, blobs/*}}`).
+//! * [`CasStore`] keeps summaries and the blob index in SQLite and blob bytes in a
+//!   sha256-keyed object store. Large JSON values (system prompts, tool schemas, long tool
+//!   results) are split into their own objects, so the copies every turn repeats are stored once.
+
+use crate::at_rest;
+use crate::db::DbPool;
+use crate::debug_bundle::{ConversationSummary, TurnDetail};
+use crate::types::{ParallaxError, Result};
+use futures_util::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use sha2::{Digest, Sha256};
+use sqlx::Row;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub type StoreFuture<'a, T> = BoxFuture<'a, Result<T>>;
+
+/// Blob file extensions, in lookup order. The extension determines the served content type.
+pub const BLOB_EXTENSIONS: [&str; 4] = ["json", "txt", "bin", "ndjson"];
+
+/// JSON values at least this large are stored as separate objects by [`CasStore`].
+const CHUNK_MIN_BYTES: usize = 2048;
+/// Marker object that stands in for an externalized value inside a chunked JSON blob.
+const CAS_REF_KEY: &str = "$parallax_cas";
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BundleStoreKind {
+    /// One file per summary, turn and blob under the capture dir.
+    #[default]
+    Files,
+    /// Content-addressed objects under `<capture dir>/cas` with an SQLite index.
+    Cas,
+}
+
+/// A blob as stored, addressed by its file name (`<blob_id>.<ext>`).
+#[derive(Debug, Clone)]
+pub struct StoredBlob {
+    pub file_name: String,
+    pub content: Vec<u8>,
+}
+
+impl StoredBlob {
+    pub fn content_type(&self) -> &'static str {
+        content_type_for(&self.file_name)
+    }
+}
+
+pub fn content_type_for(file_name: &str) -> &'static str {
+    match file_name.rsplit('.').next() {
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("ndjson") => "application/x-ndjson",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Longest conversation, turn or blob id accepted as a storage key.
+pub const MAX_ID_LEN: usize = 128;
+
+/// Rejects identifiers that could name anything but a single entry of the bundle layout:
+/// only ASCII letters, digits, `-`, `_` and `.` are allowed, and no leading dot.
+pub fn validate_id(id: &str) -> Result<()> {
+    let valid = !id.is_empty()
+        && id.len() <= MAX_ID_LEN
+        && !id.starts_with('.')
+        && id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(ParallaxError::InvalidIngress(format!(
+            "invalid bundle identifier {:?}",
+            crate::str_utils::prefix_chars(id, 64)
+        ))
+        .into())
+    }
+}
+
+fn blob_id_of(file_name: &str) -> &str {
+    match file_name.rsplit_once('.') {
+        Some((stem, _)) => stem,
+        None => file_name,
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+pub trait BundleStore: Send + Sync {
+    fn read_conversation<'a>(
+        &'a self,
+        cid: &'a str,
+    ) -> StoreFuture<'a, Option<ConversationSummary>>;
+    fn write_conversation<'a>(&'a self, summary: &'a ConversationSummary) -> StoreFuture<'a, ()>;
+    fn list_conversations(&self) -> StoreFuture<'_, Vec<ConversationSummary>>;
+    /// Removes a conversation with all its turns and blobs.
+    fn delete_conversation<'a>(&'a self, cid: &'a str) -> StoreFuture<'a, ()>;
+
+    fn read_turn<'a>(&'a self, cid: &'a str, tid: &'a str) -> StoreFuture<'a, Option<TurnDetail>>;
+    fn write_turn<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        detail: &'a TurnDetail,
+    ) -> StoreFuture<'a, ()>;
+    fn list_turns<'a>(&'a self, cid: &'a str) -> StoreFuture<'a, Vec<String>>;
+
+    /// Writes (or replaces) a whole blob.
+    fn put_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+        content: &'a [u8],
+    ) -> StoreFuture<'a, ()>;
+    /// Appends to a blob that is still being written; readable before it is sealed.
+    fn append_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+        content: &'a [u8],
+    ) -> StoreFuture<'a, ()>;
+    /// Marks an appended blob as complete.
+    fn seal_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+    ) -> StoreFuture<'a, ()>;
+    /// Looks a blob up by id, whatever its extension.
+    fn get_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        blob_id: &'a str,
+    ) -> StoreFuture<'a, Option<StoredBlob>>;
+    /// File names of all blobs of a turn.
+    fn list_blobs<'a>(&'a self, cid: &'a str, tid: &'a str) -> StoreFuture<'a, Vec<String>>;
+
+    /// Bytes used by all bundles, for the capture size limit.
+    fn total_size(&self) -> StoreFuture<'_, u64>;
+
+    /// Deletes stored data no blob references any more. Writes and deletes leave this to the
+    /// retention janitor, which calls it once per pass.
+    fn collect_garbage(&self) -> StoreFuture<'_, ()>;
+}
+
+/// Opens the configured backend rooted at `capture_dir`.
+pub fn open(kind: BundleStoreKind, capture_dir: &str, db: &DbPool) -> Arc<dyn BundleStore> {
+    match kind {
+        BundleStoreKind::Files => Arc::new(FileStore::new(capture_dir)),
+        BundleStoreKind::Cas => Arc::new(CasStore::new(
+            Path::new(capture_dir).join("cas"),
+            db.clone(),
+        )),
+    }
+}
+
+// --- Loose files ---
+
+pub struct FileStore {
+    base_path: PathBuf,
+}
+
+impl FileStore {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            base_path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// `path` if it still resolves inside the capture root once symlinks are followed.
+    /// Only the part of `path` that already exists can be resolved; the rest is made of
+    /// validated identifiers and cannot climb back out.
+    async fn confine(&self, path: PathBuf) -> Result<PathBuf> {
+        let root = match tokio::fs::canonicalize(&self.base_path).await {
+            Ok(r) => r,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(path),
+            Err(e) => return Err(e.into()),
+        };
+        let mut existing = path.as_path();
+        loop {
+            match tokio::fs::canonicalize(existing).await {
+                Ok(real) if real.starts_with(&root) => return Ok(path),
+                Ok(_) => break,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => match existing.parent() {
+                    Some(parent) => existing = parent,
+                    None => break,
+                },
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(ParallaxError::InvalidIngress(format!(
+            "bundle path {} escapes the capture dir",
+            path.display()
+        ))
+        .into())
+    }
+
+    async fn conversation_dir(&self, cid: &str) -> Result<PathBuf> {
+        validate_id(cid)?;
+        self.confine(self.base_path.join("conversations").join(cid))
+            .await
+    }
+
+    async fn turn_dir(&self, cid: &str, tid: &str) -> Result<PathBuf> {
+        validate_id(cid)?;
+        validate_id(tid)?;
+        self.confine(
+            self.base_path
+                .join("conversations")
+                .join(cid)
+                .join("turns")
+                .join(tid),
+        )
+        .await
+    }
+
+    async fn ensure_blobs_dir(&self, cid: &str, tid: &str) -> Result<PathBuf> {
+        let dir = self.turn_dir(cid, tid).await?.join("blobs");
+        tokio::fs::create_dir_all(&dir).await?;
+        Ok(dir)
+    }
+
+    async fn read_json<T: DeserializeOwned>(path: PathBuf) -> Result<Option<T>> {
+        match tokio::fs::read(&path).await {
+            Ok(content) => Ok(Some(serde_json::from_slice(&at_rest::open(&content)?)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn dir_names(dir: PathBuf) -> Result<Vec<String>> {
+        let mut names = Vec::new();
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(rd) => rd,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if entry.file_type().await?.is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        Ok(names)
+    }
+}
+
+impl BundleStore for FileStore {
+    fn read_conversation<'a>(
+        &'a self,
+        cid: &'a str,
+    ) -> StoreFuture<'a, Option<ConversationSummary>> {
+        Box::pin(async move {
+            Self::read_json(self.conversation_dir(cid).await?.join("conversation.json")).await
+        })
+    }
+
+    fn write_conversation<'a>(&'a self, summary: &'a ConversationSummary) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let dir = self.conversation_dir(&summary.conversation_id).await?;
+            tokio::fs::create_dir_all(&dir).await?;
+            let content = serde_json::to_string_pretty(summary)?;
+            tokio::fs::write(
+                dir.join("conversation.json"),
+                at_rest::seal(content.as_bytes())?,
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn list_conversations(&self) -> StoreFuture<'_, Vec<ConversationSummary>> {
+        Box::pin(async move {
+            let mut summaries = Vec::new();
+            for cid in Self::dir_names(self.base_path.join("conversations")).await? {
+                // Half-written or foreign directories are skipped rather than failing the list.
+                if let Ok(Some(summary)) = self.read_conversation(&cid).await {
+                    summaries.push(summary);
+                }
+            }
+            Ok(summaries)
+        })
+    }
+
+    fn delete_conversation<'a>(&'a self, cid: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            match tokio::fs::remove_dir_all(self.conversation_dir(cid).await?).await {
+                Ok(()) => Ok(()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn read_turn<'a>(&'a self, cid: &'a str, tid: &'a str) -> StoreFuture<'a, Option<TurnDetail>> {
+        Box::pin(
+            async move { Self::read_json(self.turn_dir(cid, tid).await?.join("turn.json")).await },
+        )
+    }
+
+    fn write_turn<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        detail: &'a TurnDetail,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let blobs = self.ensure_blobs_dir(cid, tid).await?;
+            let content = serde_json::to_string_pretty(detail)?;
+            tokio::fs::write(
+                blobs.with_file_name("turn.json"),
+                at_rest::seal(content.as_bytes())?,
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn list_turns<'a>(&'a self, cid: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(
+            async move { Self::dir_names(self.conversation_dir(cid).await?.join("turns")).await },
+        )
+    }
+
+    fn put_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+        content: &'a [u8],
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            validate_id(file_name)?;
+            let dir = self.ensure_blobs_dir(cid, tid).await?;
+            tokio::fs::write(dir.join(file_name), at_rest::seal(content)?).await?;
+            Ok(())
+        })
+    }
+
+    fn append_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+        content: &'a [u8],
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            use tokio::io::AsyncWriteExt;
+            validate_id(file_name)?;
+            let dir = self.ensure_blobs_dir(cid, tid).await?;
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(dir.join(file_name))
+                .await?;
+            // Each appended chunk is sealed as its own frame.
+            file.write_all(&at_rest::seal(content)?).await?;
+            file.flush().await?;
+            Ok(())
+        })
+    }
+
+    fn seal_blob<'a>(
+        &'a self,
+        _cid: &'a str,
+        _tid: &'a str,
+        _file_name: &'a str,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn get_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        blob_id: &'a str,
+    ) -> StoreFuture<'a, Option<StoredBlob>> {
+        Box::pin(async move {
+            validate_id(blob_id)?;
+            let dir = self.turn_dir(cid, tid).await?.join("blobs");
+            for ext in BLOB_EXTENSIONS {
+                let file_name = format!("{}.{}", blob_id, ext);
+                match tokio::fs::read(dir.join(&file_name)).await {
+                    Ok(content) => {
+                        let content = at_rest::open(&content)?;
+                        return Ok(Some(StoredBlob { file_name, content }));
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            Ok(None)
+        })
+    }
+
+    fn list_blobs<'a>(&'a self, cid: &'a str, tid: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let mut names = Vec::new();
+            let mut entries =
+                match tokio::fs::read_dir(self.turn_dir(cid, tid).await?.join("blobs")).await {
+                    Ok(rd) => rd,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+                    Err(e) => return Err(e.into()),
+                };
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_type().await?.is_file() {
+                    names.push(entry.file_name().to_string_lossy().into_owned());
+                }
+            }
+            names.sort();
+            Ok(names)
+        })
+    }
+
+    fn total_size(&self) -> StoreFuture<'_, u64> {
+        Box::pin(async move {
+            let conversations_dir = self.base_path.join("conversations");
+            if !conversations_dir.exists() {
+                return Ok(0);
+            }
+
+            let mut total_size = 0u64;
+            let mut stack = vec![conversations_dir];
+            while let Some(dir) = stack.pop() {
+                let mut entries = tokio::fs::read_dir(&dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    let metadata = entry.metadata().await?;
+                    if metadata.is_file() {
+                        total_size += metadata.len();
+                    } else if metadata.is_dir() {
+                        stack.push(entry.path());
+                    }
+                }
+            }
+            Ok(total_size)
+        })
+    }
+
+    fn collect_garbage(&self) -> StoreFuture<'_, ()> {
+        // Deleting a conversation removes its directory, so nothing is left unreferenced.
+        Box::pin(async { Ok(()) })
+    }
+}
+
+// --- Content-addressed objects + SQLite index ---
+
+pub struct CasStore {
+    root: PathBuf,
+    db: DbPool,
+    /// Serializes index mutations so garbage collection never races a concurrent write
+    /// that is about to reference an existing object.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+/// Bytes of each object a blob references, keyed by sha256.
+type Objects = Vec<(String, Vec<u8>)>;
+
+impl CasStore {
+    pub fn new<P: AsRef<Path>>(root: P, db: DbPool) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            db,
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        let (prefix, rest) = sha256.split_at(2.min(sha256.len()));
+        self.root.join("objects").join(prefix).join(rest)
+    }
+
+    fn staging_path(&self, cid: &str, tid: &str, file_name: &str) -> PathBuf {
+        let key = sha256_hex(format!("{}\0{}\0{}", cid, tid, file_name).as_bytes());
+        self.root.join("staging").join(key)
+    }
+
+    async fn write_object(&self, sha256: &str, bytes: &[u8]) -> Result<()> {
+        let path = self.object_path(sha256);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // Write-then-rename so a crash never leaves a truncated object under its final name.
+        let tmp = path.with_extension(format!("tmp-{}", uuid::Uuid::new_v4()));
+        tokio::fs::write(&tmp, at_rest::seal(bytes)?).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    async fn read_object(&self, sha256: &str) -> Result<Vec<u8>> {
+        match tokio::fs::read(self.object_path(sha256)).await {
+            Ok(bytes) => at_rest::open(&bytes),
+            Err(e) => Err(ParallaxError::Io(std::io::Error::new(
+                e.kind(),
+                format!("bundle object {} is missing: {}", sha256, e),
+            ))
+            .into()),
+        }
+    }
+
+    /// Splits a JSON blob into a root object plus one object per large value.
+    fn chunk_json(value: serde_json::Value) -> Option<(Vec<u8>, Objects)> {
+        let mut chunks = Vec::new();
+        let root = externalize(value, 0, &mut chunks);
+        if chunks.is_empty() {
+            return None;
+        }
+        let root_bytes = serde_json::to_vec(&root).ok()?;
+        Some((root_bytes, chunks))
+    }
+
+    async fn put_blob_locked(
+        &self,
+        cid: &str,
+        tid: &str,
+        file_name: &str,
+        content: &[u8],
+    ) -> Result<()> {
+        let blob_id = blob_id_of(file_name);
+        let chunked = if file_name.ends_with(".json") {
+            serde_json::from_slice::<serde_json::Value>(content)
+                .ok()
+                .and_then(Self::chunk_json)
+        } else {
+            None
+        };
+        let (root_bytes, mut objects) = match chunked {
+            Some((root, chunks)) => (root, chunks),
+            None => (content.to_vec(), Vec::new()),
+        };
+        let is_chunked = !objects.is_empty();
+        let root_sha = sha256_hex(&root_bytes);
+        objects.push((root_sha.clone(), root_bytes));
+
+        for (sha, bytes) in &objects {
+            self.write_object(sha, bytes).await?;
+        }
+
+        let mut tx = self.db.begin().await?;
+        sqlx::query(
+            "DELETE FROM bundle_blob_refs WHERE conversation_id = ? AND turn_id = ? AND blob_id = ?",
+        )
+        .bind(cid)
+        .bind(tid)
+        .bind(blob_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO bundle_blobs \
+             (conversation_id, turn_id, blob_id, file_name, sha256, chunked, size_bytes) \
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(cid)
+        .bind(tid)
+        .bind(blob_id)
+        .bind(file_name)
+        .bind(&root_sha)
+        .bind(is_chunked)
+        .bind(content.len() as i64)
+        .execute(&mut *tx)
+        .await?;
+        for (sha, bytes) in &objects {
+            sqlx::query("INSERT OR IGNORE INTO bundle_objects (sha256, size_bytes) VALUES (?, ?)")
+                .bind(sha)
+                .bind(bytes.len() as i64)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query(
+                "INSERT OR IGNORE INTO bundle_blob_refs (conversation_id, turn_id, blob_id, sha256) \
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(cid)
+            .bind(tid)
+            .bind(blob_id)
+            .bind(sha)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Deletes objects no blob references any more. Callers hold `write_lock`.
+    async fn collect_garbage_locked(&self) -> Result<()> {
+        let orphans: Vec<String> = sqlx::query(
+            "SELECT sha256 FROM bundle_objects \
+             WHERE sha256 NOT IN (SELECT sha256 FROM bundle_blob_refs)",
+        )
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+
+        for sha in orphans {
+            match tokio::fs::remove_file(self.object_path(&sha)).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => {
+                    tracing::warn!("Failed to remove bundle object {}: {}", sha, e);
+                    continue;
+                }
+            }
+            sqlx::query("DELETE FROM bundle_objects WHERE sha256 = ?")
+                .bind(&sha)
+                .execute(&self.db)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn read_blob_row(
+        &self,
+        cid: &str,
+        tid: &str,
+        blob_id: &str,
+    ) -> Result<Option<StoredBlob>> {
+        let row = sqlx::query(
+            "SELECT file_name, sha256, chunked FROM bundle_blobs \
+             WHERE conversation_id = ? AND turn_id = ? AND blob_id = ?",
+        )
+        .bind(cid)
+        .bind(tid)
+        .bind(blob_id)
+        .fetch_optional(&self.db)
+        .await?;
+        let row = match row {
+            Some(r) => r,
+            None => return Ok(None),
+        };
+        let file_name: String = row.get("file_name");
+        let root_sha: Option<String> = row.get("sha256");
+        let chunked: bool = row.get("chunked");
+
+        let root_sha = match root_sha {
+            Some(sha) => sha,
+            // Still being appended to.
+            None => {
+                let content = match tokio::fs::read(self.staging_path(cid, tid, &file_name)).await {
+                    Ok(c) => at_rest::open(&c)?,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                    Err(e) => return Err(e.into()),
+                };
+                return Ok(Some(StoredBlob { file_name, content }));
+            }
+        };
+
+        let root = self.read_object(&root_sha).await?;
+        if !chunked {
+            return Ok(Some(StoredBlob {
+                file_name,
+                content: root,
+            }));
+        }
+
+        let refs: Vec<String> = sqlx::query(
+            "SELECT sha256 FROM bundle_blob_refs \
+             WHERE conversation_id = ? AND turn_id = ? AND blob_id = ? AND sha256 != ?",
+        )
+        .bind(cid)
+        .bind(tid)
+        .bind(blob_id)
+        .bind(&root_sha)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|row| row.get(0))
+        .collect();
+        let mut chunks = HashMap::with_capacity(refs.len());
+        for sha in refs {
+            let bytes = self.read_object(&sha).await?;
+            chunks.insert(sha, serde_json::from_slice::<serde_json::Value>(&bytes)?);
+        }
+        let value = rehydrate(serde_json::from_slice(&root)?, &chunks);
+        Ok(Some(StoredBlob {
+            file_name,
+            content: serde_json::to_vec(&value)?,
+        }))
+    }
+}
+
+/// Replaces large values with `{"$parallax_cas": "<sha256>"}` markers, innermost first.
+///
+/// Long strings are split out at any depth (system prompts, tool results replayed in history);
+/// large containers only directly under the root (`tools`, `messages`), which keeps the
+/// number of objects per blob small.
+fn externalize(value: serde_json::Value, depth: usize, chunks: &mut Objects) -> serde_json::Value {
+    use serde_json::Value;
+
+    let value = match value {
+        Value::Array(items) => Value::Array(
+            items
+                .into_iter()
+                .map(|v| externalize(v, depth + 1, chunks))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, externalize(v, depth + 1, chunks)))
+                .collect(),
+        ),
+        other => other,
+    };
+
+    let eligible = match &value {
+        Value::String(s) => s.len() >= CHUNK_MIN_BYTES,
+        Value::Array(_) | Value::Object(_) => depth == 1,
+        _ => false,
+    };
+    if !eligible {
+        return value;
+    }
+    let bytes = match serde_json::to_vec(&value) {
+        Ok(b) if b.len() >= CHUNK_MIN_BYTES => b,
+        _ => return value,
+    };
+    let sha = sha256_hex(&bytes);
+    chunks.push((sha.clone(), bytes));
+    serde_json::json!({ CAS_REF_KEY: sha })
+}
+
+fn rehydrate(
+    value: serde_json::Value,
+    chunks: &HashMap<String, serde_json::Value>,
+) -> serde_json::Value {
+    use serde_json::Value;
+
+    match value {
+        Value::Object(map) => {
+            if map.len() == 1 {
+                if let Some(Value::String(sha)) = map.get(CAS_REF_KEY) {
+                    if let Some(chunk) = chunks.get(sha) {
+                        return rehydrate(chunk.clone(), chunks);
+                    }
+                }
+            }
+            Value::Object(
+                map.into_iter()
+                    .map(|(k, v)| (k, rehydrate(v, chunks)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => {
+            Value::Array(items.into_iter().map(|v| rehydrate(v, chunks)).collect())
+        }
+        other => other,
+    }
+}
+
+impl BundleStore for CasStore {
+    fn read_conversation<'a>(
+        &'a self,
+        cid: &'a str,
+    ) -> StoreFuture<'a, Option<ConversationSummary>> {
+        Box::pin(async move {
+            let row = sqlx::query(
+                "SELECT summary_json FROM bundle_conversations WHERE conversation_id = ?",
+            )
+            .bind(cid)
+            .fetch_optional(&self.db)
+            .await?;
+            match row {
+                Some(r) => Ok(Some(serde_json::from_str(&at_rest::open_str(
+                    r.get::<&str, _>(0),
+                )?)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn write_conversation<'a>(&'a self, summary: &'a ConversationSummary) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            sqlx::query(
+                "INSERT OR REPLACE INTO bundle_conversations \
+                 (conversation_id, summary_json, last_updated_ms) VALUES (?, ?, ?)",
+            )
+            .bind(&summary.conversation_id)
+            .bind(at_rest::seal_str(&serde_json::to_string(summary)?)?)
+            .bind(summary.last_updated_ms as i64)
+            .execute(&self.db)
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn list_conversations(&self) -> StoreFuture<'_, Vec<ConversationSummary>> {
+        Box::pin(async move {
+            let rows = sqlx::query("SELECT summary_json FROM bundle_conversations")
+                .fetch_all(&self.db)
+                .await?;
+            let mut summaries = Vec::with_capacity(rows.len());
+            for row in rows {
+                summaries.push(serde_json::from_str(&at_rest::open_str(
+                    row.get::<&str, _>(0),
+                )?)?);
+            }
+            Ok(summaries)
+        })
+    }
+
+    fn delete_conversation<'a>(&'a self, cid: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let _guard = self.write_lock.lock().await;
+            let staged: Vec<(String, String)> = sqlx::query(
+                "SELECT turn_id, file_name FROM bundle_blobs \
+                 WHERE conversation_id = ? AND sha256 IS NULL",
+            )
+            .bind(cid)
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|row| (row.get(0), row.get(1)))
+            .collect();
+
+            let mut tx = self.db.begin().await?;
+            for table in [
+                "bundle_blob_refs",
+                "bundle_blobs",
+                "bundle_turns",
+                "bundle_conversations",
+            ] {
+                sqlx::query(&format!("DELETE FROM {} WHERE conversation_id = ?", table))
+                    .bind(cid)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+
+            for (tid, file_name) in staged {
+                let _ = tokio::fs::remove_file(self.staging_path(cid, &tid, &file_name)).await;
+            }
+            Ok(())
+        })
+    }
+
+    fn read_turn<'a>(&'a self, cid: &'a str, tid: &'a str) -> StoreFuture<'a, Option<TurnDetail>> {
+        Box::pin(async move {
+            let row = sqlx::query(
+                "SELECT detail_json FROM bundle_turns WHERE conversation_id = ? AND turn_id = ?",
+            )
+            .bind(cid)
+            .bind(tid)
+            .fetch_optional(&self.db)
+            .await?;
+            match row {
+                Some(r) => Ok(Some(serde_json::from_str(&at_rest::open_str(
+                    r.get::<&str, _>(0),
+                )?)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn write_turn<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        detail: &'a TurnDetail,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            sqlx::query(
+                "INSERT OR REPLACE INTO bundle_turns \
+                 (conversation_id, turn_id, detail_json, started_at_ms) VALUES (?, ?, ?, ?)",
+            )
+            .bind(cid)
+            .bind(tid)
+            .bind(at_rest::seal_str(&serde_json::to_string(detail)?)?)
+            .bind(detail.started_at_ms as i64)
+            .execute(&self.db)
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn list_turns<'a>(&'a self, cid: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let rows = sqlx::query(
+                "SELECT turn_id FROM bundle_turns WHERE conversation_id = ? ORDER BY started_at_ms",
+            )
+            .bind(cid)
+            .fetch_all(&self.db)
+            .await?;
+            Ok(rows.into_iter().map(|row| row.get(0)).collect())
+        })
+    }
+
+    fn put_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+        content: &'a [u8],
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let _guard = self.write_lock.lock().await;
+            self.put_blob_locked(cid, tid, file_name, content).await
+        })
+    }
+
+    fn append_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+        content: &'a [u8],
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            use tokio::io::AsyncWriteExt;
+            sqlx::query(
+                "INSERT OR IGNORE INTO bundle_blobs \
+                 (conversation_id, turn_id, blob_id, file_name, sha256, chunked, size_bytes) \
+                 VALUES (?, ?, ?, ?, NULL, 0, 0)",
+            )
+            .bind(cid)
+            .bind(tid)
+            .bind(blob_id_of(file_name))
+            .bind(file_name)
+            .execute(&self.db)
+            .await?;
+
+            let path = self.staging_path(cid, tid, file_name);
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .await?;
+            // Each appended chunk is sealed as its own frame.
+            file.write_all(&at_rest::seal(content)?).await?;
+            file.flush().await?;
+            Ok(())
+        })
+    }
+
+    fn seal_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        file_name: &'a str,
+    ) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            let path = self.staging_path(cid, tid, file_name);
+            let content = match tokio::fs::read(&path).await {
+                Ok(c) => at_rest::open(&c)?,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            let _guard = self.write_lock.lock().await;
+            self.put_blob_locked(cid, tid, file_name, &content).await?;
+            tokio::fs::remove_file(&path).await?;
+            Ok(())
+        })
+    }
+
+    fn get_blob<'a>(
+        &'a self,
+        cid: &'a str,
+        tid: &'a str,
+        blob_id: &'a str,
+    ) -> StoreFuture<'a, Option<StoredBlob>> {
+        Box::pin(self.read_blob_row(cid, tid, blob_id))
+    }
+
+    fn list_blobs<'a>(&'a self, cid: &'a str, tid: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let rows = sqlx::query(
+                "SELECT file_name FROM bundle_blobs \
+                 WHERE conversation_id = ? AND turn_id = ? ORDER BY file_name",
+            )
+            .bind(cid)
+            .bind(tid)
+            .fetch_all(&self.db)
+            .await?;
+            Ok(rows.into_iter().map(|row| row.get(0)).collect())
+        })
+    }
+
+    fn total_size(&self) -> StoreFuture<'_, u64> {
+        Box::pin(async move {
+            let row = sqlx::query(
+                "SELECT \
+                 (SELECT COALESCE(SUM(size_bytes), 0) FROM bundle_objects \
+                  WHERE sha256 IN (SELECT sha256 FROM bundle_blob_refs)) + \
+                 (SELECT COALESCE(SUM(length(detail_json)), 0) FROM bundle_turns) + \
+                 (SELECT COALESCE(SUM(length(summary_json)), 0) FROM bundle_conversations)",
+            )
+            .fetch_one(&self.db)
+            .await?;
+            Ok(row.get::<i64, _>(0).max(0) as u64)
+        })
+    }
+
+    fn collect_garbage(&self) -> StoreFuture<'_, ()> {
+        Box::pin(async move {
+            let _guard = self.write_lock.lock().await;
+            self.collect_garbage_locked().await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    async fn cas_store() -> (tempfile::TempDir, CasStore) {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let db = match crate::db::init_db(dir.path().join("bundles.db")).await {
+            Ok(db) => db,
+            Err(e) => panic!("init_db: {}", e),
+        };
+        let store = CasStore::new(dir.path().join("cas"), db);
+        (dir, store)
+    }
+
+    async fn object_count(store: &CasStore) -> i64 {
+        match sqlx::query("SELECT count(*) FROM bundle_objects")
+            .fetch_one(&store.db)
+            .await
+        {
+            Ok(row) => row.get(0),
+            Err(e) => panic!("count: {}", e),
+        }
+    }
+
+    fn ingress(user_message: &str) -> Vec<u8> {
+        json!({
+            "model": "m/x",
+            "messages": [
+                {"role": "system", "content": "You are a careful coding agent. ".repeat(200)},
+                {"role": "user", "content": user_message}
+            ],
+            "tools": (0..30)
+                .map(|i| json!({"type": "function", "function": {
+                    "name": format!("tool_{}", i),
+                    "description": "Small tool",
+                    "parameters": {"type": "object"}
+                }}))
+                .collect::<Vec<_>>()
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[tokio::test]
+    async fn test_cas_dedups_repeated_prompt_and_tools() {
+        let (_dir, store) = cas_store().await;
+
+        let first = ingress("fix the parser");
+        let second = ingress("now add a test");
+        for (tid, body) in [("t1", &first), ("t2", &second)] {
+            if let Err(e) = store.put_blob("cid", tid, "ingress_raw.json", body).await {
+                panic!("put_blob: {}", e);
+            }
+        }
+        // Each turn has its own root; the system prompt and the tools array are shared.
+        assert_eq!(object_count(&store).await, 4);
+
+        for (tid, body) in [("t1", &first), ("t2", &second)] {
+            let blob = match store.get_blob("cid", tid, "ingress_raw").await {
+                Ok(Some(b)) => b,
+                other => panic!("get_blob {}: {:?}", tid, other.map(|b| b.is_some())),
+            };
+            assert_eq!(blob.file_name, "ingress_raw.json");
+            let stored: serde_json::Value = match serde_json::from_slice(&blob.content) {
+                Ok(v) => v,
+                Err(e) => panic!("stored blob is not JSON: {}", e),
+            };
+            let original: serde_json::Value = match serde_json::from_slice(body) {
+                Ok(v) => v,
+                Err(e) => panic!("original: {}", e),
+            };
+            assert_eq!(stored, original);
+        }
+
+        if let Err(e) = store.delete_conversation("cid").await {
+            panic!("delete_conversation: {}", e);
+        }
+        // Unreferenced objects no longer count, and stay on disk until collected.
+        assert!(matches!(store.total_size().await, Ok(0)));
+        assert_eq!(object_count(&store).await, 4);
+        if let Err(e) = store.collect_garbage().await {
+            panic!("collect_garbage: {}", e);
+        }
+        assert_eq!(object_count(&store).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cas_append_is_readable_before_and_after_seal() {
+        let (_dir, store) = cas_store().await;
+        for line in [
+            "{\"t_ms\":1,\"line\":\"a\"}\n",
+            "{\"t_ms\":2,\"line\":\"b\"}\n",
+        ] {
+            if let Err(e) = store
+                .append_blob("cid", "tid", "upstream_sse.ndjson", line.as_bytes())
+                .await
+            {
+                panic!("append_blob: {}", e);
+            }
+        }
+        let partial = match store.get_blob("cid", "tid", "upstream_sse").await {
+            Ok(Some(b)) => b.content,
+            _ => panic!("staged blob not readable"),
+        };
+        assert_eq!(partial.iter().filter(|b| **b == b'\n').count(), 2);
+
+        if let Err(e) = store.seal_blob("cid", "tid", "upstream_sse.ndjson").await {
+            panic!("seal_blob: {}", e);
+        }
+        match store.get_blob("cid", "tid", "upstream_sse").await {
+            Ok(Some(b)) => {
+                assert_eq!(b.content, partial);
+                assert_eq!(b.content_type(), "application/x-ndjson");
+            }
+            _ => panic!("sealed blob not readable"),
+        }
+        assert_eq!(object_count(&store).await, 1);
+    }
+
+    #[test]
+    fn test_validate_id_rejects_path_like_identifiers() {
+        for ok in ["e2e-text", "9f1c2d", "ingress_raw", "upstream_sse.ndjson"] {
+            assert!(validate_id(ok).is_ok(), "{}", ok);
+        }
+        for bad in [
+            "",
+            ".",
+            "..",
+            "../secret",
+            "a/b",
+            "a\\b",
+            "%2e%2e",
+            ".hidden",
+            "nul\0byte",
+            "caf\u{e9}",
+            &"x".repeat(MAX_ID_LEN + 1),
+        ] {
+            assert!(validate_id(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_file_store_refuses_symlinks_out_of_capture_dir() {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let capture = dir.path().join("capture");
+        let outside = dir.path().join("outside");
+        for d in [capture.join("conversations"), outside.join("turns/t/blobs")] {
+            if let Err(e) = std::fs::create_dir_all(&d) {
+                panic!("mkdir: {}", e);
+            }
+        }
+        if let Err(e) = std::fs::write(outside.join("turns/t/blobs/secret.json"), b"{}") {
+            panic!("write: {}", e);
+        }
+        if let Err(e) = std::os::unix::fs::symlink(&outside, capture.join("conversations/link")) {
+            panic!("symlink: {}", e);
+        }
+
+        let store = FileStore::new(&capture);
+        assert!(store.get_blob("link", "t", "secret").await.is_err());
+        assert!(store.put_blob("link", "t", "x.json", b"{}").await.is_err());
+        assert!(!outside.join("turns/t/blobs/x.json").exists());
+
+        match store.put_blob("real", "t", "x.json", b"{}").await {
+            Ok(()) => {}
+            Err(e) => panic!("put_blob inside the capture dir: {}", e),
+        }
+    }
+}
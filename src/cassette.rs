@@ -0,0 +1,331 @@
+//! Record/replay cassettes built from debug bundles.
+//!
+//! A cassette is a self-contained fixture for one turn: the client request (`ingress_raw`),
+//! the exact lines the provider streamed back (`upstream_sse`), and what Parallax produced
+//! from them (`projected` and `final`). `tests/cassette_replay_test.rs` replays every
+//! cassette under `tests/cassettes/` against a mock upstream and diffs the results.
+
+use crate::debug_bundle::{BundleManager, SseLine};
+use crate::types::{ParallaxError, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+pub const CASSETTE_VERSION: u32 = 1;
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CassetteArgs {
+    /// Conversation id of the captured turn.
+    #[arg(long)]
+    pub conversation: String,
+    /// Turn id; defaults to the most recently captured turn of the conversation.
+    #[arg(long)]
+    pub turn: Option<String>,
+    /// Fixture name (file stem); defaults to `<cid prefix>-<tid prefix>`.
+    #[arg(long)]
+    pub name: Option<String>,
+    /// Free-form note on what the cassette guards against.
+    #[arg(long)]
+    pub description: Option<String>,
+    /// Directory the fixture is written to.
+    #[arg(long, default_value = "tests/cassettes")]
+    pub out_dir: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CassetteSource {
+    pub conversation_id: String,
+    pub turn_id: String,
+    #[serde(default)]
+    pub model_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CassetteExpectation {
+    /// Request body Parallax sent upstream.
+    pub projected: Value,
+    /// Finalized assistant turn written to the bundle.
+    #[serde(rename = "final")]
+    pub final_turn: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Cassette {
+    pub version: u32,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub source: CassetteSource,
+    pub ingress: Value,
+    pub upstream_sse: Vec<String>,
+    pub expected: CassetteExpectation,
+}
+
+impl Cassette {
+    /// Builds a cassette from a captured turn in `bundles`.
+    pub async fn from_bundle(
+        bundles: &BundleManager,
+        conversation_id: &str,
+        turn_id: &str,
+        name: &str,
+    ) -> Result<Self> {
+        let read = |blob_id: &'static str| read_blob(bundles, conversation_id, turn_id, blob_id);
+        let ingress = read("ingress_raw").await?;
+        let projected = read("projected").await?;
+        let final_turn = read("final").await?;
+        let upstream: Vec<SseLine> = serde_json::from_value(read("upstream_sse").await?)?;
+
+        let model_id = ingress
+            .get("model")
+            .and_then(|m| m.as_str())
+            .map(String::from);
+
+        Ok(Self {
+            version: CASSETTE_VERSION,
+            name: name.to_string(),
+            description: None,
+            source: CassetteSource {
+                conversation_id: conversation_id.to_string(),
+                turn_id: turn_id.to_string(),
+                model_id,
+            },
+            ingress,
+            upstream_sse: upstream.into_iter().map(|l| l.line).collect(),
+            expected: CassetteExpectation {
+                projected,
+                final_turn,
+            },
+        })
+    }
+
+    pub async fn load(path: &Path) -> Result<Self> {
+        let content = tokio::fs::read_to_string(path).await?;
+        let cassette: Cassette = serde_json::from_str(&content).map_err(|e| {
+            ParallaxError::InvalidIngress(format!("Invalid cassette {}: {}", path.display(), e))
+        })?;
+        if cassette.version != CASSETTE_VERSION {
+            return Err(ParallaxError::InvalidIngress(format!(
+                "Cassette {} has version {}, expected {}",
+                path.display(),
+                cassette.version,
+                CASSETTE_VERSION
+            ))
+            .into());
+        }
+        Ok(cassette)
+    }
+
+    pub async fn save(&self, dir: &Path) -> Result<PathBuf> {
+        tokio::fs::create_dir_all(dir).await?;
+        let path = dir.join(format!("{}.json", self.name));
+        let content = serde_json::to_string_pretty(self)?;
+        tokio::fs::write(&path, content).await?;
+        Ok(path)
+    }
+
+    /// All cassettes in `dir`, sorted by file name.
+    pub async fn load_dir(dir: &Path) -> Result<Vec<(PathBuf, Cassette)>> {
+        let mut paths = Vec::new();
+        let mut read_dir = match tokio::fs::read_dir(dir).await {
+            Ok(rd) => rd,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        let mut cassettes = Vec::new();
+        for path in paths {
+            let cassette = Self::load(&path).await?;
+            cassettes.push((path, cassette));
+        }
+        Ok(cassettes)
+    }
+}
+
+async fn read_blob(
+    bundles: &BundleManager,
+    conversation_id: &str,
+    turn_id: &str,
+    blob_id: &str,
+) -> Result<Value> {
+    match bundles
+        .read_blob_value(conversation_id, turn_id, blob_id)
+        .await?
+    {
+        Some(v) => Ok(v),
+        None => Err(ParallaxError::InvalidIngress(format!(
+            "Turn {}/{} has no {} blob{}",
+            conversation_id,
+            turn_id,
+            blob_id,
+            if blob_id == "upstream_sse" {
+                " (capture it by running with --capture-sse)"
+            } else {
+                ""
+            }
+        ))
+        .into()),
+    }
+}
+
+/// Most recently started turn of a conversation, per its summary.
+async fn latest_turn(bundles: &BundleManager, conversation_id: &str) -> Result<String> {
+    let summary = match bundles.read_conversation(conversation_id).await? {
+        Some(s) => s,
+        None => {
+            return Err(ParallaxError::InvalidIngress(format!(
+                "Conversation {} was not captured",
+                conversation_id
+            ))
+            .into())
+        }
+    };
+    match summary.turns.iter().max_by_key(|t| t.started_at_ms) {
+        Some(turn) => Ok(turn.turn_id.clone()),
+        None => Err(ParallaxError::InvalidIngress(format!(
+            "Conversation {} has no captured turns",
+            conversation_id
+        ))
+        .into()),
+    }
+}
+
+/// `parallax cassette`: converts a captured turn into a fixture and returns its path.
+pub async fn run(args: &CassetteArgs, bundles: &BundleManager) -> Result<PathBuf> {
+    let turn_id = match &args.turn {
+        Some(t) => t.clone(),
+        None => latest_turn(bundles, &args.conversation).await?,
+    };
+    let name = match &args.name {
+        Some(n) => n.clone(),
+        None => format!(
+            "{}-{}",
+            crate::str_utils::prefix_chars(&args.conversation, 8),
+            crate::str_utils::prefix_chars(&turn_id, 8)
+        ),
+    };
+
+    let mut cassette = Cassette::from_bundle(bundles, &args.conversation, &turn_id, &name).await?;
+    cassette.description = args.description.clone();
+    cassette.save(Path::new(&args.out_dir)).await
+}
+
+/// Structural differences between `expected` and `actual`, one line per JSON path.
+pub fn diff(expected: &Value, actual: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    diff_at("", expected, actual, &mut out);
+    out
+}
+
+fn diff_at(path: &str, expected: &Value, actual: &Value, out: &mut Vec<String>) {
+    match (expected, actual) {
+        (Value::Object(e), Value::Object(a)) => {
+            let mut keys: Vec<&String> = e.keys().chain(a.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{}/{}", path, key);
+                match (e.get(key), a.get(key)) {
+                    (Some(ev), Some(av)) => diff_at(&child, ev, av, out),
+                    (Some(ev), None) => out.push(format!("{}: missing (expected {})", child, ev)),
+                    (None, Some(av)) => out.push(format!("{}: unexpected {}", child, av)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(e), Value::Array(a)) => {
+            for i in 0..e.len().max(a.len()) {
+                let child = format!("{}/{}", path, i);
+                match (e.get(i), a.get(i)) {
+                    (Some(ev), Some(av)) => diff_at(&child, ev, av, out),
+                    (Some(ev), None) => out.push(format!("{}: missing (expected {})", child, ev)),
+                    (None, Some(av)) => out.push(format!("{}: unexpected {}", child, av)),
+                    (None, None) => {}
+                }
+            }
+        }
+        (e, a) if e != a => out.push(format!("{}: expected {}, got {}", path_or_root(path), e, a)),
+        _ => {}
+    }
+}
+
+fn path_or_root(path: &str) -> &str {
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_reports_paths() {
+        let expected = json!({"messages": [{"role": "user", "content": "hi"}], "stream": true});
+        let actual = json!({"messages": [{"role": "user", "content": "yo"}, {"role": "x"}]});
+
+        let diffs = diff(&expected, &actual);
+        assert_eq!(
+            diffs,
+            vec![
+                "/messages/0/content: expected \"hi\", got \"yo\"".to_string(),
+                "/messages/1: unexpected {\"role\":\"x\"}".to_string(),
+                "/stream: missing (expected true)".to_string(),
+            ]
+        );
+        assert!(diff(&expected, &expected).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_from_bundle_round_trip() {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let bundles = crate::debug_bundle::BundleManager::new(dir.path());
+        let blobs = [
+            ("ingress_raw", json!({"model": "m/x", "messages": []})),
+            ("projected", json!({"model": "m/x", "stream": true})),
+            ("final", json!({"role": "assistant", "content": []})),
+            (
+                "upstream_sse",
+                json!([{"t_ms": 3, "line": "data: {}"}, {"t_ms": 5, "line": "data: [DONE]"}]),
+            ),
+        ];
+        for (id, value) in &blobs {
+            if let Err(e) = bundles
+                .write_blob("cid", "tid", id, value.to_string().as_bytes())
+                .await
+            {
+                panic!("write_blob {}: {}", id, e);
+            }
+        }
+
+        let cassette = match Cassette::from_bundle(&bundles, "cid", "tid", "sample").await {
+            Ok(c) => c,
+            Err(e) => panic!("from_bundle: {}", e),
+        };
+        assert_eq!(cassette.source.model_id.as_deref(), Some("m/x"));
+        assert_eq!(cassette.upstream_sse, vec!["data: {}", "data: [DONE]"]);
+
+        let path = match cassette.save(&dir.path().join("out")).await {
+            Ok(p) => p,
+            Err(e) => panic!("save: {}", e),
+        };
+        let loaded = match Cassette::load(&path).await {
+            Ok(c) => c,
+            Err(e) => panic!("load: {}", e),
+        };
+        assert_eq!(loaded.expected.projected, blobs[1].1);
+        assert_eq!(loaded.expected.final_turn, blobs[2].1);
+    }
+}
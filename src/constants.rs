@@ -28,6 +28,34 @@ pub const GEMINI_FLASH_FALLBACK: &str = "google/gemini-3-flash-preview-0814";
 pub const OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
 pub const OPENROUTER_CHAT_COMPLETIONS: &str = "https://openrouter.ai/api/v1/chat/completions";
 
+/// Upstream name used to key circuit breakers and metrics
+pub const UPSTREAM_OPENROUTER: &str = "openrouter";
+
+/// Retry defaults: cap on a single backoff sleep and on total time spent retrying
+pub const RETRY_MAX_DELAY_MS: u64 = 10_000;
+pub const RETRY_BUDGET_SECS: u64 = 60;
+
+/// Maximum number of SSE events retained per stream for `Last-Event-ID` replay
+pub const SSE_RESUME_MAX_EVENTS: usize = 20_000;
+
+/// Seconds a disconnected SSE stream keeps reading the upstream while waiting for a resume
+pub const SSE_RESUME_DETACH_GRACE_SECS: u64 = 10;
+
+/// Commands queued for the kernel before senders wait
+pub const KERNEL_CHANNEL_CAPACITY: usize = 1024;
+
+/// Catch-up kept by `/debug/events` for `parallax tui --connect`
+pub const EVENT_FEED_MAX_REQUESTS: usize = 50;
+pub const EVENT_FEED_MAX_LOGS: usize = 500;
+pub const EVENT_FEED_MAX_LINE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Events a paused TUI holds back before it resumes on its own
+pub const TUI_PAUSE_MAX_EVENTS: usize = 50_000;
+
+/// At-rest encryption key material, and the new key read by `parallax rotate-key`
+pub const ENCRYPTION_KEY_ENV: &str = "PARALLAX_ENCRYPTION_KEY";
+pub const ENCRYPTION_KEY_NEW_ENV: &str = "PARALLAX_ENCRYPTION_KEY_NEW";
+
 /// Database defaults
 pub const DB_CLEANUP_RETENTION_DAYS: i64 = 7;
 pub const DB_BUSY_TIMEOUT_MS: u32 = 5000;
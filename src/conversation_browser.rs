@@ -0,0 +1,648 @@
+//! Conversation browser: the TUI tab over captured debug bundles.
+//!
+//! Conversations come from [`BundleManager::list_conversations`] (most recently updated
+//! first) and drill down into turns, a turn's issues, tool calls and stages, and a stage's
+//! blob pretty-printed in a scrollable viewer. Bundle reads, replays and exports run on
+//! background tasks; their results arrive through a channel drained by [`poll`].
+//!
+//! [`poll`]: ConversationBrowser::poll
+
+use crate::debug_bundle::{
+    BundleManager, ConversationSummary, ToolArgsStatus, TurnDetail, TurnSummary,
+};
+use crossterm::event::KeyCode;
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+};
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// How far PageUp/PageDown move the blob viewer.
+const PAGE_LINES: u16 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowserView {
+    Conversations,
+    Turns,
+    Turn,
+    Blob,
+}
+
+enum BrowserUpdate {
+    Conversations(Vec<ConversationSummary>),
+    Turn(Box<TurnDetail>),
+    Blob { title: String, lines: Vec<String> },
+    Status(String),
+}
+
+pub struct ConversationBrowser {
+    bundles: BundleManager,
+    /// Base URL of the proxy that replays go through.
+    proxy_url: String,
+    export_dir: PathBuf,
+    tx: mpsc::UnboundedSender<BrowserUpdate>,
+    rx: mpsc::UnboundedReceiver<BrowserUpdate>,
+    view: BrowserView,
+    loaded: bool,
+    conversations: Vec<ConversationSummary>,
+    conversation_state: ListState,
+    turn_state: ListState,
+    turn: Option<TurnDetail>,
+    stage_state: ListState,
+    blob_title: String,
+    blob_lines: Vec<String>,
+    scroll: u16,
+    status: Option<String>,
+}
+
+impl ConversationBrowser {
+    pub fn new(bundles: BundleManager, proxy_url: String, export_dir: PathBuf) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            bundles,
+            proxy_url: proxy_url.trim_end_matches('/').to_string(),
+            export_dir,
+            tx,
+            rx,
+            view: BrowserView::Conversations,
+            loaded: false,
+            conversations: Vec::new(),
+            conversation_state: ListState::default(),
+            turn_state: ListState::default(),
+            turn: None,
+            stage_state: ListState::default(),
+            blob_title: String::new(),
+            blob_lines: Vec::new(),
+            scroll: 0,
+            status: None,
+        }
+    }
+
+    pub fn view(&self) -> BrowserView {
+        self.view
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    pub fn conversations(&self) -> &[ConversationSummary] {
+        &self.conversations
+    }
+
+    /// Loads the conversation list the first time the tab is shown.
+    pub fn activate(&mut self) {
+        if !self.loaded {
+            self.loaded = true;
+            self.reload();
+        }
+    }
+
+    /// Applies finished background work; call once per frame.
+    pub fn poll(&mut self) {
+        while let Ok(update) = self.rx.try_recv() {
+            match update {
+                BrowserUpdate::Conversations(conversations) => {
+                    let selected = self
+                        .selected_conversation()
+                        .map(|c| c.conversation_id.clone());
+                    self.conversations = conversations;
+                    let index = selected
+                        .and_then(|cid| {
+                            self.conversations
+                                .iter()
+                                .position(|c| c.conversation_id == cid)
+                        })
+                        .unwrap_or_default();
+                    self.conversation_state
+                        .select((!self.conversations.is_empty()).then_some(index));
+                    if self.selected_conversation().is_none()
+                        && self.view != BrowserView::Conversations
+                    {
+                        self.view = BrowserView::Conversations;
+                    }
+                }
+                BrowserUpdate::Turn(detail) => {
+                    self.stage_state
+                        .select((!detail.stages.is_empty()).then_some(0));
+                    self.turn = Some(*detail);
+                    self.view = BrowserView::Turn;
+                }
+                BrowserUpdate::Blob { title, lines } => {
+                    self.blob_title = title;
+                    self.blob_lines = lines;
+                    self.scroll = 0;
+                    self.view = BrowserView::Blob;
+                }
+                BrowserUpdate::Status(status) => self.status = Some(status),
+            }
+        }
+    }
+
+    /// Handles a key press on the tab; `false` leaves it to the global bindings.
+    pub fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('j') | KeyCode::Down => self.move_selection(1),
+            KeyCode::Char('k') | KeyCode::Up => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(PAGE_LINES as isize),
+            KeyCode::PageUp => self.move_selection(-(PAGE_LINES as isize)),
+            KeyCode::Enter => self.open_selected(),
+            KeyCode::Esc | KeyCode::Backspace => return self.back(),
+            KeyCode::Char('R') => self.reload(),
+            KeyCode::Char('p') => self.replay_selected(),
+            KeyCode::Char('e') => self.export_selected(),
+            _ => return false,
+        }
+        true
+    }
+
+    fn selected_conversation(&self) -> Option<&ConversationSummary> {
+        self.conversations.get(self.conversation_state.selected()?)
+    }
+
+    fn selected_turn(&self) -> Option<&TurnSummary> {
+        self.selected_conversation()?
+            .turns
+            .get(self.turn_state.selected()?)
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let (state, len) = match self.view {
+            BrowserView::Conversations => (&mut self.conversation_state, self.conversations.len()),
+            BrowserView::Turns => {
+                let len = self.selected_conversation().map_or(0, |c| c.turns.len());
+                (&mut self.turn_state, len)
+            }
+            BrowserView::Turn => {
+                let len = self.turn.as_ref().map_or(0, |t| t.stages.len());
+                (&mut self.stage_state, len)
+            }
+            BrowserView::Blob => {
+                let max = self.blob_lines.len().saturating_sub(1) as isize;
+                self.scroll = (self.scroll as isize + delta).clamp(0, max) as u16;
+                return;
+            }
+        };
+        if len == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or_default() as isize;
+        state.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+    }
+
+    fn open_selected(&mut self) {
+        match self.view {
+            BrowserView::Conversations => {
+                if let Some(conversation) = self.selected_conversation() {
+                    let has_turns = !conversation.turns.is_empty();
+                    self.turn_state.select(has_turns.then_some(0));
+                    self.view = BrowserView::Turns;
+                }
+            }
+            BrowserView::Turns => self.load_turn(),
+            BrowserView::Turn => self.load_stage_blob(),
+            BrowserView::Blob => {}
+        }
+    }
+
+    fn back(&mut self) -> bool {
+        self.view = match self.view {
+            BrowserView::Conversations => return false,
+            BrowserView::Turns => BrowserView::Conversations,
+            BrowserView::Turn => BrowserView::Turns,
+            BrowserView::Blob => BrowserView::Turn,
+        };
+        true
+    }
+
+    fn reload(&mut self) {
+        let bundles = self.bundles.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = match bundles.list_conversations().await {
+                Ok(conversations) => tx.send(BrowserUpdate::Conversations(conversations)),
+                Err(e) => tx.send(BrowserUpdate::Status(format!(
+                    "Failed to list conversations: {}",
+                    e
+                ))),
+            };
+        });
+    }
+
+    fn load_turn(&mut self) {
+        let (cid, tid) = match (self.selected_conversation(), self.selected_turn()) {
+            (Some(c), Some(t)) => (c.conversation_id.clone(), t.turn_id.clone()),
+            _ => return,
+        };
+        let bundles = self.bundles.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = match bundles.read_turn(&cid, &tid).await {
+                Ok(Some(detail)) => tx.send(BrowserUpdate::Turn(Box::new(detail))),
+                Ok(None) => tx.send(BrowserUpdate::Status(format!("Turn {} not found", tid))),
+                Err(e) => tx.send(BrowserUpdate::Status(format!(
+                    "Failed to read turn {}: {}",
+                    tid, e
+                ))),
+            };
+        });
+    }
+
+    fn load_stage_blob(&mut self) {
+        let cid = match self.selected_conversation() {
+            Some(c) => c.conversation_id.clone(),
+            None => return,
+        };
+        let (tid, stage) = match (&self.turn, self.stage_state.selected()) {
+            (Some(turn), Some(i)) => match turn.stages.get(i) {
+                Some(stage) => (turn.turn_id.clone(), stage.clone()),
+                None => return,
+            },
+            _ => return,
+        };
+        let blob_id = match &stage.blob_ref {
+            Some(blob_ref) => blob_ref.blob_id.clone(),
+            None => {
+                // Stages without a blob carry everything in their summary.
+                let _ = self.tx.send(BrowserUpdate::Blob {
+                    title: format!("{} (summary)", stage.name),
+                    lines: pretty_lines(&stage.summary),
+                });
+                return;
+            }
+        };
+        let bundles = self.bundles.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let lines = match bundles.read_blob_value(&cid, &tid, &blob_id).await {
+                Ok(Some(value)) => pretty_lines(&value),
+                // Not JSON (or NDJSON): show the raw text.
+                _ => match bundles.read_blob(&cid, &tid, &blob_id).await {
+                    Ok(Some(blob)) => String::from_utf8_lossy(&blob.content)
+                        .lines()
+                        .map(String::from)
+                        .collect(),
+                    Ok(None) => {
+                        let _ =
+                            tx.send(BrowserUpdate::Status(format!("Blob {} not found", blob_id)));
+                        return;
+                    }
+                    Err(e) => {
+                        let _ = tx.send(BrowserUpdate::Status(format!(
+                            "Failed to read blob {}: {}",
+                            blob_id, e
+                        )));
+                        return;
+                    }
+                },
+            };
+            let _ = tx.send(BrowserUpdate::Blob {
+                title: blob_id,
+                lines,
+            });
+        });
+    }
+
+    /// Re-sends the turn's captured request through the proxy as `<cid>-replay`, so the
+    /// replay is captured next to (not inside) the original conversation.
+    fn replay_selected(&mut self) {
+        if matches!(self.view, BrowserView::Conversations) {
+            return;
+        }
+        let (cid, tid) = match (self.selected_conversation(), self.selected_turn()) {
+            (Some(c), Some(t)) => (c.conversation_id.clone(), t.turn_id.clone()),
+            _ => return,
+        };
+        let replay_cid = format!("{}-replay", cid.trim_end_matches("-replay"));
+        let url = format!("{}/v1/chat/completions", self.proxy_url);
+        let bundles = self.bundles.clone();
+        let tx = self.tx.clone();
+        self.status = Some(format!("Replaying turn {}...", short(&tid)));
+        tokio::spawn(async move {
+            let request = match bundles.read_blob_value(&cid, &tid, "ingress_raw").await {
+                Ok(Some(request)) => request,
+                _ => {
+                    let _ = tx.send(BrowserUpdate::Status(format!(
+                        "Turn {} has no captured request to replay",
+                        short(&tid)
+                    )));
+                    return;
+                }
+            };
+            let result = reqwest::Client::new()
+                .post(&url)
+                .header("x-conversation-id", &replay_cid)
+                .json(&request)
+                .send()
+                .await;
+            let status = match result {
+                // Drain the (usually streamed) body so the replayed turn completes.
+                Ok(response) => {
+                    let status = response.status();
+                    let _ = response.bytes().await;
+                    format!(
+                        "Replayed turn {} into {}: HTTP {}",
+                        short(&tid),
+                        replay_cid,
+                        status.as_u16()
+                    )
+                }
+                Err(e) => format!("Replay of turn {} failed: {}", short(&tid), e),
+            };
+            let _ = tx.send(BrowserUpdate::Status(status));
+            if let Ok(conversations) = bundles.list_conversations().await {
+                let _ = tx.send(BrowserUpdate::Conversations(conversations));
+            }
+        });
+    }
+
+    /// Writes the selected conversation (from the list) or turn (elsewhere) as a zip.
+    fn export_selected(&mut self) {
+        let cid = match self.selected_conversation() {
+            Some(c) => c.conversation_id.clone(),
+            None => return,
+        };
+        let tid = match self.view {
+            BrowserView::Conversations => None,
+            _ => self.selected_turn().map(|t| t.turn_id.clone()),
+        };
+        let bundles = self.bundles.clone();
+        let export_dir = self.export_dir.clone();
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let (file_name, zip) = match &tid {
+                Some(tid) => (
+                    format!("turn-{}.zip", tid),
+                    bundles.export_turn(&cid, tid).await,
+                ),
+                None => (
+                    format!("conversation-{}.zip", cid),
+                    bundles.export_conversation(&cid).await,
+                ),
+            };
+            let path = export_dir.join(file_name);
+            let status = match zip {
+                Ok(Some(bytes)) => match tokio::fs::create_dir_all(&export_dir).await {
+                    Ok(()) => match tokio::fs::write(&path, bytes).await {
+                        Ok(()) => format!("Exported to {}", path.display()),
+                        Err(e) => format!("Failed to write {}: {}", path.display(), e),
+                    },
+                    Err(e) => format!("Failed to create {}: {}", export_dir.display(), e),
+                },
+                Ok(None) => "Nothing to export".to_string(),
+                Err(e) => format!("Export failed: {}", e),
+            };
+            let _ = tx.send(BrowserUpdate::Status(status));
+        });
+    }
+
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(area);
+
+        match self.view {
+            BrowserView::Conversations => self.render_conversations(f, chunks[0]),
+            BrowserView::Turns => self.render_turns(f, chunks[0]),
+            BrowserView::Turn => self.render_turn(f, chunks[0]),
+            BrowserView::Blob => self.render_blob(f, chunks[0]),
+        }
+
+        let hints = match self.view {
+            BrowserView::Conversations => " [Enter] Turns | [e] Export conversation | [R] Reload ",
+            BrowserView::Turns => " [Enter] Open turn | [p] Replay | [e] Export turn | [Esc] Back ",
+            BrowserView::Turn => " [Enter] View stage | [p] Replay | [e] Export turn | [Esc] Back ",
+            BrowserView::Blob => " [j/k] Scroll | [PgUp/PgDn] Page | [Esc] Back ",
+        };
+        let footer = match &self.status {
+            Some(status) => Line::from(vec![
+                Span::styled(format!(" {} ", status), Style::default().fg(Color::Yellow)),
+                Span::styled(hints, Style::default().fg(Color::DarkGray)),
+            ]),
+            None => Line::from(Span::styled(hints, Style::default().fg(Color::DarkGray))),
+        };
+        f.render_widget(Paragraph::new(footer), chunks[1]);
+    }
+
+    fn render_conversations(&mut self, f: &mut Frame, area: Rect) {
+        let items: Vec<ListItem> = self
+            .conversations
+            .iter()
+            .map(|c| {
+                let issues = c.issues.total();
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!("{:<14} ", format_ms(c.last_updated_ms))),
+                    Span::styled(
+                        format!(
+                            "{:<40} ",
+                            crate::str_utils::prefix_chars(&c.conversation_id, 40)
+                        ),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(format!("{:>4} turns  ", c.turns.len())),
+                    issue_span(issues),
+                ]))
+            })
+            .collect();
+        let title = format!(" CONVERSATIONS ({}) ", self.conversations.len());
+        f.render_stateful_widget(
+            List::new(items)
+                .block(browser_block(&title))
+                .highlight_style(highlight()),
+            area,
+            &mut self.conversation_state,
+        );
+    }
+
+    fn render_turns(&mut self, f: &mut Frame, area: Rect) {
+        let (title, items) = match self.selected_conversation() {
+            Some(c) => (
+                format!(
+                    " {} - TURNS ",
+                    crate::str_utils::prefix_chars(&c.conversation_id, 40)
+                ),
+                c.turns
+                    .iter()
+                    .map(|t| {
+                        let duration = t
+                            .ended_at_ms
+                            .map(|end| format!("{:>7}ms", end.saturating_sub(t.started_at_ms)))
+                            .unwrap_or_else(|| "    open ".to_string());
+                        ListItem::new(Line::from(vec![
+                            Span::raw(format!("{:<14} ", format_ms(t.started_at_ms))),
+                            Span::styled(
+                                format!("{:<10} ", short(&t.turn_id)),
+                                Style::default().fg(Color::Cyan),
+                            ),
+                            Span::raw(format!("{:<32} {} ", t.model_id, duration)),
+                            issue_span(t.issues.total()),
+                        ]))
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            None => (" TURNS ".to_string(), Vec::new()),
+        };
+        f.render_stateful_widget(
+            List::new(items)
+                .block(browser_block(&title))
+                .highlight_style(highlight()),
+            area,
+            &mut self.turn_state,
+        );
+    }
+
+    fn render_turn(&mut self, f: &mut Frame, area: Rect) {
+        let turn = match &self.turn {
+            Some(t) => t,
+            None => return,
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(area);
+
+        let mut lines = vec![
+            Line::from(format!("Model:   {} ({})", turn.model_id, turn.flavor)),
+            Line::from(format!(
+                "Started: {}   Request: {}",
+                format_ms(turn.started_at_ms),
+                short(&turn.request_id)
+            )),
+        ];
+        if let Some(query) = &turn.user_query {
+            lines.push(Line::from(format!(
+                "Query:   {}",
+                crate::str_utils::prefix_chars(query, 200)
+            )));
+        }
+        lines.push(Line::from(""));
+        lines.push(section(&format!("ISSUES ({})", turn.issues.len())));
+        for issue in &turn.issues {
+            let color = match issue.severity.as_str() {
+                "error" => Color::Red,
+                "warning" => Color::Yellow,
+                _ => Color::Gray,
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {:<22} ", issue.kind), Style::default().fg(color)),
+                Span::raw(issue.message.clone()),
+            ]));
+        }
+        lines.push(Line::from(""));
+        lines.push(section(&format!("TOOL CALLS ({})", turn.tool_calls.len())));
+        for call in &turn.tool_calls {
+            let (label, color) = match call.args_status {
+                ToolArgsStatus::Ok => ("ok", Color::Green),
+                ToolArgsStatus::Repaired => ("repaired", Color::Yellow),
+                ToolArgsStatus::Rescue => ("rescue", Color::Magenta),
+                ToolArgsStatus::Empty => ("empty", Color::Red),
+                ToolArgsStatus::Invalid => ("invalid", Color::Red),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {:<9} ", label), Style::default().fg(color)),
+                Span::raw(format!("{} ", call.name)),
+                Span::styled(call.id.clone(), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+        f.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .block(browser_block(&format!(" TURN {} ", short(&turn.turn_id)))),
+            chunks[0],
+        );
+
+        let stages: Vec<ListItem> = turn
+            .stages
+            .iter()
+            .map(|stage| {
+                let size = stage
+                    .blob_ref
+                    .as_ref()
+                    .map(|b| format!("{:>8}B", b.approx_bytes))
+                    .unwrap_or_else(|| "  summary".to_string());
+                ListItem::new(format!("{:<24} {}", stage.name, size))
+            })
+            .collect();
+        f.render_stateful_widget(
+            List::new(stages)
+                .block(browser_block(" STAGES "))
+                .highlight_style(highlight()),
+            chunks[1],
+            &mut self.stage_state,
+        );
+    }
+
+    fn render_blob(&self, f: &mut Frame, area: Rect) {
+        let title = format!(
+            " {} ({}/{}) ",
+            self.blob_title,
+            (self.scroll as usize + 1).min(self.blob_lines.len()),
+            self.blob_lines.len()
+        );
+        let text: Vec<Line> = self
+            .blob_lines
+            .iter()
+            .map(|l| Line::from(l.as_str()))
+            .collect();
+        f.render_widget(
+            Paragraph::new(text)
+                .scroll((self.scroll, 0))
+                .block(browser_block(&title)),
+            area,
+        );
+    }
+}
+
+fn pretty_lines(value: &serde_json::Value) -> Vec<String> {
+    serde_json::to_string_pretty(value)
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+fn format_ms(ms: u64) -> String {
+    match chrono::DateTime::from_timestamp_millis(ms as i64) {
+        Some(t) => t
+            .with_timezone(&chrono::Local)
+            .format("%m-%d %H:%M:%S")
+            .to_string(),
+        None => "-".to_string(),
+    }
+}
+
+fn short(id: &str) -> &str {
+    crate::str_utils::prefix_chars(id, 8)
+}
+
+fn issue_span(issues: u32) -> Span<'static> {
+    if issues == 0 {
+        Span::styled("no issues", Style::default().fg(Color::DarkGray))
+    } else {
+        Span::styled(
+            format!("{} issues", issues),
+            Style::default().fg(Color::Yellow),
+        )
+    }
+}
+
+fn section(title: &str) -> Line<'static> {
+    Line::from(Span::styled(
+        title.to_string(),
+        Style::default().add_modifier(Modifier::BOLD),
+    ))
+}
+
+fn highlight() -> Style {
+    Style::default().fg(Color::Black).bg(Color::Cyan)
+}
+
+fn browser_block(title: &str) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .title(title.to_string())
+        .border_style(Style::default().fg(Color::DarkGray))
+        .bg(Color::Black)
+}
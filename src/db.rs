@@ -1,4 +1,4 @@
-use crate::constants::{DB_CLEANUP_RETENTION_DAYS, DB_PRAGMAS};
+use crate::constants::DB_PRAGMAS;
 use crate::types::{ParallaxError, Result};
 use sqlx::sqlite::SqlitePool;
 use sqlx::Row;
@@ -37,11 +37,6 @@ pub async fn init_db<P: AsRef<Path>>(path: P) -> Result<DbPool> {
 
     verify_schema_version(&pool).await;
 
-    // Run cleanup
-    if let Err(e) = cleanup_old_data(&pool, DB_CLEANUP_RETENTION_DAYS).await {
-        tracing::warn!("Database cleanup failed: {}", e);
-    }
-
     Ok(pool)
 }
 
@@ -75,8 +70,17 @@ async fn verify_schema_version(pool: &DbPool) {
 pub async fn cleanup_old_data(
     pool: &DbPool,
     retention_days: i64,
-) -> std::result::Result<(), sqlx::Error> {
-    let threshold = format!("-{} days", retention_days);
+) -> std::result::Result<u64, sqlx::Error> {
+    cleanup_older_than(pool, retention_days.saturating_mul(24)).await
+}
+
+/// Deletes tool signatures and conversation states not touched for `retention_hours`.
+/// Returns the number of rows removed.
+pub async fn cleanup_older_than(
+    pool: &DbPool,
+    retention_hours: i64,
+) -> std::result::Result<u64, sqlx::Error> {
+    let threshold = format!("-{} hours", retention_hours);
 
     let deleted_sigs =
         sqlx::query("DELETE FROM tool_signatures WHERE created_at < datetime('now', ?)")
@@ -90,16 +94,47 @@ pub async fn cleanup_old_data(
             .execute(pool)
             .await?;
 
-    if deleted_sigs.rows_affected() > 0 || deleted_states.rows_affected() > 0 {
-        println!(
-            "Cleanup complete: removed {} signatures and {} conversation states older than {} days.",
-            deleted_sigs.rows_affected(),
-            deleted_states.rows_affected(),
-            retention_days
-        );
-    }
+    Ok(deleted_sigs.rows_affected() + deleted_states.rows_affected())
+}
 
-    Ok(())
+/// Like [`cleanup_older_than`], except that rows of the `extended` conversations are kept
+/// until they are `extended_hours` old.
+pub async fn cleanup_older_than_extending(
+    pool: &DbPool,
+    retention_hours: i64,
+    extended: &[String],
+    extended_hours: i64,
+) -> std::result::Result<u64, sqlx::Error> {
+    let threshold = format!("-{} hours", retention_hours);
+    let extended_threshold = format!("-{} hours", extended_hours);
+    let extended_ids = match serde_json::to_string(extended) {
+        Ok(ids) => ids,
+        Err(e) => return Err(sqlx::Error::Encode(Box::new(e))),
+    };
+
+    let deleted_sigs = sqlx::query(
+        "DELETE FROM tool_signatures WHERE created_at < datetime('now', ?1) \
+         AND (conversation_id NOT IN (SELECT value FROM json_each(?2)) \
+              OR created_at < datetime('now', ?3))",
+    )
+    .bind(&threshold)
+    .bind(&extended_ids)
+    .bind(&extended_threshold)
+    .execute(pool)
+    .await?;
+
+    let deleted_states = sqlx::query(
+        "DELETE FROM conversation_states WHERE updated_at < datetime('now', ?1) \
+         AND (id NOT IN (SELECT value FROM json_each(?2)) \
+              OR updated_at < datetime('now', ?3))",
+    )
+    .bind(&threshold)
+    .bind(&extended_ids)
+    .bind(&extended_threshold)
+    .execute(pool)
+    .await?;
+
+    Ok(deleted_sigs.rows_affected() + deleted_states.rows_affected())
 }
 
 pub async fn get_conversation_history(
@@ -113,7 +148,7 @@ pub async fn get_conversation_history(
 
     match row {
         Some(r) => {
-            let json_str: String = r.get(0);
+            let json_str = crate::at_rest::open_str(r.get::<&str, _>(0))?;
             let context: crate::types::ConversationContext = serde_json::from_str(&json_str)?;
             Ok(context.history)
         }
@@ -1,7 +1,10 @@
+use crate::bundle_store::{BundleStore, FileStore, StoredBlob};
 use crate::types::TurnRecord;
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::path::{Path, PathBuf};
+use std::path::Path;
+use std::sync::Arc;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConversationSummary {
@@ -45,6 +48,25 @@ pub struct IssueCounts {
     pub tool_args_invalid: u32,
     #[serde(default)]
     pub tool_call_duplicate_id: u32,
+    #[serde(default)]
+    pub secret_scrubbed: u32,
+    /// Routine rewrites by sanitizer rules; informational, so not part of [`Self::total`].
+    #[serde(default)]
+    pub sanitizer_rules: u32,
+}
+
+impl IssueCounts {
+    pub fn total(&self) -> u32 {
+        self.tool_args_empty
+            + self.tool_args_repaired
+            + self.rescue_used
+            + self.tag_unregistered
+            + self.tag_leak_echo
+            + self.reasoning_leak
+            + self.tool_args_invalid
+            + self.tool_call_duplicate_id
+            + self.secret_scrubbed
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -113,6 +135,13 @@ pub struct BlobRef {
     pub written_at_ms: Option<u64>,
 }
 
+/// One raw SSE line as received from (or sent to) a peer, stamped relative to turn start.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SseLine {
+    pub t_ms: u64,
+    pub line: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ToolCallIndex {
     pub id: String,
@@ -193,6 +222,15 @@ pub struct Issue {
     pub context: serde_json::Value,
 }
 
+lazy_static! {
+    /// Serializes read-modify-write updates of `turn.json` and `conversation.json`; the stream
+    /// task, request handlers and background SSE capture writers all update the same turn.
+    static ref BUNDLE_UPDATES: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+/// Issue kinds recorded before the upstream response (see [`BundleManager::add_issue`]).
+const REQUEST_ISSUE_KINDS: [&str; 2] = ["SecretScrubbed", "ToolSchemaPatched"];
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SpanSummary {
     pub name: String,
@@ -200,42 +238,206 @@ pub struct SpanSummary {
     pub fields: serde_json::Value,
 }
 
+/// Adds `value` as redacted, pretty-printed JSON to an export zip.
+fn add_json_to_zip<W: std::io::Write + std::io::Seek, T: Serialize>(
+    zip: &mut zip::ZipWriter<W>,
+    name: &str,
+    value: &T,
+) {
+    use std::io::Write;
+
+    if let Ok(content) = serde_json::to_string_pretty(value) {
+        let _ = zip.start_file(name, zip::write::FileOptions::default());
+        let _ = zip.write_all(&crate::redaction::redact_bytes(content.as_bytes()));
+    }
+}
+
+#[derive(Clone)]
 pub struct BundleManager {
-    base_path: PathBuf,
-    max_size_bytes: u64,
+    store: Arc<dyn BundleStore>,
+    search: Option<crate::db::DbPool>,
+}
+
+/// Incremental recorder for an `upstream_sse` / `client_sse` blob.
+///
+/// Lines are handed to a background writer and appended to `<blob_id>.ndjson` as they arrive,
+/// so a partial capture survives a crash mid-stream. Dropping the handle finishes the file and
+/// registers it as a stage on the turn.
+pub struct SseCapture {
+    started: std::time::Instant,
+    tx: tokio::sync::mpsc::UnboundedSender<SseLine>,
+}
+
+impl SseCapture {
+    pub fn record(&self, line: &str) {
+        let _ = self.tx.send(SseLine {
+            t_ms: self.started.elapsed().as_millis() as u64,
+            line: crate::redaction::rules().redact_line(line).into_owned(),
+        });
+    }
 }
 
 impl BundleManager {
+    /// Bundles stored as loose files under `path`.
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self::with_store(Arc::new(FileStore::new(path)))
+    }
+
+    pub fn with_store(store: Arc<dyn BundleStore>) -> Self {
         Self {
-            base_path: path.as_ref().to_path_buf(),
-            max_size_bytes: 100 * 1024 * 1024, // 100MB default
+            store,
+            search: None,
         }
     }
 
-    pub fn with_max_size<P: AsRef<Path>>(path: P, max_size_bytes: u64) -> Self {
-        Self {
-            base_path: path.as_ref().to_path_buf(),
-            max_size_bytes,
+    /// Keeps the `turn_search` full-text index in sync with every `turn.json` write.
+    /// The store, once every identifier that becomes part of a storage key is validated.
+    fn store_for(&self, ids: &[&str]) -> crate::types::Result<&dyn BundleStore> {
+        for id in ids {
+            crate::bundle_store::validate_id(id)?;
+        }
+        Ok(self.store.as_ref())
+    }
+
+    pub fn with_search_index(mut self, db: crate::db::DbPool) -> Self {
+        self.search = Some(db);
+        self
+    }
+
+    /// Starts an incremental SSE capture; `started` is the turn's start so timestamps from the
+    /// upstream and client captures line up.
+    pub fn start_sse_capture(
+        &self,
+        cid: &str,
+        tid: &str,
+        blob_id: &str,
+        started: std::time::Instant,
+    ) -> SseCapture {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<SseLine>();
+        let bundles = self.clone();
+        let (cid, tid, blob_id) = (cid.to_string(), tid.to_string(), blob_id.to_string());
+
+        tokio::spawn(async move {
+            use sha2::{Digest, Sha256};
+
+            let store = match bundles.store_for(&[&cid, &tid, &blob_id]) {
+                Ok(store) => store,
+                Err(e) => {
+                    tracing::warn!("Not capturing {} blob: {}", blob_id, e);
+                    return;
+                }
+            };
+            let file_name = format!("{}.ndjson", blob_id);
+            let mut hasher = Sha256::new();
+            let mut bytes = 0u64;
+            let mut lines = 0u64;
+            // Lines that arrived while the previous batch was being written go out together.
+            while let Some(first) = rx.recv().await {
+                let mut batch = Vec::new();
+                let mut next = Some(first);
+                while let Some(line) = next {
+                    if let Ok(encoded) = serde_json::to_vec(&line) {
+                        batch.extend_from_slice(&encoded);
+                        batch.push(b'\n');
+                        lines += 1;
+                    }
+                    next = rx.try_recv().ok();
+                }
+                if let Err(e) = store.append_blob(&cid, &tid, &file_name, &batch).await {
+                    tracing::warn!("Failed to append to {} blob: {}", blob_id, e);
+                    return;
+                }
+                hasher.update(&batch);
+                bytes += batch.len() as u64;
+            }
+            if let Err(e) = store.seal_blob(&cid, &tid, &file_name).await {
+                tracing::warn!("Failed to finish {} blob: {}", blob_id, e);
+                return;
+            }
+
+            let blob_ref = BlobRef {
+                blob_id: blob_id.clone(),
+                content_type: "application/x-ndjson".to_string(),
+                approx_bytes: bytes,
+                file_name: Some(file_name),
+                sha256: Some(format!("{:x}", hasher.finalize())),
+                written_at_ms: std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .ok(),
+            };
+            let _ = bundles
+                .add_stage(&cid, &tid, &blob_id, blob_ref, json!({ "lines": lines }))
+                .await;
+        });
+
+        SseCapture { started, tx }
+    }
+
+    /// Reads a blob as JSON; `.ndjson` blobs are returned as an array of their lines.
+    pub async fn read_blob_value(
+        &self,
+        cid: &str,
+        tid: &str,
+        blob_id: &str,
+    ) -> crate::types::Result<Option<serde_json::Value>> {
+        let blob = match self
+            .store_for(&[cid, tid, blob_id])?
+            .get_blob(cid, tid, blob_id)
+            .await?
+        {
+            Some(b) => b,
+            None => return Ok(None),
+        };
+        if blob.file_name.ends_with(".json") {
+            return Ok(Some(serde_json::from_slice(&blob.content)?));
+        }
+        if !blob.file_name.ends_with(".ndjson") {
+            return Ok(None);
+        }
+        let mut items = Vec::new();
+        for line in String::from_utf8_lossy(&blob.content)
+            .lines()
+            .filter(|l| !l.trim().is_empty())
+        {
+            items.push(serde_json::from_str(line)?);
         }
+        Ok(Some(serde_json::Value::Array(items)))
     }
 
-    pub async fn ensure_conversation_dir(&self, cid: &str) -> std::io::Result<PathBuf> {
-        let path = self.base_path.join("conversations").join(cid);
-        tokio::fs::create_dir_all(&path).await?;
-        Ok(path)
+    /// Raw blob bytes and file name, whatever the extension.
+    pub async fn read_blob(
+        &self,
+        cid: &str,
+        tid: &str,
+        blob_id: &str,
+    ) -> crate::types::Result<Option<StoredBlob>> {
+        self.store_for(&[cid, tid, blob_id])?
+            .get_blob(cid, tid, blob_id)
+            .await
+    }
+
+    /// File names of all blobs of a turn.
+    pub async fn list_blobs(&self, cid: &str, tid: &str) -> crate::types::Result<Vec<String>> {
+        self.store_for(&[cid, tid])?.list_blobs(cid, tid).await
     }
 
-    pub async fn ensure_turn_dir(&self, cid: &str, tid: &str) -> std::io::Result<PathBuf> {
-        let path = self
-            .base_path
-            .join("conversations")
-            .join(cid)
-            .join("turns")
-            .join(tid);
-        tokio::fs::create_dir_all(&path).await?;
-        tokio::fs::create_dir_all(path.join("blobs")).await?;
-        Ok(path)
+    pub async fn read_conversation(
+        &self,
+        cid: &str,
+    ) -> crate::types::Result<Option<ConversationSummary>> {
+        self.store_for(&[cid])?.read_conversation(cid).await
+    }
+
+    /// All conversation summaries, most recently updated first.
+    pub async fn list_conversations(&self) -> crate::types::Result<Vec<ConversationSummary>> {
+        let mut conversations = self.store.list_conversations().await?;
+        conversations.sort_by_key(|c| std::cmp::Reverse(c.last_updated_ms));
+        Ok(conversations)
+    }
+
+    pub async fn list_turns(&self, cid: &str) -> crate::types::Result<Vec<String>> {
+        self.store_for(&[cid])?.list_turns(cid).await
     }
 
     pub async fn write_conversation(
@@ -243,15 +445,13 @@ impl BundleManager {
         cid: &str,
         summary: &ConversationSummary,
     ) -> crate::types::Result<()> {
-        let dir = self.ensure_conversation_dir(cid).await?;
-        let path = dir.join("conversation.json");
-
+        let summary = &crate::redaction::redact_serialized(summary);
         let mut to_write = summary.clone();
+        to_write.conversation_id = cid.to_string();
 
         // Try to merge with existing if it exists
-        if let Ok(existing_content) = tokio::fs::read_to_string(&path).await {
-            if let Ok(mut existing_summary) =
-                serde_json::from_str::<ConversationSummary>(&existing_content)
+        if let Ok(Some(existing)) = self.store_for(&[cid])?.read_conversation(cid).await {
+            let mut existing_summary = existing;
             {
                 // Keep the original created_at
                 to_write.created_at_ms = existing_summary.created_at_ms;
@@ -277,9 +477,7 @@ impl BundleManager {
             }
         }
 
-        let content = serde_json::to_string_pretty(&to_write)?;
-        tokio::fs::write(path, content).await?;
-        Ok(())
+        self.store_for(&[cid])?.write_conversation(&to_write).await
     }
 
     pub async fn write_turn(
@@ -288,10 +486,15 @@ impl BundleManager {
         tid: &str,
         detail: &TurnDetail,
     ) -> crate::types::Result<()> {
-        let dir = self.ensure_turn_dir(cid, tid).await?;
-        let path = dir.join("turn.json");
-        let content = serde_json::to_string_pretty(detail)?;
-        tokio::fs::write(path, content).await?;
+        let detail = crate::redaction::redact_serialized(detail);
+        self.store_for(&[cid, tid])?
+            .write_turn(cid, tid, &detail)
+            .await?;
+        if let Some(db) = &self.search {
+            if let Err(e) = crate::search_index::index_turn(db, cid, &detail).await {
+                tracing::warn!("Failed to index turn {} for search: {}", tid, e);
+            }
+        }
         Ok(())
     }
 
@@ -300,19 +503,7 @@ impl BundleManager {
         cid: &str,
         tid: &str,
     ) -> crate::types::Result<Option<TurnDetail>> {
-        let path = self
-            .base_path
-            .join("conversations")
-            .join(cid)
-            .join("turns")
-            .join(tid)
-            .join("turn.json");
-        if !path.exists() {
-            return Ok(None);
-        }
-        let content = tokio::fs::read_to_string(&path).await?;
-        let detail = serde_json::from_str::<TurnDetail>(&content)?;
-        Ok(Some(detail))
+        self.store_for(&[cid, tid])?.read_turn(cid, tid).await
     }
 
     /// Merge two tag summaries by combining their registered/unregistered/leaks lists
@@ -355,12 +546,14 @@ impl BundleManager {
         merged
     }
 
+    /// Merges `new_detail` into the stored turn and returns what was written.
     pub async fn merge_and_write_turn(
         &self,
         cid: &str,
         tid: &str,
         new_detail: &TurnDetail,
-    ) -> crate::types::Result<()> {
+    ) -> crate::types::Result<TurnDetail> {
+        let _update = BUNDLE_UPDATES.lock().await;
         let mut detail = if let Some(existing) = self.read_turn(cid, tid).await? {
             existing
         } else {
@@ -381,7 +574,11 @@ impl BundleManager {
         // Merge cursor_tags instead of overwriting: combine registered/unregistered/leaks
         detail.cursor_tags =
             Self::merge_tag_summaries(&detail.cursor_tags, &new_detail.cursor_tags);
-        detail.issues = new_detail.issues.clone();
+        // Issues recorded while the request was prepared are not re-detected at the end
+        detail
+            .issues
+            .retain(|i| REQUEST_ISSUE_KINDS.contains(&i.kind.as_str()));
+        detail.issues.extend(new_detail.issues.iter().cloned());
         detail.trace_id = new_detail.trace_id.clone();
         detail.span_summary = new_detail.span_summary.clone();
         // Update role if provided (e.g. switching from User -> Assistant at end of stream)
@@ -397,6 +594,18 @@ impl BundleManager {
             detail.user_query_tags = new_detail.user_query_tags.clone();
         }
 
+        self.write_turn(cid, tid, &detail).await?;
+        Ok(detail)
+    }
+
+    /// Appends an issue to an existing turn (no-op until the turn has been written).
+    pub async fn add_issue(&self, cid: &str, tid: &str, issue: Issue) -> crate::types::Result<()> {
+        let _update = BUNDLE_UPDATES.lock().await;
+        let mut detail = match self.read_turn(cid, tid).await? {
+            Some(existing) => existing,
+            None => return Ok(()),
+        };
+        detail.issues.push(issue);
         self.write_turn(cid, tid, &detail).await
     }
 
@@ -408,6 +617,7 @@ impl BundleManager {
         blob_ref: BlobRef,
         summary: serde_json::Value,
     ) -> crate::types::Result<()> {
+        let _update = BUNDLE_UPDATES.lock().await;
         let mut detail = if let Some(existing) = self.read_turn(cid, tid).await? {
             existing
         } else {
@@ -436,10 +646,8 @@ impl BundleManager {
         blob_id: &str,
         content: &[u8],
     ) -> crate::types::Result<BlobRef> {
-        // Enforce size limit before writing
-        let _ = self.enforce_size_limit().await;
-
-        let dir = self.ensure_turn_dir(cid, tid).await?;
+        let content = crate::redaction::redact_bytes(content);
+        let content = content.as_slice();
 
         // Determine file extension based on content type
         let (file_name, content_type) = if let Ok(s) = std::str::from_utf8(content) {
@@ -459,8 +667,9 @@ impl BundleManager {
             )
         };
 
-        let path = dir.join("blobs").join(&file_name);
-        tokio::fs::write(&path, content).await?;
+        self.store_for(&[cid, tid, blob_id])?
+            .put_blob(cid, tid, &file_name, content)
+            .await?;
 
         // Compute SHA256
         use sha2::{Digest, Sha256};
@@ -489,28 +698,24 @@ impl BundleManager {
         tid: &str,
         detail: &TurnDetail,
     ) -> crate::types::Result<()> {
-        let summary_path = self
-            .base_path
-            .join("conversations")
-            .join(cid)
-            .join("conversation.json");
-        let mut summary = if summary_path.exists() {
-            let content = tokio::fs::read_to_string(&summary_path).await?;
-            serde_json::from_str::<ConversationSummary>(&content)?
-        } else {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_millis() as u64)
-                .unwrap_or(0);
-            ConversationSummary {
-                conversation_id: cid.to_string(),
-                conversation_id_source: detail.conversation_id_source.clone(),
-                created_at_ms: now,
-                last_updated_ms: now,
-                turns: Vec::new(),
-                issues: IssueCounts::default(),
-            }
-        };
+        let _update = BUNDLE_UPDATES.lock().await;
+        let mut summary =
+            if let Some(existing) = self.store_for(&[cid])?.read_conversation(cid).await? {
+                existing
+            } else {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0);
+                ConversationSummary {
+                    conversation_id: cid.to_string(),
+                    conversation_id_source: detail.conversation_id_source.clone(),
+                    created_at_ms: now,
+                    last_updated_ms: now,
+                    turns: Vec::new(),
+                    issues: IssueCounts::default(),
+                }
+            };
 
         let turn_summary = TurnSummary {
             turn_id: tid.to_string(),
@@ -549,6 +754,8 @@ impl BundleManager {
                 acc.reasoning_leak += t.issues.reasoning_leak;
                 acc.tool_args_invalid += t.issues.tool_args_invalid;
                 acc.tool_call_duplicate_id += t.issues.tool_call_duplicate_id;
+                acc.secret_scrubbed += t.issues.secret_scrubbed;
+                acc.sanitizer_rules += t.issues.sanitizer_rules;
                 acc
             });
 
@@ -569,6 +776,8 @@ impl BundleManager {
                 "ReasoningLeakSuspected" => counts.reasoning_leak += 1,
                 "ToolArgsInvalid" => counts.tool_args_invalid += 1,
                 "ToolCallDuplicateId" => counts.tool_call_duplicate_id += 1,
+                "SecretScrubbed" => counts.secret_scrubbed += 1,
+                "SanitizerRuleApplied" | "ToolSchemaPatched" => counts.sanitizer_rules += 1,
                 _ => {}
             }
         }
@@ -586,28 +795,23 @@ impl BundleManager {
         cid: &str,
         turn_summary: TurnSummary,
     ) -> crate::types::Result<()> {
-        let summary_path = self
-            .base_path
-            .join("conversations")
-            .join(cid)
-            .join("conversation.json");
-        let mut summary = if summary_path.exists() {
-            let content = tokio::fs::read_to_string(&summary_path).await?;
-            serde_json::from_str::<ConversationSummary>(&content)?
-        } else {
-            let now = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .map(|d| d.as_millis() as u64)
-                .unwrap_or(0);
-            ConversationSummary {
-                conversation_id: cid.to_string(),
-                conversation_id_source: turn_summary.conversation_id_source.clone(),
-                created_at_ms: now,
-                last_updated_ms: now,
-                turns: Vec::new(),
-                issues: IssueCounts::default(),
-            }
-        };
+        let mut summary =
+            if let Some(existing) = self.store_for(&[cid])?.read_conversation(cid).await? {
+                existing
+            } else {
+                let now = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_millis() as u64)
+                    .unwrap_or(0);
+                ConversationSummary {
+                    conversation_id: cid.to_string(),
+                    conversation_id_source: turn_summary.conversation_id_source.clone(),
+                    created_at_ms: now,
+                    last_updated_ms: now,
+                    turns: Vec::new(),
+                    issues: IssueCounts::default(),
+                }
+            };
 
         // Update or add turn summary
         if let Some(pos) = summary
@@ -638,6 +842,8 @@ impl BundleManager {
                 acc.reasoning_leak += t.issues.reasoning_leak;
                 acc.tool_args_invalid += t.issues.tool_args_invalid;
                 acc.tool_call_duplicate_id += t.issues.tool_call_duplicate_id;
+                acc.secret_scrubbed += t.issues.secret_scrubbed;
+                acc.sanitizer_rules += t.issues.sanitizer_rules;
                 acc
             });
 
@@ -1041,33 +1247,18 @@ impl BundleManager {
         // Extract tags from current user query
         let current_tags = crate::tag_extract::extract_tags(current_user_query);
 
-        // Find the previous turn with a user query
-        let conversation_path = self.base_path.join("conversations").join(cid);
-        let turns_dir = conversation_path.join("turns");
-
-        if !turns_dir.exists() {
-            // First turn, all tags are new
-            return Some(crate::tag_extract::compute_tag_deltas(&current_tags, &[]));
-        }
-
-        // Read conversation.json to get turn order
-        let conversation_json_path = conversation_path.join("conversation.json");
-        if let Ok(conversation_json) = tokio::fs::read_to_string(&conversation_json_path).await {
-            if let Ok(conversation) =
-                serde_json::from_str::<ConversationSummary>(&conversation_json)
-            {
-                // Find the last user turn
-                for turn in conversation.turns.iter().rev() {
-                    if turn.role.as_deref() == Some("User") {
-                        // Try to read this turn's detail
-                        if let Ok(Some(turn_detail)) = self.read_turn(cid, &turn.turn_id).await {
-                            if let Some(prev_query) = turn_detail.user_query {
-                                let previous_tags = crate::tag_extract::extract_tags(&prev_query);
-                                return Some(crate::tag_extract::compute_tag_deltas(
-                                    &current_tags,
-                                    &previous_tags,
-                                ));
-                            }
+        // Find the previous turn with a user query, in conversation order
+        if let Ok(Some(conversation)) = self.read_conversation(cid).await {
+            for turn in conversation.turns.iter().rev() {
+                if turn.role.as_deref() == Some("User") {
+                    // Try to read this turn's detail
+                    if let Ok(Some(turn_detail)) = self.read_turn(cid, &turn.turn_id).await {
+                        if let Some(prev_query) = turn_detail.user_query {
+                            let previous_tags = crate::tag_extract::extract_tags(&prev_query);
+                            return Some(crate::tag_extract::compute_tag_deltas(
+                                &current_tags,
+                                &previous_tags,
+                            ));
                         }
                     }
                 }
@@ -1078,137 +1269,87 @@ impl BundleManager {
         Some(crate::tag_extract::compute_tag_deltas(&current_tags, &[]))
     }
 
-    /// Calculate total disk usage of debug_capture directory
-    pub async fn calculate_total_size(&self) -> std::io::Result<u64> {
-        let conversations_dir = self.base_path.join("conversations");
-        if !conversations_dir.exists() {
-            return Ok(0);
-        }
-
-        let mut total_size = 0u64;
-        let mut stack = vec![conversations_dir];
-
-        while let Some(dir) = stack.pop() {
-            let mut entries = tokio::fs::read_dir(&dir).await?;
-            while let Some(entry) = entries.next_entry().await? {
-                let path = entry.path();
-                let metadata = tokio::fs::metadata(&path).await?;
-
-                if metadata.is_file() {
-                    total_size += metadata.len();
-                } else if metadata.is_dir() {
-                    stack.push(path);
-                }
-            }
-        }
-
-        Ok(total_size)
+    /// Bytes the bundle store currently occupies.
+    pub async fn calculate_total_size(&self) -> crate::types::Result<u64> {
+        self.store.total_size().await
     }
 
-    /// List all conversations sorted by last_updated_ms (oldest first)
-    pub async fn list_conversations_by_age(&self) -> crate::types::Result<Vec<(String, u64)>> {
-        let conversations_dir = self.base_path.join("conversations");
-        if !conversations_dir.exists() {
-            return Ok(Vec::new());
-        }
-
-        let mut conversations = Vec::new();
-        let mut entries = tokio::fs::read_dir(&conversations_dir).await?;
-
-        while let Some(entry) = entries.next_entry().await? {
-            let path = entry.path();
-            if !path.is_dir() {
-                continue;
-            }
-
-            let conversation_json = path.join("conversation.json");
-            if !conversation_json.exists() {
-                continue;
-            }
+    /// Removes stored data left unreferenced by overwrites and deletions.
+    pub async fn collect_garbage(&self) -> crate::types::Result<()> {
+        self.store.collect_garbage().await
+    }
 
-            let content = tokio::fs::read_to_string(&conversation_json).await?;
-            if let Ok(summary) = serde_json::from_str::<ConversationSummary>(&content) {
-                conversations.push((summary.conversation_id, summary.last_updated_ms));
+    /// Deletes a conversation's bundle and its search index entries.
+    pub async fn delete_conversation(&self, cid: &str) -> crate::types::Result<()> {
+        self.store_for(&[cid])?.delete_conversation(cid).await?;
+        if let Some(db) = &self.search {
+            if let Err(e) = crate::search_index::remove_conversation(db, cid).await {
+                tracing::warn!("Failed to drop conversation from search index: {}", e);
             }
         }
-
-        // Sort by last_updated_ms (oldest first)
-        conversations.sort_by_key(|(_, updated)| *updated);
-        Ok(conversations)
+        tracing::info!(
+            "Deleted conversation {}",
+            crate::str_utils::prefix_chars(cid, 8)
+        );
+        Ok(())
     }
 
-    /// Delete a conversation directory
-    pub async fn delete_conversation(&self, cid: &str) -> std::io::Result<()> {
-        let conversation_dir = self.base_path.join("conversations").join(cid);
-        if conversation_dir.exists() {
-            tokio::fs::remove_dir_all(&conversation_dir).await?;
-            tracing::info!(
-                "Deleted conversation {} to free space",
-                &cid[..8.min(cid.len())]
-            );
+    /// Zip of a conversation summary and every turn with its blobs, secrets redacted
+    /// (`None` when the conversation does not exist).
+    pub async fn export_conversation(&self, cid: &str) -> crate::types::Result<Option<Vec<u8>>> {
+        let summary = match self.read_conversation(cid).await? {
+            Some(s) => s,
+            None => return Ok(None),
+        };
+        let mut zip_buffer = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_buffer));
+            add_json_to_zip(&mut zip, "conversation.json", &summary);
+            for tid in self.list_turns(cid).await.unwrap_or_default() {
+                self.add_turn_to_zip(&mut zip, cid, &tid, &format!("turns/{}/", tid))
+                    .await;
+            }
+            let _ = zip.finish();
         }
-        Ok(())
+        Ok(Some(zip_buffer))
     }
 
-    /// Enforce size limit by deleting oldest conversations
-    pub async fn enforce_size_limit(&self) -> crate::types::Result<()> {
-        let total_size = self.calculate_total_size().await?;
-
-        if total_size <= self.max_size_bytes {
-            return Ok(());
+    /// Zip of one turn (`turn.json` and its blobs), secrets redacted.
+    pub async fn export_turn(&self, cid: &str, tid: &str) -> crate::types::Result<Option<Vec<u8>>> {
+        if self.read_turn(cid, tid).await?.is_none() {
+            return Ok(None);
         }
-
-        tracing::warn!(
-            "Debug capture size {}MB exceeds limit {}MB, cleaning up...",
-            total_size / (1024 * 1024),
-            self.max_size_bytes / (1024 * 1024)
-        );
-
-        let conversations = self.list_conversations_by_age().await?;
-        let mut current_size = total_size;
-
-        for (cid, _) in conversations {
-            if current_size <= self.max_size_bytes {
-                break;
-            }
-
-            // Calculate conversation size before deleting
-            let conv_dir = self.base_path.join("conversations").join(&cid);
-            let conv_size = Self::calculate_dir_size(&conv_dir).await.unwrap_or(0);
-
-            self.delete_conversation(&cid).await?;
-            current_size = current_size.saturating_sub(conv_size);
-
-            tracing::info!(
-                "Freed {}KB, remaining size: {}MB",
-                conv_size / 1024,
-                current_size / (1024 * 1024)
-            );
+        let mut zip_buffer = Vec::new();
+        {
+            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(&mut zip_buffer));
+            self.add_turn_to_zip(&mut zip, cid, tid, "").await;
+            let _ = zip.finish();
         }
-
-        Ok(())
+        Ok(Some(zip_buffer))
     }
 
-    /// Helper to calculate directory size
-    async fn calculate_dir_size(path: &Path) -> std::io::Result<u64> {
-        let mut total_size = 0u64;
-        let mut stack = vec![path.to_path_buf()];
-
-        while let Some(dir) = stack.pop() {
-            let mut entries = tokio::fs::read_dir(&dir).await?;
-            while let Some(entry) = entries.next_entry().await? {
-                let path = entry.path();
-                let metadata = tokio::fs::metadata(&path).await?;
+    async fn add_turn_to_zip<W: std::io::Write + std::io::Seek>(
+        &self,
+        zip: &mut zip::ZipWriter<W>,
+        cid: &str,
+        tid: &str,
+        prefix: &str,
+    ) {
+        use std::io::Write;
 
-                if metadata.is_file() {
-                    total_size += metadata.len();
-                } else if metadata.is_dir() {
-                    stack.push(path);
-                }
+        if let Ok(Some(detail)) = self.read_turn(cid, tid).await {
+            add_json_to_zip(zip, &format!("{}turn.json", prefix), &detail);
+        }
+        for blob_name in self.list_blobs(cid, tid).await.unwrap_or_default() {
+            let blob_id = blob_name
+                .rsplit_once('.')
+                .map_or(blob_name.as_str(), |(stem, _)| stem);
+            if let Ok(Some(blob)) = self.read_blob(cid, tid, blob_id).await {
+                let options = zip::write::FileOptions::default();
+                let _ = zip.start_file(format!("{}blobs/{}", prefix, blob_name), options);
+                let _ = zip.write_all(&crate::redaction::redact_bytes(&blob.content));
             }
         }
-
-        Ok(total_size)
     }
 
     /// Query trace events for a specific turn and build a span summary
@@ -1277,6 +1418,45 @@ impl BundleManager {
         }
     }
 
+    /// Diff two SSE captures by their `data:` payloads, aligned by position.
+    ///
+    /// Timestamps, event ids and comments are ignored; JSON payloads are diffed structurally so
+    /// the result shows exactly which fields were rewritten on the way to the client.
+    pub fn compute_sse_diff(old: &[SseLine], new: &[SseLine]) -> serde_json::Value {
+        fn payloads(lines: &[SseLine]) -> Vec<serde_json::Value> {
+            lines
+                .iter()
+                .filter_map(|l| {
+                    l.line
+                        .strip_prefix("data: ")
+                        .or_else(|| l.line.strip_prefix("data:"))
+                })
+                .map(|d| match serde_json::from_str(d.trim()) {
+                    Ok(v) => v,
+                    Err(_) => serde_json::Value::String(d.trim().to_string()),
+                })
+                .collect()
+        }
+
+        let (old, new) = (payloads(old), payloads(new));
+        let mut diff = serde_json::Map::new();
+        for i in 0..old.len().max(new.len()) {
+            match (old.get(i), new.get(i)) {
+                (Some(o), Some(n)) if o != n => {
+                    diff.insert(i.to_string(), Self::compute_json_diff(o, n));
+                }
+                (Some(_), None) => {
+                    diff.insert(i.to_string(), json!({ "removed": true }));
+                }
+                (None, Some(n)) => {
+                    diff.insert(i.to_string(), json!({ "added": n }));
+                }
+                _ => {}
+            }
+        }
+        serde_json::Value::Object(diff)
+    }
+
     /// Compute a structural diff between two JSON values
     pub fn compute_json_diff(
         old: &serde_json::Value,
@@ -1390,4 +1570,63 @@ mod tests {
         assert_eq!(tool_calls.len(), 1);
         assert_eq!(tool_calls[0].args_status, ToolArgsStatus::Empty);
     }
+
+    #[tokio::test]
+    async fn test_sse_capture_writes_ndjson_incrementally() {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let bundles = BundleManager::new(dir.path());
+        let capture =
+            bundles.start_sse_capture("cid", "tid", "upstream_sse", std::time::Instant::now());
+        capture.record("data: {\"a\":1}");
+        capture.record("data: [DONE]");
+        drop(capture);
+
+        let mut value = None;
+        for _ in 0..50 {
+            if let Ok(Some(v)) = bundles.read_blob_value("cid", "tid", "upstream_sse").await {
+                if v.as_array().map(|a| a.len()) == Some(2) {
+                    value = Some(v);
+                    break;
+                }
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let lines: Vec<SseLine> = match value.map(serde_json::from_value) {
+            Some(Ok(l)) => l,
+            other => panic!("capture not readable: {:?}", other),
+        };
+        assert_eq!(lines[0].line, "data: {\"a\":1}");
+        assert_eq!(lines[1].line, "data: [DONE]");
+    }
+
+    #[test]
+    fn test_compute_sse_diff_aligns_data_payloads() {
+        let line = |t_ms, line: &str| SseLine {
+            t_ms,
+            line: line.to_string(),
+        };
+        let upstream = vec![
+            line(1, r#"data: {"id":null,"n":1}"#),
+            line(2, "data: [DONE]"),
+        ];
+        let client = vec![
+            line(3, ": parallax-stream-start"),
+            line(3, "id: s:0"),
+            line(4, r#"data: {"id":"tool_index_0","n":1}"#),
+            line(5, "data: [DONE]"),
+        ];
+
+        let diff = BundleManager::compute_sse_diff(&upstream, &client);
+        assert_eq!(
+            diff,
+            json!({ "0": { "id": { "old": null, "new": "tool_index_0" } } })
+        );
+        assert_eq!(
+            BundleManager::compute_sse_diff(&upstream, &upstream),
+            json!({})
+        );
+    }
 }
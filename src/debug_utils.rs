@@ -15,6 +15,8 @@ pub struct FlightRecorder {
     pub stages: std::collections::HashMap<String, Value>,
     #[serde(skip)]
     pub redaction_level: RedactionLevel,
+    #[serde(skip)]
+    pub capture_dir: String,
 }
 
 impl FlightRecorder {
@@ -28,9 +30,15 @@ impl FlightRecorder {
             decisions: Vec::new(),
             stages: std::collections::HashMap::new(),
             redaction_level: RedactionLevel::default(),
+            capture_dir: "debug_capture".to_string(),
         }
     }
 
+    pub fn with_capture_dir(mut self, dir: &str) -> Self {
+        self.capture_dir = dir.to_string();
+        self
+    }
+
     pub fn record_decision(&mut self, decision: String) {
         self.decisions.push(decision);
     }
@@ -81,10 +89,10 @@ impl FlightRecorder {
         let safe_model = self.model_id.replace("/", "_").replace(":", "_");
         let safe_cid = str_utils::prefix_chars(&self.conversation_id, 8);
         let filename = format!(
-            "debug_capture/{}_flight_{}_{}.json",
-            timestamp, safe_cid, safe_model
+            "{}/{}_flight_{}_{}.json",
+            self.capture_dir, timestamp, safe_cid, safe_model
         );
-        ("debug_capture".to_string(), filename)
+        (self.capture_dir.clone(), filename)
     }
 
     pub async fn save(&self) {
@@ -116,66 +124,86 @@ impl FlightRecorder {
     }
 
     async fn write_flight_to_disk(&self, filename: &str, content: String) {
-        if let Err(e) = tokio::fs::write(filename, content).await {
+        let sealed = match crate::at_rest::seal(content.as_bytes()) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::error!("Failed to seal flight recorder artifact: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = tokio::fs::write(filename, sealed).await {
             tracing::error!("Failed to save flight recorder artifact: {}", e);
         } else {
             tracing::info!("Saved flight recorder artifact to {}", filename);
-            Self::cleanup_old_reports().await;
+            cleanup_reports(&self.capture_dir, REPORT_MAX_FILES, REPORT_MAX_BYTES, None).await;
         }
     }
+}
 
-    async fn cleanup_old_reports() {
-        let dir = "debug_capture";
-        let max_files = 1024;
-        let max_size_bytes = 100 * 1024 * 1024; // 100MB
+/// Flight recorder reports kept in the capture dir before the oldest are dropped.
+pub const REPORT_MAX_FILES: usize = 1024;
+pub const REPORT_MAX_BYTES: u64 = 100 * 1024 * 1024; // 100MB
+
+/// Deletes the oldest report files directly under `dir` until at most `max_files` totalling
+/// `max_size_bytes` remain, plus any older than `max_age`. Subdirectories (the bundles) are
+/// left alone. Returns how many files were removed.
+pub async fn cleanup_reports(
+    dir: &str,
+    max_files: usize,
+    max_size_bytes: u64,
+    max_age: Option<std::time::Duration>,
+) -> usize {
+    let mut entries = Vec::new();
+    let mut read_dir = match tokio::fs::read_dir(dir).await {
+        Ok(rd) => rd,
+        Err(_) => return 0,
+    };
 
-        let mut entries = Vec::new();
-        let mut read_dir = match tokio::fs::read_dir(dir).await {
-            Ok(rd) => rd,
-            Err(_) => return,
+    while let Ok(Some(entry)) = read_dir.next_entry().await {
+        let metadata = match entry.metadata().await {
+            Ok(m) => m,
+            Err(_) => continue,
         };
-
-        while let Ok(Some(entry)) = read_dir.next_entry().await {
-            let metadata = match entry.metadata().await {
+        if metadata.is_file() {
+            let modified = match metadata.modified() {
                 Ok(m) => m,
-                Err(_) => continue,
+                Err(_) => std::time::SystemTime::UNIX_EPOCH,
             };
-            if metadata.is_file() {
-                let modified = match metadata.modified() {
-                    Ok(m) => m,
-                    Err(_) => std::time::SystemTime::UNIX_EPOCH,
-                };
-                entries.push((entry.path(), modified, metadata.len()));
-            }
+            entries.push((entry.path(), modified, metadata.len()));
         }
+    }
 
-        // Sort by modification time (oldest first)
-        entries.sort_by_key(|e| e.1);
+    // Sort by modification time (oldest first)
+    entries.sort_by_key(|e| e.1);
 
-        let mut total_size: u64 = entries.iter().map(|e| e.2).sum();
-        let mut file_count = entries.len();
+    let cutoff = max_age.and_then(|age| std::time::SystemTime::now().checked_sub(age));
+    let mut total_size: u64 = entries.iter().map(|e| e.2).sum();
+    let mut file_count = entries.len();
+    let mut removed = 0;
 
-        for (path, _, size) in entries {
-            if file_count <= max_files && total_size <= max_size_bytes {
-                break;
-            }
+    for (path, modified, size) in entries {
+        let expired = cutoff.is_some_and(|c| modified < c);
+        if !expired && file_count <= max_files && total_size <= max_size_bytes {
+            break;
+        }
 
-            match tokio::fs::remove_file(&path).await {
-                Ok(()) => {
-                    file_count -= 1;
-                    total_size -= size;
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                    // File was already deleted, just update counts
-                    file_count -= 1;
-                    total_size -= size;
-                }
-                Err(e) => {
-                    tracing::error!("Failed to delete old debug report {:?}: {}", path, e);
-                }
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => {
+                file_count -= 1;
+                total_size -= size;
+                removed += 1;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                // File was already deleted, just update counts
+                file_count -= 1;
+                total_size -= size;
+            }
+            Err(e) => {
+                tracing::error!("Failed to delete old debug report {:?}: {}", path, e);
             }
         }
     }
+    removed
 }
 
 #[allow(dead_code)]
@@ -287,6 +315,7 @@ pub fn log_response_choices(body: &Value) {
 }
 
 pub async fn capture_debug_snapshot(
+    capture_dir: &str,
     label: &str,
     model_id: &str,
     conversation_id: &str,
@@ -301,15 +330,15 @@ pub async fn capture_debug_snapshot(
     let safe_cid = str_utils::prefix_chars(conversation_id, 8);
     let safe_rid = str_utils::prefix_chars(request_id, 8);
     let filename = format!(
-        "debug_capture/{}_{}_{}_{}_{}.json",
-        timestamp, label, safe_cid, safe_rid, safe_model
+        "{}/{}_{}_{}_{}_{}.json",
+        capture_dir, timestamp, label, safe_cid, safe_rid, safe_model
     );
 
-    save_snapshot_to_disk(&filename, payload).await;
+    save_snapshot_to_disk(capture_dir, &filename, payload).await;
 }
 
-async fn save_snapshot_to_disk(filename: &str, payload: &Value) {
-    if let Err(e) = tokio::fs::create_dir_all("debug_capture").await {
+async fn save_snapshot_to_disk(capture_dir: &str, filename: &str, payload: &Value) {
+    if let Err(e) = tokio::fs::create_dir_all(capture_dir).await {
         tracing::error!("Failed to create debug_capture directory: {}", e);
         return;
     }
@@ -319,7 +348,9 @@ async fn save_snapshot_to_disk(filename: &str, payload: &Value) {
 
 async fn write_snapshot_to_disk(filename: &str, payload: &Value) {
     if let Ok(content) = serde_json::to_string_pretty(payload) {
-        let _ = tokio::fs::write(filename, content).await;
+        if let Ok(sealed) = crate::at_rest::seal(content.as_bytes()) {
+            let _ = tokio::fs::write(filename, sealed).await;
+        }
     }
 }
 
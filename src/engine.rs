@@ -456,9 +456,12 @@ impl ParallaxEngine {
             sqlx::query("INSERT OR REPLACE INTO tool_signatures (id, conversation_id, signature, reasoning_tokens, thought_signature) VALUES (?1, ?2, ?3, ?4, ?5)")
                 .bind(tool_id)
                 .bind(conversation_id)
-                .bind(sig_json)
+                .bind(crate::at_rest::seal_str(&sig_json)?)
                 .bind(reasoning_tokens)
-                .bind(sig.thought_signature.as_ref())
+                .bind(match &sig.thought_signature {
+                    Some(t) => Some(crate::at_rest::seal_str(t)?),
+                    None => None,
+                })
                 .execute(pool)
                 .await
                 .map_err(ParallaxError::Database)?;
@@ -475,7 +478,10 @@ impl ParallaxEngine {
         .await
         .map_err(ParallaxError::Database)?;
 
-        Ok(row.map(|r| r.0))
+        match row {
+            Some((signature,)) => Ok(Some(crate::at_rest::open_str(&signature)?)),
+            None => Ok(None),
+        }
     }
 
     pub async fn get_context_from_db(
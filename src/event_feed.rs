@@ -0,0 +1,353 @@
+//! Remote dashboard feed.
+//!
+//! [`EventFeed`] mirrors the in-process `TuiEvent` broadcast and keeps a backlog of recent
+//! requests, logs and health state. `/debug/events` serves it as SSE (one JSON `TuiEvent` per
+//! `data:` line): a [`TuiEvent::Resync`], the backlog, then live events; if the feed falls
+//! behind the bus, another resync and backlog follow. `parallax tui --connect <addr>` feeds
+//! that stream into the regular [`crate::tui::App`] via [`connect`].
+
+use crate::constants::{EVENT_FEED_MAX_LOGS, EVENT_FEED_MAX_REQUESTS};
+use crate::tui::TuiEvent;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+pub struct EventFeed {
+    backlog: Mutex<Backlog>,
+    tx: broadcast::Sender<TuiEvent>,
+}
+
+#[derive(Default)]
+struct Backlog {
+    /// Events per request id, oldest request first.
+    requests: VecDeque<(String, Vec<TuiEvent>)>,
+    logs: VecDeque<TuiEvent>,
+    circuits: BTreeMap<String, TuiEvent>,
+    health: Option<TuiEvent>,
+}
+
+impl Backlog {
+    fn record(&mut self, event: &TuiEvent) {
+        match event {
+            TuiEvent::RequestStarted { id, .. } => {
+                self.requests.push_back((id.clone(), vec![event.clone()]));
+                while self.requests.len() > EVENT_FEED_MAX_REQUESTS {
+                    self.requests.pop_front();
+                }
+            }
+            TuiEvent::StreamUpdate {
+                id,
+                content_delta,
+                tool_call,
+            } => {
+                let events = match self.request_events(id) {
+                    Some(e) => e,
+                    None => return,
+                };
+                // Deltas are coalesced so a long stream costs one backlog entry.
+                if let Some(TuiEvent::StreamUpdate {
+                    content_delta: pending,
+                    tool_call: pending_tool,
+                    ..
+                }) = events.last_mut()
+                {
+                    pending.push_str(content_delta);
+                    if tool_call.is_some() {
+                        pending_tool.clone_from(tool_call);
+                    }
+                    return;
+                }
+                events.push(event.clone());
+            }
+            TuiEvent::RequestDetail { id, detail } => {
+                let events = match self.request_events(id) {
+                    Some(e) => e,
+                    None => return,
+                };
+                if let Some(TuiEvent::RequestDetail {
+                    detail: pending, ..
+                }) = events.last_mut()
+                {
+                    if pending.absorb(detail) {
+                        return;
+                    }
+                }
+                events.push(event.clone());
+            }
+            TuiEvent::RequestFinished { id, .. }
+            | TuiEvent::CostUpdate { id, .. }
+            | TuiEvent::StreamError { id, .. } => {
+                if let Some(events) = self.request_events(id) {
+                    events.push(event.clone());
+                }
+            }
+            TuiEvent::LogMessage { .. } => {
+                self.logs.push_back(event.clone());
+                while self.logs.len() > EVENT_FEED_MAX_LOGS {
+                    self.logs.pop_front();
+                }
+            }
+            TuiEvent::CircuitStateUpdate {
+                upstream, model, ..
+            } => {
+                self.circuits
+                    .insert(format!("{}/{}", upstream, model), event.clone());
+            }
+            TuiEvent::UpstreamHealthUpdate { .. } => self.health = Some(event.clone()),
+            TuiEvent::ServerPulse { .. } | TuiEvent::Resync => {}
+        }
+    }
+
+    fn request_events(&mut self, id: &str) -> Option<&mut Vec<TuiEvent>> {
+        self.requests
+            .iter_mut()
+            .rev()
+            .find(|(request_id, _)| request_id == id)
+            .map(|(_, events)| events)
+    }
+
+    fn snapshot(&self) -> Vec<TuiEvent> {
+        let mut events = vec![TuiEvent::Resync];
+        events.extend(self.logs.iter().cloned());
+        events.extend(self.health.iter().cloned());
+        events.extend(self.circuits.values().cloned());
+        for (_, request) in &self.requests {
+            events.extend(request.iter().cloned());
+        }
+        events
+    }
+}
+
+impl EventFeed {
+    /// Starts mirroring `tx_tui`; must be called inside a Tokio runtime.
+    pub fn spawn(tx_tui: &broadcast::Sender<TuiEvent>) -> Arc<Self> {
+        let feed = Arc::new(Self {
+            backlog: Mutex::new(Backlog::default()),
+            tx: broadcast::channel(1024).0,
+        });
+        let mut rx = tx_tui.subscribe();
+        let publisher = feed.clone();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) => publisher.publish(event),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => publisher.resync(missed),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        feed
+    }
+
+    fn backlog(&self) -> std::sync::MutexGuard<'_, Backlog> {
+        match self.backlog.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn publish(&self, event: TuiEvent) {
+        // Recording and sending under one lock keeps `subscribe` free of gaps and duplicates.
+        let mut backlog = self.backlog();
+        backlog.record(&event);
+        let _ = self.tx.send(event);
+    }
+
+    /// The mirror fell behind and lost `missed` events. Subscribers get a fresh resync and
+    /// backlog, so their view is rebuilt from the feed's instead of silently missing events.
+    fn resync(&self, missed: u64) {
+        tracing::warn!("Dashboard feed fell behind; dropped {} events", missed);
+        let mut backlog = self.backlog();
+        backlog.record(&TuiEvent::LogMessage {
+            level: "WARN".to_string(),
+            target: "parallax::event_feed".to_string(),
+            message: format!("Dashboard feed fell behind and dropped {} events", missed),
+            timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+        });
+        for event in backlog.snapshot() {
+            let _ = self.tx.send(event);
+        }
+    }
+
+    /// The catch-up events (starting with [`TuiEvent::Resync`]) and a receiver for what follows.
+    pub fn subscribe(&self) -> (Vec<TuiEvent>, broadcast::Receiver<TuiEvent>) {
+        let backlog = self.backlog();
+        (backlog.snapshot(), self.tx.subscribe())
+    }
+}
+
+/// Base URL of the instance a `--connect` address (`host:port` or a base URL) points at.
+pub fn base_url(addr: &str) -> String {
+    let base = addr.trim_end_matches('/');
+    if base.starts_with("http://") || base.starts_with("https://") {
+        base.to_string()
+    } else {
+        format!("http://{}", base)
+    }
+}
+
+/// `/debug/events` URL for a `--connect` address.
+pub fn events_url(addr: &str) -> String {
+    format!("{}/debug/events", base_url(addr))
+}
+
+/// Forwards a remote instance's `/debug/events` into `tx`, reconnecting until `tx` has no
+/// receivers left. Each connection starts with a resync, so the view never double counts.
+pub async fn connect(addr: String, tx: broadcast::Sender<TuiEvent>) {
+    use futures_util::StreamExt;
+    use tokio_util::codec::{FramedRead, LinesCodec};
+
+    let url = events_url(&addr);
+    let client = reqwest::Client::new();
+    let mut retry_delay = std::time::Duration::from_millis(500);
+    while tx.receiver_count() > 0 {
+        let error = match client.get(&url).send().await {
+            Ok(response) if response.status().is_success() => {
+                retry_delay = std::time::Duration::from_millis(500);
+                let bytes = response
+                    .bytes_stream()
+                    .map(|chunk| chunk.map_err(std::io::Error::other));
+                let mut lines = FramedRead::new(
+                    tokio_util::io::StreamReader::new(bytes),
+                    LinesCodec::new_with_max_length(crate::constants::EVENT_FEED_MAX_LINE_BYTES),
+                );
+                while let Some(line) = lines.next().await {
+                    let line = match line {
+                        Ok(l) => l,
+                        Err(_) => break,
+                    };
+                    let data = match line.strip_prefix("data:") {
+                        Some(d) => d.trim_start(),
+                        None => continue,
+                    };
+                    if let Ok(event) = serde_json::from_str::<TuiEvent>(data) {
+                        if tx.send(event).is_err() {
+                            return;
+                        }
+                    }
+                }
+                "event stream closed".to_string()
+            }
+            Ok(response) => format!("HTTP {}", response.status()),
+            Err(e) => e.to_string(),
+        };
+        let _ = tx.send(TuiEvent::LogMessage {
+            level: "WARN".to_string(),
+            target: "parallax::event_feed".to_string(),
+            message: format!("{}: {}; reconnecting", url, error),
+            timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+        });
+        tokio::time::sleep(retry_delay).await;
+        retry_delay = (retry_delay * 2).min(std::time::Duration::from_secs(10));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started(id: &str) -> TuiEvent {
+        TuiEvent::RequestStarted {
+            id: id.to_string(),
+            cid: format!("cid-{}", id),
+            method: "POST".to_string(),
+            model: "openai/gpt-4o".to_string(),
+            intent: None,
+        }
+    }
+
+    fn delta(id: &str, text: &str) -> TuiEvent {
+        TuiEvent::StreamUpdate {
+            id: id.to_string(),
+            content_delta: text.to_string(),
+            tool_call: None,
+        }
+    }
+
+    #[test]
+    fn test_backlog_coalesces_deltas_and_keeps_recent_requests() {
+        let mut backlog = Backlog::default();
+        for i in 0..EVENT_FEED_MAX_REQUESTS + 3 {
+            backlog.record(&started(&i.to_string()));
+        }
+        let last = (EVENT_FEED_MAX_REQUESTS + 2).to_string();
+        backlog.record(&delta(&last, "Hel"));
+        backlog.record(&delta(&last, "lo"));
+        backlog.record(&TuiEvent::RequestFinished {
+            id: last.clone(),
+            status: 200,
+            latency_ms: 12,
+        });
+
+        let snapshot = backlog.snapshot();
+        assert!(matches!(snapshot[0], TuiEvent::Resync));
+        let started_ids: Vec<&str> = snapshot
+            .iter()
+            .filter_map(|e| match e {
+                TuiEvent::RequestStarted { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(started_ids.len(), EVENT_FEED_MAX_REQUESTS);
+        assert_eq!(started_ids[0], "3");
+
+        let tail = &snapshot[snapshot.len() - 3..];
+        assert!(
+            matches!(tail[0], TuiEvent::RequestStarted { .. }),
+            "{:?}",
+            tail
+        );
+        assert!(
+            matches!(tail[2], TuiEvent::RequestFinished { .. }),
+            "{:?}",
+            tail
+        );
+        match &snapshot[snapshot.len() - 2] {
+            TuiEvent::StreamUpdate { content_delta, .. } => assert_eq!(content_delta, "Hello"),
+            other => panic!("expected a stream update, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lagging_mirror_resyncs_subscribers() {
+        let (tx_tui, _) = broadcast::channel(4);
+        let feed = EventFeed::spawn(&tx_tui);
+        let (_, mut rx) = feed.subscribe();
+        // The mirror task only runs once this test yields, so it falls behind.
+        for i in 0..10 {
+            let _ = tx_tui.send(started(&i.to_string()));
+        }
+
+        let mut resync = Vec::new();
+        while let Ok(Ok(event)) =
+            tokio::time::timeout(std::time::Duration::from_secs(1), rx.recv()).await
+        {
+            if matches!(event, TuiEvent::Resync) || !resync.is_empty() {
+                resync.push(event);
+            }
+            if matches!(resync.last(), Some(TuiEvent::RequestStarted { id, .. }) if id == "9") {
+                break;
+            }
+        }
+        assert!(
+            matches!(resync.first(), Some(TuiEvent::Resync)),
+            "{:?}",
+            resync
+        );
+        assert!(resync
+            .iter()
+            .any(|e| matches!(e, TuiEvent::LogMessage { message, .. } if message.contains("dropped 6 events"))));
+    }
+
+    #[test]
+    fn test_events_url_accepts_host_port_and_base_urls() {
+        assert_eq!(
+            events_url("127.0.0.1:8080"),
+            "http://127.0.0.1:8080/debug/events"
+        );
+        assert_eq!(
+            events_url("https://proxy.example/"),
+            "https://proxy.example/debug/events"
+        );
+    }
+}
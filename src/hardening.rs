@@ -1,18 +1,24 @@
-use crate::constants::{DIFF_MARKERS, FORBIDDEN_PLAN_TERMS};
+use crate::constants::{DIFF_MARKERS, RETRY_BUDGET_SECS, RETRY_MAX_DELAY_MS};
 use crate::tag_extract::TagRegistry;
-use crate::types::{ParallaxError, Result};
+use crate::tui::TuiEvent;
+use crate::types::{ObservedError, ParallaxError, ProviderError, ProviderErrorDetails, Result};
 use axum::http as ax_http;
-use serde_json::{Map, Value};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
 use std::future::Future;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use std::time::Instant;
-use tokio::sync::RwLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, RwLock};
 
 pub struct RetryPolicy {
     pub max_attempts: u32,
     pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// Upper bound on the total time spent retrying (backoff sleeps included).
+    pub budget: Duration,
 }
 
 impl RetryPolicy {
@@ -20,36 +26,70 @@ impl RetryPolicy {
         Self {
             max_attempts,
             base_delay_ms,
+            max_delay_ms: RETRY_MAX_DELAY_MS,
+            budget: Duration::from_secs(RETRY_BUDGET_SECS),
         }
     }
 
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    pub fn with_max_delay_ms(mut self, max_delay_ms: u64) -> Self {
+        self.max_delay_ms = max_delay_ms;
+        self
+    }
+
+    /// Full-jitter exponential backoff: a uniform delay in `[0, min(cap, base * 2^(n-1))]`.
+    /// A server-provided hint (`Retry-After`, rate-limit reset) takes precedence, with a
+    /// little jitter on top so concurrent requests do not all wake at once.
+    pub fn backoff_delay(&self, attempt: u32, hint: Option<Duration>) -> Duration {
+        if let Some(hint) = hint {
+            return hint + Duration::from_millis(fastrand::u64(0..=self.base_delay_ms));
+        }
+        let exponent = attempt.saturating_sub(1).min(20);
+        let ceiling = self
+            .base_delay_ms
+            .saturating_mul(1u64 << exponent)
+            .min(self.max_delay_ms);
+        Duration::from_millis(fastrand::u64(0..=ceiling))
+    }
+
     pub async fn execute_with_retry<F, Fut, T>(&self, mut operation: F) -> Result<T>
     where
         F: FnMut() -> Fut,
         Fut: Future<Output = Result<T>>,
     {
+        let started = Instant::now();
         let mut attempts = 0;
         loop {
             attempts += 1;
             match operation().await {
                 Ok(val) => return Ok(val),
                 Err(e) if attempts < self.max_attempts && self.is_retryable(&e) => {
-                    let base_delay = self.base_delay_ms * 2u64.pow(attempts - 1);
-                    // Add jitter: ±25% of the base delay
-                    let jitter_range = base_delay / 4;
-                    let jitter = if jitter_range > 0 {
-                        fastrand::i64(-(jitter_range as i64)..jitter_range as i64)
-                    } else {
-                        0
-                    };
-                    let final_delay_ms = (base_delay as i64 + jitter).max(1) as u64;
-                    let delay = Duration::from_millis(final_delay_ms);
+                    let delay = self.backoff_delay(attempts, e.retry_after);
+                    if started.elapsed() + delay > self.budget {
+                        tracing::warn!(
+                            "Request failed (attempt {}): {}. Retry budget of {:?} exhausted (next delay {:?}), giving up",
+                            attempts,
+                            e,
+                            self.budget,
+                            delay
+                        );
+                        return Err(e);
+                    }
 
                     tracing::warn!(
-                        "Request failed (attempt {}): {}. Retrying in {:?} (jittered)...",
+                        "Request failed (attempt {}): {}. Retrying in {:?}{}...",
                         attempts,
                         e,
-                        delay
+                        delay,
+                        if e.retry_after.is_some() {
+                            " (server hint)"
+                        } else {
+                            " (jittered)"
+                        }
                     );
                     tokio::time::sleep(delay).await;
                 }
@@ -58,6 +98,24 @@ impl RetryPolicy {
         }
     }
 
+    /// Delay before re-issuing a stream that failed mid-flight, or `None` when it must not
+    /// be retried. Once any content has been forwarded to the client a retry would splice
+    /// a second completion onto the first, so those streams are never retried.
+    pub fn stream_retry_delay(
+        &self,
+        forwarded_to_client: bool,
+        hint: Option<Duration>,
+    ) -> Option<Duration> {
+        if forwarded_to_client || self.max_attempts <= 1 {
+            return None;
+        }
+        let delay = self.backoff_delay(1, hint);
+        if delay > self.budget {
+            return None;
+        }
+        Some(delay)
+    }
+
     fn is_retryable(&self, err: &crate::types::ObservedError) -> bool {
         match &err.inner {
             ParallaxError::Network(_) | ParallaxError::Io(_) | ParallaxError::Internal(_, _) => {
@@ -71,19 +129,148 @@ impl RetryPolicy {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Parses a `Retry-After` value: either delta-seconds or an HTTP-date.
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        if secs.is_finite() && secs >= 0.0 {
+            return Some(Duration::from_secs_f64(secs));
+        }
+        return None;
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let target = UNIX_EPOCH + Duration::from_millis(date.timestamp_millis().max(0) as u64);
+    Some(match target.duration_since(now) {
+        Ok(d) => d,
+        Err(_) => Duration::ZERO,
+    })
+}
+
+/// Parses an `X-RateLimit-Reset` value. OpenRouter sends an epoch timestamp in
+/// milliseconds; some providers send epoch seconds or a delta in seconds.
+pub fn parse_rate_limit_reset(value: &str, now: SystemTime) -> Option<Duration> {
+    let raw = value.trim().parse::<f64>().ok()?;
+    if !raw.is_finite() || raw < 0.0 {
+        return None;
+    }
+    let target = if raw >= 1e12 {
+        UNIX_EPOCH + Duration::from_millis(raw as u64)
+    } else if raw >= 1e9 {
+        UNIX_EPOCH + Duration::from_secs_f64(raw)
+    } else {
+        return Some(Duration::from_secs_f64(raw));
+    };
+    Some(match target.duration_since(now) {
+        Ok(d) => d,
+        Err(_) => Duration::ZERO,
+    })
+}
+
+fn retry_hint_from_lookup(
+    lookup: impl Fn(&str) -> Option<String>,
+    now: SystemTime,
+) -> Option<Duration> {
+    if let Some(value) = lookup("retry-after") {
+        if let Some(d) = parse_retry_after(&value, now) {
+            return Some(d);
+        }
+    }
+    // Only trust the reset time when the window is actually exhausted (or unknown).
+    let exhausted = match lookup("x-ratelimit-remaining") {
+        Some(remaining) => remaining.trim() == "0",
+        None => true,
+    };
+    if exhausted {
+        if let Some(value) = lookup("x-ratelimit-reset") {
+            return parse_rate_limit_reset(&value, now);
+        }
+    }
+    None
+}
+
+/// Retry hint from upstream response headers.
+pub fn retry_after_from_headers(headers: &ax_http::HeaderMap) -> Option<Duration> {
+    retry_hint_from_lookup(
+        |name| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        },
+        SystemTime::now(),
+    )
+}
+
+/// Retry hint from an OpenRouter error payload: rate-limit headers forwarded under
+/// `metadata.headers`, or an explicit `retry_after` in the metadata/extra fields.
+pub fn retry_after_from_provider_error(details: &ProviderErrorDetails) -> Option<Duration> {
+    let now = SystemTime::now();
+    let value_to_string = |v: &Value| match v {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+
+    if let Some(Value::Object(headers)) = details.metadata.as_ref().and_then(|m| m.get("headers")) {
+        let hint = retry_hint_from_lookup(
+            |name| {
+                headers
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                    .and_then(|(_, v)| value_to_string(v))
+            },
+            now,
+        );
+        if hint.is_some() {
+            return hint;
+        }
+    }
+
+    let explicit = details
+        .metadata
+        .as_ref()
+        .and_then(|m| m.get("retry_after"))
+        .or_else(|| details.extra.get("retry_after"))
+        .and_then(value_to_string)?;
+    parse_retry_after(&explicit, now)
+}
+
+/// Retry hint for a failed upstream HTTP response, from its headers or its error body.
+pub fn retry_after_for_response(headers: &ax_http::HeaderMap, body: &str) -> Option<Duration> {
+    if let Some(hint) = retry_after_from_headers(headers) {
+        return Some(hint);
+    }
+    let err = serde_json::from_str::<ProviderError>(body).ok()?;
+    retry_after_from_provider_error(&err.error)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CircuitState {
     Closed,
     Open,
     HalfOpen,
 }
 
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
 pub struct CircuitBreaker {
     state: Arc<RwLock<CircuitState>>,
     failure_threshold: u32,
     recovery_timeout: Duration,
     consecutive_failures: Arc<AtomicU32>,
     last_failure_time: Arc<RwLock<Option<Instant>>>,
+    /// Id of the request holding the half-open probe slot; 0 when the slot is free.
+    probe_owner: Arc<AtomicU64>,
+    next_probe: AtomicU64,
 }
 
 impl CircuitBreaker {
@@ -94,54 +281,91 @@ impl CircuitBreaker {
             recovery_timeout,
             consecutive_failures: Arc::new(AtomicU32::new(0)),
             last_failure_time: Arc::new(RwLock::new(None)),
+            probe_owner: Arc::new(AtomicU64::new(0)),
+            next_probe: AtomicU64::new(1),
         }
     }
 
-    pub async fn check(&self) -> Result<()> {
+    /// Admits a request; `Some(probe)` when it was admitted as the half-open probe.
+    pub async fn check(&self) -> Result<Option<u64>> {
         let mut state = self.state.write().await;
         self.check_locked(&mut state).await
     }
 
-    pub async fn check_locked(&self, state: &mut CircuitState) -> Result<()> {
-        if *state == CircuitState::Open {
-            let last_failure = match self.last_failure_time.try_read() {
-                Ok(last) => last,
-                Err(_) => {
-                    tracing::warn!(
-                        "Could not read last failure time for circuit breaker, assuming still OPEN"
-                    );
-                    return Err(ParallaxError::Upstream(
-                        axum::http::StatusCode::SERVICE_UNAVAILABLE,
-                        "Circuit breaker is OPEN (failure time locked)".to_string(),
+    /// Closed admits everything. Open rejects until the recovery timeout elapses, then
+    /// moves to Half-Open and admits a single probe; further requests are rejected until
+    /// that probe records a success (close) or a failure (re-open), or is released.
+    pub async fn check_locked(&self, state: &mut CircuitState) -> Result<Option<u64>> {
+        match *state {
+            CircuitState::Closed => Ok(None),
+            CircuitState::HalfOpen => {
+                let probe = self.next_probe.fetch_add(1, Ordering::Relaxed);
+                if self
+                    .probe_owner
+                    .compare_exchange(0, probe, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+                {
+                    Ok(Some(probe))
+                } else {
+                    Err(ParallaxError::CircuitOpen(
+                        "Circuit breaker is HALF-OPEN (probe in flight)".to_string(),
                     )
-                    .into());
-                }
-            };
-
-            if let Some(last) = *last_failure {
-                if last.elapsed() > self.recovery_timeout {
-                    tracing::info!("Circuit breaker transitioning to Half-Open");
-                    *state = CircuitState::HalfOpen;
-                    return Ok(());
+                    .into())
                 }
             }
+            CircuitState::Open => {
+                let last_failure = match self.last_failure_time.try_read() {
+                    Ok(last) => last,
+                    Err(_) => {
+                        tracing::warn!(
+                            "Could not read last failure time for circuit breaker, assuming still OPEN"
+                        );
+                        return Err(ParallaxError::CircuitOpen(
+                            "Circuit breaker is OPEN (failure time locked)".to_string(),
+                        )
+                        .into());
+                    }
+                };
+
+                if let Some(last) = *last_failure {
+                    if last.elapsed() > self.recovery_timeout {
+                        tracing::info!("Circuit breaker transitioning to Half-Open");
+                        *state = CircuitState::HalfOpen;
+                        let probe = self.next_probe.fetch_add(1, Ordering::Relaxed);
+                        self.probe_owner.store(probe, Ordering::Release);
+                        return Ok(Some(probe));
+                    }
+                }
 
-            return Err(ParallaxError::Upstream(
-                ax_http::StatusCode::SERVICE_UNAVAILABLE,
-                "Circuit breaker is OPEN".to_string(),
-            )
-            .into());
+                Err(ParallaxError::CircuitOpen("Circuit breaker is OPEN".to_string()).into())
+            }
         }
-        Ok(())
     }
 
     pub async fn state_raw_lock(&self) -> tokio::sync::RwLockReadGuard<'_, CircuitState> {
         self.state.read().await
     }
 
+    pub async fn state(&self) -> CircuitState {
+        *self.state.read().await
+    }
+
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    /// Frees the half-open probe slot without counting the outcome either way, but only while
+    /// `probe` still holds it.
+    pub fn release_probe(&self, probe: u64) {
+        let _ = self
+            .probe_owner
+            .compare_exchange(probe, 0, Ordering::AcqRel, Ordering::Acquire);
+    }
+
     pub async fn record_success(&self) {
         let mut state = self.state.write().await;
         self.consecutive_failures.store(0, Ordering::Relaxed);
+        self.probe_owner.store(0, Ordering::Release);
         if *state != CircuitState::Closed {
             tracing::info!("Circuit breaker transitioning to CLOSED");
             *state = CircuitState::Closed;
@@ -152,8 +376,12 @@ impl CircuitBreaker {
         let mut state = self.state.write().await;
         let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
         *self.last_failure_time.write().await = Some(Instant::now());
+        self.probe_owner.store(0, Ordering::Release);
 
-        if failures >= self.failure_threshold && *state != CircuitState::Open {
+        if *state == CircuitState::HalfOpen {
+            tracing::error!("Circuit breaker probe failed; transitioning back to OPEN");
+            *state = CircuitState::Open;
+        } else if failures >= self.failure_threshold && *state != CircuitState::Open {
             tracing::error!(
                 "Circuit breaker transitioning to OPEN ({} consecutive failures)",
                 failures
@@ -163,67 +391,261 @@ impl CircuitBreaker {
     }
 }
 
-pub fn sanitize_tool_call(name: &str, args: &mut serde_json::Value) {
-    match name {
-        "grep" => sanitize_grep_args(args),
-        "create_plan" => sanitize_plan_args(args),
-        _ => {}
+/// Breaker key for failures that are not attributable to one model (transport errors).
+pub const ANY_MODEL: &str = "*";
+
+/// Which breaker a failed upstream request counts against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitFault {
+    /// Connection or I/O failure: the upstream-wide breaker.
+    Transport,
+    /// 5xx or 429 from the upstream: the model's breaker.
+    Model,
+    /// Client errors and breaker rejections are not counted; a half-open probe the request
+    /// held is freed when its [`CircuitPermit`] is released.
+    NotCounted,
+}
+
+impl CircuitFault {
+    pub fn of(err: &ObservedError) -> Self {
+        match &err.inner {
+            ParallaxError::Network(_) | ParallaxError::Io(_) => CircuitFault::Transport,
+            ParallaxError::Upstream(status, _)
+                if status.is_server_error()
+                    || *status == ax_http::StatusCode::TOO_MANY_REQUESTS =>
+            {
+                CircuitFault::Model
+            }
+            _ => CircuitFault::NotCounted,
+        }
     }
 }
 
-fn sanitize_grep_args(args: &mut Value) {
-    if let Value::Object(map) = args {
-        // Fix mutual exclusivity: If -A or -B is set (and > 0), remove -C if it is 0
-        let has_a = has_positive_value(map, "-A");
-        let has_b = has_positive_value(map, "-B");
-        let c_is_zero = is_zero(map, "-C");
+/// The half-open probes one admitted request holds, by breaker key.
+///
+/// Whoever receives a permit must release it once the request is done, whatever the outcome;
+/// releasing never frees a probe slot that has since passed to another request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CircuitPermit {
+    pub upstream: String,
+    pub probes: Vec<(String, u64)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitSnapshot {
+    pub upstream: String,
+    pub model: String,
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+}
+
+/// Circuit breakers keyed by upstream and model id.
+///
+/// Each model gets its own breaker so one misbehaving model does not take the others
+/// down with it. Transport-level failures (connection errors, I/O) are recorded on an
+/// upstream-wide breaker (`model = "*"`) which gates every model on that upstream.
+pub struct CircuitBreakerRegistry {
+    failure_threshold: u32,
+    recovery_timeout: Duration,
+    breakers: RwLock<HashMap<(String, String), Arc<CircuitBreaker>>>,
+    tx_tui: Option<broadcast::Sender<TuiEvent>>,
+}
 
-        if (has_a || has_b) && c_is_zero {
-            map.remove("-C");
+impl CircuitBreakerRegistry {
+    pub fn new(failure_threshold: u32, recovery_timeout: Duration) -> Self {
+        Self {
+            failure_threshold,
+            recovery_timeout,
+            breakers: RwLock::new(HashMap::new()),
+            tx_tui: None,
         }
     }
-}
 
-fn sanitize_plan_args(args: &mut Value) {
-    if let Value::Object(map) = args {
-        // Extract title first before any mutable borrows
-        let title = match map.get("name").and_then(|v| v.as_str()) {
-            Some(s) if !s.is_empty() => s.to_string(),
-            _ => "Implementation Plan".to_string(),
-        };
+    /// Publish state transitions to the TUI.
+    pub fn with_tui(mut self, tx_tui: broadcast::Sender<TuiEvent>) -> Self {
+        self.tx_tui = Some(tx_tui);
+        self
+    }
 
-        // Ensure plan has a proper H1 title if missing
-        if let Some(Value::String(plan)) = map.get_mut("plan") {
-            let trimmed_plan = plan.trim();
-            if !trimmed_plan.starts_with("# ") {
-                // Prepend the title as H1
-                *plan = format!("# {}\n\n{}", title, plan);
+    pub async fn breaker(&self, upstream: &str, model: &str) -> Arc<CircuitBreaker> {
+        let key = (upstream.to_string(), model.to_string());
+        if let Some(existing) = self.breakers.read().await.get(&key) {
+            return existing.clone();
+        }
+        self.breakers
+            .write()
+            .await
+            .entry(key)
+            .or_insert_with(|| {
+                Arc::new(CircuitBreaker::new(
+                    self.failure_threshold,
+                    self.recovery_timeout,
+                ))
+            })
+            .clone()
+    }
+
+    async fn existing(&self, upstream: &str, model: &str) -> Option<Arc<CircuitBreaker>> {
+        self.breakers
+            .read()
+            .await
+            .get(&(upstream.to_string(), model.to_string()))
+            .cloned()
+    }
+
+    /// Admits a request for `model` if both the model and the upstream-wide breaker allow it.
+    ///
+    /// The model breaker is checked first; if the wide breaker then rejects, a probe the model
+    /// breaker just handed out is given back, so no slot is left held by a rejected request.
+    pub async fn check(&self, upstream: &str, model: &str) -> Result<CircuitPermit> {
+        let mut permit = CircuitPermit {
+            upstream: upstream.to_string(),
+            probes: Vec::new(),
+        };
+        let breaker = self.breaker(upstream, model).await;
+        let before = breaker.state().await;
+        let result = breaker.check().await;
+        self.emit_if_changed(upstream, model, &breaker, before)
+            .await;
+        match result {
+            Ok(Some(probe)) => permit.probes.push((model.to_string(), probe)),
+            Ok(None) => {}
+            Err(_) => {
+                return Err(ParallaxError::CircuitOpen(format!("{}/{}", upstream, model)).into())
             }
         }
 
-        // Clean up any forbidden terms that might cause execution failures
-        if let Some(Value::String(plan)) = map.get_mut("plan") {
-            for (forbidden, replacement) in FORBIDDEN_PLAN_TERMS {
-                if plan.contains(forbidden) {
-                    *plan = plan.replace(forbidden, replacement);
+        if let Some(wide) = self.existing(upstream, ANY_MODEL).await {
+            let before = wide.state().await;
+            let result = wide.check().await;
+            self.emit_if_changed(upstream, ANY_MODEL, &wide, before)
+                .await;
+            match result {
+                Ok(Some(probe)) => permit.probes.push((ANY_MODEL.to_string(), probe)),
+                Ok(None) => {}
+                Err(_) => {
+                    self.release(&permit).await;
+                    return Err(ParallaxError::CircuitOpen(upstream.to_string()).into());
                 }
             }
         }
+        Ok(permit)
+    }
+
+    pub async fn record_success(&self, upstream: &str, model: &str) {
+        for key in [model, ANY_MODEL] {
+            if let Some(breaker) = self.existing(upstream, key).await {
+                let before = breaker.state().await;
+                breaker.record_success().await;
+                self.emit_if_changed(upstream, key, &breaker, before).await;
+            }
+        }
+    }
+
+    pub async fn record_failure(&self, upstream: &str, model: &str) {
+        let breaker = self.breaker(upstream, model).await;
+        let before = breaker.state().await;
+        breaker.record_failure().await;
+        self.emit_if_changed(upstream, model, &breaker, before)
+            .await;
+    }
+
+    /// Gives back the probes `permit` still holds; a no-op once an outcome was recorded.
+    pub async fn release(&self, permit: &CircuitPermit) {
+        for (key, probe) in &permit.probes {
+            if let Some(breaker) = self.existing(&permit.upstream, key).await {
+                breaker.release_probe(*probe);
+            }
+        }
     }
-}
 
-fn has_positive_value(map: &Map<String, Value>, key: &str) -> bool {
-    match map.get(key).and_then(|v| v.as_u64()) {
-        Some(v) => v > 0,
-        None => false,
+    /// Records `err` against the breaker it is attributable to.
+    pub async fn record_error(&self, upstream: &str, model: &str, err: &ObservedError) {
+        self.record_fault(upstream, model, CircuitFault::of(err))
+            .await
+    }
+
+    pub async fn record_fault(&self, upstream: &str, model: &str, fault: CircuitFault) {
+        match fault {
+            CircuitFault::Transport => self.record_failure(upstream, ANY_MODEL).await,
+            CircuitFault::Model => self.record_failure(upstream, model).await,
+            CircuitFault::NotCounted => {}
+        }
+    }
+
+    pub async fn snapshot(&self) -> Vec<CircuitSnapshot> {
+        let breakers: Vec<_> = self
+            .breakers
+            .read()
+            .await
+            .iter()
+            .map(|((upstream, model), b)| (upstream.clone(), model.clone(), b.clone()))
+            .collect();
+        let mut out = Vec::with_capacity(breakers.len());
+        for (upstream, model, breaker) in breakers {
+            out.push(CircuitSnapshot {
+                upstream,
+                model,
+                state: breaker.state().await,
+                consecutive_failures: breaker.consecutive_failures(),
+            });
+        }
+        out.sort_by(|a, b| (&a.upstream, &a.model).cmp(&(&b.upstream, &b.model)));
+        out
+    }
+
+    /// True when the upstream-wide breaker is open, i.e. no model can be served.
+    pub async fn upstream_open(&self, upstream: &str) -> bool {
+        match self.existing(upstream, ANY_MODEL).await {
+            Some(b) => b.state().await == CircuitState::Open,
+            None => false,
+        }
+    }
+
+    async fn emit_if_changed(
+        &self,
+        upstream: &str,
+        model: &str,
+        breaker: &CircuitBreaker,
+        before: CircuitState,
+    ) {
+        let after = breaker.state().await;
+        if after == before {
+            return;
+        }
+        tracing::warn!(
+            "[⚙️ ] Circuit {}/{}: {} -> {}",
+            upstream,
+            model,
+            before.as_str(),
+            after.as_str()
+        );
+        if let Some(tx) = &self.tx_tui {
+            let _ = tx.send(TuiEvent::CircuitStateUpdate {
+                upstream: upstream.to_string(),
+                model: model.to_string(),
+                state: after.as_str().to_string(),
+                consecutive_failures: breaker.consecutive_failures(),
+            });
+        }
     }
 }
 
-fn is_zero(map: &Map<String, Value>, key: &str) -> bool {
-    match map.get(key).and_then(|v| v.as_u64()) {
-        Some(v) => v == 0,
-        None => false,
+/// Runs the installed sanitizer rules (see [`crate::sanitizer_rules`]) on a tool call's arguments.
+pub fn sanitize_tool_call(
+    name: &str,
+    args: &mut serde_json::Value,
+) -> Vec<crate::sanitizer_rules::RuleApplication> {
+    let applied = crate::sanitizer_rules::rules().apply(name, args);
+    for application in &applied {
+        tracing::debug!(
+            "[⚙️ ] Sanitizer rule '{}' on '{}': {}",
+            application.rule,
+            application.tool,
+            application.change
+        );
     }
+    applied
 }
 
 pub fn is_diff_like(text: &str) -> bool {
@@ -466,6 +888,91 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_parse_retry_after_seconds_and_http_date() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_445_412_470); // Wed, 21 Oct 2015 07:27:50 GMT
+        assert_eq!(parse_retry_after("7", now), Some(Duration::from_secs(7)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_retry_after("-3", now), None);
+    }
+
+    #[test]
+    fn test_parse_rate_limit_reset_forms() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            parse_rate_limit_reset("1700000002500", now),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(
+            parse_rate_limit_reset("1700000003", now),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            parse_rate_limit_reset("4", now),
+            Some(Duration::from_secs(4))
+        );
+    }
+
+    #[test]
+    fn test_retry_after_from_openrouter_error_metadata() {
+        let body = json!({
+            "error": {
+                "message": "Rate limit exceeded",
+                "code": 429,
+                "metadata": {
+                    "headers": {
+                        "X-RateLimit-Limit": "20",
+                        "X-RateLimit-Remaining": "0",
+                        "Retry-After": "3"
+                    }
+                }
+            }
+        })
+        .to_string();
+        assert_eq!(
+            retry_after_for_response(&ax_http::HeaderMap::new(), &body),
+            Some(Duration::from_secs(3))
+        );
+
+        let mut headers = ax_http::HeaderMap::new();
+        headers.insert("retry-after", ax_http::HeaderValue::from_static("1"));
+        assert_eq!(
+            retry_after_for_response(&headers, &body),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_backoff_is_full_jitter_and_capped() {
+        let policy = RetryPolicy::new(5, 100).with_max_delay_ms(250);
+        for attempt in 1..=6 {
+            let delay = policy.backoff_delay(attempt, None);
+            assert!(delay <= Duration::from_millis(250));
+        }
+        let hinted = policy.backoff_delay(1, Some(Duration::from_secs(2)));
+        assert!(hinted >= Duration::from_secs(2));
+        assert!(hinted <= Duration::from_millis(2100));
+    }
+
+    #[test]
+    fn test_stream_retry_never_after_forwarding() {
+        let policy = RetryPolicy::new(3, 10);
+        assert!(policy.stream_retry_delay(true, None).is_none());
+        assert!(policy.stream_retry_delay(false, None).is_some());
+        let tight = RetryPolicy::new(3, 10).with_budget(Duration::from_secs(1));
+        assert!(tight
+            .stream_retry_delay(false, Some(Duration::from_secs(5)))
+            .is_none());
+    }
+
     #[test]
     fn test_scrub_cursor_tags_simple() {
         let input = "Hello <user_query>What is up?</user_query> more <system_reminder>secret</system_reminder> end";
@@ -1,3 +1,5 @@
+use crate::constants::UPSTREAM_OPENROUTER;
+use crate::hardening::CircuitSnapshot;
 use crate::redaction::{redact_value, RedactionLevel};
 use crate::AppState;
 use axum::{
@@ -18,6 +20,8 @@ pub struct ReadinessResponse {
     pub status: String,
     pub database: String,
     pub pricing: String,
+    pub upstream: String,
+    pub circuit_breakers: Vec<CircuitSnapshot>,
 }
 
 pub async fn liveness() -> Json<LivenessResponse> {
@@ -42,7 +46,16 @@ pub async fn readiness(
         pricing_ok = false;
     }
 
-    let status_code = if db_ok && pricing_ok {
+    // A tripped upstream-wide breaker means no model can be served.
+    let (upstream_ok, circuit_breakers) = match state.kernel.health().await {
+        Ok(health) => (!health.upstream_open(UPSTREAM_OPENROUTER), health.circuits),
+        Err(e) => {
+            tracing::error!("Readiness check: {}", e);
+            (false, Vec::new())
+        }
+    };
+
+    let status_code = if db_ok && pricing_ok && upstream_ok {
         StatusCode::OK
     } else {
         StatusCode::SERVICE_UNAVAILABLE
@@ -51,7 +64,7 @@ pub async fn readiness(
     (
         status_code,
         Json(ReadinessResponse {
-            status: if db_ok && pricing_ok {
+            status: if db_ok && pricing_ok && upstream_ok {
                 "ready"
             } else {
                 "unready"
@@ -59,10 +72,35 @@ pub async fn readiness(
             .to_string(),
             database: if db_ok { "ok" } else { "error" }.to_string(),
             pricing: if pricing_ok { "ok" } else { "empty" }.to_string(),
+            upstream: if upstream_ok { "ok" } else { "circuit_open" }.to_string(),
+            circuit_breakers,
         }),
     )
 }
 
+/// `GET /admin/health`: the kernel's view of upstream health, circuits, spend and rate limits.
+pub async fn admin_health(
+    State(state): State<Arc<AppState>>,
+    axum::extract::ConnectInfo(addr): axum::extract::ConnectInfo<std::net::SocketAddr>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    let ip = addr.ip();
+    if !ip.is_loopback() {
+        tracing::warn!("Blocked admin access attempt from {}", ip);
+        return (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "Unauthorized" })),
+        );
+    }
+
+    match state.kernel.health().await {
+        Ok(health) => (StatusCode::OK, Json(serde_json::json!(health))),
+        Err(e) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        ),
+    }
+}
+
 pub async fn admin_conversation(
     State(state): State<Arc<AppState>>,
     axum::extract::ConnectInfo(addr): axum::extract::ConnectInfo<std::net::SocketAddr>,
@@ -0,0 +1,572 @@
+//! Hedged upstream requests.
+//!
+//! For models with long tail latency we optionally fire a second request when the first
+//! one has not produced a line within a time-to-first-token threshold. Whichever attempt
+//! yields its first content pulse first is committed to; the other is dropped, which
+//! cancels the underlying HTTP request.
+
+use crate::main_helper::Args;
+use crate::specs::openai::OpenAiRequest;
+use crate::token_counting::TokenEstimator;
+use crate::types::{parse_provider_line, LineEvent, ParallaxError, Result, Usage};
+use bytes::Bytes;
+use futures_util::{Stream, StreamExt};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+pub type UpstreamByteStream =
+    Pin<Box<dyn Stream<Item = std::result::Result<Bytes, std::io::Error>> + Send>>;
+
+#[derive(Debug, Clone)]
+pub struct HedgeConfig {
+    pub ttft_threshold: Duration,
+    pub alternate_model: Option<String>,
+}
+
+impl HedgeConfig {
+    /// Returns the hedge configuration for `model_id`, or `None` when hedging is disabled
+    /// or the model is not in the `--hedge-models` allow-list.
+    pub fn for_model(args: &Args, model_id: &str) -> Option<Self> {
+        if args.hedge_ttft_ms == 0 {
+            return None;
+        }
+        if !args.hedge_models.is_empty() && !args.hedge_models.iter().any(|m| m == model_id) {
+            return None;
+        }
+        Some(Self {
+            ttft_threshold: Duration::from_millis(args.hedge_ttft_ms),
+            alternate_model: args.hedge_alternate_model.clone(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HedgeRole {
+    Primary,
+    Hedge,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AttemptOutcome {
+    Pending,
+    Won,
+    /// Dropped while its stream was still open.
+    Cancelled,
+    /// Ended without content (EOF or `[DONE]`) and the other attempt was committed to.
+    Lost,
+    Failed,
+}
+
+/// What an attempt produced before we stopped looking at it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FirstSignal {
+    Content,
+    Error,
+    Eof,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HedgeAttempt {
+    pub role: HedgeRole,
+    pub model: String,
+    pub outcome: AttemptOutcome,
+    pub launched_after_ms: u64,
+    pub first_line_ms: Option<u64>,
+    pub first_content_ms: Option<u64>,
+    pub bytes_read: usize,
+    pub error: Option<String>,
+    /// Prompt-only cost estimate for cancelled attempts (we never see their usage).
+    pub estimated_cost_usd: Option<f64>,
+    /// [`estimated_cancelled_usage`] of the request actually sent for this attempt; filled
+    /// in by the caller, since only `launch` knows what it sent.
+    #[serde(skip)]
+    pub sent_usage: Option<Usage>,
+}
+
+pub struct HedgeOutcome {
+    pub model: String,
+    pub stream: UpstreamByteStream,
+    pub attempts: Vec<HedgeAttempt>,
+}
+
+impl HedgeOutcome {
+    pub fn hedged(&self) -> bool {
+        self.attempts.len() > 1
+    }
+}
+
+struct Primed {
+    stream: UpstreamByteStream,
+    signal: FirstSignal,
+}
+
+type Tracker = Arc<Mutex<HedgeAttempt>>;
+
+fn update(tracker: &Tracker, f: impl FnOnce(&mut HedgeAttempt)) {
+    match tracker.lock() {
+        Ok(mut guard) => f(&mut guard),
+        Err(poisoned) => f(&mut poisoned.into_inner()),
+    }
+}
+
+fn snapshot(tracker: &Tracker) -> HedgeAttempt {
+    match tracker.lock() {
+        Ok(guard) => guard.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+fn classify_line(line: &str) -> Option<FirstSignal> {
+    let data = line.strip_prefix("data:")?.trim();
+    if data == "[DONE]" {
+        return Some(FirstSignal::Eof);
+    }
+    match parse_provider_line(data) {
+        LineEvent::Pulse(pulse) => {
+            let has_content = pulse.choices.iter().any(|choice| {
+                let delta = &choice.delta;
+                let text = match delta.content.as_deref() {
+                    Some(c) => !c.is_empty(),
+                    None => false,
+                };
+                let tools = match delta.tool_calls.as_ref() {
+                    Some(t) => !t.is_empty(),
+                    None => false,
+                };
+                text || tools || delta.extract_reasoning().is_some()
+            });
+            if has_content {
+                Some(FirstSignal::Content)
+            } else {
+                None
+            }
+        }
+        LineEvent::Error(_) => Some(FirstSignal::Error),
+        LineEvent::Unknown(_) => None,
+    }
+}
+
+/// Reads an attempt until it produces a decisive line, buffering everything read so far
+/// so the committed stream can be replayed byte-for-byte into the stream handler.
+///
+/// SSE comments (e.g. `: OPENROUTER PROCESSING`) do not count as a first line.
+async fn prime<Fut>(
+    launch: Fut,
+    origin: Instant,
+    tracker: Tracker,
+    mut first_line_tx: Option<oneshot::Sender<()>>,
+) -> Result<Primed>
+where
+    Fut: Future<Output = Result<UpstreamByteStream>>,
+{
+    let mut stream = match launch.await {
+        Ok(s) => s,
+        Err(e) => {
+            update(&tracker, |a| a.error = Some(e.to_string()));
+            return Err(e);
+        }
+    };
+
+    let mut buffered: Vec<Bytes> = Vec::new();
+    let mut pending = String::new();
+    let mut signal = FirstSignal::Eof;
+
+    'read: while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => {
+                update(&tracker, |a| a.error = Some(e.to_string()));
+                return Err(ParallaxError::Io(e).into());
+            }
+        };
+        update(&tracker, |a| a.bytes_read += chunk.len());
+        pending.push_str(&String::from_utf8_lossy(&chunk));
+        buffered.push(chunk);
+
+        while let Some(pos) = pending.find('\n') {
+            let line: String = pending.drain(..=pos).collect();
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(':') {
+                continue;
+            }
+            if let Some(tx) = first_line_tx.take() {
+                let elapsed = origin.elapsed().as_millis() as u64;
+                update(&tracker, |a| a.first_line_ms = Some(elapsed));
+                let _ = tx.send(());
+            }
+            if let Some(found) = classify_line(line) {
+                if found == FirstSignal::Content {
+                    let elapsed = origin.elapsed().as_millis() as u64;
+                    update(&tracker, |a| a.first_content_ms = Some(elapsed));
+                }
+                signal = found;
+                break 'read;
+            }
+        }
+    }
+
+    let replay = futures_util::stream::iter(buffered.into_iter().map(Ok));
+    Ok(Primed {
+        stream: replay.chain(stream).boxed(),
+        signal,
+    })
+}
+
+fn tracker(role: HedgeRole, model: &str, launched_after_ms: u64) -> Tracker {
+    Arc::new(Mutex::new(HedgeAttempt {
+        role,
+        model: model.to_string(),
+        outcome: AttemptOutcome::Pending,
+        launched_after_ms,
+        first_line_ms: None,
+        first_content_ms: None,
+        bytes_read: 0,
+        error: None,
+        estimated_cost_usd: None,
+        sent_usage: None,
+    }))
+}
+
+/// Runs the primary request and, if it misses the TTFT threshold, a hedge request.
+///
+/// `launch` opens an upstream stream for a request (including any retry/circuit logic).
+pub async fn race<F, Fut>(
+    request: &OpenAiRequest,
+    config: &HedgeConfig,
+    launch: F,
+) -> Result<HedgeOutcome>
+where
+    F: Fn(OpenAiRequest) -> Fut,
+    Fut: Future<Output = Result<UpstreamByteStream>>,
+{
+    let origin = Instant::now();
+    let primary_tracker = tracker(HedgeRole::Primary, &request.model, 0);
+    let (first_line_tx, first_line_rx) = oneshot::channel();
+    let primary = prime(
+        launch(request.clone()),
+        origin,
+        primary_tracker.clone(),
+        Some(first_line_tx),
+    );
+    tokio::pin!(primary);
+
+    let threshold = tokio::time::sleep(config.ttft_threshold);
+    tokio::pin!(threshold);
+
+    let primary_first = tokio::select! {
+        res = &mut primary => Some(res),
+        _ = first_line_rx => Some(primary.as_mut().await),
+        _ = &mut threshold => None,
+    };
+    if let Some(res) = primary_first {
+        return settle(&request.model, res, primary_tracker, None);
+    }
+
+    let hedge_model = match config.alternate_model.as_ref() {
+        Some(m) => m.clone(),
+        None => request.model.clone(),
+    };
+    tracing::warn!(
+        "[⚙️ ] No first line from {} after {:?}; hedging with {}",
+        request.model,
+        config.ttft_threshold,
+        hedge_model
+    );
+    let hedge_tracker = tracker(
+        HedgeRole::Hedge,
+        &hedge_model,
+        origin.elapsed().as_millis() as u64,
+    );
+    let mut hedge_request = request.clone();
+    hedge_request.model = hedge_model.clone();
+    let hedge = prime(launch(hedge_request), origin, hedge_tracker.clone(), None);
+    tokio::pin!(hedge);
+
+    tokio::select! {
+        res = &mut primary => {
+            if is_content(&res) {
+                return settle(&request.model, res, primary_tracker, Some(hedge_tracker));
+            }
+            let other = hedge.as_mut().await;
+            pick(
+                (&request.model, res, primary_tracker),
+                (&hedge_model, other, hedge_tracker),
+            )
+        }
+        res = &mut hedge => {
+            if is_content(&res) {
+                return settle(&hedge_model, res, hedge_tracker, Some(primary_tracker));
+            }
+            let other = primary.as_mut().await;
+            pick(
+                (&request.model, other, primary_tracker),
+                (&hedge_model, res, hedge_tracker),
+            )
+        }
+    }
+}
+
+fn is_content(res: &Result<Primed>) -> bool {
+    match res {
+        Ok(p) => p.signal == FirstSignal::Content,
+        Err(_) => false,
+    }
+}
+
+/// Commits to `winner`, marking `loser` (if any) as cancelled.
+fn settle(
+    model: &str,
+    res: Result<Primed>,
+    winner: Tracker,
+    loser: Option<Tracker>,
+) -> Result<HedgeOutcome> {
+    let primed = res?;
+    update(&winner, |a| a.outcome = AttemptOutcome::Won);
+    let mut attempts = vec![snapshot(&winner)];
+    if let Some(loser) = loser {
+        update(&loser, |a| a.outcome = AttemptOutcome::Cancelled);
+        attempts.push(snapshot(&loser));
+    }
+    attempts.sort_by_key(|a| a.launched_after_ms);
+    Ok(HedgeOutcome {
+        model: model.to_string(),
+        stream: primed.stream,
+        attempts,
+    })
+}
+
+/// How an attempt that `pick` did not commit to ended.
+fn loser_outcome(res: &Result<Primed>) -> AttemptOutcome {
+    match res {
+        Ok(p) => match p.signal {
+            FirstSignal::Content => AttemptOutcome::Cancelled,
+            FirstSignal::Eof => AttemptOutcome::Lost,
+            FirstSignal::Error => AttemptOutcome::Failed,
+        },
+        Err(_) => AttemptOutcome::Failed,
+    }
+}
+
+/// Neither attempt produced content first: prefer whichever has content, then the
+/// primary's stream (so the stream handler sees its error/EOF as usual).
+fn pick(
+    primary: (&str, Result<Primed>, Tracker),
+    hedge: (&str, Result<Primed>, Tracker),
+) -> Result<HedgeOutcome> {
+    let (primary_model, primary_res, primary_tracker) = primary;
+    let (hedge_model, hedge_res, hedge_tracker) = hedge;
+
+    let hedge_wins = is_content(&hedge_res) || (primary_res.is_err() && hedge_res.is_ok());
+    let (model, res, winner, loser, lost) = if hedge_wins {
+        let lost = loser_outcome(&primary_res);
+        (hedge_model, hedge_res, hedge_tracker, primary_tracker, lost)
+    } else {
+        let lost = loser_outcome(&hedge_res);
+        (
+            primary_model,
+            primary_res,
+            primary_tracker,
+            hedge_tracker,
+            lost,
+        )
+    };
+
+    let primed = res?;
+    update(&winner, |a| a.outcome = AttemptOutcome::Won);
+    update(&loser, |a| a.outcome = lost);
+    let mut attempts = vec![snapshot(&winner), snapshot(&loser)];
+    attempts.sort_by_key(|a| a.launched_after_ms);
+    Ok(HedgeOutcome {
+        model: model.to_string(),
+        stream: primed.stream,
+        attempts,
+    })
+}
+
+/// Usage estimate for an attempt we cancelled before it reported usage: providers still
+/// bill the prompt, so we charge the estimated prompt tokens and no completion tokens.
+pub fn estimated_cancelled_usage(request: &OpenAiRequest) -> Usage {
+    let prompt = match serde_json::to_string(&request.messages) {
+        Ok(s) => TokenEstimator::estimate_text_tokens(&s) as u32,
+        Err(_) => 0,
+    };
+    Usage {
+        prompt_tokens: prompt,
+        completion_tokens: 0,
+        total_tokens: prompt,
+        prompt_tokens_details: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(model: &str) -> OpenAiRequest {
+        match serde_json::from_value(serde_json::json!({
+            "model": model,
+            "messages": [{"role": "user", "content": "hi"}],
+            "stream": true
+        })) {
+            Ok(r) => r,
+            Err(e) => panic!("bad request fixture: {}", e),
+        }
+    }
+
+    fn delayed_stream(delay: Duration, body: &'static str) -> UpstreamByteStream {
+        futures_util::stream::once(async move {
+            tokio::time::sleep(delay).await;
+            Ok(Bytes::from_static(body.as_bytes()))
+        })
+        .boxed()
+    }
+
+    const CONTENT: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"hello\"}}]}\n\n";
+
+    async fn collect(stream: UpstreamByteStream) -> String {
+        let chunks: Vec<_> = stream.collect().await;
+        chunks
+            .into_iter()
+            .filter_map(|c| c.ok())
+            .map(|b| String::from_utf8_lossy(&b).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_classify_line() {
+        assert_eq!(classify_line(CONTENT.trim()), Some(FirstSignal::Content));
+        assert_eq!(classify_line("data: [DONE]"), Some(FirstSignal::Eof));
+        assert_eq!(
+            classify_line("data: {\"error\":{\"message\":\"boom\",\"code\":500}}"),
+            Some(FirstSignal::Error)
+        );
+        assert_eq!(
+            classify_line("data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"}}]}"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fast_primary_is_not_hedged() {
+        let config = HedgeConfig {
+            ttft_threshold: Duration::from_millis(200),
+            alternate_model: Some("alt/model".to_string()),
+        };
+        let outcome = match race(&request("slow/model"), &config, |_req| async {
+            Ok(delayed_stream(Duration::from_millis(5), CONTENT))
+        })
+        .await
+        {
+            Ok(o) => o,
+            Err(e) => panic!("race failed: {}", e),
+        };
+        assert!(!outcome.hedged());
+        assert_eq!(outcome.model, "slow/model");
+        assert_eq!(collect(outcome.stream).await, CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_slow_primary_loses_to_hedge() {
+        let config = HedgeConfig {
+            ttft_threshold: Duration::from_millis(20),
+            alternate_model: Some("alt/model".to_string()),
+        };
+        let outcome = match race(&request("slow/model"), &config, |req| async move {
+            if req.model == "slow/model" {
+                Ok(delayed_stream(Duration::from_secs(5), CONTENT))
+            } else {
+                Ok(delayed_stream(Duration::from_millis(5), CONTENT))
+            }
+        })
+        .await
+        {
+            Ok(o) => o,
+            Err(e) => panic!("race failed: {}", e),
+        };
+        assert!(outcome.hedged());
+        assert_eq!(outcome.model, "alt/model");
+        let primary = match outcome
+            .attempts
+            .iter()
+            .find(|a| a.role == HedgeRole::Primary)
+        {
+            Some(a) => a.clone(),
+            None => panic!("primary attempt missing"),
+        };
+        assert_eq!(primary.outcome, AttemptOutcome::Cancelled);
+        assert_eq!(collect(outcome.stream).await, CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_failed_hedge_falls_back_to_primary() {
+        let config = HedgeConfig {
+            ttft_threshold: Duration::from_millis(10),
+            alternate_model: None,
+        };
+        let calls = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let outcome = match race(&request("slow/model"), &config, |_req| {
+            let n = calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            async move {
+                if n == 0 {
+                    Ok(delayed_stream(Duration::from_millis(60), CONTENT))
+                } else {
+                    Err(ParallaxError::Upstream(
+                        axum::http::StatusCode::BAD_GATEWAY,
+                        "nope".to_string(),
+                    )
+                    .into())
+                }
+            }
+        })
+        .await
+        {
+            Ok(o) => o,
+            Err(e) => panic!("race failed: {}", e),
+        };
+        assert_eq!(outcome.model, "slow/model");
+        let hedge = match outcome.attempts.iter().find(|a| a.role == HedgeRole::Hedge) {
+            Some(a) => a.clone(),
+            None => panic!("hedge attempt missing"),
+        };
+        assert_eq!(hedge.outcome, AttemptOutcome::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_empty_primary_is_recorded_as_lost_not_failed() {
+        let config = HedgeConfig {
+            ttft_threshold: Duration::from_millis(10),
+            alternate_model: Some("alt/model".to_string()),
+        };
+        let outcome = match race(&request("slow/model"), &config, |req| async move {
+            if req.model == "slow/model" {
+                Ok(delayed_stream(
+                    Duration::from_millis(40),
+                    "data: [DONE]\n\n",
+                ))
+            } else {
+                Ok(delayed_stream(Duration::from_millis(80), CONTENT))
+            }
+        })
+        .await
+        {
+            Ok(o) => o,
+            Err(e) => panic!("race failed: {}", e),
+        };
+        assert_eq!(outcome.model, "alt/model");
+        let primary = match outcome
+            .attempts
+            .iter()
+            .find(|a| a.role == HedgeRole::Primary)
+        {
+            Some(a) => a.clone(),
+            None => panic!("primary attempt missing"),
+        };
+        assert_eq!(primary.outcome, AttemptOutcome::Lost);
+    }
+}
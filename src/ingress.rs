@@ -16,6 +16,16 @@ pub struct CursorMetadata {
     pub request_id: Option<String>,
 }
 
+/// The conversation id a client sent as a header; Cursor uses any of these names.
+pub fn header_conversation_id(headers: &axum::http::HeaderMap) -> Option<String> {
+    headers
+        .get("x-cursor-conversation-id")
+        .or_else(|| headers.get("x-conversation-id"))
+        .or_else(|| headers.get("cursor-conversation-id"))
+        .and_then(|h| h.to_str().ok())
+        .map(|s| s.to_string())
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub enum ModelProvider {
     Gemini(String),
@@ -224,6 +234,17 @@ impl RawTurn {
     }
 
     pub fn generate_anchor_hash(&self) -> Result<String> {
+        let hash = self.anchor_hash();
+        let model_name = self.model.model_name();
+        tracing::info!(
+            "[⚙️  -> ⚙️ ] Identify: [{}...] Model: {}",
+            str_utils::prefix_chars(&hash, 8),
+            model_name
+        );
+        Ok(hash)
+    }
+
+    fn anchor_hash(&self) -> String {
         let mut hasher = Sha256::new();
         // NOTE: We intentionally do NOT include the model name in the fallback conversation ID hash.
         // Cursor may omit metadata on some paths; excluding the model keeps the fallback CID stable across model switches.
@@ -235,14 +256,7 @@ impl RawTurn {
         let cleaned_text = self.clean_anchor_text(anchor_text);
 
         hasher.update(cleaned_text.trim().as_bytes());
-        let hash = format!("{:x}", hasher.finalize());
-        let model_name = self.model.model_name();
-        tracing::info!(
-            "[⚙️  -> ⚙️ ] Identify: [{}...] Model: {}",
-            str_utils::prefix_chars(&hash, 8),
-            model_name
-        );
-        Ok(hash)
+        format!("{:x}", hasher.finalize())
     }
 
     fn extract_anchor_text(&self) -> String {
@@ -320,6 +334,18 @@ impl RawTurn {
         }
     }
 
+    /// The id [`Self::extract_conversation_id`] resolves, without logging the lookup.
+    pub fn conversation_id(&self) -> (String, ConversationIdSource) {
+        match self
+            .metadata
+            .as_ref()
+            .and_then(|m| m.conversation_id.as_ref())
+        {
+            Some(cursor_cid) => (cursor_cid.clone(), ConversationIdSource::CursorMetadata),
+            None => (self.anchor_hash(), ConversationIdSource::AnchorHash),
+        }
+    }
+
     pub fn extract_conversation_id(&self) -> Result<(String, ConversationIdSource)> {
         // Prefer Cursor's conversation ID if available - this is the authoritative source
         if let Some(meta) = &self.metadata {
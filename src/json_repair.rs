@@ -3,6 +3,7 @@
 //! Handles incomplete JSON from streaming APIs by detecting incomplete structures
 //! and attempting to repair them gracefully.
 
+use crate::sanitizer_rules::RuleApplication;
 use serde_json::Value;
 
 /// Detects if a JSON string is incomplete (unbalanced braces/quotes)
@@ -120,14 +121,30 @@ pub fn parse_json_with_repair(json_str: &str) -> Result<Value, String> {
 
 /// Attempts to repair tool call arguments with semantic understanding
 pub fn repair_tool_call_arguments(name: &str, arguments: &str) -> Result<Value, String> {
-    // For create_plan tool, always use semantic repair regardless of JSON validity
-    if name == "create_plan" {
-        return repair_create_plan_arguments(arguments);
+    repair_tool_call_arguments_with_rule(name, arguments).map(|(value, _)| value)
+}
+
+/// Like [`repair_tool_call_arguments`], also returning the `text_field` rule application when
+/// the arguments were wrapped as text.
+pub fn repair_tool_call_arguments_with_rule(
+    name: &str,
+    arguments: &str,
+) -> Result<(Value, Option<RuleApplication>), String> {
+    // Tools with a text_field sanitizer rule (e.g. create_plan) take any non-JSON arguments
+    // as text, without attempting a repair
+    if let Some((value, application)) = crate::sanitizer_rules::rules().wrap_text(name, arguments) {
+        tracing::warn!(
+            "[JSON-REPAIR] Tool '{}' arguments: {} ({} chars)",
+            name,
+            application.change,
+            arguments.len()
+        );
+        return Ok((value, Some(application)));
     }
 
     // First try standard JSON repair for other tools
     if let Ok(value) = parse_json_with_repair(arguments) {
-        return Ok(value);
+        return Ok((value, None));
     }
 
     // For other tools, return the repaired JSON even if imperfect
@@ -140,7 +157,7 @@ pub fn repair_tool_call_arguments(name: &str, arguments: &str) -> Result<Value,
                 arguments.len(),
                 repaired.len()
             );
-            Ok(value)
+            Ok((value, None))
         }
         Err(e) => Err(format!(
             "Failed to repair tool '{}' arguments: {} (original: {} chars, repaired: {} chars)",
@@ -152,65 +169,6 @@ pub fn repair_tool_call_arguments(name: &str, arguments: &str) -> Result<Value,
     }
 }
 
-/// Special repair logic for create_plan tool arguments
-fn repair_create_plan_arguments(arguments: &str) -> Result<Value, String> {
-    let trimmed = arguments.trim();
-
-    // If it's empty or just whitespace, return default structure
-    if trimmed.is_empty() {
-        return Ok(serde_json::json!({
-            "plan": "# Implementation Plan\n\nNo plan provided.",
-            "name": "Default Plan"
-        }));
-    }
-
-    // Try to parse as JSON first
-    if let Ok(mut value) = serde_json::from_str::<Value>(trimmed) {
-        // If it's a string, treat it as the plan content
-        if let Some(plan_text) = value.as_str() {
-            let plan_text = plan_text.to_string();
-            let repaired_value = serde_json::json!({
-                "plan": plan_text,
-                "name": "Implementation Plan"
-            });
-            return Ok(repaired_value);
-        }
-
-        // If it's already an object, ensure it has required fields
-        if let Some(obj) = value.as_object_mut() {
-            if !obj.contains_key("plan") {
-                obj.insert(
-                    "plan".to_string(),
-                    serde_json::Value::String("No plan content provided.".to_string()),
-                );
-            }
-            if !obj.contains_key("name") {
-                obj.insert(
-                    "name".to_string(),
-                    serde_json::Value::String("Implementation Plan".to_string()),
-                );
-            }
-            return Ok(value);
-        }
-
-        return Ok(value);
-    }
-
-    // If JSON parsing failed, treat the entire string as plan content
-    let plan_content = trimmed.to_string();
-    let repaired_value = serde_json::json!({
-        "plan": plan_content,
-        "name": "Implementation Plan"
-    });
-
-    tracing::warn!(
-        "[JSON-REPAIR] create_plan arguments treated as plain text: {} chars",
-        arguments.len()
-    );
-
-    Ok(repaired_value)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -357,96 +315,108 @@ mod tests {
     }
 
     #[test]
-    fn test_repair_tool_call_arguments_other_tool() {
-        let result = repair_tool_call_arguments("grep", r#"{"pattern": "test""#);
-        assert!(result.is_ok());
-
-        let value = match result {
-            Ok(v) => v,
+    fn test_repair_tool_call_arguments_create_plan_object_is_not_wrapped() {
+        match repair_tool_call_arguments_with_rule("create_plan", r#"{"plan": "x"}"#) {
+            Ok((value, wrapped)) => {
+                assert_eq!(value, serde_json::json!({"plan": "x"}));
+                assert!(wrapped.is_none());
+            }
             Err(e) => panic!("Repair failed: {}", e),
-        };
-        assert!(value.is_object());
+        }
     }
 
     #[test]
-    fn test_repair_create_plan_arguments_plain_text() {
-        let result = repair_create_plan_arguments("This is just plain text plan");
-        assert!(result.is_ok());
-
-        let value = match result {
-            Ok(v) => v,
+    fn test_repair_tool_call_arguments_create_plan_unrepairable_json_is_text() {
+        let arguments = "{plan: step one, then step two";
+        let (value, wrapped) = match repair_tool_call_arguments_with_rule("create_plan", arguments)
+        {
+            Ok(r) => r,
             Err(e) => panic!("Repair failed: {}", e),
         };
-        let obj = match value.as_object() {
-            Some(o) => o,
-            None => panic!("Value should be an object"),
-        };
-        let plan = match obj["plan"].as_str() {
-            Some(s) => s,
-            None => panic!("Plan should be a string"),
-        };
-        assert_eq!(plan, "This is just plain text plan");
-        let name = match obj["name"].as_str() {
-            Some(s) => s,
-            None => panic!("Name should be a string"),
-        };
-        assert_eq!(name, "Implementation Plan");
+        assert_eq!(value["plan"], arguments);
+        assert_eq!(value["name"], "Implementation Plan");
+        match wrapped {
+            Some(application) => assert_eq!(application.rule, "create_plan_text_arguments"),
+            None => panic!("unrepairable arguments should be wrapped as text"),
+        }
     }
 
+    /// Outputs of the former `repair_create_plan_arguments` followed by the plan sanitizer,
+    /// which the create_plan rules must reproduce exactly.
     #[test]
-    fn test_repair_create_plan_arguments_json_string() {
-        let result = repair_create_plan_arguments(r#"{"plan": "JSON plan content"}"#);
-        assert!(result.is_ok());
-
-        let value = match result {
-            Ok(v) => v,
-            Err(e) => panic!("Repair failed: {}", e),
-        };
-        let obj = match value.as_object() {
-            Some(o) => o,
-            None => panic!("Value should be an object"),
-        };
-        let plan = match obj["plan"].as_str() {
-            Some(s) => s,
-            None => panic!("Plan should be a string"),
-        };
-        assert_eq!(plan, "JSON plan content");
-        let name = match obj["name"].as_str() {
-            Some(s) => s,
-            None => panic!("Name should be a string"),
-        };
-        assert_eq!(name, "Implementation Plan");
+    fn test_create_plan_rules_match_the_former_repair() {
+        let cases = [
+            (
+                "",
+                r##"{"name":"Default Plan","plan":"# Implementation Plan\n\nNo plan provided."}"##,
+            ),
+            (
+                "   ",
+                r##"{"name":"Default Plan","plan":"# Implementation Plan\n\nNo plan provided."}"##,
+            ),
+            (
+                "Step 1: read the code\nStep 2: change it",
+                r##"{"name":"Implementation Plan","plan":"# Implementation Plan\n\nStep 1: read the code\nStep 2: change it"}"##,
+            ),
+            (
+                "  padded text  \n",
+                r##"{"name":"Implementation Plan","plan":"# Implementation Plan\n\npadded text"}"##,
+            ),
+            (
+                r##""JSON plan content""##,
+                r##"{"name":"Implementation Plan","plan":"# Implementation Plan\n\nJSON plan content"}"##,
+            ),
+            (
+                r##"{"plan": "Complete plan", "name": "Custom Plan", "overview": "Test overview"}"##,
+                r##"{"name":"Custom Plan","overview":"Test overview","plan":"# Custom Plan\n\nComplete plan"}"##,
+            ),
+            (
+                r##"{"name": "Only a name"}"##,
+                r##"{"name":"Only a name","plan":"# Only a name\n\nNo plan content provided."}"##,
+            ),
+            (
+                "{}",
+                r##"{"name":"Implementation Plan","plan":"# Implementation Plan\n\nNo plan content provided."}"##,
+            ),
+            (
+                r##"{"plan": "# Titled\n\nbody"}"##,
+                r##"{"name":"Implementation Plan","plan":"# Titled\n\nbody"}"##,
+            ),
+            (
+                "{plan: step one, then step two",
+                r##"{"name":"Implementation Plan","plan":"# Implementation Plan\n\n{plan: step one, then step two"}"##,
+            ),
+            (
+                r##"{"plan": "Partial plan"##,
+                r##"{"name":"Implementation Plan","plan":"# Implementation Plan\n\n{\"plan\": \"Partial plan"}"##,
+            ),
+            (
+                "Run npm install then grep the logs",
+                r##"{"name":"Implementation Plan","plan":"# Implementation Plan\n\nRun package manager install then ripgrep the logs"}"##,
+            ),
+            ("42", "42"),
+            ("[1, 2]", "[1,2]"),
+            ("null", "null"),
+        ];
+        for (arguments, expected) in cases {
+            let mut value = match repair_tool_call_arguments("create_plan", arguments) {
+                Ok(v) => v,
+                Err(e) => panic!("Repair of {:?} failed: {}", arguments, e),
+            };
+            crate::hardening::sanitize_tool_call("create_plan", &mut value);
+            assert_eq!(value.to_string(), expected, "arguments: {:?}", arguments);
+        }
     }
 
     #[test]
-    fn test_repair_create_plan_arguments_complete_json() {
-        let input =
-            r#"{"plan": "Complete plan", "name": "Custom Plan", "overview": "Test overview"}"#;
-        let result = repair_create_plan_arguments(input);
+    fn test_repair_tool_call_arguments_other_tool() {
+        let result = repair_tool_call_arguments("grep", r#"{"pattern": "test""#);
         assert!(result.is_ok());
 
         let value = match result {
             Ok(v) => v,
             Err(e) => panic!("Repair failed: {}", e),
         };
-        let obj = match value.as_object() {
-            Some(o) => o,
-            None => panic!("Value should be an object"),
-        };
-        let plan = match obj["plan"].as_str() {
-            Some(s) => s,
-            None => panic!("Plan should be a string"),
-        };
-        assert_eq!(plan, "Complete plan");
-        let name = match obj["name"].as_str() {
-            Some(s) => s,
-            None => panic!("Name should be a string"),
-        };
-        assert_eq!(name, "Custom Plan");
-        let overview = match obj["overview"].as_str() {
-            Some(s) => s,
-            None => panic!("Overview should be a string"),
-        };
-        assert_eq!(overview, "Test overview");
+        assert!(value.is_object());
     }
 }
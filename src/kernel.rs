@@ -1,48 +1,190 @@
-use crate::hardening::{CircuitBreaker, CircuitState};
+//! The kernel: one task that owns upstream health, circuit breakers, the spend ledger and
+//! the inbound rate limiter.
+//!
+//! Request handlers never touch that state directly; they go through a [`KernelHandle`],
+//! which sends [`KernelCommand`]s and awaits the replies. Because the kernel applies commands
+//! one at a time, a success and a failure racing each other cannot interleave half-way through
+//! an update, and every `UpstreamHealthUpdate` the dashboard sees is emitted from one place.
+//! Subscribers such as the notifier get the same changes as [`KernelEvent`]s, in order and
+//! without the drops a lagging dashboard receiver would see.
+
+use crate::constants::KERNEL_CHANNEL_CAPACITY;
+use crate::hardening::{
+    CircuitBreakerRegistry, CircuitFault, CircuitPermit, CircuitSnapshot, CircuitState,
+};
+use crate::main_helper::Args;
+use crate::rate_limit::{
+    AdmissionTicket, RateLimitSnapshot, RateLimiter, RejectReason, Rejection, RequestKeys,
+};
 use crate::tui::TuiEvent;
-use crate::types::{Result, UpstreamHealth};
+use crate::types::{ObservedError, ParallaxError, Result, UpstreamHealth};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::Ordering;
 use tokio::sync::broadcast;
 use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
 
 pub enum KernelCommand {
     UpdateHealth {
         success: bool,
     },
     CheckCircuit {
-        resp: oneshot::Sender<Result<()>>,
+        upstream: String,
+        model: String,
+        resp: oneshot::Sender<Result<CircuitPermit>>,
+    },
+    RecordCircuitSuccess {
+        upstream: String,
+        model: String,
+    },
+    RecordCircuitFailure {
+        upstream: String,
+        model: String,
+    },
+    /// A failed upstream request; `fault` says which breaker (if any) it counts against.
+    RecordCircuitFault {
+        upstream: String,
+        model: String,
+        fault: CircuitFault,
+    },
+    /// The admitted request is done; frees the half-open probes it still holds.
+    ReleaseProbe {
+        permit: CircuitPermit,
+    },
+    RecordSpend {
+        model: String,
+        usd: f64,
+    },
+    AdmitRequest {
+        keys: RequestKeys,
+        resp: oneshot::Sender<std::result::Result<AdmissionTicket, Rejection>>,
+    },
+    /// The admitted request is done; frees its concurrency slots.
+    ReleaseAdmission {
+        ticket: AdmissionTicket,
     },
-    RecordCircuitSuccess,
-    RecordCircuitFailure,
     GetHealth {
         resp: oneshot::Sender<HealthSnapshot>,
     },
+    /// Opens a feed of [`KernelEvent`]s; it never drops events, so the reader must keep up.
+    Subscribe {
+        resp: oneshot::Sender<mpsc::UnboundedReceiver<KernelEvent>>,
+    },
+    /// An outcome seen by the request path, passed on to subscribers in order.
+    Report {
+        event: KernelEvent,
+    },
 }
 
-#[derive(Debug, Clone)]
+/// State changes the kernel publishes to subscribers such as the notifier.
+#[derive(Debug, Clone, PartialEq)]
+pub enum KernelEvent {
+    Health {
+        consecutive_failures: u32,
+        total_requests: u64,
+        failed_requests: u64,
+        degraded: bool,
+    },
+    /// A breaker changed state.
+    Circuit {
+        upstream: String,
+        model: String,
+        state: CircuitState,
+        consecutive_failures: u32,
+    },
+    /// `usd` was charged to `model`; `spent_usd` is the session total after it.
+    Spend {
+        model: String,
+        usd: f64,
+        spent_usd: f64,
+    },
+    RequestFinished {
+        id: String,
+        status: u16,
+    },
+    /// A stream ended on an upstream error that no retry or fallback recovered.
+    StreamFailed {
+        id: String,
+        model: String,
+        message: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct HealthSnapshot {
     pub consecutive_failures: u32,
-    pub total_requests: u32,
-    pub failed_requests: u32,
-    pub circuit_state: CircuitState,
+    pub total_requests: u64,
+    pub failed_requests: u64,
+    pub degraded: bool,
+    pub circuits: Vec<CircuitSnapshot>,
+    /// Session spend in USD, in total and per model.
+    pub spent_usd: f64,
+    pub spend_by_model: BTreeMap<String, f64>,
+    pub rate_limits: Vec<RateLimitSnapshot>,
+}
+
+impl HealthSnapshot {
+    /// True when the upstream-wide breaker is open, i.e. no model can be served.
+    pub fn upstream_open(&self, upstream: &str) -> bool {
+        self.circuits.iter().any(|c| {
+            c.upstream == upstream
+                && c.model == crate::hardening::ANY_MODEL
+                && c.state == CircuitState::Open
+        })
+    }
+}
+
+/// A request the rate limiter could not admit yet, queued until `deadline`.
+struct Waiter {
+    keys: RequestKeys,
+    deadline: Instant,
+    /// When to try again; concurrency waiters are also retried whenever a slot frees up.
+    retry_at: Instant,
+    resp: oneshot::Sender<std::result::Result<AdmissionTicket, Rejection>>,
 }
 
 pub struct Kernel {
     health: UpstreamHealth,
-    circuit_breaker: CircuitBreaker,
+    circuit_breakers: CircuitBreakerRegistry,
+    rate_limiter: RateLimiter,
+    waiters: Vec<Waiter>,
+    spend_by_model: BTreeMap<String, f64>,
+    subscribers: Vec<mpsc::UnboundedSender<KernelEvent>>,
+    /// Breaker states as last published, to report transitions.
+    circuit_states: BTreeMap<(String, String), CircuitState>,
     tx_tui: broadcast::Sender<TuiEvent>,
     rx_cmd: mpsc::Receiver<KernelCommand>,
 }
 
+/// Starts the kernel with state configured from `args` and returns a handle to it.
+pub fn spawn(args: &Args, tx_tui: broadcast::Sender<TuiEvent>) -> KernelHandle {
+    let (tx, rx) = mpsc::channel(KERNEL_CHANNEL_CAPACITY);
+    let circuit_breakers = CircuitBreakerRegistry::new(
+        args.circuit_breaker_threshold,
+        std::time::Duration::from_secs(30),
+    )
+    .with_tui(tx_tui.clone());
+    let rate_limiter = RateLimiter::from_args(args);
+    tokio::spawn(Kernel::new(circuit_breakers, rate_limiter, tx_tui, rx).run());
+    KernelHandle { tx }
+}
+
 impl Kernel {
     pub fn new(
-        circuit_threshold: u32,
-        recovery_timeout: std::time::Duration,
+        circuit_breakers: CircuitBreakerRegistry,
+        rate_limiter: RateLimiter,
         tx_tui: broadcast::Sender<TuiEvent>,
         rx_cmd: mpsc::Receiver<KernelCommand>,
     ) -> Self {
         Self {
             health: UpstreamHealth::default(),
-            circuit_breaker: CircuitBreaker::new(circuit_threshold, recovery_timeout),
+            circuit_breakers,
+            rate_limiter,
+            waiters: Vec::new(),
+            spend_by_model: BTreeMap::new(),
+            subscribers: Vec::new(),
+            circuit_states: BTreeMap::new(),
             tx_tui,
             rx_cmd,
         }
@@ -50,70 +192,565 @@ impl Kernel {
 
     pub async fn run(mut self) {
         tracing::info!("Kernel event loop started");
-        while let Some(cmd) = self.rx_cmd.recv().await {
-            match cmd {
-                KernelCommand::UpdateHealth { success } => {
-                    if success {
-                        self.health.record_success();
-                    } else {
-                        self.health.record_failure();
-                    }
-                    self.emit_health_update().await;
+        loop {
+            let next_retry = self.waiters.iter().map(|w| w.retry_at).min();
+            let retry_due = async {
+                match next_retry {
+                    Some(at) => tokio::time::sleep_until(at).await,
+                    None => std::future::pending().await,
                 }
-                KernelCommand::CheckCircuit { resp } => {
-                    let _ = resp.send(self.circuit_breaker.check().await);
+            };
+            tokio::select! {
+                cmd = self.rx_cmd.recv() => match cmd {
+                    Some(cmd) => self.handle(cmd).await,
+                    None => break,
+                },
+                _ = retry_due => self.retry_waiters(),
+            }
+        }
+    }
+
+    async fn handle(&mut self, cmd: KernelCommand) {
+        match cmd {
+            KernelCommand::UpdateHealth { success } => {
+                if success {
+                    self.health.record_success();
+                } else {
+                    self.health.record_failure();
                 }
-                KernelCommand::RecordCircuitSuccess => {
-                    self.circuit_breaker.record_success().await;
-                    self.emit_health_update().await;
+                self.emit_health_update();
+            }
+            KernelCommand::CheckCircuit {
+                upstream,
+                model,
+                resp,
+            } => {
+                let _ = resp.send(self.circuit_breakers.check(&upstream, &model).await);
+                self.publish_circuit_changes().await;
+            }
+            KernelCommand::RecordCircuitSuccess { upstream, model } => {
+                self.circuit_breakers
+                    .record_success(&upstream, &model)
+                    .await;
+                self.publish_circuit_changes().await;
+            }
+            KernelCommand::RecordCircuitFailure { upstream, model } => {
+                self.circuit_breakers
+                    .record_failure(&upstream, &model)
+                    .await;
+                self.publish_circuit_changes().await;
+            }
+            KernelCommand::RecordCircuitFault {
+                upstream,
+                model,
+                fault,
+            } => {
+                self.circuit_breakers
+                    .record_fault(&upstream, &model, fault)
+                    .await;
+                self.publish_circuit_changes().await;
+            }
+            KernelCommand::ReleaseProbe { permit } => {
+                self.circuit_breakers.release(&permit).await;
+            }
+            KernelCommand::RecordSpend { model, usd } => {
+                *self.spend_by_model.entry(model.clone()).or_insert(0.0) += usd;
+                let spent_usd = self.spent_usd();
+                self.publish(KernelEvent::Spend {
+                    model,
+                    usd,
+                    spent_usd,
+                });
+            }
+            KernelCommand::AdmitRequest { keys, resp } => {
+                let now = Instant::now();
+                let waiter = Waiter {
+                    keys,
+                    deadline: now + self.rate_limiter.queue_timeout(),
+                    retry_at: now,
+                    resp,
+                };
+                if let Some(waiter) = self.admit_or_queue(waiter, now) {
+                    self.waiters.push(waiter);
                 }
-                KernelCommand::RecordCircuitFailure => {
-                    self.circuit_breaker.record_failure().await;
-                    self.emit_health_update().await;
+            }
+            KernelCommand::ReleaseAdmission { ticket } => {
+                self.rate_limiter.release(ticket);
+                self.retry_waiters();
+            }
+            KernelCommand::GetHealth { resp } => {
+                let _ = resp.send(self.snapshot().await);
+            }
+            KernelCommand::Subscribe { resp } => {
+                let (tx, rx) = mpsc::unbounded_channel();
+                if resp.send(rx).is_ok() {
+                    self.subscribers.push(tx);
                 }
-                KernelCommand::GetHealth { resp } => {
-                    let snapshot = HealthSnapshot {
-                        consecutive_failures: self
-                            .health
-                            .consecutive_failures
-                            .load(std::sync::atomic::Ordering::Relaxed),
-                        total_requests: self
-                            .health
-                            .total_requests
-                            .load(std::sync::atomic::Ordering::Relaxed)
-                            as u32,
-                        failed_requests: self
-                            .health
-                            .failed_requests
-                            .load(std::sync::atomic::Ordering::Relaxed)
-                            as u32,
-                        circuit_state: *self.circuit_breaker.state_raw_lock().await,
-                    };
-                    let _ = resp.send(snapshot);
+            }
+            KernelCommand::Report { event } => self.publish(event),
+        }
+    }
+
+    /// Admits `waiter` if the limiter allows it now; returns it if it should keep waiting.
+    fn admit_or_queue(&mut self, waiter: Waiter, now: Instant) -> Option<Waiter> {
+        if waiter.resp.is_closed() {
+            return None;
+        }
+        let rejection = match self.rate_limiter.try_acquire(&waiter.keys) {
+            Ok(ticket) => {
+                // The requester went away while queued: hand the slots straight back.
+                if let Err(Ok(ticket)) = waiter.resp.send(Ok(ticket)) {
+                    self.rate_limiter.release(ticket);
                 }
+                return None;
+            }
+            Err(rejection) => rejection,
+        };
+        // Rate waits are known up front; concurrency waits last until a slot frees up.
+        let retry_at = match rejection.reason {
+            RejectReason::Rate => now + rejection.retry_after,
+            RejectReason::Concurrency => waiter.deadline,
+        };
+        if now >= waiter.deadline || retry_at > waiter.deadline {
+            let _ = waiter.resp.send(Err(rejection));
+            return None;
+        }
+        Some(Waiter { retry_at, ..waiter })
+    }
+
+    /// Retries queued admissions in arrival order.
+    fn retry_waiters(&mut self) {
+        let now = Instant::now();
+        for waiter in std::mem::take(&mut self.waiters) {
+            if let Some(waiter) = self.admit_or_queue(waiter, now) {
+                self.waiters.push(waiter);
+            }
+        }
+    }
+
+    async fn snapshot(&self) -> HealthSnapshot {
+        let consecutive_failures = self.health.consecutive_failures.load(Ordering::Relaxed);
+        HealthSnapshot {
+            consecutive_failures,
+            total_requests: self.health.total_requests.load(Ordering::Relaxed),
+            failed_requests: self.health.failed_requests.load(Ordering::Relaxed),
+            degraded: consecutive_failures > 0,
+            circuits: self.circuit_breakers.snapshot().await,
+            spent_usd: self.spent_usd(),
+            spend_by_model: self.spend_by_model.clone(),
+            rate_limits: self.rate_limiter.snapshot(),
+        }
+    }
+
+    fn spent_usd(&self) -> f64 {
+        self.spend_by_model
+            .values()
+            .fold(0.0, |total, usd| total + usd)
+    }
+
+    /// Sends `event` to every live subscriber, forgetting the ones that went away.
+    fn publish(&mut self, event: KernelEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Publishes the breakers whose state changed since the last call.
+    async fn publish_circuit_changes(&mut self) {
+        for circuit in self.circuit_breakers.snapshot().await {
+            let key = (circuit.upstream.clone(), circuit.model.clone());
+            let before = match self.circuit_states.insert(key, circuit.state) {
+                Some(state) => state,
+                None => CircuitState::Closed,
+            };
+            if before != circuit.state {
+                self.publish(KernelEvent::Circuit {
+                    upstream: circuit.upstream,
+                    model: circuit.model,
+                    state: circuit.state,
+                    consecutive_failures: circuit.consecutive_failures,
+                });
             }
         }
     }
 
-    async fn emit_health_update(&self) {
+    fn emit_health_update(&mut self) {
+        let consecutive_failures = self.health.consecutive_failures.load(Ordering::Relaxed);
+        let total_requests = self.health.total_requests.load(Ordering::Relaxed);
+        let failed_requests = self.health.failed_requests.load(Ordering::Relaxed);
+        let degraded = consecutive_failures > 0;
         let _ = self.tx_tui.send(TuiEvent::UpstreamHealthUpdate {
-            consecutive_failures: self
-                .health
-                .consecutive_failures
-                .load(std::sync::atomic::Ordering::Relaxed),
-            total_requests: self
-                .health
-                .total_requests
-                .load(std::sync::atomic::Ordering::Relaxed),
-            failed_requests: self
-                .health
-                .failed_requests
-                .load(std::sync::atomic::Ordering::Relaxed),
-            degraded: self
-                .health
-                .consecutive_failures
-                .load(std::sync::atomic::Ordering::Relaxed)
-                > 0,
+            consecutive_failures,
+            total_requests,
+            failed_requests,
+            degraded,
+        });
+        self.publish(KernelEvent::Health {
+            consecutive_failures,
+            total_requests,
+            failed_requests,
+            degraded,
         });
     }
 }
+
+/// The request path's connection to the kernel.
+#[derive(Clone)]
+pub struct KernelHandle {
+    tx: mpsc::Sender<KernelCommand>,
+}
+
+fn kernel_stopped() -> ObservedError {
+    ParallaxError::Internal(
+        "kernel is not running".to_string(),
+        tracing_error::SpanTrace::capture(),
+    )
+    .into()
+}
+
+impl KernelHandle {
+    async fn send(&self, cmd: KernelCommand) {
+        if self.tx.send(cmd).await.is_err() {
+            tracing::error!("[⚙️ ] Kernel is not running; dropping command");
+        }
+    }
+
+    async fn ask<T>(&self, cmd: impl FnOnce(oneshot::Sender<T>) -> KernelCommand) -> Result<T> {
+        let (resp, rx) = oneshot::channel();
+        if self.tx.send(cmd(resp)).await.is_err() {
+            return Err(kernel_stopped());
+        }
+        match rx.await {
+            Ok(value) => Ok(value),
+            Err(_) => Err(kernel_stopped()),
+        }
+    }
+
+    pub async fn update_health(&self, success: bool) {
+        self.send(KernelCommand::UpdateHealth { success }).await
+    }
+
+    /// Admits a request for `model` if both the model and the upstream-wide breaker allow it.
+    /// Keep the guard until the attempt's outcome has been recorded.
+    pub async fn check_circuit(&self, upstream: &str, model: &str) -> Result<ProbeGuard> {
+        let permit = self
+            .ask(|resp| KernelCommand::CheckCircuit {
+                upstream: upstream.to_string(),
+                model: model.to_string(),
+                resp,
+            })
+            .await??;
+        Ok(ProbeGuard {
+            permit,
+            tx: self.tx.clone(),
+        })
+    }
+
+    pub async fn record_circuit_success(&self, upstream: &str, model: &str) {
+        self.send(KernelCommand::RecordCircuitSuccess {
+            upstream: upstream.to_string(),
+            model: model.to_string(),
+        })
+        .await
+    }
+
+    pub async fn record_circuit_failure(&self, upstream: &str, model: &str) {
+        self.send(KernelCommand::RecordCircuitFailure {
+            upstream: upstream.to_string(),
+            model: model.to_string(),
+        })
+        .await
+    }
+
+    /// Records `err` against the breaker it is attributable to (see [`CircuitFault::of`]).
+    pub async fn record_circuit_error(&self, upstream: &str, model: &str, err: &ObservedError) {
+        self.send(KernelCommand::RecordCircuitFault {
+            upstream: upstream.to_string(),
+            model: model.to_string(),
+            fault: CircuitFault::of(err),
+        })
+        .await
+    }
+
+    pub async fn record_spend(&self, model: &str, usd: f64) {
+        self.send(KernelCommand::RecordSpend {
+            model: model.to_string(),
+            usd,
+        })
+        .await
+    }
+
+    /// Waits (up to the queue deadline) until the inbound rate limits admit the request.
+    /// Keep the guard until the response has been fully sent.
+    pub async fn admit(
+        &self,
+        keys: RequestKeys,
+    ) -> Result<std::result::Result<Admission, Rejection>> {
+        let admitted = self
+            .ask(|resp| KernelCommand::AdmitRequest { keys, resp })
+            .await?;
+        Ok(admitted.map(|ticket| Admission {
+            ticket: Some(ticket),
+            tx: self.tx.clone(),
+        }))
+    }
+
+    pub async fn health(&self) -> Result<HealthSnapshot> {
+        self.ask(|resp| KernelCommand::GetHealth { resp }).await
+    }
+
+    /// Passes `event` on to subscribers, in order with the kernel's own events.
+    pub async fn report(&self, event: KernelEvent) {
+        self.send(KernelCommand::Report { event }).await
+    }
+
+    /// Everything the kernel publishes from now on, in order and without gaps.
+    pub async fn subscribe(&self) -> Result<mpsc::UnboundedReceiver<KernelEvent>> {
+        self.ask(|resp| KernelCommand::Subscribe { resp }).await
+    }
+}
+
+/// An admitted request's hold on its half-open probes.
+///
+/// Dropping the guard gives back whichever probes the request still holds, so every exit path
+/// (errors, fallbacks, cancelled hedge attempts) frees the slot without counting an outcome.
+pub struct ProbeGuard {
+    permit: CircuitPermit,
+    tx: mpsc::Sender<KernelCommand>,
+}
+
+impl ProbeGuard {
+    /// True when the request was admitted as a half-open probe.
+    pub fn holds_probe(&self) -> bool {
+        !self.permit.probes.is_empty()
+    }
+}
+
+impl Drop for ProbeGuard {
+    fn drop(&mut self) {
+        if self.permit.probes.is_empty() {
+            return;
+        }
+        let cmd = KernelCommand::ReleaseProbe {
+            permit: std::mem::take(&mut self.permit),
+        };
+        send_from_drop(&self.tx, cmd, "a half-open probe");
+    }
+}
+
+/// An admitted request's hold on its inbound concurrency slots.
+///
+/// Dropping the guard hands the slots back to the kernel, which then admits whoever is queued.
+pub struct Admission {
+    ticket: Option<AdmissionTicket>,
+    tx: mpsc::Sender<KernelCommand>,
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket.take() {
+            let cmd = KernelCommand::ReleaseAdmission { ticket };
+            send_from_drop(&self.tx, cmd, "a rate-limit slot");
+        }
+    }
+}
+
+/// Sends a release command from a `Drop` impl, which cannot await a full channel.
+fn send_from_drop(tx: &mpsc::Sender<KernelCommand>, cmd: KernelCommand, what: &str) {
+    match tx.try_send(cmd) {
+        Ok(()) => {}
+        Err(mpsc::error::TrySendError::Full(cmd)) => match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let tx = tx.clone();
+                runtime.spawn(async move {
+                    let _ = tx.send(cmd).await;
+                });
+            }
+            Err(_) => tracing::warn!("[⚙️ ] No runtime to release {}", what),
+        },
+        Err(mpsc::error::TrySendError::Closed(_)) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::UPSTREAM_OPENROUTER;
+    use crate::rate_limit::LimitSpec;
+
+    fn start(threshold: u32) -> (KernelHandle, broadcast::Receiver<TuiEvent>) {
+        start_with_recovery(threshold, std::time::Duration::from_secs(30))
+    }
+
+    fn start_with_recovery(
+        threshold: u32,
+        recovery: std::time::Duration,
+    ) -> (KernelHandle, broadcast::Receiver<TuiEvent>) {
+        let (tx_tui, rx_tui) = broadcast::channel(64);
+        let (tx, rx) = mpsc::channel(KERNEL_CHANNEL_CAPACITY);
+        let registry = CircuitBreakerRegistry::new(threshold, recovery);
+        let limiter = RateLimiter::new(None, None, None, std::time::Duration::ZERO);
+        tokio::spawn(Kernel::new(registry, limiter, tx_tui, rx).run());
+        (KernelHandle { tx }, rx_tui)
+    }
+
+    fn start_with_limits(model: LimitSpec, queue_timeout: std::time::Duration) -> KernelHandle {
+        let (tx_tui, _) = broadcast::channel(64);
+        let (tx, rx) = mpsc::channel(KERNEL_CHANNEL_CAPACITY);
+        let registry = CircuitBreakerRegistry::new(3, std::time::Duration::from_secs(30));
+        let limiter = RateLimiter::new(None, None, Some(model), queue_timeout);
+        tokio::spawn(Kernel::new(registry, limiter, tx_tui, rx).run());
+        KernelHandle { tx }
+    }
+
+    fn keys(client: &str) -> RequestKeys {
+        RequestKeys {
+            client: Some(client.to_string()),
+            conversation: None,
+            model: Some("m".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_kernel_owns_health_circuits_and_spend() {
+        let (kernel, mut rx_tui) = start(2);
+        let mut events = match kernel.subscribe().await {
+            Ok(rx) => rx,
+            Err(e) => panic!("subscribe: {}", e),
+        };
+        kernel.update_health(false).await;
+        kernel.update_health(false).await;
+        kernel
+            .record_circuit_failure(UPSTREAM_OPENROUTER, "m")
+            .await;
+        kernel
+            .record_circuit_failure(UPSTREAM_OPENROUTER, "m")
+            .await;
+        kernel.record_spend("m", 0.25).await;
+        kernel.record_spend("n", 0.5).await;
+
+        assert!(kernel
+            .check_circuit(UPSTREAM_OPENROUTER, "m")
+            .await
+            .is_err());
+        assert!(kernel.check_circuit(UPSTREAM_OPENROUTER, "n").await.is_ok());
+
+        let health = match kernel.health().await {
+            Ok(h) => h,
+            Err(e) => panic!("health: {}", e),
+        };
+        assert_eq!(health.consecutive_failures, 2);
+        assert_eq!(health.failed_requests, 2);
+        assert!(health.degraded);
+        assert!(!health.upstream_open(UPSTREAM_OPENROUTER));
+        assert!((health.spent_usd - 0.75).abs() < 1e-9);
+        let mut published = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            if matches!(event, KernelEvent::Spend { .. }) {
+                break;
+            }
+            published.push(event);
+        }
+        assert!(
+            published.iter().any(|e| matches!(
+                e,
+                KernelEvent::Circuit { model, state: CircuitState::Open, .. } if model == "m"
+            )),
+            "{:?}",
+            published
+        );
+        assert_eq!(
+            published
+                .iter()
+                .filter(|e| matches!(e, KernelEvent::Health { .. }))
+                .count(),
+            2
+        );
+        match events.try_recv() {
+            Ok(KernelEvent::Spend { spent_usd, .. }) => assert!((spent_usd - 0.75).abs() < 1e-9),
+            other => panic!("expected the running total, got {:?}", other),
+        }
+        assert!(health
+            .circuits
+            .iter()
+            .any(|c| c.model == "m" && c.state == CircuitState::Open));
+
+        kernel.update_health(true).await;
+        let mut updates = Vec::new();
+        while let Ok(TuiEvent::UpstreamHealthUpdate { degraded, .. }) = rx_tui.recv().await {
+            updates.push(degraded);
+            if updates.len() == 3 {
+                break;
+            }
+        }
+        assert_eq!(updates, vec![true, true, false]);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_probe_guard_frees_the_half_open_slot() {
+        let (kernel, _rx_tui) = start_with_recovery(1, std::time::Duration::from_millis(20));
+        kernel
+            .record_circuit_failure(UPSTREAM_OPENROUTER, "m")
+            .await;
+        tokio::time::sleep(std::time::Duration::from_millis(40)).await;
+
+        let probe = match kernel.check_circuit(UPSTREAM_OPENROUTER, "m").await {
+            Ok(guard) => guard,
+            Err(e) => panic!("probe should be admitted: {}", e),
+        };
+        assert!(probe.holds_probe());
+        assert!(kernel
+            .check_circuit(UPSTREAM_OPENROUTER, "m")
+            .await
+            .is_err());
+
+        drop(probe);
+        match kernel.check_circuit(UPSTREAM_OPENROUTER, "m").await {
+            Ok(guard) => assert!(guard.holds_probe()),
+            Err(e) => panic!("released probe should be admitted again: {}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queued_admission_is_granted_when_a_slot_frees() {
+        let spec = LimitSpec {
+            rpm: None,
+            burst: None,
+            max_in_flight: Some(1),
+        };
+        let kernel = start_with_limits(spec, std::time::Duration::from_millis(300));
+
+        let first = match kernel.admit(keys("a")).await {
+            Ok(Ok(admission)) => admission,
+            _ => panic!("first request should be admitted"),
+        };
+        let waiter = {
+            let kernel = kernel.clone();
+            tokio::spawn(async move { matches!(kernel.admit(keys("b")).await, Ok(Ok(_))) })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        drop(first);
+        assert!(matches!(waiter.await, Ok(true)));
+
+        // Holding the slot past the deadline yields a rejection.
+        let _held = kernel.admit(keys("a")).await;
+        match kernel.admit(keys("c")).await {
+            Ok(Err(r)) => assert_eq!(r.reason, RejectReason::Concurrency),
+            _ => panic!("slot should still be held"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_admission_waits_for_a_token() {
+        // One token per 60ms: the second request waits for the refill instead of failing.
+        let spec = LimitSpec {
+            rpm: Some(1000.0),
+            burst: Some(1),
+            max_in_flight: None,
+        };
+        let kernel = start_with_limits(spec, std::time::Duration::from_millis(500));
+        assert!(matches!(kernel.admit(keys("a")).await, Ok(Ok(_))));
+        let started = Instant::now();
+        assert!(matches!(kernel.admit(keys("a")).await, Ok(Ok(_))));
+        assert!(started.elapsed() >= std::time::Duration::from_millis(40));
+    }
+}
@@ -2,13 +2,19 @@
 #![allow(clippy::manual_unwrap_or)]
 
 pub mod agent_layer;
+pub mod at_rest;
+pub mod bundle_store;
+pub mod cassette;
 pub mod constants;
+pub mod conversation_browser;
 pub mod db;
 pub mod debug_bundle;
 pub mod debug_utils;
 pub mod engine;
+pub mod event_feed;
 pub mod hardening;
 pub mod health;
+pub mod hedging;
 pub mod history_pruning;
 pub mod ingress;
 pub mod json_repair;
@@ -17,19 +23,30 @@ pub mod log_rotation;
 pub mod logging;
 pub mod main_helper;
 pub mod metrics;
+pub mod notify;
 pub mod pricing;
 pub mod projections;
+pub mod rate_limit;
 pub mod redaction;
 pub mod redaction_layer;
 pub mod repro_issue;
 pub mod rescue;
+pub mod retention;
+pub mod sanitizer_rules;
+pub mod search_index;
+pub mod secret_scrub;
+pub mod server;
+pub mod shutdown;
 pub mod specs;
+pub mod sse_resume;
 pub mod str_utils;
 pub mod streaming;
 pub mod tag_extract;
 pub mod token_counting;
 pub mod tool_schema;
 pub mod tui;
+pub mod tui_detail;
+pub mod tui_filter;
 pub mod types;
 
 pub use types::*;
@@ -49,19 +49,21 @@ impl LogRotationManager {
     }
 
     /// Applies retention policy for all log files whose file name starts with `log_prefix`.
+    /// Returns how many files were removed.
     ///
     /// This is safe to call periodically.
-    pub fn check_and_rotate(&self, log_dir: &Path, log_prefix: &str) -> std::io::Result<()> {
+    pub fn check_and_rotate(&self, log_dir: &Path, log_prefix: &str) -> std::io::Result<usize> {
         let mut log_files = self.find_log_files(log_dir, log_prefix)?;
-        if log_files.is_empty() {
-            return Ok(());
+        let found = log_files.len();
+        if found == 0 {
+            return Ok(0);
         }
 
         self.cleanup_by_age(&mut log_files)?;
         self.cleanup_by_total_size(&mut log_files)?;
         self.cleanup_by_max_files(&mut log_files)?;
 
-        Ok(())
+        Ok(found - log_files.len())
     }
 
     fn cleanup_by_age(&self, log_files: &mut Vec<PathBuf>) -> std::io::Result<()> {
@@ -156,6 +156,7 @@ pub struct StreamMetric {
     pub tokens: usize,
     pub tool_parts: usize,
     pub text_chars: usize,
+    pub reasoning_chars: usize,
     pub tool_names: Vec<String>,
 }
 
@@ -173,6 +174,9 @@ impl StreamMetric {
             if let Some(content) = &choice.delta.content {
                 self.text_chars += content.len();
             }
+            if let Some(reasoning) = choice.delta.extract_reasoning() {
+                self.reasoning_chars += reasoning.len();
+            }
             if let Some(tools) = &choice.delta.tool_calls {
                 self.tool_parts += tools.len();
                 for t in tools {
@@ -188,6 +192,11 @@ impl StreamMetric {
         }
     }
 
+    /// Whether any text, reasoning or tool call has streamed yet.
+    pub fn has_output(&self) -> bool {
+        self.text_chars + self.reasoning_chars + self.tool_parts > 0
+    }
+
     pub fn log_summary(&self) {
         let turn_id = get_turn_id();
         let tools_str = if self.tool_names.is_empty() {
@@ -26,12 +26,206 @@ pub struct Args {
     pub max_body_size: usize,
     #[arg(long, default_value_t = 3)]
     pub max_retries: u32,
+    /// Total time budget for upstream retries, backoff sleeps included.
+    #[arg(long, default_value_t = crate::constants::RETRY_BUDGET_SECS)]
+    pub retry_budget_secs: u64,
     #[arg(long, default_value_t = 5)]
     pub circuit_breaker_threshold: u32,
     #[arg(long, default_value_t = false)]
     pub gemini_fallback: bool,
     #[arg(long, default_value_t = false)]
     pub enable_debug_capture: bool,
+    /// How long finished SSE streams stay resumable via `Last-Event-ID` (0 disables).
+    #[arg(long, default_value_t = 60)]
+    pub sse_resume_window_secs: u64,
+    /// How long a stream whose client disconnected keeps reading the upstream while waiting
+    /// for a `Last-Event-ID` resume before cancelling it.
+    #[arg(long, default_value_t = crate::constants::SSE_RESUME_DETACH_GRACE_SECS)]
+    pub sse_resume_grace_secs: u64,
+    /// Fire a hedged upstream request when no first line arrives within this many ms (0 disables).
+    #[arg(long, default_value_t = 0)]
+    pub hedge_ttft_ms: u64,
+    /// Model used for the hedged request (defaults to the requested model).
+    #[arg(long)]
+    pub hedge_alternate_model: Option<String>,
+    /// Only hedge these models (comma-separated; empty hedges every model).
+    #[arg(long, value_delimiter = ',')]
+    pub hedge_models: Vec<String>,
+    /// Models to try, in order, when the requested model's circuit breaker is open.
+    #[arg(long, value_delimiter = ',')]
+    pub fallback_models: Vec<String>,
+    /// Inbound limit per client key, e.g. `rpm=60,burst=10,inflight=4`.
+    #[arg(long)]
+    pub client_limit: Option<crate::rate_limit::LimitSpec>,
+    /// Inbound limit per conversation id, e.g. `rpm=20,inflight=1`.
+    #[arg(long)]
+    pub conversation_limit: Option<crate::rate_limit::LimitSpec>,
+    /// Inbound limit per requested model, e.g. `inflight=8`.
+    #[arg(long)]
+    pub model_limit: Option<crate::rate_limit::LimitSpec>,
+    /// How long a limited request may queue before it is rejected with a 429.
+    #[arg(long, default_value_t = 5000)]
+    pub rate_limit_queue_ms: u64,
+    /// Base URL of the OpenAI-compatible upstream (override to point at a mock or proxy).
+    #[arg(long, default_value = crate::constants::OPENROUTER_BASE_URL)]
+    pub upstream_url: String,
+    /// Directory debug bundles are written to and served from.
+    #[arg(long, default_value = "debug_capture")]
+    pub debug_capture_dir: String,
+    /// Record raw upstream and client SSE lines per turn (`upstream_sse`/`client_sse` blobs).
+    #[arg(long, default_value_t = false)]
+    pub capture_sse: bool,
+    /// Where debug bundles are stored: loose files, or deduplicated objects indexed in SQLite.
+    #[arg(long, value_enum, default_value_t = crate::bundle_store::BundleStoreKind::Files)]
+    pub bundle_store: crate::bundle_store::BundleStoreKind,
+    /// How often the retention janitor prunes bundles, reports, database rows and logs (0 disables).
+    #[arg(long, default_value_t = 300)]
+    pub retention_interval_secs: u64,
+    /// Age after which captured data, database state and logs are deleted.
+    #[arg(long, default_value_t = crate::constants::DB_CLEANUP_RETENTION_DAYS as u64 * 24)]
+    pub retention_max_age_hours: u64,
+    /// Age limit for conversations that recorded issues (kept longer for debugging).
+    #[arg(long, default_value_t = 30 * 24)]
+    pub retention_issue_max_age_hours: u64,
+    /// Total size of debug bundles before the oldest conversations are deleted.
+    #[arg(long, default_value_t = 100 * 1024 * 1024)]
+    pub retention_max_bundle_bytes: u64,
+    /// Number of captured conversations kept.
+    #[arg(long, default_value_t = 1000)]
+    pub retention_max_conversations: usize,
+    /// Encrypt captures and sensitive database columns with the key in this file
+    /// (defaults to the `PARALLAX_ENCRYPTION_KEY` environment variable; unset stores plaintext).
+    #[arg(long)]
+    pub encryption_key_file: Option<std::path::PathBuf>,
+    /// Replace secrets found in user text and tool results with placeholders before the
+    /// request is sent upstream.
+    #[arg(long, default_value_t = false)]
+    pub scrub_secrets: bool,
+    /// Put the real values back when the model uses a placeholder in tool-call arguments.
+    #[arg(long, default_value_t = false, requires = "scrub_secrets")]
+    pub restore_secrets: bool,
+    /// JSON file with custom redaction rules (extra detectors, disabled built-ins,
+    /// allow-listed and secret key names) for logs, captures and exports.
+    #[arg(long)]
+    pub redaction_rules: Option<std::path::PathBuf>,
+    /// JSON file with extra tool-call sanitizer rules (argument rewrites, schema patches)
+    /// and built-in rules to disable.
+    #[arg(long)]
+    pub sanitizer_rules: Option<std::path::PathBuf>,
+    /// Run without the TUI (for systemd, Docker or non-interactive sessions); logs go to stdout.
+    #[arg(long, default_value_t = false)]
+    pub headless: bool,
+    /// Format of the stdout log in headless mode.
+    #[arg(long, value_enum, default_value_t = LogFormat::Human)]
+    pub log_format: LogFormat,
+    /// How long a headless shutdown waits for in-flight streams before exiting.
+    #[arg(long, default_value_t = 30)]
+    pub shutdown_grace_secs: u64,
+    /// Highlight dashboard requests past a threshold, e.g. `cost>0.5,latency>60s`. Saved to
+    /// `--tui-state` and reused when the flag is omitted.
+    #[arg(long, value_delimiter = ',')]
+    pub tui_highlight: Vec<crate::tui_filter::HighlightRule>,
+    /// File the dashboard keeps its settings (highlight rules) in between runs.
+    #[arg(long, default_value = "tui_state.json")]
+    pub tui_state: std::path::PathBuf,
+    /// POST a JSON alert here on upstream failures, open circuits and budget thresholds.
+    #[arg(long)]
+    pub notify_webhook: Option<String>,
+    /// Shell command run per alert, with the alert JSON on stdin and `PARALLAX_ALERT_*` set.
+    #[arg(long)]
+    pub notify_command: Option<String>,
+    /// Session spend (USD) thresholds that each raise one alert, e.g. `1,5,20`.
+    #[arg(long, value_delimiter = ',')]
+    pub notify_budget_usd: Vec<f64>,
+    /// Consecutive upstream failures before alerting that the upstream is failing.
+    #[arg(long, default_value_t = 3)]
+    pub notify_failure_threshold: u32,
+    /// Alerts of the same kind within this window are sent once, with a count.
+    #[arg(long, default_value_t = 15_000)]
+    pub notify_debounce_ms: u64,
+    /// Hold streamed tool calls until their arguments validate against the advertised schema;
+    /// when they don't, retry once with the violations instead of forwarding the bad call.
+    #[arg(long, default_value_t = false)]
+    pub tool_arg_retry: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Convert a captured turn into a replayable regression fixture.
+    Cassette(crate::cassette::CassetteArgs),
+    /// Re-encrypt captures and database columns under a new key (or back to plaintext).
+    RotateKey(crate::at_rest::RotateKeyArgs),
+    /// Run the proxy without the TUI; same as `--headless`.
+    Serve,
+    /// Show the dashboard of a running (e.g. headless) instance.
+    Tui(TuiArgs),
+    /// Print alert webhooks as they arrive; a local stand-in for `--notify-webhook`.
+    WebhookSink(crate::notify::WebhookSinkArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct TuiArgs {
+    /// Address of the instance to attach to (`host:port` or a base URL).
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub connect: String,
+}
+
+/// Stdout log format for headless runs.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Human,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+impl Args {
+    /// Upstream chat completions endpoint derived from `--upstream-url`.
+    pub fn chat_completions_url(&self) -> String {
+        format!(
+            "{}/chat/completions",
+            self.upstream_url.trim_end_matches('/')
+        )
+    }
+
+    /// Whether to run without the TUI (`--headless` or `parallax serve`).
+    pub fn is_headless(&self) -> bool {
+        self.headless || matches!(self.command, Some(Command::Serve))
+    }
+
+    /// The at-rest encryption key configured by `--encryption-key-file` or the environment.
+    pub fn keyring(&self) -> Result<crate::at_rest::Keyring> {
+        crate::at_rest::Keyring::load(
+            self.encryption_key_file.as_deref(),
+            crate::constants::ENCRYPTION_KEY_ENV,
+        )
+    }
+
+    /// Built-in redaction rules extended by `--redaction-rules`.
+    pub fn redaction_rules(&self) -> Result<crate::redaction::RedactionRules> {
+        crate::redaction::RedactionRules::load(self.redaction_rules.as_deref())
+    }
+
+    /// Built-in sanitizer rules extended by `--sanitizer-rules`.
+    pub fn sanitizer_rules(&self) -> Result<crate::sanitizer_rules::SanitizerRules> {
+        crate::sanitizer_rules::SanitizerRules::load(self.sanitizer_rules.as_deref())
+    }
+
+    /// The requested model followed by its fallbacks, used when circuit breakers are open.
+    pub fn fallback_chain(&self, model_id: &str) -> Vec<String> {
+        let mut chain = vec![model_id.to_string()];
+        if self.gemini_fallback && model_id.contains("gemini-3-pro") {
+            chain.push(crate::constants::GEMINI_FLASH_FALLBACK.to_string());
+        }
+        for model in &self.fallback_models {
+            if !chain.contains(model) {
+                chain.push(model.clone());
+            }
+        }
+        chain
+    }
 }
 
 #[derive(Clone)]
@@ -43,9 +237,61 @@ pub struct AppState {
     pub pricing: Arc<std::collections::HashMap<String, CostModel>>,
     pub disable_rescue: bool,
     pub args: Arc<Args>,
-    pub tx_kernel: tokio::sync::mpsc::Sender<crate::kernel::KernelCommand>,
-    pub health: Arc<crate::types::UpstreamHealth>,
-    pub circuit_breaker: Arc<crate::hardening::CircuitBreaker>,
+    /// Owns upstream health, circuit breakers, spend and the inbound rate limiter.
+    pub kernel: crate::kernel::KernelHandle,
+    pub sse_resume: Arc<crate::sse_resume::ResumeRegistry>,
+    pub bundle_store: Arc<dyn crate::bundle_store::BundleStore>,
+    pub retention: Arc<crate::retention::RetentionStats>,
+    pub in_flight: Arc<crate::shutdown::InFlight>,
+    /// Unredacted projected requests of in-flight turns, for retries that re-send them.
+    pub projected_requests: Arc<crate::streaming::ProjectedRequests>,
+    pub events: Arc<crate::event_feed::EventFeed>,
+}
+
+impl AppState {
+    /// Bundle writer over the configured bundle store, wired to the search index.
+    pub fn bundles(&self) -> crate::debug_bundle::BundleManager {
+        crate::debug_bundle::BundleManager::with_store(self.bundle_store.clone())
+            .with_search_index(self.db.clone())
+    }
+
+    /// Assembles the shared state and starts the kernel, configured from `args`.
+    pub fn new(
+        args: Arc<Args>,
+        client: reqwest::Client,
+        openrouter_key: String,
+        db: DbPool,
+        tx_tui: broadcast::Sender<TuiEvent>,
+        pricing: std::collections::HashMap<String, CostModel>,
+    ) -> Self {
+        let kernel = crate::kernel::spawn(&args, tx_tui.clone());
+        let bundle_store =
+            crate::bundle_store::open(args.bundle_store, &args.debug_capture_dir, &db);
+        let events = crate::event_feed::EventFeed::spawn(&tx_tui);
+
+        Self {
+            client,
+            openrouter_key,
+            db,
+            tx_tui,
+            pricing: Arc::new(pricing),
+            disable_rescue: args.disable_rescue,
+            kernel,
+            sse_resume: Arc::new(
+                crate::sse_resume::ResumeRegistry::new(
+                    std::time::Duration::from_secs(args.sse_resume_window_secs),
+                    crate::constants::SSE_RESUME_MAX_EVENTS,
+                )
+                .with_detach_grace(std::time::Duration::from_secs(args.sse_resume_grace_secs)),
+            ),
+            bundle_store,
+            retention: Arc::new(crate::retention::RetentionStats::default()),
+            in_flight: Arc::new(crate::shutdown::InFlight::default()),
+            projected_requests: Arc::new(crate::streaming::ProjectedRequests::default()),
+            events,
+            args,
+        }
+    }
 }
 
 pub struct CostBreakdown {
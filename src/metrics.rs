@@ -3,7 +3,14 @@
 //! Tracks aggregated metrics for tool arguments, JSON parsing, and provider-specific issues
 //! to reduce log noise while maintaining observability.
 
+use crate::hardening::CircuitState;
+use crate::AppState;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
 use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -113,6 +120,148 @@ impl MetricsAggregator {
     }
 }
 
+/// A single Prometheus metric family in text exposition format.
+pub struct PromMetric<'a> {
+    pub name: &'a str,
+    pub help: &'a str,
+    pub kind: &'a str,
+    pub samples: Vec<(Vec<(&'a str, String)>, f64)>,
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub fn render_prometheus(metrics: &[PromMetric<'_>]) -> String {
+    let mut out = String::new();
+    for metric in metrics {
+        let _ = writeln!(out, "# HELP {} {}", metric.name, metric.help);
+        let _ = writeln!(out, "# TYPE {} {}", metric.name, metric.kind);
+        for (labels, value) in &metric.samples {
+            if labels.is_empty() {
+                let _ = writeln!(out, "{} {}", metric.name, value);
+            } else {
+                let rendered: Vec<String> = labels
+                    .iter()
+                    .map(|(k, v)| format!("{}=\"{}\"", k, escape_label(v)))
+                    .collect();
+                let _ = writeln!(out, "{}{{{}}} {}", metric.name, rendered.join(","), value);
+            }
+        }
+    }
+    out
+}
+
+fn circuit_state_value(state: CircuitState) -> f64 {
+    match state {
+        CircuitState::Closed => 0.0,
+        CircuitState::HalfOpen => 1.0,
+        CircuitState::Open => 2.0,
+    }
+}
+
+/// `GET /metrics` in Prometheus text format.
+pub async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    let (total_requests, failed_requests, circuits, spend_by_model) =
+        match state.kernel.health().await {
+            Ok(health) => (
+                health.total_requests,
+                health.failed_requests,
+                health.circuits,
+                health.spend_by_model,
+            ),
+            Err(e) => {
+                tracing::error!("Metrics: {}", e);
+                (0, 0, Vec::new(), Default::default())
+            }
+        };
+    let metrics = vec![
+        PromMetric {
+            name: "parallax_upstream_requests_total",
+            help: "Upstream requests attempted.",
+            kind: "counter",
+            samples: vec![(vec![], total_requests as f64)],
+        },
+        PromMetric {
+            name: "parallax_upstream_failures_total",
+            help: "Upstream requests that failed.",
+            kind: "counter",
+            samples: vec![(vec![], failed_requests as f64)],
+        },
+        PromMetric {
+            name: "parallax_spend_usd_total",
+            help: "Estimated spend this session in USD, by model.",
+            kind: "counter",
+            samples: spend_by_model
+                .into_iter()
+                .map(|(model, usd)| (vec![("model", model)], usd))
+                .collect(),
+        },
+        PromMetric {
+            name: "parallax_circuit_state",
+            help: "Circuit breaker state (0 = closed, 1 = half-open, 2 = open).",
+            kind: "gauge",
+            samples: circuits
+                .iter()
+                .map(|c| {
+                    (
+                        vec![("upstream", c.upstream.clone()), ("model", c.model.clone())],
+                        circuit_state_value(c.state),
+                    )
+                })
+                .collect(),
+        },
+        PromMetric {
+            name: "parallax_circuit_consecutive_failures",
+            help: "Consecutive failures recorded by each circuit breaker.",
+            kind: "gauge",
+            samples: circuits
+                .iter()
+                .map(|c| {
+                    (
+                        vec![("upstream", c.upstream.clone()), ("model", c.model.clone())],
+                        c.consecutive_failures as f64,
+                    )
+                })
+                .collect(),
+        },
+        PromMetric {
+            name: "parallax_retention_runs_total",
+            help: "Retention janitor passes completed.",
+            kind: "counter",
+            samples: vec![(vec![], state.retention.runs.load(Ordering::Relaxed) as f64)],
+        },
+        PromMetric {
+            name: "parallax_retention_deleted_total",
+            help: "Items deleted by the retention janitor, by target.",
+            kind: "counter",
+            samples: state
+                .retention
+                .deleted_by_target()
+                .into_iter()
+                .map(|(target, n)| (vec![("target", target.to_string())], n as f64))
+                .collect(),
+        },
+        PromMetric {
+            name: "parallax_retention_bundle_bytes_freed_total",
+            help: "Debug bundle bytes freed by the retention janitor.",
+            kind: "counter",
+            samples: vec![(
+                vec![],
+                state.retention.bundle_bytes_freed.load(Ordering::Relaxed) as f64,
+            )],
+        },
+    ];
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render_prometheus(&metrics),
+    )
+}
+
 impl Default for MetricsAggregator {
     fn default() -> Self {
         Self::new()
@@ -123,6 +272,24 @@ impl Default for MetricsAggregator {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_render_prometheus() {
+        let text = render_prometheus(&[PromMetric {
+            name: "parallax_circuit_state",
+            help: "Circuit state.",
+            kind: "gauge",
+            samples: vec![(
+                vec![
+                    ("upstream", "openrouter".to_string()),
+                    ("model", "a\"b".to_string()),
+                ],
+                2.0,
+            )],
+        }]);
+        assert!(text.contains("# TYPE parallax_circuit_state gauge"));
+        assert!(text.contains("parallax_circuit_state{upstream=\"openrouter\",model=\"a\\\"b\"} 2"));
+    }
+
     #[tokio::test]
     async fn test_record_empty_args() {
         let agg = MetricsAggregator::new();
@@ -0,0 +1,502 @@
+//! Failure and budget notifications.
+//!
+//! The notifier subscribes to the kernel's events (circuit breakers, upstream health, failed
+//! requests and streams, spend) and turns what matters into [`Alert`]s. That feed never drops
+//! events, unlike the dashboard bus, so a burst cannot hide a failure or a budget crossing. Alerts with the same
+//! key are debounced: the first event opens a window and one alert carrying the event count
+//! goes out when it closes, so a burst of failures pages once. Each alert is POSTed as JSON to
+//! `--notify-webhook` and/or handed to `--notify-command` (JSON on stdin, summary in
+//! `PARALLAX_ALERT_*` environment variables). `parallax webhook-sink` is a local stand-in
+//! receiver that prints what it gets.
+
+use crate::hardening::CircuitState;
+use crate::kernel::KernelEvent;
+use crate::main_helper::{AppState, Args};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+pub struct NotifyConfig {
+    pub webhook_url: Option<String>,
+    pub command: Option<String>,
+    /// Session spend thresholds in dollars, ascending.
+    pub budget_usd: Vec<f64>,
+    /// Consecutive upstream failures before an `upstream_failing` alert.
+    pub failure_threshold: u32,
+    pub debounce: Duration,
+}
+
+impl NotifyConfig {
+    /// The configured notifications, or `None` when no webhook or command is set.
+    pub fn from_args(args: &Args) -> Option<Self> {
+        if args.notify_webhook.is_none() && args.notify_command.is_none() {
+            return None;
+        }
+        let mut budget_usd: Vec<f64> = args
+            .notify_budget_usd
+            .iter()
+            .copied()
+            .filter(|usd| usd.is_finite() && *usd > 0.0)
+            .collect();
+        budget_usd.sort_by(f64::total_cmp);
+        Some(Self {
+            webhook_url: args.notify_webhook.clone(),
+            command: args.notify_command.clone(),
+            budget_usd,
+            failure_threshold: args.notify_failure_threshold.max(1),
+            debounce: Duration::from_millis(args.notify_debounce_ms),
+        })
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    CircuitOpen,
+    UpstreamFailing,
+    RequestFailed,
+    StreamError,
+    Budget,
+}
+
+impl AlertKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertKind::CircuitOpen => "circuit_open",
+            AlertKind::UpstreamFailing => "upstream_failing",
+            AlertKind::RequestFailed => "request_failed",
+            AlertKind::StreamError => "stream_error",
+            AlertKind::Budget => "budget",
+        }
+    }
+}
+
+/// The webhook payload.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub source: String,
+    pub kind: AlertKind,
+    pub title: String,
+    /// The most recent event's description.
+    pub message: String,
+    /// Events folded into this alert by debouncing.
+    pub count: u32,
+    pub first_at: String,
+    pub last_at: String,
+}
+
+/// One notable event, before debouncing.
+#[derive(Debug, Clone, PartialEq)]
+struct Signal {
+    key: String,
+    kind: AlertKind,
+    title: String,
+    message: String,
+}
+
+/// Decides which events are worth an alert; remembers which budgets have been passed.
+struct Rules {
+    budget_usd: Vec<f64>,
+    failure_threshold: u32,
+    budgets_crossed: usize,
+}
+
+impl Rules {
+    fn new(config: &NotifyConfig) -> Self {
+        Self {
+            budget_usd: config.budget_usd.clone(),
+            failure_threshold: config.failure_threshold,
+            budgets_crossed: 0,
+        }
+    }
+
+    fn observe(&mut self, event: &KernelEvent) -> Vec<Signal> {
+        match event {
+            KernelEvent::Circuit {
+                upstream,
+                model,
+                state: CircuitState::Open,
+                consecutive_failures,
+            } => vec![Signal {
+                key: format!("circuit_open:{}/{}", upstream, model),
+                kind: AlertKind::CircuitOpen,
+                title: format!("Circuit open for {}", model),
+                message: format!(
+                    "{} consecutive failures on {}; requests fail fast or fall back until it recovers",
+                    consecutive_failures, upstream
+                ),
+            }],
+            KernelEvent::Health {
+                consecutive_failures,
+                total_requests,
+                failed_requests,
+                degraded: true,
+            } if *consecutive_failures >= self.failure_threshold => vec![Signal {
+                key: "upstream_failing".to_string(),
+                kind: AlertKind::UpstreamFailing,
+                title: "Upstream failing".to_string(),
+                message: format!(
+                    "{} consecutive upstream failures ({} of {} requests failed)",
+                    consecutive_failures, failed_requests, total_requests
+                ),
+            }],
+            KernelEvent::RequestFinished { id, status } if *status >= 500 => vec![Signal {
+                key: "request_failed".to_string(),
+                kind: AlertKind::RequestFailed,
+                title: "Requests failing".to_string(),
+                message: format!(
+                    "request {} finished with HTTP {}",
+                    crate::str_utils::prefix_chars(id, 8),
+                    status
+                ),
+            }],
+            KernelEvent::StreamFailed { model, message, .. } => vec![Signal {
+                key: format!("stream_error:{}", model),
+                kind: AlertKind::StreamError,
+                title: format!("Stream failed on {}", model),
+                message: message.clone(),
+            }],
+            // The kernel's ledger total, the same one `/admin/health` shows
+            KernelEvent::Spend { spent_usd, .. } => {
+                let mut signals = Vec::new();
+                while let Some(threshold) = self.budget_usd.get(self.budgets_crossed) {
+                    if *spent_usd < *threshold {
+                        break;
+                    }
+                    signals.push(Signal {
+                        key: format!("budget:{}", threshold),
+                        kind: AlertKind::Budget,
+                        title: format!("Spend passed ${:.2}", threshold),
+                        message: format!("Session spend is ${:.4}", spent_usd),
+                    });
+                    self.budgets_crossed += 1;
+                }
+                signals
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+struct Pending {
+    due: Instant,
+    alert: Alert,
+}
+
+/// Folds signals with the same key into one alert per window.
+struct Debouncer {
+    window: Duration,
+    pending: BTreeMap<String, Pending>,
+}
+
+impl Debouncer {
+    fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    fn push(&mut self, signal: Signal, now: Instant) {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        match self.pending.get_mut(&signal.key) {
+            Some(pending) => {
+                pending.alert.count += 1;
+                pending.alert.message = signal.message;
+                pending.alert.last_at = timestamp;
+            }
+            None => {
+                self.pending.insert(
+                    signal.key,
+                    Pending {
+                        due: now + self.window,
+                        alert: Alert {
+                            source: "parallax".to_string(),
+                            kind: signal.kind,
+                            title: signal.title,
+                            message: signal.message,
+                            count: 1,
+                            first_at: timestamp.clone(),
+                            last_at: timestamp,
+                        },
+                    },
+                );
+            }
+        }
+    }
+
+    fn next_due(&self) -> Option<Instant> {
+        self.pending.values().map(|p| p.due).min()
+    }
+
+    /// Alerts whose window has closed by `now`.
+    fn take_due(&mut self, now: Instant) -> Vec<Alert> {
+        let due: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.due <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+        due.iter()
+            .filter_map(|key| self.pending.remove(key))
+            .map(|p| p.alert)
+            .collect()
+    }
+
+    fn take_all(&mut self) -> Vec<Alert> {
+        std::mem::take(&mut self.pending)
+            .into_values()
+            .map(|p| p.alert)
+            .collect()
+    }
+}
+
+/// Starts the notifier when a webhook or command is configured.
+pub async fn spawn(state: Arc<AppState>) -> Option<tokio::task::JoinHandle<()>> {
+    let config = Arc::new(NotifyConfig::from_args(&state.args)?);
+    let rx = match state.kernel.subscribe().await {
+        Ok(rx) => rx,
+        Err(e) => {
+            tracing::error!("Notifier not started: {}", e);
+            return None;
+        }
+    };
+    Some(tokio::spawn(run(config, rx, state.client.clone())))
+}
+
+async fn run(
+    config: Arc<NotifyConfig>,
+    mut rx: mpsc::UnboundedReceiver<KernelEvent>,
+    client: reqwest::Client,
+) {
+    let mut rules = Rules::new(&config);
+    let mut debouncer = Debouncer::new(config.debounce);
+    loop {
+        let window_closes = async {
+            match debouncer.next_due() {
+                Some(due) => tokio::time::sleep_until(due).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => {
+                    for signal in rules.observe(&event) {
+                        debouncer.push(signal, Instant::now());
+                    }
+                }
+                None => break,
+            },
+            _ = window_closes => {
+                for alert in debouncer.take_due(Instant::now()) {
+                    tokio::spawn(dispatch(config.clone(), client.clone(), alert));
+                }
+            }
+        }
+    }
+    for alert in debouncer.take_all() {
+        dispatch(config.clone(), client.clone(), alert).await;
+    }
+}
+
+async fn dispatch(config: Arc<NotifyConfig>, client: reqwest::Client, mut alert: Alert) {
+    alert.title = crate::redaction::redact_text(&alert.title);
+    alert.message = crate::redaction::redact_text(&alert.message);
+    tracing::warn!(
+        "[🔔] {} ({} event{}): {}",
+        alert.title,
+        alert.count,
+        if alert.count == 1 { "" } else { "s" },
+        alert.message
+    );
+
+    if let Some(url) = &config.webhook_url {
+        match client
+            .post(url)
+            .timeout(Duration::from_secs(10))
+            .json(&alert)
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => tracing::warn!("Alert webhook returned HTTP {}", response.status()),
+            Err(e) => tracing::warn!("Alert webhook failed: {}", e),
+        }
+    }
+
+    if let Some(command) = &config.command {
+        if let Err(e) = run_command(command, &alert).await {
+            tracing::warn!("Alert command failed: {}", e);
+        }
+    }
+}
+
+/// Runs `command` through the shell with the alert JSON on stdin.
+async fn run_command(command: &str, alert: &Alert) -> std::io::Result<()> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut shell = if cfg!(windows) {
+        let mut cmd = tokio::process::Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    let mut child = shell
+        .arg(command)
+        .env("PARALLAX_ALERT_KIND", alert.kind.as_str())
+        .env("PARALLAX_ALERT_TITLE", &alert.title)
+        .env("PARALLAX_ALERT_MESSAGE", &alert.message)
+        .env("PARALLAX_ALERT_COUNT", alert.count.to_string())
+        .stdin(std::process::Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        match serde_json::to_vec(alert) {
+            // A command that ignores stdin may close it early; that is not a failure.
+            Ok(payload) => {
+                let _ = stdin.write_all(&payload).await;
+            }
+            Err(e) => tracing::warn!("Failed to serialize alert for the command: {}", e),
+        }
+    }
+    match tokio::time::timeout(Duration::from_secs(30), child.wait()).await {
+        Ok(status) => {
+            let status = status?;
+            if status.success() {
+                Ok(())
+            } else {
+                Err(std::io::Error::other(format!("exited with {}", status)))
+            }
+        }
+        Err(_) => Err(std::io::Error::other("timed out after 30s")),
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct WebhookSinkArgs {
+    /// Address to accept alert webhooks on.
+    #[arg(long, default_value = "127.0.0.1:9099")]
+    pub listen: String,
+}
+
+/// A receiver for alert webhooks that hands each JSON body to `on_alert`.
+pub fn webhook_sink_router<F>(on_alert: F) -> axum::Router
+where
+    F: Fn(serde_json::Value) + Clone + Send + Sync + 'static,
+{
+    axum::Router::new().fallback(move |axum::Json(body): axum::Json<serde_json::Value>| {
+        let on_alert = on_alert.clone();
+        async move {
+            on_alert(body);
+            axum::http::StatusCode::NO_CONTENT
+        }
+    })
+}
+
+/// `parallax webhook-sink`: prints every alert it receives, one JSON object per line.
+pub async fn run_webhook_sink(args: &WebhookSinkArgs) -> std::io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(&args.listen).await?;
+    println!(
+        "Listening for alert webhooks on http://{}/",
+        listener.local_addr()?
+    );
+    let app = webhook_sink_router(|alert| println!("{}", alert));
+    axum::serve(listener, app).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(budget_usd: Vec<f64>) -> NotifyConfig {
+        NotifyConfig {
+            webhook_url: None,
+            command: Some("true".to_string()),
+            budget_usd,
+            failure_threshold: 3,
+            debounce: Duration::from_secs(10),
+        }
+    }
+
+    fn spent(spent_usd: f64) -> KernelEvent {
+        KernelEvent::Spend {
+            model: "m".to_string(),
+            usd: 0.1,
+            spent_usd,
+        }
+    }
+
+    fn health(consecutive_failures: u32) -> KernelEvent {
+        KernelEvent::Health {
+            consecutive_failures,
+            total_requests: 10,
+            failed_requests: consecutive_failures as u64,
+            degraded: consecutive_failures > 0,
+        }
+    }
+
+    #[test]
+    fn test_rules_pick_failures_and_budget_crossings() {
+        let mut rules = Rules::new(&config(vec![0.5, 1.0, 5.0]));
+        assert!(rules.observe(&health(2)).is_empty());
+        assert_eq!(
+            rules.observe(&health(3))[0].kind,
+            AlertKind::UpstreamFailing
+        );
+        let circuit = rules.observe(&KernelEvent::Circuit {
+            upstream: "openrouter".to_string(),
+            model: "openai/gpt-4o".to_string(),
+            state: CircuitState::Open,
+            consecutive_failures: 5,
+        });
+        assert_eq!(circuit[0].key, "circuit_open:openrouter/openai/gpt-4o");
+        assert!(rules
+            .observe(&KernelEvent::RequestFinished {
+                id: "r".to_string(),
+                status: 429,
+            })
+            .is_empty());
+
+        assert!(rules.observe(&spent(0.4)).is_empty());
+        let crossed: Vec<String> = rules
+            .observe(&spent(1.1))
+            .into_iter()
+            .map(|s| s.title)
+            .collect();
+        assert_eq!(crossed, vec!["Spend passed $0.50", "Spend passed $1.00"]);
+        assert!(rules.observe(&spent(1.2)).is_empty());
+    }
+
+    #[test]
+    fn test_debouncer_folds_a_burst_into_one_alert() {
+        let mut debouncer = Debouncer::new(Duration::from_secs(10));
+        let signal = |message: &str| Signal {
+            key: "request_failed".to_string(),
+            kind: AlertKind::RequestFailed,
+            title: "Requests failing".to_string(),
+            message: message.to_string(),
+        };
+        let start = Instant::now();
+        debouncer.push(signal("first"), start);
+        debouncer.push(signal("second"), start + Duration::from_secs(3));
+        debouncer.push(signal("third"), start + Duration::from_secs(9));
+        assert_eq!(debouncer.next_due(), Some(start + Duration::from_secs(10)));
+        assert!(debouncer
+            .take_due(start + Duration::from_secs(9))
+            .is_empty());
+
+        let alerts = debouncer.take_due(start + Duration::from_secs(10));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].count, 3);
+        assert_eq!(alerts[0].message, "third");
+        assert_eq!(debouncer.next_due(), None);
+
+        debouncer.push(signal("later"), start + Duration::from_secs(11));
+        assert_eq!(debouncer.take_all().len(), 1);
+    }
+}
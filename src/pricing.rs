@@ -2,6 +2,7 @@ use crate::types::CostModel;
 
 pub async fn fetch_pricing(
     client: &reqwest::Client,
+    base_url: &str,
 ) -> std::collections::HashMap<String, CostModel> {
     let mut attempts = 0;
     let max_attempts = 3;
@@ -9,7 +10,7 @@ pub async fn fetch_pricing(
     loop {
         attempts += 1;
         match client
-            .get("https://openrouter.ai/api/v1/models")
+            .get(format!("{}/models", base_url.trim_end_matches('/')))
             .send()
             .await
         {
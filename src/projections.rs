@@ -107,6 +107,7 @@ impl OpenRouterAdapter {
         db: &crate::db::DbPool,
         _intent: Option<crate::tui::Intent>,
         pricing_map: &std::collections::HashMap<String, CostModel>,
+        vault: Option<&mut crate::secret_scrub::SecretVault>,
     ) -> OpenAiRequest {
         tracing::info!("[⚙️  -> ⚙️ ] Projecting turn for model: {}", model_id);
         let is_thinking = model_id.contains("thinking")
@@ -118,7 +119,7 @@ impl OpenRouterAdapter {
         // Extract and prune history if needed (Google depth and general context length)
         let pruned_context = Self::prune_history_if_needed(context, flavor, model_id, pricing_map);
 
-        let messages = Self::transform_messages(&pruned_context, flavor, db).await;
+        let messages = Self::transform_messages(&pruned_context, flavor, db, vault).await;
 
         let (max_tokens, max_completion_tokens, extra) =
             Self::extract_request_config(context, is_thinking);
@@ -313,21 +314,9 @@ impl OpenRouterAdapter {
                     .or_else(|| obj.get("function").and_then(|f| f.get("parameters")));
 
                 if let (Some(n), Some(p)) = (name.and_then(|v| v.as_str()), parameters) {
-                    let mut final_params = p.clone();
-
-                    // PATCH: Fix grep schema for models that get confused
-                    if n == "grep" {
-                        if let Some(props) = final_params
-                            .get_mut("properties")
-                            .and_then(|v| v.as_object_mut())
-                        {
-                            // Ripgrep treats -C as mutually exclusive with -A and -B.
-                            // We remove -C from the schema to force the model to use -A/-B if it wants context,
-                            // or we could rewrite the descriptions. Removing -C is the safest way to
-                            // ensure the model doesn't send conflicting flags.
-                            props.remove("-C");
-                        }
-                    }
+                    // Schema patches (e.g. grep's -C) are applied after projection by the
+                    // sanitizer rules, see `SanitizerRules::patch_tools`.
+                    let final_params = p.clone();
 
                     projected_tools.push(OpenAiTool {
                         r#type: "function".to_string(),
@@ -356,6 +345,7 @@ impl OpenRouterAdapter {
         context: &ConversationContext,
         flavor: &dyn ProviderFlavor,
         db: &crate::db::DbPool,
+        mut vault: Option<&mut crate::secret_scrub::SecretVault>,
     ) -> Vec<OpenAiMessage> {
         let mut messages = Vec::new();
         let history_len = context.history.len();
@@ -364,9 +354,9 @@ impl OpenRouterAdapter {
             let _is_last_turn = i == history_len - 1;
             let is_cache_breakpoint = flavor.kind() == ProviderKind::Anthropic
                 && i > 0
-                && (i == history_len - 3 || i == history_len - 5);
+                && (i + 3 == history_len || i + 5 == history_len);
 
-            let msg = match record.role {
+            let mut msg = match record.role {
                 Role::System | Role::Developer => Self::transform_system_message(record, flavor),
                 Role::User => Self::transform_user_message(record, flavor, is_cache_breakpoint),
                 Role::Assistant | Role::Model => {
@@ -393,6 +383,11 @@ impl OpenRouterAdapter {
                 }
             }
 
+            // Outbound secret scrubbing (--scrub-secrets)
+            if let Some(vault) = vault.as_deref_mut() {
+                vault.scrub_message(&mut msg, i);
+            }
+
             messages.push(msg);
         }
         messages
@@ -473,30 +468,28 @@ impl OpenRouterAdapter {
             }
         }
 
-        let text_content = text_parts.join("\n");
+        let mut text_content = text_parts.join("\n");
 
         if tool_calls.is_empty() {
-            if let Some(rescue) = crate::rescue::detect_xml_invoke(&text_content) {
-                tracing::info!(
-                    "[RESCUE-PROJECT] Converted XML in history to ToolCall: {}",
-                    rescue.name
-                );
-                tool_calls.push(OpenAiToolCall {
-                    id: match rescue.tool_call["id"].as_str() {
-                        Some(s) if !s.is_empty() => s.to_string(),
-                        _ => "gen_id".to_string(),
-                    },
-                    r#type: "function".to_string(),
-                    function: OpenAiFunctionCall {
-                        name: rescue.name,
-                        arguments: match rescue.tool_call["function"]["arguments"].as_str() {
-                            Some(s) if !s.is_empty() => s.to_string(),
-                            _ => "{}".to_string(),
+            let rescued = crate::rescue::rescue_text(&text_content);
+            if !rescued.calls.is_empty() {
+                for call in rescued.calls {
+                    tracing::info!(
+                        "[RESCUE-PROJECT] Converted XML in history to ToolCall: {}",
+                        call.name
+                    );
+                    tool_calls.push(OpenAiToolCall {
+                        id: call.id,
+                        r#type: "function".to_string(),
+                        function: OpenAiFunctionCall {
+                            name: call.name,
+                            arguments: call.arguments.to_string(),
                         },
-                    },
-                    thought_signature: None,
-                    extra_content: None,
-                });
+                        thought_signature: None,
+                        extra_content: None,
+                    });
+                }
+                text_content = rescued.text.trim().to_string();
             }
         }
 
@@ -0,0 +1,653 @@
+//! Inbound rate limiting.
+//!
+//! Token-bucket rate limits and max-in-flight concurrency limits, applied per client key,
+//! per conversation and per model. Requests that cannot be admitted immediately wait in
+//! line up to a queue deadline; anything still blocked after that gets an OpenAI-style 429.
+
+use crate::main_helper::Args;
+use axum::body::Body;
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Keyed limiter maps are pruned of idle entries beyond this size.
+const MAX_TRACKED_KEYS: usize = 4096;
+
+/// Limits for one scope, parsed from e.g. `rpm=60,burst=10,inflight=4`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitSpec {
+    /// Sustained requests per minute (token refill rate).
+    pub rpm: Option<f64>,
+    /// Bucket capacity; defaults to one minute's worth of `rpm`.
+    pub burst: Option<u32>,
+    /// Maximum concurrent requests.
+    pub max_in_flight: Option<usize>,
+}
+
+impl FromStr for LimitSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut spec = LimitSpec {
+            rpm: None,
+            burst: None,
+            max_in_flight: None,
+        };
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = match part.split_once('=') {
+                Some(kv) => kv,
+                None => return Err(format!("expected key=value, got '{}'", part)),
+            };
+            match key.trim() {
+                "rpm" => {
+                    let rpm = value
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|e| format!("invalid rpm '{}': {}", value, e))?;
+                    if !rpm.is_finite() || rpm <= 0.0 {
+                        return Err(format!("rpm must be positive, got '{}'", value));
+                    }
+                    spec.rpm = Some(rpm);
+                }
+                "burst" => {
+                    spec.burst = Some(
+                        value
+                            .trim()
+                            .parse::<u32>()
+                            .map_err(|e| format!("invalid burst '{}': {}", value, e))?,
+                    )
+                }
+                "inflight" | "concurrency" => {
+                    spec.max_in_flight = Some(
+                        value
+                            .trim()
+                            .parse::<usize>()
+                            .map_err(|e| format!("invalid inflight '{}': {}", value, e))?,
+                    )
+                }
+                other => return Err(format!("unknown limit key '{}'", other)),
+            }
+        }
+        Ok(spec)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitScope {
+    Client,
+    Conversation,
+    Model,
+}
+
+impl LimitScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LimitScope::Client => "client",
+            LimitScope::Conversation => "conversation",
+            LimitScope::Model => "model",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    Rate,
+    Concurrency,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rejection {
+    pub scope: LimitScope,
+    pub key: String,
+    pub reason: RejectReason,
+    pub retry_after: Duration,
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        let what = match self.reason {
+            RejectReason::Rate => "Rate limit reached",
+            RejectReason::Concurrency => "Too many concurrent requests",
+        };
+        let retry_secs = self.retry_after.as_secs_f64().max(0.001);
+        let body = serde_json::json!({
+            "error": {
+                "message": format!(
+                    "{} for {} `{}`. Please try again in {:.1}s.",
+                    what,
+                    self.scope.as_str(),
+                    self.key,
+                    retry_secs
+                ),
+                "type": "requests",
+                "param": null,
+                "code": "rate_limit_exceeded",
+            }
+        });
+        let mut response = (StatusCode::TOO_MANY_REQUESTS, axum::Json(body)).into_response();
+        if let Ok(value) = HeaderValue::from_str(&(retry_secs.ceil() as u64).to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, value);
+        }
+        if let Ok(value) = HeaderValue::from_str(self.scope.as_str()) {
+            response.headers_mut().insert("x-ratelimit-scope", value);
+        }
+        response
+    }
+}
+
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(spec: &LimitSpec, rpm: f64) -> Self {
+        let capacity = match spec.burst {
+            Some(b) => b.max(1) as f64,
+            None => rpm.max(1.0),
+        };
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: rpm / 60.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// How long until a token is available, or `None` if one is available now.
+    fn wait_time(&mut self, now: Instant) -> Option<Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (1.0 - self.tokens) / self.refill_per_sec,
+            ))
+        }
+    }
+
+    fn is_full(&self, now: Instant) -> bool {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens + elapsed * self.refill_per_sec >= self.capacity
+    }
+}
+
+struct KeyState {
+    bucket: Option<TokenBucket>,
+    in_flight: usize,
+}
+
+impl KeyState {
+    fn is_idle(&self, now: Instant) -> bool {
+        let bucket_idle = match &self.bucket {
+            Some(bucket) => bucket.is_full(now),
+            None => true,
+        };
+        bucket_idle && self.in_flight == 0
+    }
+}
+
+struct KeyedLimiter {
+    scope: LimitScope,
+    spec: LimitSpec,
+    keys: HashMap<String, KeyState>,
+}
+
+impl KeyedLimiter {
+    fn new(scope: LimitScope, spec: LimitSpec) -> Self {
+        Self {
+            scope,
+            spec,
+            keys: HashMap::new(),
+        }
+    }
+
+    fn state_for(&mut self, key: &str, now: Instant) -> &mut KeyState {
+        if !self.keys.contains_key(key) && self.keys.len() >= MAX_TRACKED_KEYS {
+            self.keys.retain(|_, state| !state.is_idle(now));
+        }
+        let spec = &self.spec;
+        self.keys
+            .entry(key.to_string())
+            .or_insert_with(|| KeyState {
+                bucket: spec.rpm.map(|rpm| TokenBucket::new(spec, rpm)),
+                in_flight: 0,
+            })
+    }
+
+    /// Why `key` cannot be admitted right now, without taking anything from it.
+    fn blocked(&mut self, key: &str, now: Instant) -> Option<Rejection> {
+        let scope = self.scope;
+        let max_in_flight = self.spec.max_in_flight;
+        let state = self.state_for(key, now);
+        let reject = |reason, retry_after| {
+            Some(Rejection {
+                scope,
+                key: key.to_string(),
+                reason,
+                retry_after,
+            })
+        };
+        if let Some(wait) = state.bucket.as_mut().and_then(|b| b.wait_time(now)) {
+            return reject(RejectReason::Rate, wait);
+        }
+        match max_in_flight {
+            Some(max) if state.in_flight >= max => {
+                reject(RejectReason::Concurrency, Duration::from_secs(1))
+            }
+            _ => None,
+        }
+    }
+
+    /// Takes a token and a concurrency slot; only call once [`Self::blocked`] returned `None`.
+    fn take(&mut self, key: &str, now: Instant) {
+        let state = self.state_for(key, now);
+        if let Some(bucket) = state.bucket.as_mut() {
+            bucket.tokens -= 1.0;
+        }
+        state.in_flight += 1;
+    }
+
+    fn release(&mut self, key: &str) {
+        if let Some(state) = self.keys.get_mut(key) {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+    }
+}
+
+/// Identifies an inbound request for limiting purposes.
+#[derive(Debug, Clone, Default)]
+pub struct RequestKeys {
+    pub client: Option<String>,
+    pub conversation: Option<String>,
+    pub model: Option<String>,
+}
+
+impl RequestKeys {
+    /// Client key: a short hash of the bearer token, else the forwarded client address.
+    pub fn from_parts(headers: &HeaderMap, body: &[u8]) -> Self {
+        let header_str = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+        };
+
+        let client = match header_str("authorization") {
+            Some(auth) => {
+                let digest = Sha256::digest(auth.as_bytes());
+                let hex: String = digest
+                    .iter()
+                    .take(6)
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                Some(format!("key:{}", hex))
+            }
+            None => header_str("x-forwarded-for")
+                .map(|ip| match ip.split(',').next() {
+                    Some(first) => first.trim().to_string(),
+                    None => ip,
+                })
+                .map(|ip| format!("ip:{}", ip)),
+        };
+
+        // The id the turn will be filed under: the header, Cursor's metadata id, else the
+        // anchor hash of the conversation's first user query.
+        let conversation = match crate::ingress::header_conversation_id(headers) {
+            Some(cid) => Some(cid),
+            None => match serde_json::from_slice::<crate::ingress::RawTurn>(body) {
+                Ok(turn) => Some(turn.conversation_id().0),
+                Err(_) => None,
+            },
+        };
+
+        #[derive(serde::Deserialize)]
+        struct ModelOnly {
+            model: Option<String>,
+        }
+        let model = match serde_json::from_slice::<ModelOnly>(body) {
+            Ok(m) => m.model,
+            Err(_) => None,
+        };
+
+        Self {
+            client: Some(match client {
+                Some(c) => c,
+                None => "anonymous".to_string(),
+            }),
+            conversation,
+            model,
+        }
+    }
+}
+
+/// The concurrency slots an admitted request holds until it is released.
+#[derive(Debug, Default)]
+pub struct AdmissionTicket {
+    slots: Vec<(usize, String)>,
+}
+
+pub struct RateLimiter {
+    queue_timeout: Duration,
+    limiters: Vec<KeyedLimiter>,
+}
+
+/// One scope's limiter as reported by `/admin/health`.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct RateLimitSnapshot {
+    pub scope: &'static str,
+    pub tracked_keys: usize,
+    /// Requests currently holding a concurrency slot.
+    pub in_flight: usize,
+}
+
+impl RateLimiter {
+    pub fn new(
+        client: Option<LimitSpec>,
+        conversation: Option<LimitSpec>,
+        model: Option<LimitSpec>,
+        queue_timeout: Duration,
+    ) -> Self {
+        let limiters = [
+            (LimitScope::Client, client),
+            (LimitScope::Conversation, conversation),
+            (LimitScope::Model, model),
+        ]
+        .into_iter()
+        .filter_map(|(scope, spec)| spec.map(|s| KeyedLimiter::new(scope, s)))
+        .collect();
+        Self {
+            queue_timeout,
+            limiters,
+        }
+    }
+
+    pub fn from_args(args: &Args) -> Self {
+        Self::new(
+            args.client_limit.clone(),
+            args.conversation_limit.clone(),
+            args.model_limit.clone(),
+            Duration::from_millis(args.rate_limit_queue_ms),
+        )
+    }
+
+    /// Whether `args` configure any limit; without one, requests skip the limiter entirely.
+    pub fn configured(args: &Args) -> bool {
+        args.client_limit.is_some()
+            || args.conversation_limit.is_some()
+            || args.model_limit.is_some()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.limiters.is_empty()
+    }
+
+    /// How long a blocked request may wait in line before it is rejected.
+    pub fn queue_timeout(&self) -> Duration {
+        self.queue_timeout
+    }
+
+    pub fn snapshot(&self) -> Vec<RateLimitSnapshot> {
+        self.limiters
+            .iter()
+            .map(|limiter| RateLimitSnapshot {
+                scope: limiter.scope.as_str(),
+                tracked_keys: limiter.keys.len(),
+                in_flight: match limiter.spec.max_in_flight {
+                    Some(_) => limiter.keys.values().map(|state| state.in_flight).sum(),
+                    None => 0,
+                },
+            })
+            .collect()
+    }
+
+    /// Admits the request if every applicable scope allows it right now.
+    ///
+    /// Nothing is taken unless all scopes admit, so a rejection by one scope never costs the
+    /// request a token or a slot in another.
+    pub fn try_acquire(&mut self, keys: &RequestKeys) -> Result<AdmissionTicket, Rejection> {
+        let now = Instant::now();
+        let scoped: Vec<(usize, String)> = self
+            .limiters
+            .iter()
+            .enumerate()
+            .filter_map(|(i, limiter)| {
+                let key = match limiter.scope {
+                    LimitScope::Client => keys.client.as_ref(),
+                    LimitScope::Conversation => keys.conversation.as_ref(),
+                    LimitScope::Model => keys.model.as_ref(),
+                };
+                key.map(|k| (i, k.clone()))
+            })
+            .collect();
+        for (i, key) in &scoped {
+            if let Some(rejection) = self.limiters[*i].blocked(key, now) {
+                return Err(rejection);
+            }
+        }
+        for (i, key) in &scoped {
+            self.limiters[*i].take(key, now);
+        }
+        Ok(AdmissionTicket { slots: scoped })
+    }
+
+    /// Gives back the concurrency slots held by `ticket`.
+    pub fn release(&mut self, ticket: AdmissionTicket) {
+        for (i, key) in ticket.slots {
+            if let Some(limiter) = self.limiters.get_mut(i) {
+                limiter.release(&key);
+            }
+        }
+    }
+}
+
+/// Tower middleware (via `axum::middleware::from_fn_with_state`) guarding chat completions.
+/// The limiter itself lives in the kernel, which answers admission requests.
+///
+/// Concurrency permits are moved into the response body so they are released only once a
+/// streamed response has been fully sent (or the client goes away).
+pub async fn rate_limit_middleware(
+    State(state): State<Arc<crate::main_helper::AppState>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !RateLimiter::configured(&state.args) {
+        return next.run(req).await;
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes = match axum::body::to_bytes(body, state.args.max_body_size).await {
+        Ok(b) => b,
+        Err(e) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                axum::Json(serde_json::json!({"error": format!("Failed to read body: {}", e)})),
+            )
+                .into_response()
+        }
+    };
+
+    let keys = RequestKeys::from_parts(&parts.headers, &bytes);
+    let admission = match state.kernel.admit(keys).await {
+        Ok(Ok(a)) => a,
+        Err(e) => return e.into_response(),
+        Ok(Err(rejection)) => {
+            tracing::warn!(
+                scope = rejection.scope.as_str(),
+                key = %rejection.key,
+                reason = ?rejection.reason,
+                "[🖱️  -> ⚙️ ] Request rejected by inbound rate limiter"
+            );
+            return rejection.into_response();
+        }
+    };
+
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    let (parts, body) = response.into_parts();
+    let guarded = body.into_data_stream().map(move |chunk| {
+        let _held = &admission;
+        chunk
+    });
+    Response::from_parts(parts, Body::from_stream(guarded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(client: &str, model: &str) -> RequestKeys {
+        RequestKeys {
+            client: Some(client.to_string()),
+            conversation: None,
+            model: Some(model.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_parse_limit_spec() {
+        let spec = match "rpm=60, burst=5, inflight=2".parse::<LimitSpec>() {
+            Ok(s) => s,
+            Err(e) => panic!("parse failed: {}", e),
+        };
+        assert_eq!(spec.rpm, Some(60.0));
+        assert_eq!(spec.burst, Some(5));
+        assert_eq!(spec.max_in_flight, Some(2));
+
+        assert!("rpm=0".parse::<LimitSpec>().is_err());
+        assert!("speed=9".parse::<LimitSpec>().is_err());
+        assert!("rpm".parse::<LimitSpec>().is_err());
+    }
+
+    #[test]
+    fn test_token_bucket_rejects_when_empty() {
+        let spec = LimitSpec {
+            rpm: Some(1.0),
+            burst: Some(2),
+            max_in_flight: None,
+        };
+        let mut limiter = RateLimiter::new(Some(spec), None, None, Duration::from_millis(20));
+
+        assert!(limiter.try_acquire(&keys("a", "m")).is_ok());
+        assert!(limiter.try_acquire(&keys("a", "m")).is_ok());
+        match limiter.try_acquire(&keys("a", "m")) {
+            Err(r) => {
+                assert_eq!(r.scope, LimitScope::Client);
+                assert_eq!(r.reason, RejectReason::Rate);
+                assert!(r.retry_after > Duration::from_secs(1));
+            }
+            Ok(_) => panic!("third request should be rate limited"),
+        }
+        // Other clients have their own bucket.
+        assert!(limiter.try_acquire(&keys("b", "m")).is_ok());
+    }
+
+    #[test]
+    fn test_concurrency_slot_is_freed_on_release() {
+        let spec = LimitSpec {
+            rpm: None,
+            burst: None,
+            max_in_flight: Some(1),
+        };
+        let mut limiter = RateLimiter::new(None, None, Some(spec), Duration::from_millis(200));
+
+        let first = match limiter.try_acquire(&keys("a", "m")) {
+            Ok(t) => t,
+            Err(_) => panic!("first request should be admitted"),
+        };
+        match limiter.try_acquire(&keys("b", "m")) {
+            Err(r) => assert_eq!(r.reason, RejectReason::Concurrency),
+            Ok(_) => panic!("slot should still be held"),
+        }
+        assert_eq!(limiter.snapshot()[0].in_flight, 1);
+
+        limiter.release(first);
+        assert!(limiter.try_acquire(&keys("b", "m")).is_ok());
+    }
+
+    #[test]
+    fn test_rejection_by_a_later_scope_takes_nothing_from_earlier_ones() {
+        let client = LimitSpec {
+            rpm: Some(1.0),
+            burst: Some(1),
+            max_in_flight: None,
+        };
+        let model = LimitSpec {
+            rpm: None,
+            burst: None,
+            max_in_flight: Some(1),
+        };
+        let mut limiter =
+            RateLimiter::new(Some(client), None, Some(model), Duration::from_millis(200));
+
+        let held = match limiter.try_acquire(&keys("a", "m")) {
+            Ok(t) => t,
+            Err(_) => panic!("first request should be admitted"),
+        };
+        match limiter.try_acquire(&keys("b", "m")) {
+            Err(r) => assert_eq!(r.scope, LimitScope::Model),
+            Ok(_) => panic!("model slot should still be held"),
+        }
+        // Client `b` still has its only token once the model slot frees up.
+        limiter.release(held);
+        assert!(limiter.try_acquire(&keys("b", "m")).is_ok());
+    }
+
+    #[test]
+    fn test_request_keys_use_the_body_conversation_id() {
+        let body = br#"{"model":"m/x","messages":[{"role":"user","content":"hi"}],"metadata":{"cursorConversationId":"conv-body"}}"#;
+        let keys = RequestKeys::from_parts(&HeaderMap::new(), body);
+        assert_eq!(keys.conversation.as_deref(), Some("conv-body"));
+
+        // Without any id the turn is filed under its anchor hash, and so is the limit.
+        let body = br#"{"model":"m/x","messages":[{"role":"user","content":"hi"}]}"#;
+        let turn = match serde_json::from_slice::<crate::ingress::RawTurn>(body) {
+            Ok(t) => t,
+            Err(e) => panic!("body should parse: {}", e),
+        };
+        let keys = RequestKeys::from_parts(&HeaderMap::new(), body);
+        assert_eq!(keys.conversation, Some(turn.conversation_id().0));
+    }
+
+    #[test]
+    fn test_request_keys_hash_bearer_token() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Bearer sk-secret"),
+        );
+        headers.insert("x-conversation-id", HeaderValue::from_static("conv-1"));
+        let keys = RequestKeys::from_parts(&headers, br#"{"model":"m/x","messages":[]}"#);
+
+        let client = match keys.client {
+            Some(c) => c,
+            None => panic!("client key missing"),
+        };
+        assert!(client.starts_with("key:"));
+        assert!(!client.contains("sk-secret"));
+        assert_eq!(keys.conversation.as_deref(), Some("conv-1"));
+        assert_eq!(keys.model.as_deref(), Some("m/x"));
+    }
+}
@@ -1,18 +1,9 @@
-use lazy_static::lazy_static;
-use regex::Regex;
 use std::io::Write;
 use tracing::Subscriber;
 use tracing_subscriber::layer::Layer;
 use tracing_subscriber::registry::LookupSpan;
 
-lazy_static! {
-    static ref REDACTION_REGEX: Regex =
-        match Regex::new(r"(?i)(sk-[A-Za-z0-9]{20,}|Bearer\s+[^\s]+|x-api-key:\s*[^\s]+)") {
-            Ok(r) => r,
-            Err(e) => panic!("Invalid redaction regex: {}", e),
-        };
-}
-
+/// Runs every write through the installed [`crate::redaction`] rules.
 pub struct RedactingWriter<W: Write> {
     inner: W,
 }
@@ -25,9 +16,8 @@ impl<W: Write> RedactingWriter<W> {
 
 impl<W: Write> Write for RedactingWriter<W> {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        let input = String::from_utf8_lossy(buf);
-        let redacted = REDACTION_REGEX.replace_all(&input, "[REDACTED]");
-        self.inner.write_all(redacted.as_bytes())?;
+        let rules = crate::redaction::rules();
+        self.inner.write_all(&rules.redact_bytes(buf))?;
         Ok(buf.len())
     }
 
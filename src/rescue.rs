@@ -1,48 +1,442 @@
+//! Recovery of tool calls that models leak into plain text instead of the `tool_calls` channel.
+//!
+//! Three leak formats are recognised:
+//! - Anthropic `<function_calls><invoke name="x"><parameter name="k">v</parameter></invoke></function_calls>`,
+//!   with any number of invokes per block
+//! - a bare `<invoke name="x">{json}</invoke>` (or with `<parameter>` children)
+//! - Hermes `<tool_call>{"name": "x", "arguments": {...}}</tool_call>`
+
 use crate::str_utils;
-use serde_json::json;
+use crate::types::{ProviderToolCallDelta, RawFunction};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+/// Withheld markup larger than this is given up on and forwarded as text.
+const MAX_WITHHELD_BYTES: usize = 256 * 1024;
+
+/// Bytes after an opening tag within which the block must show its lead-in (`name="` or a
+/// JSON body); otherwise the tag is ordinary text and is released.
+const MAX_LEAD_IN_BYTES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RescueFormat {
+    FunctionCalls,
+    Invoke,
+    Hermes,
+}
 
-pub struct RescueResult {
+struct Block {
+    open: &'static str,
+    /// What follows the opening tag, after whitespace, in real tool-call markup.
+    lead_in: &'static str,
+    close: &'static str,
+    format: RescueFormat,
+}
+
+const BLOCKS: &[Block] = &[
+    Block {
+        open: "<function_calls>",
+        lead_in: "<invoke",
+        close: "</function_calls>",
+        format: RescueFormat::FunctionCalls,
+    },
+    Block {
+        open: "<invoke",
+        lead_in: "name=\"",
+        close: "</invoke>",
+        format: RescueFormat::Invoke,
+    },
+    Block {
+        open: "<tool_call>",
+        lead_in: "{",
+        close: "</tool_call>",
+        format: RescueFormat::Hermes,
+    },
+];
+
+/// A tool call recovered from text.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RescuedCall {
+    pub id: String,
     pub name: String,
-    pub tool_call: serde_json::Value,
+    pub arguments: Value,
+    pub format: RescueFormat,
 }
 
-pub fn detect_xml_invoke(text: &str) -> Option<RescueResult> {
-    if !text.contains("<invoke") || !text.contains("</invoke>") {
-        return None;
+impl RescuedCall {
+    fn new(name: String, arguments: Value, format: RescueFormat) -> Self {
+        Self {
+            id: format!(
+                "call_{}",
+                str_utils::prefix_chars(&uuid::Uuid::new_v4().to_string(), 8)
+            ),
+            name,
+            arguments,
+            format,
+        }
+    }
+
+    /// The streaming delta announcing this call in one piece.
+    pub fn tool_call_delta(&self, index: u32) -> ProviderToolCallDelta {
+        let mut extra = Map::new();
+        extra.insert("type".to_string(), json!("function"));
+        ProviderToolCallDelta {
+            index,
+            id: Some(self.id.clone()),
+            function: Some(RawFunction {
+                name: Some(self.name.clone()),
+                arguments: Some(self.arguments.to_string()),
+            }),
+            extra,
+        }
+    }
+
+    /// The debug-bundle issue recording that this call was rescued.
+    pub fn issue(&self) -> crate::debug_bundle::Issue {
+        crate::debug_bundle::Issue {
+            kind: "RescueUsed".to_string(),
+            severity: "warning".to_string(),
+            message: format!(
+                "Tool call '{}' was leaked as text and rescued ({})",
+                self.name,
+                match serde_json::to_string(&self.format) {
+                    Ok(format) => format,
+                    Err(_) => String::new(),
+                }
+            ),
+            context: json!(self),
+        }
+    }
+}
+
+/// What a chunk of text turned into: text safe to forward and completed tool calls.
+#[derive(Debug, Default)]
+pub struct RescueOutput {
+    pub text: String,
+    pub calls: Vec<RescuedCall>,
+}
+
+/// Detects leaked tool-call markup across streamed text deltas.
+///
+/// Text from a possible opening tag onwards is withheld until its closing tag arrives, as long as
+/// the tag is followed by tool-call markup within a few bytes. A block that parses into tool
+/// calls is replaced by those calls; anything else is released as text.
+#[derive(Debug, Default)]
+pub struct StreamRescuer {
+    pending: String,
+    rescued: Vec<RescuedCall>,
+}
+
+impl StreamRescuer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, chunk: &str) -> RescueOutput {
+        self.pending.push_str(chunk);
+        let mut out = RescueOutput::default();
+
+        loop {
+            let opener = BLOCKS
+                .iter()
+                .filter_map(|b| self.pending.find(b.open).map(|i| (i, b)))
+                .min_by_key(|(i, _)| *i);
+            let (start, block) = match opener {
+                Some(found) => found,
+                None => {
+                    let emit_to = self.pending.len() - partial_opener_len(&self.pending);
+                    out.text.extend(self.pending.drain(..emit_to));
+                    break;
+                }
+            };
+            out.text.extend(self.pending.drain(..start));
+
+            match lead_in(block, &self.pending[block.open.len()..]) {
+                LeadIn::Markup => {}
+                LeadIn::Undecided => break,
+                LeadIn::Text => {
+                    out.text.extend(self.pending.drain(..block.open.len()));
+                    continue;
+                }
+            }
+
+            let end = match self.pending.find(block.close) {
+                Some(close) => close + block.close.len(),
+                None => {
+                    if self.pending.len() > MAX_WITHHELD_BYTES {
+                        out.text.push_str(&self.pending);
+                        self.pending.clear();
+                    }
+                    break;
+                }
+            };
+            let calls = parse_block(&self.pending[..end], block.format);
+            if calls.is_empty() {
+                out.text.push_str(&self.pending[..end]);
+            } else {
+                out.calls.extend(calls);
+            }
+            self.pending.drain(..end);
+        }
+
+        self.rescued.extend(out.calls.iter().cloned());
+        out
     }
 
-    // Very simple parser for <invoke name="...">{...}</invoke>
-    let start_idx = text.find("<invoke")?;
-    let end_tag = "</invoke>";
-    let end_idx = text.find(end_tag)?;
+    /// Releases whatever is still withheld (an unterminated block) as text.
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
 
-    let tag_content = str_utils::slice_bytes_safe(text, start_idx, end_idx + end_tag.len())?;
+    /// Every call rescued so far.
+    pub fn rescued(&self) -> &[RescuedCall] {
+        &self.rescued
+    }
+}
 
-    // Extract name
-    let name_start = tag_content.find("name=\"")? + 6;
-    let name_end = tag_content[name_start..].find("\"")? + name_start;
-    let name = str_utils::slice_bytes_safe(tag_content, name_start, name_end)?.to_string();
+/// Rescues every leaked tool call in a complete text.
+pub fn rescue_text(text: &str) -> RescueOutput {
+    let mut rescuer = StreamRescuer::new();
+    let mut out = rescuer.push(text);
+    out.text.push_str(&rescuer.finish());
+    out
+}
 
-    // Extract body (JSON)
-    let body_start = tag_content.find(">")? + 1;
-    let body_end = tag_content.find("</invoke>")?;
-    let body_str = str_utils::slice_bytes_safe(tag_content, body_start, body_end)?.trim();
+enum LeadIn {
+    Markup,
+    /// Too little text after the tag to tell yet.
+    Undecided,
+    Text,
+}
 
-    let arguments = if body_str.is_empty() {
-        "{}".to_string()
+/// Whether the text after an opening tag starts like the block's markup.
+fn lead_in(block: &Block, after: &str) -> LeadIn {
+    let trimmed = after.trim_start();
+    if trimmed.starts_with(block.lead_in) {
+        LeadIn::Markup
+    } else if after.len() > MAX_LEAD_IN_BYTES {
+        LeadIn::Text
+    } else if block.lead_in.starts_with(trimmed) {
+        LeadIn::Undecided
     } else {
-        body_str.to_string()
-    };
+        LeadIn::Text
+    }
+}
 
-    Some(RescueResult {
-        name: name.clone(),
-        tool_call: json!({
-            "id": format!("call_{}", str_utils::prefix_chars(&uuid::Uuid::new_v4().to_string(), 8)),
-            "type": "function",
-            "function": {
-                "name": name,
-                "arguments": arguments,
+/// Bytes at the end of `text` that could still grow into an opening tag.
+fn partial_opener_len(text: &str) -> usize {
+    match text.rfind('<') {
+        Some(i) if BLOCKS.iter().any(|b| b.open.starts_with(&text[i..])) => text.len() - i,
+        _ => 0,
+    }
+}
+
+fn parse_block(block: &str, format: RescueFormat) -> Vec<RescuedCall> {
+    match format {
+        RescueFormat::FunctionCalls => {
+            let mut calls = Vec::new();
+            let mut rest = block;
+            while let Some(start) = rest.find("<invoke") {
+                let end = match rest[start..].find("</invoke>") {
+                    Some(i) => start + i + "</invoke>".len(),
+                    None => break,
+                };
+                if let Some((name, arguments)) = parse_invoke(&rest[start..end]) {
+                    calls.push(RescuedCall::new(name, arguments, format));
+                }
+                rest = &rest[end..];
             }
-        }),
-    })
+            calls
+        }
+        RescueFormat::Invoke => parse_invoke(block)
+            .map(|(name, arguments)| RescuedCall::new(name, arguments, format))
+            .into_iter()
+            .collect(),
+        RescueFormat::Hermes => parse_hermes(block)
+            .map(|(name, arguments)| RescuedCall::new(name, arguments, format))
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// `<invoke name="x">` with either `<parameter>` children or a JSON body.
+fn parse_invoke(invoke: &str) -> Option<(String, Value)> {
+    let tag_end = invoke.find('>')?;
+    let name = attribute(&invoke[..tag_end], "name")?;
+    let body_end = invoke.rfind("</invoke>")?;
+    let body = str_utils::slice_bytes_safe(invoke, tag_end + 1, body_end)?;
+
+    if body.contains("<parameter") {
+        return Some((name, Value::Object(parse_parameters(body))));
+    }
+    let body = body.trim();
+    if body.is_empty() {
+        return Some((name, json!({})));
+    }
+    match crate::json_repair::parse_json_with_repair(body) {
+        Ok(arguments) if arguments.is_object() => Some((name, arguments)),
+        _ => None,
+    }
+}
+
+/// `<parameter name="k">v</parameter>` pairs. Values stay strings unless they are JSON objects or
+/// arrays; schema coercion takes care of numbers and booleans.
+fn parse_parameters(body: &str) -> Map<String, Value> {
+    let mut arguments = Map::new();
+    let mut rest = body;
+    while let Some(start) = rest.find("<parameter") {
+        let tag_end = match rest[start..].find('>') {
+            Some(i) => start + i,
+            None => break,
+        };
+        let value_end = match rest[tag_end..].find("</parameter>") {
+            Some(i) => tag_end + i,
+            None => break,
+        };
+        if let Some(key) = attribute(&rest[start..tag_end], "name") {
+            let raw = &rest[tag_end + 1..value_end];
+            let raw = match raw.strip_prefix('\n') {
+                Some(stripped) => stripped,
+                None => raw,
+            };
+            let raw = match raw.strip_suffix('\n') {
+                Some(stripped) => stripped,
+                None => raw,
+            };
+            let trimmed = raw.trim();
+            let value = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+                match serde_json::from_str(trimmed) {
+                    Ok(v) => v,
+                    Err(_) => Value::String(raw.to_string()),
+                }
+            } else {
+                Value::String(raw.to_string())
+            };
+            arguments.insert(key, value);
+        }
+        rest = &rest[value_end + "</parameter>".len()..];
+    }
+    arguments
+}
+
+/// `<tool_call>{"name": "x", "arguments": {...}}</tool_call>`; `arguments` may be a JSON string.
+fn parse_hermes(block: &str) -> Option<(String, Value)> {
+    let body = block
+        .strip_prefix("<tool_call>")?
+        .strip_suffix("</tool_call>")?
+        .trim();
+    let call = crate::json_repair::parse_json_with_repair(body).ok()?;
+    let name = call.get("name")?.as_str()?.to_string();
+    let arguments = match call.get("arguments").or_else(|| call.get("parameters")) {
+        Some(Value::String(s)) => crate::json_repair::parse_json_with_repair(s).ok()?,
+        Some(v) => v.clone(),
+        None => json!({}),
+    };
+    if !arguments.is_object() {
+        return None;
+    }
+    Some((name, arguments))
+}
+
+fn attribute(tag: &str, attr: &str) -> Option<String> {
+    let marker = format!("{}=\"", attr);
+    let start = tag.find(&marker)? + marker.len();
+    let end = tag[start..].find('"')? + start;
+    Some(tag[start..end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stream(chunks: &[&str]) -> (String, Vec<RescuedCall>) {
+        let mut rescuer = StreamRescuer::new();
+        let mut text = String::new();
+        let mut calls = Vec::new();
+        for chunk in chunks {
+            let out = rescuer.push(chunk);
+            text.push_str(&out.text);
+            calls.extend(out.calls);
+        }
+        text.push_str(&rescuer.finish());
+        (text, calls)
+    }
+
+    #[test]
+    fn test_function_calls_split_across_chunks() {
+        let (text, calls) = stream(&[
+            "Reading both.\n<func",
+            "tion_calls>\n<invoke name=\"read_file\">\n<parameter name=\"path\">a.rs</par",
+            "ameter>\n</invoke>\n<invoke name=\"grep\"><parameter name=\"pattern\">fn main</parameter>",
+            "<parameter name=\"-A\">2</parameter></invoke>\n</function_calls>",
+        ]);
+        assert_eq!(text, "Reading both.\n");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].name, "read_file");
+        assert_eq!(calls[0].arguments, json!({"path": "a.rs"}));
+        assert_eq!(calls[1].name, "grep");
+        assert_eq!(calls[1].arguments, json!({"pattern": "fn main", "-A": "2"}));
+        assert_eq!(calls[0].format, RescueFormat::FunctionCalls);
+        assert_ne!(calls[0].id, calls[1].id);
+    }
+
+    #[test]
+    fn test_hermes_and_bare_invoke() {
+        let (text, calls) = stream(&[
+            "<tool_call>{\"name\": \"list_dir\", \"arguments\": \"{\\\"path\\\": \\\"src\\\"}\"}</tool_call>",
+            " and <invoke name=\"read_file\">{\"path\": \"b.rs\"}</invoke>",
+        ]);
+        assert_eq!(text, " and ");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].format, RescueFormat::Hermes);
+        assert_eq!(calls[0].arguments, json!({"path": "src"}));
+        assert_eq!(calls[1].format, RescueFormat::Invoke);
+        assert_eq!(calls[1].arguments, json!({"path": "b.rs"}));
+    }
+
+    #[test]
+    fn test_unparseable_and_unterminated_markup_is_released_as_text() {
+        let (text, calls) = stream(&[
+            "a <tool_call>not json</tool_call> b <",
+            "invoke name=\"x\">",
+        ]);
+        assert!(calls.is_empty());
+        assert_eq!(
+            text,
+            "a <tool_call>not json</tool_call> b <invoke name=\"x\">"
+        );
+
+        let (text, calls) = stream(&["1 < 2 and x<y"]);
+        assert!(calls.is_empty());
+        assert_eq!(text, "1 < 2 and x<y");
+    }
+
+    #[test]
+    fn test_tags_without_markup_are_released_without_waiting_for_a_close() {
+        let mut rescuer = StreamRescuer::new();
+        let out = rescuer.push("Wrap it in <tool_call> tags and call <invoke");
+        assert_eq!(out.text, "Wrap it in <tool_call> tags and call ");
+        let out = rescuer.push("() on the handler.");
+        assert_eq!(out.text, "<invoke() on the handler.");
+
+        let out = rescuer.push(&format!("<function_calls>{}", " ".repeat(80)));
+        assert!(out.calls.is_empty());
+        assert!(out.text.starts_with("<function_calls>"));
+        assert_eq!(rescuer.finish(), "");
+    }
+
+    #[test]
+    fn test_rescued_call_delta_and_issue() {
+        let out = rescue_text(
+            "<invoke name=\"read_file\"><parameter name=\"path\">a.rs</parameter></invoke>",
+        );
+        let call = &out.calls[0];
+        let delta = call.tool_call_delta(3);
+        assert_eq!(delta.index, 3);
+        assert_eq!(delta.id.as_deref(), Some(call.id.as_str()));
+        let arguments = delta.function.and_then(|f| f.arguments).unwrap_or_default();
+        assert_eq!(arguments, r#"{"path":"a.rs"}"#);
+        assert_eq!(call.issue().kind, "RescueUsed");
+    }
 }
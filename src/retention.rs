@@ -0,0 +1,395 @@
+//! Retention janitor.
+//!
+//! One [`RetentionPolicy`] (age, total size, count, and a longer age for conversations that
+//! recorded issues) applied on a schedule to everything Parallax accumulates: debug bundles
+//! (and their search index rows), flight recorder reports, SQLite state tables and log files.
+
+use crate::debug_bundle::{BundleManager, ConversationSummary};
+use crate::log_rotation::{LogRotationConfig, LogRotationManager};
+use crate::tui::TuiEvent;
+use crate::types::Result;
+use crate::{AppState, Args};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Log files swept by the janitor, as `(directory, file name prefix)`.
+const LOG_FILES: [(&str, &str); 2] = [(".", "parallax.log"), ("logs", "trace_buffer.json")];
+
+#[derive(Debug, Clone)]
+pub struct RetentionPolicy {
+    pub interval: Duration,
+    pub max_age: Duration,
+    /// Age limit for conversations with at least one recorded issue.
+    pub issue_max_age: Duration,
+    pub max_bundle_bytes: u64,
+    pub max_conversations: usize,
+}
+
+impl RetentionPolicy {
+    pub fn from_args(args: &Args) -> Self {
+        let hours = |h: u64| Duration::from_secs(h.saturating_mul(3600));
+        Self {
+            interval: Duration::from_secs(args.retention_interval_secs),
+            max_age: hours(args.retention_max_age_hours),
+            issue_max_age: hours(args.retention_issue_max_age_hours),
+            max_bundle_bytes: args.retention_max_bundle_bytes,
+            max_conversations: args.retention_max_conversations,
+        }
+    }
+
+    fn max_age_for(&self, summary: &ConversationSummary) -> Duration {
+        if summary.issues.total() > 0 {
+            self.issue_max_age.max(self.max_age)
+        } else {
+            self.max_age
+        }
+    }
+}
+
+/// What one janitor pass deleted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RetentionReport {
+    pub conversations: u64,
+    pub bundle_bytes_freed: u64,
+    pub reports: u64,
+    pub db_rows: u64,
+    pub log_files: u64,
+}
+
+impl RetentionReport {
+    pub fn is_empty(&self) -> bool {
+        self.conversations == 0 && self.reports == 0 && self.db_rows == 0 && self.log_files == 0
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "Retention removed {} conversations ({}KB), {} reports, {} database rows, {} log files",
+            self.conversations,
+            self.bundle_bytes_freed / 1024,
+            self.reports,
+            self.db_rows,
+            self.log_files
+        )
+    }
+}
+
+/// Running totals exported on `/metrics`.
+#[derive(Debug, Default)]
+pub struct RetentionStats {
+    pub runs: AtomicU64,
+    pub conversations: AtomicU64,
+    pub bundle_bytes_freed: AtomicU64,
+    pub reports: AtomicU64,
+    pub db_rows: AtomicU64,
+    pub log_files: AtomicU64,
+}
+
+impl RetentionStats {
+    pub fn record(&self, report: &RetentionReport) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.conversations
+            .fetch_add(report.conversations, Ordering::Relaxed);
+        self.bundle_bytes_freed
+            .fetch_add(report.bundle_bytes_freed, Ordering::Relaxed);
+        self.reports.fetch_add(report.reports, Ordering::Relaxed);
+        self.db_rows.fetch_add(report.db_rows, Ordering::Relaxed);
+        self.log_files
+            .fetch_add(report.log_files, Ordering::Relaxed);
+    }
+
+    /// `(target, deleted)` pairs for the `parallax_retention_deleted_total` metric.
+    pub fn deleted_by_target(&self) -> [(&'static str, u64); 4] {
+        [
+            ("conversations", self.conversations.load(Ordering::Relaxed)),
+            ("reports", self.reports.load(Ordering::Relaxed)),
+            ("db_rows", self.db_rows.load(Ordering::Relaxed)),
+            ("log_files", self.log_files.load(Ordering::Relaxed)),
+        ]
+    }
+}
+
+/// Starts the janitor; the first pass runs immediately. With an interval of 0 there is no
+/// janitor, but the database state is still pruned once, as the logs are at startup.
+pub fn spawn(state: Arc<AppState>) -> tokio::task::JoinHandle<()> {
+    let policy = RetentionPolicy::from_args(&state.args);
+    if policy.interval.is_zero() {
+        return tokio::spawn(async move {
+            let report = RetentionReport {
+                db_rows: prune_database(&state, &policy).await,
+                ..RetentionReport::default()
+            };
+            publish(&state, &report);
+        });
+    }
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(policy.interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let report = run_once(&state, &policy).await;
+            publish(&state, &report);
+        }
+    })
+}
+
+/// Adds a pass to the `/metrics` totals and reports it in the TUI console if it deleted anything.
+fn publish(state: &AppState, report: &RetentionReport) {
+    state.retention.record(report);
+    if report.is_empty() {
+        return;
+    }
+    let message = report.summary();
+    tracing::info!("{}", message);
+    let _ = state.tx_tui.send(TuiEvent::LogMessage {
+        level: "INFO".to_string(),
+        target: "parallax::retention".to_string(),
+        message,
+        timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+    });
+}
+
+/// One pass over every retention target. Failures are logged and leave that target for
+/// the next pass.
+pub async fn run_once(state: &AppState, policy: &RetentionPolicy) -> RetentionReport {
+    let mut report = RetentionReport::default();
+
+    match prune_bundles(&state.bundles(), policy, now_ms()).await {
+        Ok((conversations, bytes)) => {
+            report.conversations = conversations;
+            report.bundle_bytes_freed = bytes;
+        }
+        Err(e) => tracing::warn!("Retention: pruning debug bundles failed: {}", e),
+    }
+    if let Err(e) = state.bundles().collect_garbage().await {
+        tracing::warn!(
+            "Retention: collecting unreferenced bundle objects failed: {}",
+            e
+        );
+    }
+
+    report.reports = crate::debug_utils::cleanup_reports(
+        &state.args.debug_capture_dir,
+        crate::debug_utils::REPORT_MAX_FILES,
+        crate::debug_utils::REPORT_MAX_BYTES,
+        Some(policy.max_age),
+    )
+    .await as u64;
+
+    report.db_rows = prune_database(state, policy).await;
+
+    let logs = LogRotationManager::new(LogRotationConfig {
+        max_age_hours: policy.max_age.as_secs() / 3600,
+        ..LogRotationConfig::default()
+    });
+    // Directory scans and deletes block, so they run off the async workers
+    let rotated = tokio::task::spawn_blocking(move || {
+        LOG_FILES.map(|(dir, prefix)| {
+            let removed = logs.check_and_rotate(std::path::Path::new(dir), prefix);
+            (dir, prefix, removed)
+        })
+    })
+    .await;
+    match rotated {
+        Ok(results) => {
+            for (dir, prefix, removed) in results {
+                match removed {
+                    Ok(removed) => report.log_files += removed as u64,
+                    Err(e) => {
+                        tracing::warn!("Retention: rotating {}/{} failed: {}", dir, prefix, e)
+                    }
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Retention: log rotation task failed: {}", e),
+    }
+
+    report
+}
+
+/// Deletes expired tool signatures and conversation states; returns the rows removed.
+async fn prune_database(state: &AppState, policy: &RetentionPolicy) -> u64 {
+    let max_age_hours = (policy.max_age.as_secs() / 3600) as i64;
+    // Conversations that recorded issues keep their database rows as long as their bundles
+    let summaries = match state.bundles().list_conversations().await {
+        Ok(summaries) => summaries,
+        Err(e) => {
+            tracing::warn!(
+                "Retention: listing conversations for database cleanup failed: {}",
+                e
+            );
+            return 0;
+        }
+    };
+    let extended: Vec<String> = summaries
+        .into_iter()
+        .filter(|s| policy.max_age_for(s) > policy.max_age)
+        .map(|s| s.conversation_id)
+        .collect();
+    let issue_max_age_hours = (policy.issue_max_age.as_secs() / 3600) as i64;
+    match crate::db::cleanup_older_than_extending(
+        &state.db,
+        max_age_hours,
+        &extended,
+        issue_max_age_hours,
+    )
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            tracing::warn!("Retention: database cleanup failed: {}", e);
+            0
+        }
+    }
+}
+
+/// Deletes expired conversations, then the oldest ones until the count and size limits
+/// hold. Conversations without issues go first. Returns `(conversations, bytes freed)`.
+pub async fn prune_bundles(
+    bundles: &BundleManager,
+    policy: &RetentionPolicy,
+    now_ms: u64,
+) -> Result<(u64, u64)> {
+    let size_before = bundles.calculate_total_size().await?;
+    let mut deleted = 0u64;
+    let mut kept = Vec::new();
+
+    for summary in bundles.list_conversations().await? {
+        let age = Duration::from_millis(now_ms.saturating_sub(summary.last_updated_ms));
+        if age > policy.max_age_for(&summary) {
+            bundles
+                .delete_conversation(&summary.conversation_id)
+                .await?;
+            deleted += 1;
+        } else {
+            kept.push(summary);
+        }
+    }
+
+    kept.sort_by_key(|s| (s.issues.total() > 0, s.last_updated_ms));
+    let mut remaining = kept.len();
+    let mut size = bundles.calculate_total_size().await?;
+    for summary in kept {
+        if remaining <= policy.max_conversations && size <= policy.max_bundle_bytes {
+            break;
+        }
+        bundles
+            .delete_conversation(&summary.conversation_id)
+            .await?;
+        deleted += 1;
+        remaining -= 1;
+        // Re-measure: with deduplicated storage a conversation's share is not additive.
+        size = bundles.calculate_total_size().await?;
+    }
+
+    Ok((deleted, size_before.saturating_sub(size)))
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug_bundle::IssueCounts;
+
+    const HOUR_MS: u64 = 3600 * 1000;
+    const NOW_MS: u64 = 1_000 * HOUR_MS;
+
+    fn policy() -> RetentionPolicy {
+        RetentionPolicy {
+            interval: Duration::from_secs(60),
+            max_age: Duration::from_secs(24 * 3600),
+            issue_max_age: Duration::from_secs(72 * 3600),
+            max_bundle_bytes: u64::MAX,
+            max_conversations: usize::MAX,
+        }
+    }
+
+    async fn write(bundles: &BundleManager, cid: &str, hours_ago: u64, issues: u32) {
+        let summary = ConversationSummary {
+            conversation_id: cid.to_string(),
+            conversation_id_source: crate::types::ConversationIdSource::Unknown,
+            created_at_ms: NOW_MS - hours_ago * HOUR_MS,
+            last_updated_ms: NOW_MS - hours_ago * HOUR_MS,
+            turns: Vec::new(),
+            issues: IssueCounts {
+                rescue_used: issues,
+                ..IssueCounts::default()
+            },
+        };
+        if let Err(e) = bundles.write_conversation(cid, &summary).await {
+            panic!("write_conversation {}: {}", cid, e);
+        }
+    }
+
+    async fn remaining(bundles: &BundleManager) -> Vec<String> {
+        let mut ids: Vec<String> = match bundles.list_conversations().await {
+            Ok(c) => c.into_iter().map(|s| s.conversation_id).collect(),
+            Err(e) => panic!("list_conversations: {}", e),
+        };
+        ids.sort();
+        ids
+    }
+
+    #[tokio::test]
+    async fn test_issue_conversations_outlive_the_age_limit() {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let bundles = BundleManager::new(dir.path());
+        write(&bundles, "fresh", 1, 0).await;
+        write(&bundles, "stale", 30, 0).await;
+        write(&bundles, "stale-with-issues", 30, 2).await;
+        write(&bundles, "ancient-with-issues", 100, 2).await;
+
+        let (deleted, freed) = match prune_bundles(&bundles, &policy(), NOW_MS).await {
+            Ok(r) => r,
+            Err(e) => panic!("prune_bundles: {}", e),
+        };
+        assert_eq!(deleted, 2);
+        assert!(freed > 0);
+        assert_eq!(
+            remaining(&bundles).await,
+            vec!["fresh", "stale-with-issues"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_count_limit_drops_clean_conversations_first() {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let bundles = BundleManager::new(dir.path());
+        write(&bundles, "old-with-issues", 10, 1).await;
+        write(&bundles, "old-clean", 5, 0).await;
+        write(&bundles, "new-clean", 1, 0).await;
+
+        let policy = RetentionPolicy {
+            max_conversations: 2,
+            ..policy()
+        };
+        if let Err(e) = prune_bundles(&bundles, &policy, NOW_MS).await {
+            panic!("prune_bundles: {}", e);
+        }
+        assert_eq!(
+            remaining(&bundles).await,
+            vec!["new-clean", "old-with-issues"]
+        );
+
+        let policy = RetentionPolicy {
+            max_bundle_bytes: 0,
+            ..policy
+        };
+        if let Err(e) = prune_bundles(&bundles, &policy, NOW_MS).await {
+            panic!("prune_bundles: {}", e);
+        }
+        assert!(remaining(&bundles).await.is_empty());
+    }
+}
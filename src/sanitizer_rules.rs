@@ -0,0 +1,703 @@
+//! Declarative tool-call sanitizer rules
+//!
+//! A rule matches a tool by name and rewrites its arguments (drop, rename, default or derive
+//! fields, replace forbidden terms), wraps plain-text arguments into an object, or patches the
+//! parameter schema advertised upstream. The grep and create_plan fixes are built-in rules;
+//! `--sanitizer-rules` adds rules from a JSON file or disables built-ins by name.
+
+use crate::constants::FORBIDDEN_PLAN_TERMS;
+use crate::types::{ParallaxError, Result};
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+
+/// Contents of a `--sanitizer-rules` file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SanitizerConfig {
+    /// Extra rules, run after the built-in ones.
+    #[serde(default)]
+    pub rules: Vec<SanitizerRule>,
+    /// Names of built-in rules to turn off.
+    #[serde(default)]
+    pub disable: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SanitizerRule {
+    pub name: String,
+    /// Tool the rule applies to; `*` matches every tool.
+    pub tool: String,
+    /// Plain-text arguments (or a JSON string) become this field of an object.
+    #[serde(default)]
+    pub text_field: Option<String>,
+    /// Actions run before `actions` when a `text_field` rule gets empty arguments.
+    #[serde(default)]
+    pub if_empty: Vec<Action>,
+    /// Conditions on the arguments that must all hold for `actions` to run.
+    #[serde(default)]
+    pub when: Vec<Condition>,
+    #[serde(default)]
+    pub actions: Vec<Action>,
+    /// Patches to the tool's parameter schema before it is sent upstream.
+    #[serde(default)]
+    pub schema: Vec<SchemaPatch>,
+}
+
+/// A test on one argument, e.g. `{"positive": "-A"}` or `{"not": {"present": "path"}}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    Present(String),
+    Absent(String),
+    /// A number greater than zero.
+    Positive(String),
+    Zero(String),
+    /// A string that starts with `prefix`, ignoring leading whitespace.
+    StartsWith {
+        field: String,
+        prefix: String,
+    },
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Action {
+    Drop {
+        field: String,
+    },
+    /// Moves `from` to `to` unless `to` is already set.
+    Rename {
+        from: String,
+        to: String,
+    },
+    /// Sets `field` when it is missing.
+    Default {
+        field: String,
+        value: Value,
+    },
+    /// Sets `field` from a template; `{key}` is replaced by that argument, or by its
+    /// `fallbacks` entry when missing or empty. Skipped when a key has neither.
+    Derive {
+        field: String,
+        template: String,
+        #[serde(default)]
+        fallbacks: HashMap<String, String>,
+    },
+    /// Replaces each `[term, replacement]` pair, in order, inside a string field.
+    ForbidTerms {
+        field: String,
+        replace: Vec<(String, String)>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SchemaPatch {
+    /// Removes the property (and its `required` entry).
+    DropProperty { property: String },
+    /// Merges keys into the property's schema, adding the property if it is missing.
+    MergeProperty { property: String, schema: Value },
+}
+
+/// One change a rule made to a tool call or a tool schema.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RuleApplication {
+    pub rule: String,
+    pub tool: String,
+    pub change: String,
+}
+
+impl RuleApplication {
+    fn new(rule: &SanitizerRule, tool: &str, change: String) -> Self {
+        Self {
+            rule: rule.name.clone(),
+            tool: tool.to_string(),
+            change,
+        }
+    }
+
+    /// The debug-bundle issue for this application; `kind` tells argument and schema rules apart.
+    pub fn issue(&self, kind: &str) -> crate::debug_bundle::Issue {
+        crate::debug_bundle::Issue {
+            kind: kind.to_string(),
+            severity: "info".to_string(),
+            message: format!("Rule '{}' on '{}': {}", self.rule, self.tool, self.change),
+            context: serde_json::json!(self),
+        }
+    }
+}
+
+/// The grep and create_plan fixes, by name so a config can disable them.
+fn builtin_rules() -> Vec<SanitizerRule> {
+    let rule = |name: &str, tool: &str| SanitizerRule {
+        name: name.to_string(),
+        tool: tool.to_string(),
+        text_field: None,
+        if_empty: Vec::new(),
+        when: Vec::new(),
+        actions: Vec::new(),
+        schema: Vec::new(),
+    };
+    vec![
+        // Ripgrep rejects -C alongside -A/-B; a zero -C is what models send by accident.
+        SanitizerRule {
+            when: vec![
+                Condition::Any(vec![
+                    Condition::Positive("-A".to_string()),
+                    Condition::Positive("-B".to_string()),
+                ]),
+                Condition::Zero("-C".to_string()),
+            ],
+            actions: vec![Action::Drop {
+                field: "-C".to_string(),
+            }],
+            ..rule("grep_context_exclusive", "grep")
+        },
+        // Hiding -C keeps models on -A/-B and away from conflicting flags.
+        SanitizerRule {
+            schema: vec![SchemaPatch::DropProperty {
+                property: "-C".to_string(),
+            }],
+            ..rule("grep_schema_without_context", "grep")
+        },
+        SanitizerRule {
+            text_field: Some("plan".to_string()),
+            if_empty: vec![
+                Action::Default {
+                    field: "plan".to_string(),
+                    value: Value::String("# Implementation Plan\n\nNo plan provided.".to_string()),
+                },
+                Action::Default {
+                    field: "name".to_string(),
+                    value: Value::String("Default Plan".to_string()),
+                },
+            ],
+            actions: vec![
+                Action::Default {
+                    field: "plan".to_string(),
+                    value: Value::String("No plan content provided.".to_string()),
+                },
+                Action::Default {
+                    field: "name".to_string(),
+                    value: Value::String("Implementation Plan".to_string()),
+                },
+            ],
+            ..rule("create_plan_text_arguments", "create_plan")
+        },
+        SanitizerRule {
+            when: vec![Condition::Not(Box::new(Condition::StartsWith {
+                field: "plan".to_string(),
+                prefix: "# ".to_string(),
+            }))],
+            actions: vec![Action::Derive {
+                field: "plan".to_string(),
+                template: "# {name}\n\n{plan}".to_string(),
+                fallbacks: HashMap::from([("name".to_string(), "Implementation Plan".to_string())]),
+            }],
+            ..rule("create_plan_title", "create_plan")
+        },
+        SanitizerRule {
+            actions: vec![Action::ForbidTerms {
+                field: "plan".to_string(),
+                replace: FORBIDDEN_PLAN_TERMS
+                    .iter()
+                    .map(|(term, replacement)| (term.to_string(), replacement.to_string()))
+                    .collect(),
+            }],
+            ..rule("create_plan_forbidden_terms", "create_plan")
+        },
+    ]
+}
+
+#[derive(Debug)]
+pub struct SanitizerRules {
+    rules: Vec<SanitizerRule>,
+}
+
+impl Default for SanitizerRules {
+    fn default() -> Self {
+        Self {
+            rules: builtin_rules(),
+        }
+    }
+}
+
+impl SanitizerRules {
+    pub fn from_config(config: SanitizerConfig) -> Result<Self> {
+        let builtins = builtin_rules();
+        if let Some(unknown) = config
+            .disable
+            .iter()
+            .find(|name| !builtins.iter().any(|r| &r.name == *name))
+        {
+            return Err(ParallaxError::InvalidIngress(format!(
+                "cannot disable unknown sanitizer rule '{}'",
+                unknown
+            ))
+            .into());
+        }
+        let mut rules: Vec<SanitizerRule> = builtins
+            .into_iter()
+            .filter(|r| !config.disable.contains(&r.name))
+            .collect();
+        rules.extend(config.rules);
+        Ok(Self { rules })
+    }
+
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let config = match path {
+            Some(path) => {
+                let raw = std::fs::read_to_string(path)?;
+                serde_json::from_str(&raw)?
+            }
+            None => SanitizerConfig::default(),
+        };
+        Self::from_config(config)
+    }
+
+    pub fn rule_names(&self) -> Vec<&str> {
+        self.rules.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    fn matching<'a>(&'a self, tool: &'a str) -> impl Iterator<Item = &'a SanitizerRule> {
+        self.rules
+            .iter()
+            .filter(move |r| r.tool == "*" || r.tool == tool)
+    }
+
+    /// Turns text arguments into an object for tools with a `text_field` rule, then runs that
+    /// rule's actions on it. `None` when no rule applies or the arguments are JSON other than a
+    /// string; invalid JSON (even if it looks like an object) is text and is not repaired.
+    pub fn wrap_text(&self, tool: &str, arguments: &str) -> Option<(Value, RuleApplication)> {
+        let rule = self.matching(tool).find(|r| r.text_field.is_some())?;
+        let field = rule.text_field.as_deref()?;
+        let trimmed = arguments.trim();
+
+        let mut wrapped = Map::new();
+        let mut changes = Vec::new();
+        if trimmed.is_empty() {
+            changes.push("empty arguments replaced by an object".to_string());
+            for action in &rule.if_empty {
+                changes.extend(run_action(action, &mut wrapped));
+            }
+        } else {
+            let text = match serde_json::from_str::<Value>(trimmed) {
+                Ok(Value::String(s)) => s,
+                Ok(_) => return None,
+                Err(_) => trimmed.to_string(),
+            };
+            wrapped.insert(field.to_string(), Value::String(text));
+            changes.push(format!("text arguments wrapped as `{}`", field));
+        }
+        if rule.when.iter().all(|c| holds(c, &wrapped)) {
+            for action in &rule.actions {
+                changes.extend(run_action(action, &mut wrapped));
+            }
+        }
+        Some((
+            Value::Object(wrapped),
+            RuleApplication::new(rule, tool, changes.join("; ")),
+        ))
+    }
+
+    /// Runs the matching rules' actions on a tool call's arguments.
+    pub fn apply(&self, tool: &str, args: &mut Value) -> Vec<RuleApplication> {
+        let mut applied = Vec::new();
+        let map = match args.as_object_mut() {
+            Some(m) => m,
+            None => return applied,
+        };
+        for rule in self.matching(tool) {
+            if !rule.when.iter().all(|c| holds(c, map)) {
+                continue;
+            }
+            for action in &rule.actions {
+                if let Some(change) = run_action(action, map) {
+                    applied.push(RuleApplication::new(rule, tool, change));
+                }
+            }
+        }
+        applied
+    }
+
+    /// Runs the matching rules' schema patches on an advertised tool's parameters.
+    pub fn patch_schema(&self, tool: &str, parameters: &mut Value) -> Vec<RuleApplication> {
+        let mut applied = Vec::new();
+        for rule in self.matching(tool) {
+            for patch in &rule.schema {
+                if let Some(change) = run_patch(patch, parameters) {
+                    applied.push(RuleApplication::new(rule, tool, change));
+                }
+            }
+        }
+        applied
+    }
+
+    /// Patches the schemas of every tool in an outgoing request.
+    pub fn patch_tools(
+        &self,
+        tools: Option<&mut Vec<crate::specs::openai::OpenAiTool>>,
+    ) -> Vec<RuleApplication> {
+        let mut applied = Vec::new();
+        for tool in tools.into_iter().flatten() {
+            applied.extend(self.patch_schema(&tool.function.name, &mut tool.function.parameters));
+        }
+        applied
+    }
+}
+
+fn holds(condition: &Condition, args: &Map<String, Value>) -> bool {
+    match condition {
+        Condition::Present(field) => args.contains_key(field),
+        Condition::Absent(field) => !args.contains_key(field),
+        Condition::Positive(field) => match args.get(field).and_then(|v| v.as_f64()) {
+            Some(n) => n > 0.0,
+            None => false,
+        },
+        Condition::Zero(field) => match args.get(field).and_then(|v| v.as_f64()) {
+            Some(n) => n == 0.0,
+            None => false,
+        },
+        Condition::StartsWith { field, prefix } => match args.get(field).and_then(|v| v.as_str()) {
+            Some(s) => s.trim_start().starts_with(prefix.as_str()),
+            None => false,
+        },
+        Condition::Any(conditions) => conditions.iter().any(|c| holds(c, args)),
+        Condition::Not(inner) => !holds(inner, args),
+    }
+}
+
+/// Fills `{key}` placeholders; `None` when a key has no usable value or fallback.
+fn render(
+    template: &str,
+    args: &Map<String, Value>,
+    fallbacks: &HashMap<String, String>,
+) -> Option<String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = match after.find('}') {
+            Some(e) => e,
+            None => {
+                out.push_str(&rest[start..]);
+                return Some(out);
+            }
+        };
+        let key = &after[..end];
+        let value = match args.get(key) {
+            Some(Value::String(s)) if !s.is_empty() => s.clone(),
+            Some(Value::Number(n)) => n.to_string(),
+            Some(Value::Bool(b)) => b.to_string(),
+            _ => fallbacks.get(key)?.clone(),
+        };
+        out.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+fn run_action(action: &Action, args: &mut Map<String, Value>) -> Option<String> {
+    match action {
+        Action::Drop { field } => args.remove(field).map(|_| format!("dropped `{}`", field)),
+        Action::Rename { from, to } => {
+            if args.contains_key(to) {
+                return None;
+            }
+            let value = args.remove(from)?;
+            args.insert(to.clone(), value);
+            Some(format!("renamed `{}` to `{}`", from, to))
+        }
+        Action::Default { field, value } => {
+            if args.contains_key(field) {
+                return None;
+            }
+            args.insert(field.clone(), value.clone());
+            Some(format!("defaulted `{}`", field))
+        }
+        Action::Derive {
+            field,
+            template,
+            fallbacks,
+        } => {
+            let derived = render(template, args, fallbacks)?;
+            if args.get(field).and_then(|v| v.as_str()) == Some(derived.as_str()) {
+                return None;
+            }
+            args.insert(field.clone(), Value::String(derived));
+            Some(format!("derived `{}`", field))
+        }
+        Action::ForbidTerms { field, replace } => {
+            let text = match args.get_mut(field) {
+                Some(Value::String(s)) => s,
+                _ => return None,
+            };
+            let mut replaced = Vec::new();
+            for (term, replacement) in replace {
+                if text.contains(term.as_str()) {
+                    *text = text.replace(term.as_str(), replacement);
+                    replaced.push(format!("`{}`", term.trim()));
+                }
+            }
+            if replaced.is_empty() {
+                None
+            } else {
+                Some(format!("replaced {} in `{}`", replaced.join(", "), field))
+            }
+        }
+    }
+}
+
+fn run_patch(patch: &SchemaPatch, parameters: &mut Value) -> Option<String> {
+    match patch {
+        SchemaPatch::DropProperty { property } => {
+            parameters
+                .get_mut("properties")
+                .and_then(|p| p.as_object_mut())?
+                .remove(property)?;
+            if let Some(required) = parameters
+                .get_mut("required")
+                .and_then(|r| r.as_array_mut())
+            {
+                required.retain(|r| r.as_str() != Some(property.as_str()));
+            }
+            Some(format!("dropped schema property `{}`", property))
+        }
+        SchemaPatch::MergeProperty { property, schema } => {
+            let additions = schema.as_object()?;
+            let properties = parameters
+                .as_object_mut()?
+                .entry("properties")
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()?;
+            let target = properties
+                .entry(property.clone())
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()?;
+            for (key, value) in additions {
+                target.insert(key.clone(), value.clone());
+            }
+            Some(format!("merged schema property `{}`", property))
+        }
+    }
+}
+
+static RULES: RwLock<Option<Arc<SanitizerRules>>> = RwLock::new(None);
+
+lazy_static! {
+    static ref BUILTIN_RULES: Arc<SanitizerRules> = Arc::new(SanitizerRules::default());
+}
+
+/// Makes `rules` the process-wide sanitizer rules. Call once at startup.
+pub fn install(rules: SanitizerRules) {
+    match RULES.write() {
+        Ok(mut slot) => *slot = Some(Arc::new(rules)),
+        Err(poisoned) => *poisoned.into_inner() = Some(Arc::new(rules)),
+    }
+}
+
+/// The installed rules (the built-in set until [`install`] is called).
+pub fn rules() -> Arc<SanitizerRules> {
+    let slot = match RULES.read() {
+        Ok(slot) => slot,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    match slot.as_ref() {
+        Some(rules) => rules.clone(),
+        None => BUILTIN_RULES.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_create_plan_text_arguments_are_wrapped_then_completed() {
+        let rules = SanitizerRules::default();
+        let (mut args, wrapped) = match rules.wrap_text("create_plan", "Just a plain plan") {
+            Some(w) => w,
+            None => panic!("plain text should be wrapped"),
+        };
+        assert_eq!(wrapped.rule, "create_plan_text_arguments");
+        assert_eq!(
+            args,
+            json!({"plan": "Just a plain plan", "name": "Implementation Plan"})
+        );
+
+        let applied = rules.apply("create_plan", &mut args);
+        assert_eq!(
+            args,
+            json!({
+                "plan": "# Implementation Plan\n\nJust a plain plan",
+                "name": "Implementation Plan"
+            })
+        );
+        let names: Vec<&str> = applied.iter().map(|a| a.rule.as_str()).collect();
+        assert_eq!(names, vec!["create_plan_title"]);
+
+        assert!(rules.wrap_text("grep", "plain").is_none());
+    }
+
+    #[test]
+    fn test_repair_create_plan_arguments_plain_text() {
+        let rules = SanitizerRules::default();
+        let text = "Step 1: read the code\nStep 2: change it";
+        match rules.wrap_text("create_plan", text) {
+            Some((args, _)) => {
+                assert_eq!(args["plan"], text);
+                assert_eq!(args["name"], "Implementation Plan");
+            }
+            None => panic!("plain text should be wrapped"),
+        }
+    }
+
+    #[test]
+    fn test_repair_create_plan_arguments_empty() {
+        let rules = SanitizerRules::default();
+        let (mut args, wrapped) = match rules.wrap_text("create_plan", "  ") {
+            Some(w) => w,
+            None => panic!("empty arguments should be wrapped"),
+        };
+        assert_eq!(
+            args,
+            json!({"plan": "# Implementation Plan\n\nNo plan provided.", "name": "Default Plan"})
+        );
+        assert!(wrapped
+            .change
+            .starts_with("empty arguments replaced by an object"));
+
+        assert!(rules.apply("create_plan", &mut args).is_empty());
+        assert_eq!(args["plan"], "# Implementation Plan\n\nNo plan provided.");
+    }
+
+    #[test]
+    fn test_repair_create_plan_arguments_json_string() {
+        let rules = SanitizerRules::default();
+        let mut args = json!({"plan": "JSON plan content"});
+        rules.apply("create_plan", &mut args);
+        assert_eq!(
+            args,
+            json!({
+                "plan": "# Implementation Plan\n\nJSON plan content",
+                "name": "Implementation Plan"
+            })
+        );
+    }
+
+    #[test]
+    fn test_repair_create_plan_arguments_complete_json() {
+        let rules = SanitizerRules::default();
+        let mut args = json!({
+            "plan": "Complete plan",
+            "name": "Custom Plan",
+            "overview": "Test overview"
+        });
+        let applied = rules.apply("create_plan", &mut args);
+        assert_eq!(
+            args,
+            json!({
+                "plan": "# Custom Plan\n\nComplete plan",
+                "name": "Custom Plan",
+                "overview": "Test overview"
+            })
+        );
+        let names: Vec<&str> = applied.iter().map(|a| a.rule.as_str()).collect();
+        assert_eq!(names, vec!["create_plan_title"]);
+    }
+
+    #[test]
+    fn test_json_string_arguments_become_the_text_field() {
+        let rules = SanitizerRules::default();
+        match rules.wrap_text("create_plan", r#""JSON plan content""#) {
+            Some((args, _)) => assert_eq!(
+                args,
+                json!({"plan": "JSON plan content", "name": "Implementation Plan"})
+            ),
+            None => panic!("JSON string should be wrapped"),
+        }
+    }
+
+    #[test]
+    fn test_custom_rules_and_disabled_builtins() {
+        let config: SanitizerConfig = match serde_json::from_value(json!({
+            "disable": ["grep_schema_without_context"],
+            "rules": [{
+                "name": "read_file_path",
+                "tool": "read_file",
+                "when": [{"absent": "target_file"}],
+                "actions": [
+                    {"op": "rename", "from": "path", "to": "target_file"},
+                    {"op": "default", "field": "should_read_entire_file", "value": false},
+                    {"op": "drop", "field": "explanation"}
+                ],
+                "schema": [
+                    {"op": "merge_property", "property": "target_file", "schema": {"description": "Relative path"}}
+                ]
+            }]
+        })) {
+            Ok(c) => c,
+            Err(e) => panic!("config should parse: {}", e),
+        };
+        let rules = match SanitizerRules::from_config(config) {
+            Ok(r) => r,
+            Err(e) => panic!("config should load: {}", e),
+        };
+        assert!(!rules.rule_names().contains(&"grep_schema_without_context"));
+
+        let mut args = json!({"path": "a.rs", "explanation": "look"});
+        let applied = rules.apply("read_file", &mut args);
+        assert_eq!(
+            args,
+            json!({"target_file": "a.rs", "should_read_entire_file": false})
+        );
+        assert_eq!(applied.len(), 3);
+
+        let mut grep_schema = json!({"type": "object", "properties": {"-C": {"type": "number"}}});
+        assert!(rules.patch_schema("grep", &mut grep_schema).is_empty());
+
+        let mut schema =
+            json!({"type": "object", "properties": {"target_file": {"type": "string"}}});
+        let patched = rules.patch_schema("read_file", &mut schema);
+        assert_eq!(patched.len(), 1);
+        assert_eq!(
+            schema["properties"]["target_file"],
+            json!({"type": "string", "description": "Relative path"})
+        );
+
+        let unknown = SanitizerConfig {
+            rules: Vec::new(),
+            disable: vec!["no_such_rule".to_string()],
+        };
+        assert!(SanitizerRules::from_config(unknown).is_err());
+    }
+
+    #[test]
+    fn test_builtin_grep_schema_patch_drops_context_flag() {
+        let mut schema = json!({
+            "type": "object",
+            "properties": {"pattern": {"type": "string"}, "-C": {"type": "number"}},
+            "required": ["pattern", "-C"]
+        });
+        let applied = SanitizerRules::default().patch_schema("grep", &mut schema);
+        assert_eq!(applied.len(), 1);
+        assert_eq!(
+            schema,
+            json!({
+                "type": "object",
+                "properties": {"pattern": {"type": "string"}},
+                "required": ["pattern"]
+            })
+        );
+    }
+}
@@ -0,0 +1,431 @@
+//! Full-text search over captured debug bundles.
+//!
+//! Every `turn.json` write re-indexes that turn into the `turn_search` FTS5 table (user query,
+//! tool call names/arguments, tool result snippets, issues and model), so `/debug/search` can
+//! answer "which turn called apply_patch on foo.rs" without walking `debug_capture/`.
+
+use crate::db::DbPool;
+use crate::debug_bundle::{BundleManager, TurnDetail};
+use crate::types::Result;
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+
+/// Per-field cap on indexed text; tool arguments can carry whole files.
+const MAX_FIELD_CHARS: usize = 16 * 1024;
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 500;
+
+/// Query parameters accepted by `/debug/search`. All filters are optional and combined with AND.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Free text; each whitespace-separated term must match (a trailing `*` makes it a prefix).
+    pub q: Option<String>,
+    /// Exact model id.
+    pub model: Option<String>,
+    /// Issue kind, e.g. `ToolArgsRepaired`.
+    pub issue: Option<String>,
+    /// Exact tool name, e.g. `apply_patch`.
+    pub tool: Option<String>,
+    /// Turn start lower bound (unix ms, inclusive).
+    pub since_ms: Option<u64>,
+    /// Turn start upper bound (unix ms, exclusive).
+    pub until_ms: Option<u64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SearchHit {
+    pub conversation_id: String,
+    pub turn_id: String,
+    pub started_at_ms: u64,
+    pub model_id: String,
+    pub tool_names: Vec<String>,
+    pub issue_kinds: Vec<String>,
+    pub user_query: Option<String>,
+    /// Highlighted excerpt (`[match]`) of the best matching field; only set for text queries.
+    pub snippet: Option<String>,
+}
+
+fn cap(s: String) -> String {
+    match s.char_indices().nth(MAX_FIELD_CHARS) {
+        Some((idx, _)) => s[..idx].to_string(),
+        None => s,
+    }
+}
+
+fn unique_words<'a>(words: impl Iterator<Item = &'a str>) -> String {
+    let mut seen: Vec<&str> = Vec::new();
+    for w in words {
+        if !w.is_empty() && !seen.contains(&w) {
+            seen.push(w);
+        }
+    }
+    seen.join(" ")
+}
+
+/// Replaces the index entry for one turn.
+pub async fn index_turn(db: &DbPool, cid: &str, detail: &TurnDetail) -> Result<()> {
+    let tool_names = unique_words(
+        detail
+            .tool_calls
+            .iter()
+            .map(|t| t.name.as_str())
+            .chain(detail.tool_results.iter().filter_map(|r| r.name.as_deref())),
+    );
+    let tool_args: Vec<&str> = detail
+        .tool_calls
+        .iter()
+        .filter_map(|t| t.evidence.raw_arguments_snippet.as_deref())
+        .collect();
+    let tool_results: Vec<&str> = detail
+        .tool_results
+        .iter()
+        .map(|r| r.snippet.as_str())
+        .collect();
+    let issue_kinds = unique_words(detail.issues.iter().map(|i| i.kind.as_str()));
+    let issues: Vec<String> = detail
+        .issues
+        .iter()
+        .map(|i| format!("{}: {}", i.kind, i.message))
+        .collect();
+
+    // With at-rest encryption on, free text stays out of the (plaintext) index; turns remain
+    // findable by model, tool and issue kind.
+    let text = |value: String| {
+        if crate::at_rest::is_enabled() {
+            String::new()
+        } else {
+            cap(value)
+        }
+    };
+
+    let mut tx = db.begin().await?;
+    sqlx::query("DELETE FROM turn_search WHERE conversation_id = ? AND turn_id = ?")
+        .bind(cid)
+        .bind(&detail.turn_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(
+        "INSERT INTO turn_search (conversation_id, turn_id, started_at_ms, model, issue_kinds, \
+         tool_names, user_query, tool_args, tool_results, issues) \
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(cid)
+    .bind(&detail.turn_id)
+    .bind(detail.started_at_ms as i64)
+    .bind(&detail.model_id)
+    .bind(issue_kinds)
+    .bind(tool_names)
+    .bind(text(match &detail.user_query {
+        Some(q) => q.clone(),
+        None => String::new(),
+    }))
+    .bind(text(tool_args.join("\n")))
+    .bind(text(tool_results.join("\n")))
+    .bind(text(issues.join("\n")))
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+pub async fn remove_conversation(db: &DbPool, cid: &str) -> Result<()> {
+    sqlx::query("DELETE FROM turn_search WHERE conversation_id = ?")
+        .bind(cid)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Turns free text into an FTS5 expression of quoted terms so user input can't produce
+/// syntax errors (`foo.rs` becomes the phrase `"foo.rs"`, `apply*` a prefix query).
+fn fts_expression(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .split_whitespace()
+        .filter_map(|term| {
+            let (term, prefix) = match term.strip_suffix('*') {
+                Some(t) => (t, true),
+                None => (term, false),
+            };
+            let term = term.replace('"', "");
+            if term.is_empty() {
+                return None;
+            }
+            Some(if prefix {
+                format!("\"{}\"*", term)
+            } else {
+                format!("\"{}\"", term)
+            })
+        })
+        .collect();
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+fn split_words(s: String) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+pub async fn search(db: &DbPool, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+    let expression = query.q.as_deref().and_then(fts_expression);
+    let limit = match query.limit {
+        Some(limit) => limit,
+        None => DEFAULT_LIMIT,
+    }
+    .clamp(1, MAX_LIMIT);
+
+    let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "SELECT conversation_id, turn_id, CAST(started_at_ms AS INTEGER) AS started, model, \
+         tool_names, issue_kinds, user_query, ",
+    );
+    if expression.is_some() {
+        builder.push("snippet(turn_search, -1, '[', ']', '…', 12)");
+    } else {
+        builder.push("NULL");
+    }
+    builder.push(" AS excerpt FROM turn_search WHERE 1 = 1");
+
+    if let Some(expr) = &expression {
+        builder
+            .push(" AND turn_search MATCH ")
+            .push_bind(expr.clone());
+    }
+    if let Some(model) = &query.model {
+        builder.push(" AND model = ").push_bind(model.clone());
+    }
+    if let Some(issue) = &query.issue {
+        builder
+            .push(" AND instr(' ' || issue_kinds || ' ', ' ' || ")
+            .push_bind(issue.clone())
+            .push(" || ' ') > 0");
+    }
+    if let Some(tool) = &query.tool {
+        builder
+            .push(" AND instr(' ' || tool_names || ' ', ' ' || ")
+            .push_bind(tool.clone())
+            .push(" || ' ') > 0");
+    }
+    if let Some(since) = query.since_ms {
+        builder
+            .push(" AND CAST(started_at_ms AS INTEGER) >= ")
+            .push_bind(since as i64);
+    }
+    if let Some(until) = query.until_ms {
+        builder
+            .push(" AND CAST(started_at_ms AS INTEGER) < ")
+            .push_bind(until as i64);
+    }
+    if expression.is_some() {
+        builder.push(" ORDER BY rank, started DESC");
+    } else {
+        builder.push(" ORDER BY started DESC");
+    }
+    builder.push(" LIMIT ").push_bind(limit as i64);
+
+    let rows = builder.build().fetch_all(db).await?;
+    let hits = rows
+        .into_iter()
+        .map(|row| {
+            let user_query: String = row.get("user_query");
+            SearchHit {
+                conversation_id: row.get("conversation_id"),
+                turn_id: row.get("turn_id"),
+                started_at_ms: row.get::<i64, _>("started").max(0) as u64,
+                model_id: row.get("model"),
+                tool_names: split_words(row.get("tool_names")),
+                issue_kinds: split_words(row.get("issue_kinds")),
+                user_query: if user_query.is_empty() {
+                    None
+                } else {
+                    Some(crate::str_utils::prefix_chars(&user_query, 200).to_string())
+                },
+                snippet: row.get("excerpt"),
+            }
+        })
+        .collect();
+    Ok(hits)
+}
+
+/// Indexes every turn already in the bundle store when the index is empty (first start after upgrading,
+/// or after the database was deleted). Returns the number of turns indexed.
+pub async fn backfill(db: &DbPool, bundles: &BundleManager) -> Result<usize> {
+    let existing: i64 = sqlx::query("SELECT count(*) FROM turn_search")
+        .fetch_one(db)
+        .await?
+        .get(0);
+    if existing > 0 {
+        return Ok(0);
+    }
+
+    let mut indexed = 0;
+    for conversation in bundles.list_conversations().await? {
+        let cid = conversation.conversation_id;
+        for tid in bundles.list_turns(&cid).await? {
+            let detail = match bundles.read_turn(&cid, &tid).await {
+                Ok(Some(d)) => d,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::debug!("Skipping unreadable turn {}/{}: {}", cid, tid, e);
+                    continue;
+                }
+            };
+            index_turn(db, &cid, &detail).await?;
+            indexed += 1;
+        }
+    }
+    Ok(indexed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::debug_bundle::{
+        Issue, TagSummary, ToolArgsStatus, ToolCallEvidence, ToolCallIndex, ToolCallOrigin,
+    };
+
+    fn turn(tid: &str, model: &str, started_at_ms: u64, tool: Option<(&str, &str)>) -> TurnDetail {
+        TurnDetail {
+            turn_id: tid.to_string(),
+            request_id: format!("req-{}", tid),
+            model_id: model.to_string(),
+            flavor: "streaming".to_string(),
+            started_at_ms,
+            ended_at_ms: None,
+            stages: Vec::new(),
+            tool_calls: tool
+                .map(|(name, args)| ToolCallIndex {
+                    id: "call_1".to_string(),
+                    name: name.to_string(),
+                    args_status: ToolArgsStatus::Ok,
+                    origin: ToolCallOrigin::UpstreamStream,
+                    evidence: ToolCallEvidence {
+                        stage: "final".to_string(),
+                        message_index: None,
+                        snippet: None,
+                        blob_ref: None,
+                        offsets: None,
+                        request_tool_index: None,
+                        response_tool_index: None,
+                        raw_arguments_snippet: Some(args.to_string()),
+                    },
+                })
+                .into_iter()
+                .collect(),
+            cursor_tags: TagSummary::default(),
+            issues: Vec::new(),
+            trace_id: None,
+            span_summary: None,
+            user_query: Some("please fix the parser".to_string()),
+            role: Some("Assistant".to_string()),
+            conversation_id_source: crate::types::ConversationIdSource::Unknown,
+            user_query_tags: None,
+            tool_results: Vec::new(),
+        }
+    }
+
+    async fn test_db() -> (tempfile::TempDir, DbPool) {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        match crate::db::init_db(dir.path().join("search.db")).await {
+            Ok(db) => (dir, db),
+            Err(e) => panic!("init_db: {}", e),
+        }
+    }
+
+    async fn run(db: &DbPool, query: SearchQuery) -> Vec<String> {
+        match search(db, &query).await {
+            Ok(hits) => hits.into_iter().map(|h| h.turn_id).collect(),
+            Err(e) => panic!("search: {}", e),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_text_and_filters() {
+        let (_dir, db) = test_db().await;
+        let mut patched = turn(
+            "t1",
+            "anthropic/claude",
+            1_000,
+            Some(("apply_patch", r#"{"path":"src/foo.rs"}"#)),
+        );
+        patched.issues.push(Issue {
+            kind: "ToolArgsRepaired".to_string(),
+            severity: "warn".to_string(),
+            message: "closed an unterminated string".to_string(),
+            context: serde_json::Value::Null,
+        });
+        let turns = [
+            patched,
+            turn(
+                "t2",
+                "openai/gpt-4o",
+                2_000,
+                Some(("read_file", r#"{"path":"src/foo.rs"}"#)),
+            ),
+            turn("t3", "openai/gpt-4o", 3_000, None),
+        ];
+        for t in &turns {
+            if let Err(e) = index_turn(&db, "cid", t).await {
+                panic!("index_turn: {}", e);
+            }
+        }
+
+        let q = |q: &str| SearchQuery {
+            q: Some(q.to_string()),
+            ..Default::default()
+        };
+        let mut both = run(&db, q("foo.rs")).await;
+        both.sort();
+        assert_eq!(both, vec!["t1", "t2"]);
+        assert_eq!(run(&db, q("apply_patch foo.rs")).await, vec!["t1"]);
+        assert_eq!(run(&db, q("unterminated")).await, vec!["t1"]);
+        assert_eq!(run(&db, q("pars*")).await.len(), 3);
+        assert!(run(&db, q("\"unbalanced")).await.is_empty());
+
+        let filtered = SearchQuery {
+            model: Some("openai/gpt-4o".to_string()),
+            since_ms: Some(2_500),
+            ..Default::default()
+        };
+        assert_eq!(run(&db, filtered).await, vec!["t3"]);
+        let by_tool = SearchQuery {
+            tool: Some("apply_patch".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(run(&db, by_tool).await, vec!["t1"]);
+        let by_issue = SearchQuery {
+            issue: Some("ToolArgsRepaired".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(run(&db, by_issue).await, vec!["t1"]);
+
+        // Re-indexing replaces the entry instead of duplicating it.
+        if let Err(e) = index_turn(&db, "cid", &turns[2]).await {
+            panic!("index_turn: {}", e);
+        }
+        assert_eq!(
+            run(&db, SearchQuery::default()).await,
+            vec!["t3", "t2", "t1"]
+        );
+
+        if let Err(e) = remove_conversation(&db, "cid").await {
+            panic!("remove_conversation: {}", e);
+        }
+        assert!(run(&db, SearchQuery::default()).await.is_empty());
+    }
+
+    #[test]
+    fn test_fts_expression_quotes_terms() {
+        assert_eq!(
+            fts_expression("apply_patch foo.rs").as_deref(),
+            Some("\"apply_patch\" \"foo.rs\"")
+        );
+        assert_eq!(fts_expression("pars*").as_deref(), Some("\"pars\"*"));
+        assert_eq!(fts_expression("  \" * ").as_deref(), None);
+    }
+}
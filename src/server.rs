@@ -0,0 +1,1869 @@
+//! HTTP server: the chat completions proxy and the debug API.
+//!
+//! The binary wires this up behind the TUI; integration tests boot it directly against a
+//! mock upstream.
+
+use crate::engine::*;
+use crate::logging::turn_id_middleware;
+use crate::tui::TuiEvent;
+use crate::*;
+
+use crate::constants::UPSTREAM_OPENROUTER;
+use crate::ingress::RawTurn;
+use crate::projections::AnthropicFlavor;
+use crate::projections::GeminiFlavor;
+use crate::projections::OpenAiFlavor;
+use crate::projections::OpenRouterAdapter;
+use crate::projections::ProviderFlavor;
+use crate::projections::StandardFlavor;
+use crate::redaction::redact_serialized;
+use crate::streaming::StreamHandler;
+
+use axum::response::sse::KeepAlive;
+use axum::{
+    extract::State,
+    http as ax_http, middleware,
+    response::{IntoResponse, Response, Sse},
+    routing::post,
+    Json, Router,
+};
+use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::codec::{FramedRead, LinesCodec};
+use tracing::Instrument;
+
+/// Builds the full application router (chat completions, health, metrics and debug API).
+pub fn router(state: Arc<AppState>) -> Router {
+    let chat_routes = Router::new()
+        .route("/v1/chat/completions", post(chat_completions_handler))
+        .route("/chat/completions", post(chat_completions_handler))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            crate::rate_limit::rate_limit_middleware,
+        ));
+
+    Router::new()
+        .merge(chat_routes)
+        .route("/health", axum::routing::get(health::liveness))
+        .route(
+            "/metrics",
+            axum::routing::get(crate::metrics::metrics_handler),
+        )
+        .route("/readyz", axum::routing::get(health::readiness))
+        .route("/admin/health", axum::routing::get(health::admin_health))
+        .route(
+            "/admin/conversation/:cid",
+            axum::routing::get(health::admin_conversation),
+        )
+        // Debug API
+        .route(
+            "/debug/conversations",
+            axum::routing::get(list_conversations),
+        )
+        .route("/debug/search", axum::routing::get(search_turns))
+        .route("/debug/events", axum::routing::get(debug_events))
+        .route(
+            "/debug/conversation/:cid",
+            axum::routing::get(get_conversation),
+        )
+        .route(
+            "/debug/conversation/:cid/turn/:tid",
+            axum::routing::get(get_turn),
+        )
+        .route("/debug/blob/:cid/:tid/:bid", axum::routing::get(get_blob))
+        .route(
+            "/debug/diff/:cid/:tid",
+            axum::routing::post(compute_stage_diff),
+        )
+        .route(
+            "/debug/export/conversation/:cid",
+            axum::routing::get(export_conversation),
+        )
+        .route(
+            "/debug/export/turn/:cid/:tid",
+            axum::routing::get(export_turn),
+        )
+        .route("/debug/replay/:cid/:tid", axum::routing::post(replay_turn))
+        // Serve static UI with SPA fallback
+        .route("/debug/ui", axum::routing::get(debug_ui_root))
+        .route("/debug/ui/*path", axum::routing::get(debug_ui_handler))
+        .layer(axum::extract::DefaultBodyLimit::max(
+            state.args.max_body_size,
+        ))
+        .layer(middleware::from_fn(turn_id_middleware))
+        .with_state(state)
+}
+
+fn detect_intent(_model: &str, payload: &serde_json::Value) -> Option<crate::tui::Intent> {
+    let raw_content = payload
+        .get("messages")
+        .or_else(|| payload.get("input"))
+        .and_then(|m| m.as_array())
+        .and_then(|a| a.last())
+        .and_then(|l| l.get("content"))
+        .and_then(|c| c.as_str())?;
+
+    // 1. Explicit Tag Parsing (Prioritized Source of Truth)
+    // We only look for tags that are NOT inside triple-backtick code blocks.
+    let mut clean_content = String::new();
+    let mut in_code_block = false;
+    for line in raw_content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if !in_code_block {
+            clean_content.push_str(line);
+            clean_content.push('\n');
+        }
+    }
+
+    if let Some(intent) = detect_intent_tag(&clean_content) {
+        return Some(intent);
+    }
+
+    // 2. Keyword Fallback (Only if no explicit tag found outside code blocks)
+    // We look at the last 500 chars to avoid catching keywords in long system prompts or scaffold text.
+    let search_window = if clean_content.len() > 500 {
+        &clean_content[clean_content.len() - 500..]
+    } else {
+        &clean_content
+    };
+
+    detect_intent_keywords(search_window)
+}
+
+fn detect_intent_tag(clean_content: &str) -> Option<crate::tui::Intent> {
+    if let Some(start) = clean_content.find("<system_reminder>") {
+        let after_start = &clean_content[start + "<system_reminder>".len()..];
+        if let Some(end_offset) = after_start.find("</system_reminder>") {
+            let reminder_content = &after_start[..end_offset];
+            let upper_reminder = reminder_content.to_uppercase();
+
+            let intent = if upper_reminder.contains("AGENT")
+                || upper_reminder.contains("COMPOSER")
+                || upper_reminder.contains("BUILD")
+            {
+                crate::tui::Intent::Agent
+            } else if upper_reminder.contains("PLAN") {
+                crate::tui::Intent::Plan
+            } else if upper_reminder.contains("DEBUG") {
+                crate::tui::Intent::Debug
+            } else {
+                crate::tui::Intent::Ask
+            };
+
+            tracing::debug!(
+                "Intent detected via <system_reminder>: {:?} (snippet: {:?})",
+                intent,
+                reminder_content.chars().take(50).collect::<String>()
+            );
+            return Some(intent);
+        }
+    }
+    None
+}
+
+fn detect_intent_keywords(search_window: &str) -> Option<crate::tui::Intent> {
+    let content = search_window.to_uppercase();
+
+    let intent = if content.contains(" PLAN MODE") || content.contains(" PLANNING MODE") {
+        Some(crate::tui::Intent::Plan)
+    } else if content.contains(" AGENT MODE")
+        || content.contains(" COMPOSER MODE")
+        || content.contains(" BUILD MODE")
+    {
+        Some(crate::tui::Intent::Agent)
+    } else if content.contains(" DEBUG MODE") {
+        Some(crate::tui::Intent::Debug)
+    } else if content.contains(" ASK MODE") || content.contains(" CHAT MODE") {
+        Some(crate::tui::Intent::Ask)
+    } else {
+        None
+    };
+
+    if let Some(i) = intent {
+        tracing::debug!(
+            "Intent detected via keywords: {:?} (window: {:?})",
+            i,
+            search_window.chars().take(50).collect::<String>()
+        );
+    }
+
+    intent
+}
+
+#[allow(dead_code)]
+async fn replay_artifact(path: &str) -> Result<()> {
+    println!("--- REPLAYING ARTIFACT: {} ---", path);
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .map_err(ParallaxError::Io)?;
+    let recorder: crate::debug_utils::FlightRecorder = serde_json::from_str(&content)
+        .map_err(|e| ParallaxError::InvalidIngress(format!("Failed to parse artifact: {}", e)))?;
+
+    if let Some(ingress_raw) = recorder.stages.get("ingress_raw") {
+        println!("Stage: Ingress Raw found.");
+        let raw: RawTurn = serde_json::from_value(ingress_raw.clone()).map_err(|e| {
+            ParallaxError::InvalidIngress(format!("Invalid ingress in artifact: {}", e))
+        })?;
+
+        println!("Validating...");
+        raw.validate()?;
+        println!("Validation OK.");
+
+        // For replay to work fully we'd need a DB pool.
+        // We'll skip the bits that need DB for now or just print what we have.
+        println!(
+            "Replay of lifting/projection requires a DB pool. Artifact contains {} decisions.",
+            recorder.decisions.len()
+        );
+        for (i, d) in recorder.decisions.iter().enumerate() {
+            println!("Decision {}: {}", i, d);
+        }
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(
+    name = "shim.request",
+    skip_all,
+    fields(
+        request_id = tracing::field::Empty,
+        model.target = tracing::field::Empty,
+        tokens.prompt = tracing::field::Empty,
+        tokens.completion = tracing::field::Empty,
+        http.status = tracing::field::Empty,
+        shim.outcome = tracing::field::Empty,
+        cf.ray = tracing::field::Empty,
+        cf.ip = tracing::field::Empty,
+    )
+)]
+async fn chat_completions_handler(
+    State(state): State<Arc<AppState>>,
+    headers: axum::http::HeaderMap,
+    Json(payload): Json<serde_json::Value>,
+) -> Response {
+    let _start = std::time::Instant::now();
+    let span = tracing::Span::current();
+
+    // Capture Cloudflare/Tunnel headers if present
+    if let Some(ray_id) = headers.get("cf-ray").and_then(|h| h.to_str().ok()) {
+        span.record("cf.ray", ray_id);
+        tracing::debug!("Request received via Cloudflare Ray: {}", ray_id);
+    }
+    if let Some(forwarded) = headers.get("x-forwarded-for").and_then(|h| h.to_str().ok()) {
+        span.record("cf.ip", forwarded);
+    }
+
+    // Extract Cursor conversation ID from headers if present
+    let cursor_conversation_id = crate::ingress::header_conversation_id(&headers);
+
+    if let Some(ref cid) = cursor_conversation_id {
+        tracing::info!(
+            "[🖱️  -> ⚙️ ] Found Cursor conversation ID in header: [{}...]",
+            crate::str_utils::prefix_chars(cid, 8)
+        );
+    }
+
+    // A reconnecting client replays the buffered stream instead of re-running the turn.
+    if let Some(last_event_id) = headers.get("last-event-id").and_then(|h| h.to_str().ok()) {
+        match state.sse_resume.resume(last_event_id) {
+            Some(rx) => {
+                tracing::info!(
+                    "[🖱️  -> ⚙️ ] Resuming SSE stream after event {}",
+                    last_event_id
+                );
+                span.record("shim.outcome", "resumed");
+                return sse_response(rx);
+            }
+            None => tracing::warn!(
+                "Last-Event-ID {} is not resumable, starting a fresh turn",
+                last_event_id
+            ),
+        }
+    }
+
+    if let Err(resp) = validate_payload(&payload) {
+        span.record("shim.outcome", "client_error");
+        return *resp;
+    }
+
+    let entry = match ParallaxEngine::lift(payload.clone(), &state.db, cursor_conversation_id).await
+    {
+        Ok(e) => e,
+        Err(e) => {
+            tracing::error!("[🖱️  -> ⚙️ ] Lift Failed: {}", e);
+            span.record("shim.outcome", "internal_error");
+            return (
+                ax_http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e.to_string() })),
+            )
+                .into_response();
+        }
+    };
+
+    let (model_id, context, rid, flavor) = resolve_flavor_context(entry);
+    span.record("request_id", &rid);
+    span.record("model.target", &model_id);
+
+    let cid = context.conversation_id.clone();
+    let cid_source = context.conversation_id_source.clone();
+    let turn_id_uuid = uuid::Uuid::new_v4().to_string();
+    let tid = turn_id_uuid.clone();
+
+    // Phase 2: Initialize bundle
+    let bundle_manager = state.bundles();
+
+    tracing::info!(
+        "[⚙️  -> ⚙️ ] Turn Context: CID: [{}...] (Source: {}) TID: [{}...] RID: [{}...]",
+        crate::str_utils::prefix_chars(&cid, 8),
+        cid_source,
+        crate::str_utils::prefix_chars(&tid, 8),
+        crate::str_utils::prefix_chars(&rid, 8)
+    );
+
+    // Always write ingress_raw blob so users can see their messages
+    let _ = bundle_manager
+        .write_blob(&cid, &tid, "ingress_raw", payload.to_string().as_bytes())
+        .await;
+
+    // Capture the lifted context for debugging
+    let lifted_json = match serde_json::to_value(&context) {
+        Ok(val) => val,
+        Err(e) => {
+            tracing::warn!("Failed to serialize context for debug: {}", e);
+            serde_json::Value::Null
+        }
+    };
+    if state.args.enable_debug_capture {
+        let _ = bundle_manager
+            .write_blob(&cid, &tid, "lifted", lifted_json.to_string().as_bytes())
+            .await;
+        crate::debug_utils::capture_debug_snapshot(
+            &state.args.debug_capture_dir,
+            "lifted",
+            &rid,
+            &cid,
+            &model_id,
+            &lifted_json,
+        )
+        .await;
+    }
+
+    let intent = detect_intent(&model_id, &payload);
+
+    let mut recorder =
+        crate::debug_utils::FlightRecorder::new(&tid, &rid, &cid, &model_id, flavor.name())
+            .with_capture_dir(&state.args.debug_capture_dir);
+    recorder.record_stage("ingress_raw", payload.clone());
+
+    // Phase 2: Start TurnDetail
+    let start_ms = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0);
+
+    let mut stages_vec: Vec<crate::debug_bundle::StageIndex> = Vec::new();
+
+    // Extract useful metadata from ingress payload for summary
+    let messages_len = payload
+        .get("messages")
+        .and_then(|m| m.as_array())
+        .map(|a| a.len())
+        .unwrap_or(0);
+    let tools_len = payload
+        .get("tools")
+        .and_then(|t| t.as_array())
+        .map(|a| a.len())
+        .unwrap_or(0);
+
+    stages_vec.push(crate::debug_bundle::StageIndex {
+        name: "ingress_raw".to_string(),
+        kind: crate::debug_bundle::StageKind::Snapshot,
+        summary: serde_json::json!({
+            "len": payload.to_string().len(),
+            "messages_len": messages_len,
+            "tools_len": tools_len,
+        }),
+        blob_ref: Some(crate::debug_bundle::BlobRef {
+            blob_id: "ingress_raw".to_string(),
+            content_type: "application/json".to_string(),
+            approx_bytes: payload.to_string().len() as u64,
+            file_name: Some("ingress_raw.json".to_string()),
+            sha256: None,
+            written_at_ms: None,
+        }),
+    });
+
+    if state.args.enable_debug_capture {
+        stages_vec.push(crate::debug_bundle::StageIndex {
+            name: "lifted".to_string(),
+            kind: crate::debug_bundle::StageKind::Snapshot,
+            summary: serde_json::json!({
+                "history_len": context.history.len(),
+            }),
+            blob_ref: Some(crate::debug_bundle::BlobRef {
+                blob_id: "lifted".to_string(),
+                content_type: "application/json".to_string(),
+                approx_bytes: lifted_json.to_string().len() as u64,
+                file_name: Some("lifted.json".to_string()),
+                sha256: None,
+                written_at_ms: None,
+            }),
+        });
+    }
+
+    let user_query_opt = crate::debug_bundle::BundleManager::extract_user_query(&payload);
+
+    // Compute tag deltas if user_query exists
+    let user_query_tags = if let Some(ref user_query) = user_query_opt {
+        bundle_manager
+            .compute_user_query_tag_deltas(&cid, user_query)
+            .await
+    } else {
+        None
+    };
+
+    // Extract cursor tags from ingress payload (final will be added during finalization)
+    let cursor_tags = crate::debug_bundle::BundleManager::extract_cursor_tags(&payload, None);
+
+    let turn_detail = crate::debug_bundle::TurnDetail {
+        turn_id: tid.clone(),
+        request_id: rid.clone(),
+        model_id: model_id.clone(),
+        flavor: flavor.name().to_string(),
+        started_at_ms: start_ms,
+        ended_at_ms: None,
+        stages: stages_vec,
+        tool_calls: Vec::new(),
+        tool_results: Vec::new(),
+        cursor_tags,
+        issues: Vec::new(),
+        trace_id: None,
+        span_summary: None,
+        user_query: user_query_opt,
+        role: Some("User".to_string()),
+        conversation_id_source: context.conversation_id_source.clone(),
+        user_query_tags,
+    };
+
+    // Initial write
+    let _ = bundle_manager
+        .update_summaries(&cid, &tid, &turn_detail)
+        .await;
+
+    if let Err(e) = ParallaxEngine::validate_context(&context) {
+        return handle_validation_error(e, &mut recorder).await;
+    }
+
+    let _ = state.tx_tui.send(TuiEvent::RequestStarted {
+        id: rid.clone(),
+        cid: cid.clone(),
+        method: "Chat".to_string(),
+        model: model_id.clone(),
+        intent,
+    });
+
+    // Delegated to reduce complexity
+    handle_turn_processing(
+        state,
+        context,
+        model_id,
+        flavor,
+        rid,
+        &mut recorder,
+        &payload,
+        intent,
+        tid,
+    )
+    .await
+}
+
+async fn handle_validation_error(
+    e: ObservedError,
+    recorder: &mut crate::debug_utils::FlightRecorder,
+) -> Response {
+    tracing::error!("[⚙️  -> ⚙️ ] Context Validation Failed: {}", e);
+    recorder.record_decision(format!("Validation Failed: {}", e));
+    recorder.save().await;
+    e.into_response()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_turn_processing(
+    state: Arc<AppState>,
+    context: ConversationContext,
+    model_id: String,
+    flavor: Arc<dyn ProviderFlavor + Send + Sync>,
+    rid: String,
+    recorder: &mut crate::debug_utils::FlightRecorder,
+    payload: &serde_json::Value,
+    intent: Option<crate::tui::Intent>,
+    tid: String,
+) -> Response {
+    let start_time = std::time::Instant::now();
+    let cid = context.conversation_id.clone();
+
+    let span = tracing::info_span!(
+        "turn",
+        cid = &cid[..8.min(cid.len())],
+        rid = &rid[..8.min(rid.len())]
+    );
+
+    crate::logging::log_request_summary(payload);
+
+    let response = process_turn(
+        state.clone(),
+        context,
+        model_id,
+        flavor,
+        rid.clone(),
+        start_time,
+        recorder,
+        intent,
+        tid,
+    )
+    .instrument(span)
+    .await;
+
+    recorder.save().await;
+
+    let latency = start_time.elapsed().as_millis();
+
+    let status = match response.status() {
+        s if s.is_success() => 200,
+        s => s.as_u16(),
+    };
+
+    state
+        .kernel
+        .report(crate::kernel::KernelEvent::RequestFinished {
+            id: rid.clone(),
+            status,
+        })
+        .await;
+    let _ = state.tx_tui.send(TuiEvent::RequestFinished {
+        id: rid,
+        status,
+        latency_ms: latency,
+    });
+
+    response
+}
+
+fn validate_payload(payload: &serde_json::Value) -> std::result::Result<(), Box<Response>> {
+    let raw: RawTurn = match serde_json::from_value(payload.clone()) {
+        Ok(r) => r,
+        Err(e) => {
+            return Err(Box::new((
+                ax_http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": format!("Payload deserialization failed: {}", e) })),
+            ).into_response()));
+        }
+    };
+
+    if let Err(e) = raw.validate() {
+        tracing::error!("[🖱️  -> ⚙️ ] Validation Failed: {}", e);
+        return Err(Box::new(
+            (
+                ax_http::StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": e.to_string(), "code": "VALIDATION_ERROR" })),
+            )
+                .into_response(),
+        ));
+    }
+
+    Ok(())
+}
+
+fn resolve_flavor_context(
+    entry: TurnOperationEntry,
+) -> (
+    String,
+    ConversationContext,
+    String,
+    Arc<dyn ProviderFlavor + Send + Sync>,
+) {
+    match entry {
+        TurnOperationEntry::Gemini(op) => (
+            op.model.model_name().to_string(),
+            op.input_context,
+            op.request_id,
+            Arc::new(GeminiFlavor),
+        ),
+        TurnOperationEntry::Anthropic(op) => (
+            op.model.model_name().to_string(),
+            op.input_context,
+            op.request_id,
+            Arc::new(AnthropicFlavor),
+        ),
+        TurnOperationEntry::OpenAI(op) => (
+            op.model.model_name().to_string(),
+            op.input_context,
+            op.request_id,
+            Arc::new(OpenAiFlavor),
+        ),
+        TurnOperationEntry::Standard(op) => (
+            op.model.model_name().to_string(),
+            op.input_context,
+            op.request_id,
+            Arc::new(StandardFlavor),
+        ),
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::cognitive_complexity)]
+async fn process_turn(
+    state: Arc<AppState>,
+    context: ConversationContext,
+    model_id: String,
+    flavor: Arc<dyn ProviderFlavor + Send + Sync>,
+    request_id: String,
+    start_time: std::time::Instant,
+    recorder: &mut crate::debug_utils::FlightRecorder,
+    intent: Option<crate::tui::Intent>,
+    tid: String,
+) -> Response {
+    tracing::info!(
+        "[🖱️  -> ⚙️ ] Received Turn [History: {}]",
+        context.history.len()
+    );
+
+    // Held until the attempt's outcome is recorded; dropping it frees an unused half-open probe.
+    let (model_id, flavor, _probe) =
+        match select_available_model(&state, model_id, flavor, &request_id).await {
+            Ok(val) => val,
+            Err(e) => {
+                recorder.record_decision(format!("All circuit breakers open: {}", e));
+                return e.into_response();
+            }
+        };
+
+    let (mut outgoing_request, vault) =
+        match project_request(&state, &context, &model_id, flavor, intent).await {
+            Ok(val) => val,
+            Err(e) => return e,
+        };
+    let schema_patches =
+        crate::sanitizer_rules::rules().patch_tools(outgoing_request.tools.as_mut());
+    if !schema_patches.is_empty() {
+        record_schema_patches(&state, &context.conversation_id, &tid, &schema_patches).await;
+    }
+    let secret_vault = match vault {
+        Some(vault) if !vault.is_empty() => {
+            record_scrubbed_secrets(&state, &context.conversation_id, &tid, &vault).await;
+            state.args.restore_secrets.then(|| Arc::new(vault))
+        }
+        _ => None,
+    };
+
+    let outgoing_request_json = match serde_json::to_value(&outgoing_request) {
+        Ok(val) => val,
+        Err(e) => {
+            tracing::error!("Failed to serialize request for logging: {}", e);
+            return (
+                ax_http::StatusCode::INTERNAL_SERVER_ERROR,
+                Json(serde_json::json!({"error": "Serialization failed"})),
+            )
+                .into_response();
+        }
+    };
+
+    crate::debug_utils::log_traffic_summary(
+        &format!("Shim -> OpenRouter ({})", model_id),
+        &outgoing_request_json,
+    );
+
+    recorder.record_stage("upstream_request", outgoing_request_json.clone());
+
+    // Phase 2: Write projected request (redacted); retries re-send the held copy
+    let projected_request = state
+        .projected_requests
+        .hold(&tid, outgoing_request_json.clone());
+    let bundle_manager = state.bundles();
+    if let Ok(blob_ref) = bundle_manager
+        .write_blob(
+            &context.conversation_id,
+            &tid,
+            "projected",
+            outgoing_request_json.to_string().as_bytes(),
+        )
+        .await
+    {
+        let _ = bundle_manager
+            .add_stage(
+                &context.conversation_id,
+                &tid,
+                "projected",
+                blob_ref,
+                serde_json::json!({
+                    "len": outgoing_request_json.to_string().len(),
+                }),
+            )
+            .await;
+    }
+
+    let is_streaming = match outgoing_request.stream {
+        Some(s) => s,
+        None => false,
+    };
+    let hedge_config = if is_streaming {
+        crate::hedging::HedgeConfig::for_model(&state.args, &model_id)
+    } else {
+        None
+    };
+
+    let result = match hedge_config {
+        Some(ref config) => execute_hedged_request(
+            &state,
+            &context,
+            intent,
+            &outgoing_request,
+            config,
+            &request_id,
+        )
+        .await
+        .map(UpstreamStart::Hedged),
+        None => execute_upstream_request(&state, &outgoing_request, &request_id)
+            .await
+            .map(UpstreamStart::Direct),
+    };
+
+    match result {
+        Ok(start) => {
+            let served_model = match &start {
+                UpstreamStart::Hedged(outcome) => outcome.model.clone(),
+                UpstreamStart::Direct(_) => model_id.clone(),
+            };
+            state.kernel.update_health(true).await;
+            state
+                .kernel
+                .record_circuit_success(UPSTREAM_OPENROUTER, &served_model)
+                .await;
+
+            let tool_schemas = Arc::new(match outgoing_request.tools.as_deref() {
+                Some(tools) => crate::tool_schema::ToolSchemaRegistry::from_openai_tools(tools),
+                None => crate::tool_schema::ToolSchemaRegistry::new(),
+            });
+
+            match start {
+                UpstreamStart::Hedged(mut outcome) => {
+                    record_hedge_outcome(
+                        &state,
+                        &mut outcome,
+                        &context.conversation_id,
+                        &tid,
+                        &request_id,
+                    )
+                    .await;
+                    stream_to_client(
+                        outcome.stream,
+                        state.clone(),
+                        context,
+                        outcome.model,
+                        request_id,
+                        start_time,
+                        tool_schemas,
+                        tid,
+                        secret_vault,
+                        projected_request,
+                    )
+                }
+                UpstreamStart::Direct(response) if is_streaming => {
+                    handle_upstream_response(
+                        response,
+                        state.clone(),
+                        context,
+                        model_id,
+                        request_id,
+                        start_time,
+                        recorder,
+                        tool_schemas,
+                        tid,
+                        secret_vault,
+                        projected_request,
+                    )
+                    .await
+                }
+                UpstreamStart::Direct(response) => {
+                    handle_non_streaming_response(
+                        &state,
+                        response,
+                        recorder,
+                        &model_id,
+                        &request_id,
+                        &context.conversation_id,
+                        &tid,
+                        secret_vault.as_deref(),
+                    )
+                    .await
+                }
+            }
+        }
+        Err(e) => {
+            state.kernel.update_health(false).await;
+            state
+                .kernel
+                .record_circuit_error(UPSTREAM_OPENROUTER, &model_id, &e)
+                .await;
+
+            tracing::error!("[☁️  -> ⚙️ ] Request Error: {}", e);
+
+            match &e.inner {
+                ParallaxError::Upstream(status, body) => {
+                    recorder.record_upstream_error(*status, body);
+                }
+                _ => {
+                    recorder.record_decision(format!("Request Error: {}", e));
+                }
+            }
+
+            e.into_response()
+        }
+    }
+}
+
+/// Walks the fallback chain for `model_id` and returns the first model whose circuit
+/// breaker admits the request, re-resolving the flavor when we fall back.
+async fn select_available_model(
+    state: &Arc<AppState>,
+    model_id: String,
+    flavor: Arc<dyn ProviderFlavor + Send + Sync>,
+    request_id: &str,
+) -> Result<(
+    String,
+    Arc<dyn ProviderFlavor + Send + Sync>,
+    crate::kernel::ProbeGuard,
+)> {
+    let mut last_err = None;
+    for candidate in state.args.fallback_chain(&model_id) {
+        match state
+            .kernel
+            .check_circuit(UPSTREAM_OPENROUTER, &candidate)
+            .await
+        {
+            Ok(probe) if candidate == model_id => return Ok((model_id, flavor, probe)),
+            Ok(probe) => {
+                tracing::warn!(
+                    "[⚙️ ] Circuit open for {}; falling back to {}",
+                    model_id,
+                    candidate
+                );
+                let _ = state.tx_tui.send(TuiEvent::LogMessage {
+                    level: "WARN".to_string(),
+                    target: "parallax::hardening".to_string(),
+                    message: format!(
+                        "Circuit open for {}; falling back to {}",
+                        model_id, candidate
+                    ),
+                    timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+                });
+                let _ = state.tx_tui.send(TuiEvent::hop(
+                    request_id,
+                    crate::tui::HopKind::Fallback,
+                    &candidate,
+                    format!("circuit open for {}", model_id),
+                ));
+                let fallback_flavor = flavor_for_model(&candidate);
+                return Ok((candidate, fallback_flavor, probe));
+            }
+            Err(e) => last_err = Some(e),
+        }
+    }
+    match last_err {
+        Some(e) => Err(e),
+        None => Err(ParallaxError::CircuitOpen(model_id).into()),
+    }
+}
+
+fn flavor_for_model(model_id: &str) -> Arc<dyn ProviderFlavor + Send + Sync> {
+    match serde_json::from_value::<crate::ingress::ModelProvider>(serde_json::Value::String(
+        model_id.to_string(),
+    )) {
+        Ok(crate::ingress::ModelProvider::Gemini(_)) => Arc::new(GeminiFlavor),
+        Ok(crate::ingress::ModelProvider::Anthropic(_)) => Arc::new(AnthropicFlavor),
+        Ok(crate::ingress::ModelProvider::OpenAI(_)) => Arc::new(OpenAiFlavor),
+        Ok(crate::ingress::ModelProvider::Standard(_)) | Err(_) => Arc::new(StandardFlavor),
+    }
+}
+
+enum UpstreamStart {
+    Direct(reqwest::Response),
+    Hedged(crate::hedging::HedgeOutcome),
+}
+
+fn upstream_byte_stream(response: reqwest::Response) -> crate::hedging::UpstreamByteStream {
+    response
+        .bytes_stream()
+        .map(|r| r.map_err(std::io::Error::other))
+        .boxed()
+}
+
+async fn execute_hedged_request(
+    state: &Arc<AppState>,
+    context: &ConversationContext,
+    intent: Option<crate::tui::Intent>,
+    outgoing_request: &crate::specs::openai::OpenAiRequest,
+    config: &crate::hedging::HedgeConfig,
+    request_id: &str,
+) -> Result<crate::hedging::HedgeOutcome> {
+    let primary_model = outgoing_request.model.clone();
+    // Prompt usage of what each model was actually sent, for costing cancelled attempts.
+    let sent: Arc<std::sync::Mutex<std::collections::HashMap<String, crate::types::Usage>>> =
+        Arc::default();
+    let mut outcome = crate::hedging::race(outgoing_request, config, |req| {
+        let state = state.clone();
+        let primary_model = primary_model.clone();
+        let request_id = request_id.to_string();
+        let sent = sent.clone();
+        async move {
+            // The primary model was admitted by `select_available_model`. The alternate holds
+            // its own guard, which a losing (cancelled) attempt drops with its future.
+            let _probe = if req.model != primary_model {
+                Some(
+                    state
+                        .kernel
+                        .check_circuit(UPSTREAM_OPENROUTER, &req.model)
+                        .await?,
+                )
+            } else {
+                None
+            };
+            // The primary's projection was built for its own flavor, caching and model id.
+            let req = if req.model != primary_model {
+                project_for_model(&state, context, &req.model, intent).await
+            } else {
+                req
+            };
+            if let Ok(mut sent) = sent.lock() {
+                sent.insert(
+                    req.model.clone(),
+                    crate::hedging::estimated_cancelled_usage(&req),
+                );
+            }
+            let response = execute_upstream_request(&state, &req, &request_id).await?;
+            Ok(upstream_byte_stream(response))
+        }
+    })
+    .await?;
+    if let Ok(sent) = sent.lock() {
+        for attempt in outcome.attempts.iter_mut() {
+            attempt.sent_usage = sent.get(&attempt.model).cloned();
+        }
+    }
+    Ok(outcome)
+}
+
+/// Projects `context` for an attempt on a model other than the one `process_turn` projected
+/// for. Secret placeholders are derived from the secret, so they match the primary's vault.
+async fn project_for_model(
+    state: &Arc<AppState>,
+    context: &ConversationContext,
+    model_id: &str,
+    intent: Option<crate::tui::Intent>,
+) -> crate::specs::openai::OpenAiRequest {
+    let mut vault = state
+        .args
+        .scrub_secrets
+        .then(crate::secret_scrub::SecretVault::default);
+    let mut request = OpenRouterAdapter::project(
+        context,
+        model_id,
+        flavor_for_model(model_id).as_ref(),
+        &state.db,
+        intent,
+        &state.pricing,
+        vault.as_mut(),
+    )
+    .await;
+    let _ = crate::sanitizer_rules::rules().patch_tools(request.tools.as_mut());
+    request
+}
+
+/// Charges cancelled hedge attempts to the cost ledger and records every attempt in the bundle.
+async fn record_hedge_outcome(
+    state: &Arc<AppState>,
+    outcome: &mut crate::hedging::HedgeOutcome,
+    cid: &str,
+    tid: &str,
+    request_id: &str,
+) {
+    if !outcome.hedged() {
+        return;
+    }
+
+    for attempt in outcome.attempts.iter_mut() {
+        if attempt.outcome != crate::hedging::AttemptOutcome::Cancelled {
+            continue;
+        }
+        // An attempt cancelled before its request went out was never billed.
+        let usage = match attempt.sent_usage.clone() {
+            Some(u) => u,
+            None => continue,
+        };
+        match crate::main_helper::calculate_cost(&attempt.model, &usage, &state.pricing) {
+            Ok(breakdown) => {
+                attempt.estimated_cost_usd = Some(breakdown.actual_cost);
+                state
+                    .kernel
+                    .record_spend(&attempt.model, breakdown.actual_cost)
+                    .await;
+                let _ = state.tx_tui.send(TuiEvent::CostUpdate {
+                    id: format!("{}-hedge", request_id),
+                    model: attempt.model.clone(),
+                    usage,
+                    actual_cost: breakdown.actual_cost,
+                    potential_cost_no_cache: breakdown.potential_cost_no_cache,
+                    pricing: state.pricing.get(&attempt.model).cloned(),
+                    prompt_cost: breakdown.prompt_cost,
+                    completion_cost: breakdown.completion_cost,
+                    cache_read_cost: breakdown.cache_read_cost,
+                    request_cost: breakdown.request_cost,
+                });
+            }
+            Err(reason) => {
+                tracing::warn!("[⚙️ ] Unable to cost cancelled hedge attempt: {}", reason);
+            }
+        }
+    }
+
+    tracing::info!(
+        "[⚙️ ] Hedge committed to {} ({} attempts)",
+        outcome.model,
+        outcome.attempts.len()
+    );
+    let _ = state.tx_tui.send(TuiEvent::hop(
+        request_id,
+        crate::tui::HopKind::Hedge,
+        &outcome.model,
+        format!(
+            "no first line within {}ms; {} attempts raced",
+            state.args.hedge_ttft_ms,
+            outcome.attempts.len()
+        ),
+    ));
+
+    let report = serde_json::json!({
+        "winner_model": outcome.model,
+        "ttft_threshold_ms": state.args.hedge_ttft_ms,
+        "attempts": outcome.attempts,
+    });
+    let bundle_manager = state.bundles();
+    if let Ok(blob_ref) = bundle_manager
+        .write_blob(cid, tid, "hedge", report.to_string().as_bytes())
+        .await
+    {
+        let _ = bundle_manager
+            .add_stage(
+                cid,
+                tid,
+                "hedge",
+                blob_ref,
+                serde_json::json!({
+                    "winner_model": outcome.model,
+                    "attempts": outcome.attempts.len(),
+                }),
+            )
+            .await;
+    }
+}
+
+async fn execute_upstream_request(
+    state: &Arc<AppState>,
+    outgoing_request: &crate::specs::openai::OpenAiRequest,
+    request_id: &str,
+) -> Result<reqwest::Response> {
+    let retry_policy = crate::hardening::RetryPolicy::new(state.args.max_retries, 100)
+        .with_budget(Duration::from_secs(state.args.retry_budget_secs));
+
+    let state_clone = state.clone();
+    let req_clone = outgoing_request.clone();
+    let mut attempt = 0;
+
+    retry_policy
+        .execute_with_retry(move || {
+            let state = state_clone.clone();
+            let req = req_clone.clone();
+            attempt += 1;
+            if attempt > 1 {
+                let _ = state.tx_tui.send(TuiEvent::hop(
+                    request_id,
+                    crate::tui::HopKind::Retry,
+                    &req.model,
+                    format!("attempt {} of {}", attempt, state.args.max_retries),
+                ));
+            }
+            async move {
+                let response = state
+                    .client
+                    .post(state.args.chat_completions_url())
+                    .header("Authorization", format!("Bearer {}", state.openrouter_key))
+                    .json(&req)
+                    .send()
+                    .await
+                    .map_err(|e| ObservedError::from(ParallaxError::Network(e)))?;
+
+                let status = response.status();
+                if status.is_success() {
+                    Ok(response)
+                } else {
+                    let headers = response.headers().clone();
+                    let error_body = match response.text().await {
+                        Ok(text) => text,
+                        Err(_) => "Unknown error".to_string(),
+                    };
+                    let retry_after =
+                        crate::hardening::retry_after_for_response(&headers, &error_body);
+                    Err(
+                        ObservedError::from(ParallaxError::Upstream(status, error_body))
+                            .with_retry_after(retry_after),
+                    )
+                }
+            }
+        })
+        .await
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_non_streaming_response(
+    state: &AppState,
+    response: reqwest::Response,
+    recorder: &mut crate::debug_utils::FlightRecorder,
+    model_id: &str,
+    request_id: &str,
+    cid: &str,
+    tid: &str,
+    secret_vault: Option<&crate::secret_scrub::SecretVault>,
+) -> Response {
+    let status = response.status();
+    let mut body = match response.json::<serde_json::Value>().await {
+        Ok(b) => b,
+        Err(_) => serde_json::Value::Null,
+    };
+
+    if let Some(vault) = secret_vault {
+        restore_secrets_in_response(&mut body, vault);
+    }
+
+    recorder.record_stage("upstream_response", body.clone());
+
+    // Charged like a streamed turn, so the kernel's spend ledger sees every completion.
+    if let Some(usage) = body.get("usage").filter(|u| !u.is_null()) {
+        match serde_json::from_value::<crate::types::Usage>(usage.clone()) {
+            Ok(usage) => {
+                crate::streaming::StreamHandler::compute_and_send_cost(
+                    model_id,
+                    request_id,
+                    &usage,
+                    &state.pricing,
+                    &state.tx_tui,
+                    &state.kernel,
+                )
+                .await
+            }
+            Err(e) => tracing::warn!("[⚙️ ] Unable to read usage from response: {}", e),
+        }
+    }
+
+    // Phase 2: Write upstream response
+    let bundle_manager = state.bundles();
+    if let Ok(blob_ref) = bundle_manager
+        .write_blob(cid, tid, "upstream_response", body.to_string().as_bytes())
+        .await
+    {
+        let _ = bundle_manager
+            .add_stage(
+                cid,
+                tid,
+                "upstream_response",
+                blob_ref,
+                serde_json::json!({
+                    "len": body.to_string().len(),
+                    "status": status.as_u16(),
+                }),
+            )
+            .await;
+    }
+
+    crate::logging::sanitize_response_body(&mut body);
+    recorder.record_stage("sanitized_response", body.clone());
+    crate::logging::log_response_summary(&body);
+
+    (status, Json(body)).into_response()
+}
+
+/// Projects the turn for `model_id`; with `--scrub-secrets` the vault holds the secrets
+/// replaced by placeholders.
+async fn project_request(
+    state: &Arc<AppState>,
+    context: &ConversationContext,
+    model_id: &str,
+    flavor: Arc<dyn ProviderFlavor + Send + Sync>,
+    intent: Option<crate::tui::Intent>,
+) -> std::result::Result<
+    (
+        crate::specs::openai::OpenAiRequest,
+        Option<crate::secret_scrub::SecretVault>,
+    ),
+    Response,
+> {
+    let mut vault = state
+        .args
+        .scrub_secrets
+        .then(crate::secret_scrub::SecretVault::default);
+    let request = OpenRouterAdapter::project(
+        context,
+        model_id,
+        flavor.as_ref(),
+        &state.db,
+        intent,
+        &state.pricing,
+        vault.as_mut(),
+    )
+    .await;
+    Ok((request, vault))
+}
+
+/// Records a `ToolSchemaPatched` issue on the turn per sanitizer schema patch.
+async fn record_schema_patches(
+    state: &AppState,
+    cid: &str,
+    tid: &str,
+    patches: &[crate::sanitizer_rules::RuleApplication],
+) {
+    for patch in patches {
+        tracing::debug!(
+            "[⚙️ ] Sanitizer rule '{}' on '{}': {}",
+            patch.rule,
+            patch.tool,
+            patch.change
+        );
+        if let Err(e) = state
+            .bundles()
+            .add_issue(cid, tid, patch.issue("ToolSchemaPatched"))
+            .await
+        {
+            tracing::warn!("Failed to record ToolSchemaPatched issue: {}", e);
+        }
+    }
+}
+
+/// Records a `SecretScrubbed` issue on the turn; only detectors and placeholders are logged.
+async fn record_scrubbed_secrets(
+    state: &AppState,
+    cid: &str,
+    tid: &str,
+    vault: &crate::secret_scrub::SecretVault,
+) {
+    let scrubbed = vault.scrubbed();
+    tracing::warn!(
+        "[🔒] Scrubbed {} secret(s) from the outbound request",
+        scrubbed.len()
+    );
+    let issue = crate::debug_bundle::Issue {
+        kind: "SecretScrubbed".to_string(),
+        severity: "warning".to_string(),
+        message: format!(
+            "Replaced {} secret(s) with placeholders before sending upstream",
+            scrubbed.len()
+        ),
+        context: serde_json::json!({ "secrets": scrubbed }),
+    };
+    if let Err(e) = state.bundles().add_issue(cid, tid, issue).await {
+        tracing::warn!("Failed to record SecretScrubbed issue: {}", e);
+    }
+}
+
+async fn handle_error_response(
+    response: reqwest::Response,
+    recorder: &mut crate::debug_utils::FlightRecorder,
+) -> Response {
+    let status = response.status();
+    let error_body = match response.text().await {
+        Ok(text) => text,
+        Err(e) => {
+            tracing::warn!("Failed to read error body: {}", e);
+            format!("Upstream error (body unreadable): {}", e)
+        }
+    };
+    tracing::error!("[☁️  -> ⚙️ ] Upstream Error: {}", error_body);
+    recorder.record_stage("upstream_error", serde_json::json!({ "body": error_body }));
+    (status, error_body).into_response()
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_upstream_response(
+    response: reqwest::Response,
+    state: Arc<AppState>,
+    context: ConversationContext,
+    model_id: String,
+    request_id: String,
+    start_time: std::time::Instant,
+    recorder: &mut crate::debug_utils::FlightRecorder,
+    tool_schemas: Arc<crate::tool_schema::ToolSchemaRegistry>,
+    tid: String,
+    secret_vault: Option<Arc<crate::secret_scrub::SecretVault>>,
+    projected_request: crate::streaming::ProjectedRequestGuard,
+) -> Response {
+    let status = response.status();
+    tracing::info!("[☁️  -> ⚙️ ] Status: {}", status);
+
+    if !status.is_success() {
+        return handle_error_response(response, recorder).await;
+    }
+
+    stream_to_client(
+        upstream_byte_stream(response),
+        state,
+        context,
+        model_id,
+        request_id,
+        start_time,
+        tool_schemas,
+        tid,
+        secret_vault,
+        projected_request,
+    )
+}
+
+/// Restores scrubbed secrets in the tool-call arguments of a non-streaming completion.
+fn restore_secrets_in_response(
+    body: &mut serde_json::Value,
+    vault: &crate::secret_scrub::SecretVault,
+) {
+    let choices = match body.get_mut("choices").and_then(|c| c.as_array_mut()) {
+        Some(c) => c,
+        None => return,
+    };
+    for choice in choices {
+        let tool_calls = match choice
+            .pointer_mut("/message/tool_calls")
+            .and_then(|t| t.as_array_mut())
+        {
+            Some(t) => t,
+            None => continue,
+        };
+        for call in tool_calls {
+            if let Some(serde_json::Value::String(arguments)) =
+                call.pointer_mut("/function/arguments")
+            {
+                *arguments = vault.restore_json(arguments);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn stream_to_client(
+    bytes_stream: crate::hedging::UpstreamByteStream,
+    state: Arc<AppState>,
+    context: ConversationContext,
+    model_id: String,
+    request_id: String,
+    start_time: std::time::Instant,
+    tool_schemas: Arc<crate::tool_schema::ToolSchemaRegistry>,
+    tid: String,
+    secret_vault: Option<Arc<crate::secret_scrub::SecretVault>>,
+    projected_request: crate::streaming::ProjectedRequestGuard,
+) -> Response {
+    let lines_stream = FramedRead::new(
+        tokio_util::io::StreamReader::new(bytes_stream),
+        LinesCodec::new_with_max_length(1024 * 1024), // 1MB per line
+    );
+
+    let tid_capture = tid.clone();
+    let stream_id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = if state.sse_resume.is_enabled() {
+        let (tx_handler, rx_handler) = mpsc::channel(100);
+        let (tx_client, rx_client) = mpsc::channel(100);
+        state
+            .sse_resume
+            .relay(stream_id.clone(), rx_handler, tx_client);
+        (tx_handler, rx_client)
+    } else {
+        mpsc::channel(100)
+    };
+
+    // Send an initial SSE comment immediately so Cloudflare/clients never see an "empty response".
+    // This hardens the stream against Cloudflare 520 errors when upstream (e.g. Gemini) returns an empty stream.
+    let _ = tx.try_send(Ok(
+        axum::response::sse::Event::default().comment("parallax-stream-start")
+    ));
+
+    let db = state.db.clone();
+    let _conversation_id = context.conversation_id.clone();
+    let tx_tui = state.tx_tui.clone();
+    let pricing = state.pricing.clone();
+    let disable_rescue = state.disable_rescue;
+
+    let current_span = tracing::Span::current();
+    let _ = current_span;
+    let state_clone = state.clone();
+    let rid_clone = request_id.clone();
+    let rid_log = request_id.clone();
+    let cid_clone = context.conversation_id.clone();
+    let model_clone = model_id.clone();
+
+    // A headless shutdown waits for this task, so the turn's bundle is finalized too.
+    let in_flight = state.in_flight.enter();
+    tokio::spawn(async move {
+        let _in_flight = in_flight;
+        let _projected_request = projected_request;
+        let stream_span = tracing::info_span!(
+            "stream",
+            rid = %rid_clone,
+            cid = %crate::str_utils::prefix_chars(&cid_clone, 6),
+            model = %model_clone,
+            stream_id = %stream_id
+        );
+
+        StreamHandler::handle_stream(
+            lines_stream,
+            db,
+            cid_clone,
+            rid_clone,
+            tx,
+            model_id,
+            pricing,
+            tx_tui,
+            start_time,
+            disable_rescue,
+            state_clone.args.tool_arg_retry,
+            tool_schemas,
+            state_clone,
+            tid,
+            secret_vault.map(crate::secret_scrub::PlaceholderRestorer::new),
+            Vec::new(),
+        )
+        .instrument(stream_span)
+        .await;
+    });
+
+    tracing::info!(
+        rid = %crate::str_utils::prefix_chars(&rid_log, 8),
+        "[⚙️  -> 🖱️ ] SSE response created, stream task spawned"
+    );
+
+    let response = sse_response(rx);
+    if state.args.capture_sse {
+        let capture = state.bundles().start_sse_capture(
+            &context.conversation_id,
+            &tid_capture,
+            "client_sse",
+            start_time,
+        );
+        capture_response_lines(response, capture)
+    } else {
+        response
+    }
+}
+
+/// Tees the SSE body bytes actually written to the client into a `client_sse` capture.
+fn capture_response_lines(
+    response: Response,
+    capture: crate::debug_bundle::SseCapture,
+) -> Response {
+    let (parts, body) = response.into_parts();
+    let mut pending = String::new();
+    let teed = body.into_data_stream().map(move |chunk| {
+        if let Ok(bytes) = &chunk {
+            pending.push_str(&String::from_utf8_lossy(bytes));
+            while let Some(pos) = pending.find('\n') {
+                let line: String = pending.drain(..=pos).collect();
+                let line = line.trim_end_matches(['\r', '\n']);
+                if !line.is_empty() {
+                    capture.record(line);
+                }
+            }
+        }
+        chunk
+    });
+    Response::from_parts(parts, axum::body::Body::from_stream(teed))
+}
+
+fn sse_response(rx: mpsc::Receiver<crate::sse_resume::SseItem>) -> Response {
+    Sse::new(ReceiverStream::new(rx))
+        .keep_alive(
+            KeepAlive::new()
+                .interval(Duration::from_secs(10))
+                .text(": keepalive"),
+        )
+        .into_response()
+}
+
+// --- DEBUG API HANDLERS ---
+
+/// Dashboard feed for `parallax tui --connect`: a resync, the recent backlog, then live
+/// events, each a redacted JSON `TuiEvent`. A subscriber that falls behind is disconnected
+/// so its reconnect resyncs instead of silently missing events.
+async fn debug_events(State(state): State<Arc<AppState>>) -> Response {
+    let (backlog, rx) = state.events.subscribe();
+    let live = futures_util::stream::unfold(rx, |mut rx| async move {
+        match rx.recv().await {
+            Ok(event) => Some((event, rx)),
+            Err(_) => None,
+        }
+    });
+    let events = futures_util::stream::iter(backlog)
+        .chain(live)
+        .map(|event| axum::response::sse::Event::default().json_data(redact_serialized(&event)));
+    Sse::new(events)
+        .keep_alive(
+            KeepAlive::new()
+                .interval(Duration::from_secs(10))
+                .text(": keepalive"),
+        )
+        .into_response()
+}
+
+async fn list_conversations(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.bundles().list_conversations().await {
+        Ok(conversations) => Json(conversations).into_response(),
+        Err(e) => {
+            tracing::warn!("Failed to list conversations: {}", e);
+            Json(Vec::<crate::debug_bundle::ConversationSummary>::new()).into_response()
+        }
+    }
+}
+
+async fn search_turns(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Query(query): axum::extract::Query<crate::search_index::SearchQuery>,
+) -> impl IntoResponse {
+    match crate::search_index::search(&state.db, &query).await {
+        Ok(hits) => Json(hits).into_response(),
+        Err(e) => (
+            ax_http::StatusCode::INTERNAL_SERVER_ERROR,
+            axum::Json(serde_json::json!({ "error": format!("Search failed: {}", e) })),
+        )
+            .into_response(),
+    }
+}
+
+/// 400 for route or body identifiers that cannot name an entry of a debug bundle, so
+/// values like `..` never reach the bundle store.
+fn reject_invalid_ids(ids: &[&str]) -> Option<Response> {
+    for id in ids {
+        if let Err(e) = crate::bundle_store::validate_id(id) {
+            return Some(
+                (
+                    ax_http::StatusCode::BAD_REQUEST,
+                    axum::Json(serde_json::json!({ "error": e.inner.to_string() })),
+                )
+                    .into_response(),
+            );
+        }
+    }
+    None
+}
+
+async fn get_conversation(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(cid): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if let Some(rejected) = reject_invalid_ids(&[&cid]) {
+        return rejected;
+    }
+    match state.bundles().read_conversation(&cid).await {
+        Ok(Some(summary)) => Json(summary).into_response(),
+        _ => (ax_http::StatusCode::NOT_FOUND, "Conversation not found").into_response(),
+    }
+}
+
+async fn get_turn(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path((cid, tid)): axum::extract::Path<(String, String)>,
+) -> impl IntoResponse {
+    if let Some(rejected) = reject_invalid_ids(&[&cid, &tid]) {
+        return rejected;
+    }
+    match state.bundles().read_turn(&cid, &tid).await {
+        Ok(Some(detail)) => Json(detail).into_response(),
+        _ => (ax_http::StatusCode::NOT_FOUND, "Turn not found").into_response(),
+    }
+}
+
+async fn get_blob(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path((cid, tid, bid)): axum::extract::Path<(String, String, String)>,
+) -> impl IntoResponse {
+    if let Some(rejected) = reject_invalid_ids(&[&cid, &tid, &bid]) {
+        return rejected;
+    }
+    let bundle_manager = state.bundles();
+    let blob = match bundle_manager.read_blob(&cid, &tid, &bid).await {
+        Ok(Some(blob)) => blob,
+        _ => return (ax_http::StatusCode::NOT_FOUND, "Blob not found").into_response(),
+    };
+
+    // Incrementally written captures (upstream_sse/client_sse) are served as a JSON array.
+    if blob.file_name.ends_with(".ndjson") {
+        if let Ok(Some(value)) = bundle_manager.read_blob_value(&cid, &tid, &bid).await {
+            return (ax_http::StatusCode::OK, axum::Json(value)).into_response();
+        }
+    }
+
+    (
+        ax_http::StatusCode::OK,
+        [(ax_http::header::CONTENT_TYPE, blob.content_type())],
+        blob.content,
+    )
+        .into_response()
+}
+
+async fn compute_stage_diff(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path((cid, tid)): axum::extract::Path<(String, String)>,
+    axum::extract::Json(payload): axum::extract::Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let stage1 = payload.get("stage1").and_then(|s| s.as_str());
+    let stage2 = payload.get("stage2").and_then(|s| s.as_str());
+
+    let (stage1, stage2) = match (stage1, stage2) {
+        (Some(a), Some(b)) => (a, b),
+        _ => {
+            return (
+                ax_http::StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({ "error": "Missing stage1 or stage2" })),
+            )
+                .into_response();
+        }
+    };
+    if let Some(rejected) = reject_invalid_ids(&[&cid, &tid, stage1, stage2]) {
+        return rejected;
+    }
+
+    // Read both stage blobs
+    let bundle_manager = state.bundles();
+    let mut blobs = Vec::with_capacity(2);
+    for (label, stage) in [("stage1", stage1), ("stage2", stage2)] {
+        match bundle_manager.read_blob_value(&cid, &tid, stage).await {
+            Ok(Some(val)) => blobs.push(val),
+            Ok(None) => {
+                return (
+                    ax_http::StatusCode::NOT_FOUND,
+                    axum::Json(serde_json::json!({ "error": format!("{} blob not found", label) })),
+                )
+                    .into_response();
+            }
+            Err(_) => {
+                return (
+                    ax_http::StatusCode::BAD_REQUEST,
+                    axum::Json(
+                        serde_json::json!({ "error": format!("Failed to parse {} JSON", label) }),
+                    ),
+                )
+                    .into_response();
+            }
+        }
+    }
+
+    // SSE captures are compared payload-by-payload; everything else structurally.
+    let sse_lines = |v: &serde_json::Value| {
+        serde_json::from_value::<Vec<crate::debug_bundle::SseLine>>(v.clone()).ok()
+    };
+    let diff = match (
+        stage1.ends_with("_sse"),
+        stage2.ends_with("_sse"),
+        sse_lines(&blobs[0]),
+        sse_lines(&blobs[1]),
+    ) {
+        (true, true, Some(old), Some(new)) => {
+            crate::debug_bundle::BundleManager::compute_sse_diff(&old, &new)
+        }
+        _ => crate::debug_bundle::BundleManager::compute_json_diff(&blobs[0], &blobs[1]),
+    };
+
+    (
+        ax_http::StatusCode::OK,
+        axum::Json(serde_json::json!({
+            "stage1": stage1,
+            "stage2": stage2,
+            "diff": diff,
+        })),
+    )
+        .into_response()
+}
+
+/// Exports re-apply the current redaction rules, covering bundles written under older ones.
+async fn export_conversation(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(cid): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    if let Some(rejected) = reject_invalid_ids(&[&cid]) {
+        return rejected;
+    }
+    let zip_buffer = match state.bundles().export_conversation(&cid).await {
+        Ok(Some(zip)) => zip,
+        _ => {
+            return (ax_http::StatusCode::NOT_FOUND, "Conversation not found").into_response();
+        }
+    };
+
+    (
+        ax_http::StatusCode::OK,
+        [
+            (ax_http::header::CONTENT_TYPE, "application/zip"),
+            (
+                ax_http::header::CONTENT_DISPOSITION,
+                &format!("attachment; filename=\"conversation-{}.zip\"", cid),
+            ),
+        ],
+        zip_buffer,
+    )
+        .into_response()
+}
+
+async fn export_turn(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path((cid, tid)): axum::extract::Path<(String, String)>,
+) -> impl IntoResponse {
+    if let Some(rejected) = reject_invalid_ids(&[&cid, &tid]) {
+        return rejected;
+    }
+    let zip_buffer = match state.bundles().export_turn(&cid, &tid).await {
+        Ok(Some(zip)) => zip,
+        _ => {
+            return (ax_http::StatusCode::NOT_FOUND, "Turn not found").into_response();
+        }
+    };
+
+    (
+        ax_http::StatusCode::OK,
+        [
+            (ax_http::header::CONTENT_TYPE, "application/zip"),
+            (
+                ax_http::header::CONTENT_DISPOSITION,
+                &format!("attachment; filename=\"turn-{}.zip\"", tid),
+            ),
+        ],
+        zip_buffer,
+    )
+        .into_response()
+}
+
+async fn replay_turn(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path((cid, tid)): axum::extract::Path<(String, String)>,
+    axum::extract::Json(payload): axum::extract::Json<serde_json::Value>,
+) -> impl IntoResponse {
+    if let Some(rejected) = reject_invalid_ids(&[&cid, &tid]) {
+        return rejected;
+    }
+    // Read the ingress_raw blob to get the original payload
+    let ingress_content = match state
+        .bundles()
+        .read_blob_value(&cid, &tid, "ingress_raw")
+        .await
+    {
+        Ok(Some(val)) => val,
+        Ok(None) => {
+            return (
+                ax_http::StatusCode::NOT_FOUND,
+                axum::Json(serde_json::json!({ "error": "ingress_raw blob not found" })),
+            )
+                .into_response();
+        }
+        Err(_) => {
+            return (
+                ax_http::StatusCode::BAD_REQUEST,
+                axum::Json(serde_json::json!({ "error": "Failed to parse ingress_raw JSON" })),
+            )
+                .into_response();
+        }
+    };
+
+    // Extract options from payload
+    let stages_to_run = payload
+        .get("stages")
+        .and_then(|s| s.as_array())
+        .map(|arr| arr.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>())
+        .unwrap_or_else(|| vec!["projected", "final"]);
+
+    // For now, return a simple response indicating replay capability
+    // Full replay would require re-running the engine with the stored payload
+    (
+        ax_http::StatusCode::OK,
+        axum::Json(serde_json::json!({
+            "status": "replay_capability_available",
+            "message": "Replay endpoint is available for future implementation",
+            "ingress_payload_size": ingress_content.to_string().len(),
+            "requested_stages": stages_to_run,
+            "note": "Full replay requires re-running the engine with stored payload and current code"
+        })),
+    )
+        .into_response()
+}
+
+#[allow(clippy::cognitive_complexity)]
+async fn debug_ui_handler(
+    axum::extract::Path(path): axum::extract::Path<String>,
+) -> impl IntoResponse {
+    // Strip leading slash if present and construct path
+    let clean_path = path.trim_start_matches('/');
+    let file_path = format!("debug_ui/dist/{}", clean_path);
+
+    tracing::debug!("Serving UI asset: {} (from path: {})", file_path, path);
+
+    match tokio::fs::read(&file_path).await {
+        Ok(content) => {
+            let content_type = if file_path.ends_with(".js") {
+                "application/javascript; charset=utf-8"
+            } else if file_path.ends_with(".css") {
+                "text/css; charset=utf-8"
+            } else if file_path.ends_with(".json") {
+                "application/json"
+            } else if file_path.ends_with(".svg") {
+                "image/svg+xml"
+            } else if file_path.ends_with(".html") {
+                "text/html; charset=utf-8"
+            } else {
+                "application/octet-stream"
+            };
+
+            ([(ax_http::header::CONTENT_TYPE, content_type)], content).into_response()
+        }
+        Err(e) => {
+            tracing::warn!("Failed to serve asset {}: {}", file_path, e);
+            // Fallback to index.html for SPA routing
+            match tokio::fs::read("debug_ui/dist/index.html").await {
+                Ok(content) => (
+                    [(ax_http::header::CONTENT_TYPE, "text/html; charset=utf-8")],
+                    content,
+                )
+                    .into_response(),
+                Err(e) => {
+                    tracing::error!("Failed to read index.html: {}", e);
+                    (
+                        ax_http::StatusCode::NOT_FOUND,
+                        "Debug UI not found. Run: cd debug_ui && npm run build",
+                    )
+                        .into_response()
+                }
+            }
+        }
+    }
+}
+
+async fn debug_ui_root() -> impl IntoResponse {
+    // Serve index.html at /debug/ui root
+    match tokio::fs::read("debug_ui/dist/index.html").await {
+        Ok(content) => (
+            [(ax_http::header::CONTENT_TYPE, "text/html; charset=utf-8")],
+            content,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!("Failed to read index.html: {}", e);
+            (
+                ax_http::StatusCode::NOT_FOUND,
+                "Debug UI not found. Run: cd debug_ui && npm run build",
+            )
+                .into_response()
+        }
+    }
+}
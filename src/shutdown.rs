@@ -0,0 +1,107 @@
+//! Graceful shutdown for headless runs.
+//!
+//! On SIGTERM/SIGINT the listener stops accepting connections and Parallax waits (up to
+//! `--shutdown-grace-secs`) for in-flight streams to finish, so a turn that is mid-flight
+//! when systemd or Docker stops the service still reaches its client and its debug bundle.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Counts the stream tasks still running; see [`InFlight::enter`].
+#[derive(Debug, Default)]
+pub struct InFlight {
+    active: AtomicUsize,
+    idle: Notify,
+}
+
+/// Held by a stream task for as long as it runs.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+}
+
+impl InFlight {
+    pub fn enter(self: &Arc<Self>) -> InFlightGuard {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard {
+            in_flight: self.clone(),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Waits until no stream is in flight; `false` when `timeout` elapsed first.
+    pub async fn drained(&self, timeout: Duration) -> bool {
+        let wait = async {
+            loop {
+                let idle = self.idle.notified();
+                if self.active() == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.is_ok()
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if self.in_flight.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.in_flight.idle.notify_waiters();
+        }
+    }
+}
+
+/// Resolves with the signal name once SIGINT (Ctrl-C) or SIGTERM arrives.
+pub async fn signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let mut term = match signal(SignalKind::terminate()) {
+            Ok(s) => s,
+            Err(e) => {
+                tracing::warn!("Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                return "SIGINT";
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => "SIGINT",
+            _ = term.recv() => "SIGTERM",
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drained_waits_for_every_guard() {
+        let in_flight = Arc::new(InFlight::default());
+        assert!(in_flight.drained(Duration::from_millis(10)).await);
+
+        let first = in_flight.enter();
+        let second = in_flight.enter();
+        assert_eq!(in_flight.active(), 2);
+        assert!(!in_flight.drained(Duration::from_millis(10)).await);
+
+        drop(first);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(second);
+        });
+        assert!(in_flight.drained(Duration::from_secs(5)).await);
+        assert_eq!(in_flight.active(), 0);
+    }
+}
@@ -0,0 +1,411 @@
+use crate::types::ParallaxError;
+use axum::response::sse::Event;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, Notify};
+
+pub type SseItem = std::result::Result<Event, ParallaxError>;
+
+/// Per-request replay buffers for outgoing SSE streams.
+///
+/// Every event relayed to the client is stamped with an id of the form
+/// `<stream_id>:<seq>` and kept in memory. When a client reconnects with a
+/// `Last-Event-ID` header we replay everything after that sequence number and
+/// then follow the live stream, instead of re-issuing the upstream request.
+///
+/// Note: after the client disconnects the upstream keeps draining into the
+/// buffer for a short grace period, so a quick reconnect loses nothing. When no
+/// client resumes within it, the relay stops reading and the stream handler
+/// cancels the upstream request as it would without resumption.
+pub struct ResumeRegistry {
+    window: Duration,
+    max_events: usize,
+    detach_grace: Duration,
+    streams: Mutex<HashMap<String, Arc<ResumableStream>>>,
+}
+
+struct ResumableStream {
+    buffer: Mutex<StreamBuffer>,
+    notify: Notify,
+}
+
+#[derive(Default)]
+struct StreamBuffer {
+    events: VecDeque<(u64, Event)>,
+    next_seq: u64,
+    terminal_error: Option<String>,
+    finished_at: Option<Instant>,
+    /// Resumed clients currently following the stream.
+    readers: usize,
+    /// When the stream was last left without any client reading it.
+    unattended_since: Option<Instant>,
+}
+
+impl ResumableStream {
+    fn new() -> Self {
+        Self {
+            buffer: Mutex::new(StreamBuffer::default()),
+            notify: Notify::new(),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, StreamBuffer> {
+        match self.buffer.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+/// Counts a resumed client as a reader for as long as its replay task runs.
+struct Reader(Arc<ResumableStream>);
+
+impl Reader {
+    fn attach(stream: Arc<ResumableStream>) -> Self {
+        {
+            let mut buffer = stream.lock();
+            buffer.readers += 1;
+            buffer.unattended_since = None;
+        }
+        Self(stream)
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        let mut buffer = self.0.lock();
+        buffer.readers = buffer.readers.saturating_sub(1);
+        if buffer.readers == 0 {
+            buffer.unattended_since = Some(Instant::now());
+        }
+    }
+}
+
+/// Parses a `Last-Event-ID` value produced by [`ResumeRegistry::relay`].
+pub fn parse_event_id(last_event_id: &str) -> Option<(&str, u64)> {
+    let (stream_id, seq) = last_event_id.trim().rsplit_once(':')?;
+    if stream_id.is_empty() {
+        return None;
+    }
+    let seq = seq.parse::<u64>().ok()?;
+    Some((stream_id, seq))
+}
+
+impl ResumeRegistry {
+    pub fn new(window: Duration, max_events: usize) -> Self {
+        Self {
+            window,
+            max_events,
+            detach_grace: Duration::from_secs(crate::constants::SSE_RESUME_DETACH_GRACE_SECS),
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long a stream with no client attached keeps reading the upstream.
+    pub fn with_detach_grace(mut self, grace: Duration) -> Self {
+        self.detach_grace = grace;
+        self
+    }
+
+    /// Resumption is disabled when the retention window is zero.
+    pub fn is_enabled(&self) -> bool {
+        !self.window.is_zero()
+    }
+
+    fn streams(&self) -> std::sync::MutexGuard<'_, HashMap<String, Arc<ResumableStream>>> {
+        match self.streams.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn evict_expired(&self) {
+        let window = self.window;
+        self.streams()
+            .retain(|_, stream| match stream.lock().finished_at {
+                Some(finished_at) => finished_at.elapsed() < window,
+                None => true,
+            });
+    }
+
+    pub fn active_streams(&self) -> usize {
+        self.streams().len()
+    }
+
+    /// Spawns a relay task that stamps, buffers and forwards events from the
+    /// stream handler (`rx_handler`) to the original client (`tx_client`).
+    ///
+    /// The relay keeps draining `rx_handler` after the client goes away so
+    /// that a reconnecting client can pick up where it left off, but drops it
+    /// once nobody has been reading for the detach grace period.
+    pub fn relay(
+        self: &Arc<Self>,
+        stream_id: String,
+        mut rx_handler: mpsc::Receiver<SseItem>,
+        tx_client: mpsc::Sender<SseItem>,
+    ) {
+        self.evict_expired();
+        let stream = Arc::new(ResumableStream::new());
+        self.streams().insert(stream_id.clone(), stream.clone());
+
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let grace = registry.detach_grace;
+            let mut client_attached = true;
+            loop {
+                // While a resumed client reads, re-check once per grace period
+                let deadline = match (client_attached, stream.lock().unattended_since) {
+                    (true, _) => None,
+                    (false, Some(since)) => Some(since + grace),
+                    (false, None) => Some(Instant::now() + grace),
+                };
+                let next = match deadline {
+                    Some(deadline) => tokio::select! {
+                        next = rx_handler.recv() => next,
+                        _ = tokio::time::sleep_until(deadline.into()) => {
+                            let abandoned = match stream.lock().unattended_since {
+                                Some(since) => since.elapsed() >= grace,
+                                None => false,
+                            };
+                            if !abandoned {
+                                continue;
+                            }
+                            tracing::info!(
+                                stream_id = %stream_id,
+                                "No client resumed within {:?}; cancelling the stream",
+                                grace
+                            );
+                            stream.lock().terminal_error =
+                                Some("Stream cancelled after the client disconnected".to_string());
+                            break;
+                        }
+                    },
+                    None => rx_handler.recv().await,
+                };
+                let item = match next {
+                    Some(item) => item,
+                    None => break,
+                };
+                let item = match item {
+                    Ok(event) => {
+                        let mut buffer = stream.lock();
+                        let seq = buffer.next_seq;
+                        buffer.next_seq += 1;
+                        let event = event.id(format!("{}:{}", stream_id, seq));
+                        buffer.events.push_back((seq, event.clone()));
+                        while buffer.events.len() > registry.max_events {
+                            buffer.events.pop_front();
+                        }
+                        Ok(event)
+                    }
+                    Err(e) => {
+                        stream.lock().terminal_error = Some(e.to_string());
+                        Err(e)
+                    }
+                };
+                stream.notify.notify_waiters();
+
+                if client_attached && tx_client.send(item).await.is_err() {
+                    tracing::info!(
+                        stream_id = %stream_id,
+                        "Client detached; buffering stream for resumption"
+                    );
+                    client_attached = false;
+                    let mut buffer = stream.lock();
+                    if buffer.readers == 0 {
+                        buffer.unattended_since = Some(Instant::now());
+                    }
+                }
+            }
+            // Dropping the receiver makes the stream handler's next send fail, which stops it.
+            drop(rx_handler);
+            drop(tx_client);
+
+            stream.lock().finished_at = Some(Instant::now());
+            stream.notify.notify_waiters();
+
+            let window = registry.window;
+            tokio::time::sleep(window).await;
+            registry.streams().remove(&stream_id);
+        });
+    }
+
+    /// Returns a receiver that replays every event after `last_event_id` and
+    /// then follows the live stream. Returns `None` when the id is unknown,
+    /// expired, or has already been trimmed from the buffer.
+    pub fn resume(&self, last_event_id: &str) -> Option<mpsc::Receiver<SseItem>> {
+        let (stream_id, last_seq) = parse_event_id(last_event_id)?;
+        self.evict_expired();
+        let stream = self.streams().get(stream_id)?.clone();
+
+        {
+            let buffer = stream.lock();
+            if last_seq >= buffer.next_seq {
+                return None;
+            }
+            if let Some((first_seq, _)) = buffer.events.front() {
+                if last_seq + 1 < *first_seq {
+                    return None;
+                }
+            }
+        }
+
+        let (tx, rx) = mpsc::channel(100);
+        let stream_id = stream_id.to_string();
+        let reader = Reader::attach(stream.clone());
+        tokio::spawn(async move {
+            let _reader = reader;
+            let mut cursor = last_seq;
+            loop {
+                let notified = stream.notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                let (pending, terminal_error, finished, trimmed) = {
+                    let buffer = stream.lock();
+                    let trimmed = match buffer.events.front() {
+                        Some((first_seq, _)) => cursor + 1 < *first_seq,
+                        None => false,
+                    };
+                    let pending: Vec<Event> = buffer
+                        .events
+                        .iter()
+                        .filter(|(seq, _)| *seq > cursor)
+                        .map(|(_, event)| event.clone())
+                        .collect();
+                    if let Some((seq, _)) = buffer.events.back() {
+                        cursor = cursor.max(*seq);
+                    }
+                    (
+                        pending,
+                        buffer.terminal_error.clone(),
+                        buffer.finished_at.is_some(),
+                        trimmed,
+                    )
+                };
+
+                if trimmed {
+                    let _ = tx
+                        .send(Err(ParallaxError::Internal(
+                            format!("Resumed stream {} fell behind its replay buffer", stream_id),
+                            tracing_error::SpanTrace::capture(),
+                        )))
+                        .await;
+                    return;
+                }
+
+                for event in pending {
+                    if tx.send(Ok(event)).await.is_err() {
+                        return;
+                    }
+                }
+
+                if finished {
+                    if let Some(message) = terminal_error {
+                        let _ = tx
+                            .send(Err(ParallaxError::Internal(
+                                message,
+                                tracing_error::SpanTrace::capture(),
+                            )))
+                            .await;
+                    }
+                    return;
+                }
+
+                notified.await;
+            }
+        });
+
+        Some(rx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn drain(mut rx: mpsc::Receiver<SseItem>) -> Vec<SseItem> {
+        let mut out = Vec::new();
+        while let Some(item) = rx.recv().await {
+            out.push(item);
+        }
+        out
+    }
+
+    #[test]
+    fn test_parse_event_id() {
+        assert_eq!(parse_event_id("abc-123:7"), Some(("abc-123", 7)));
+        assert_eq!(parse_event_id(" abc:0 "), Some(("abc", 0)));
+        assert_eq!(parse_event_id("abc"), None);
+        assert_eq!(parse_event_id(":3"), None);
+        assert_eq!(parse_event_id("abc:x"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resume_replays_events_after_last_id() {
+        let registry = Arc::new(ResumeRegistry::new(Duration::from_secs(30), 100));
+        let (tx_handler, rx_handler) = mpsc::channel(16);
+        let (tx_client, rx_client) = mpsc::channel(16);
+        registry.relay("s1".to_string(), rx_handler, tx_client);
+
+        for i in 0..5 {
+            let _ = tx_handler
+                .send(Ok(Event::default().data(format!("chunk-{}", i))))
+                .await;
+        }
+        drop(tx_handler);
+        assert_eq!(drain(rx_client).await.len(), 5);
+
+        let resumed = match registry.resume("s1:2") {
+            Some(rx) => drain(rx).await,
+            None => panic!("stream should be resumable"),
+        };
+        assert_eq!(resumed.len(), 2);
+        assert!(resumed.iter().all(|item| item.is_ok()));
+    }
+
+    #[tokio::test]
+    async fn test_resume_follows_live_stream() {
+        let registry = Arc::new(ResumeRegistry::new(Duration::from_secs(30), 100));
+        let (tx_handler, rx_handler) = mpsc::channel(16);
+        let (tx_client, rx_client) = mpsc::channel(16);
+        registry.relay("live".to_string(), rx_handler, tx_client);
+
+        let _ = tx_handler.send(Ok(Event::default().data("a"))).await;
+        // Client drops after the first event.
+        drop(rx_client);
+        tokio::task::yield_now().await;
+
+        let rx = match registry.resume("live:0") {
+            Some(rx) => rx,
+            None => panic!("stream should be resumable"),
+        };
+        let _ = tx_handler.send(Ok(Event::default().data("b"))).await;
+        let _ = tx_handler.send(Ok(Event::default().data("c"))).await;
+        drop(tx_handler);
+
+        assert_eq!(drain(rx).await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_rejects_unknown_and_trimmed_ids() {
+        let registry = Arc::new(ResumeRegistry::new(Duration::from_secs(30), 2));
+        let (tx_handler, rx_handler) = mpsc::channel(16);
+        let (tx_client, rx_client) = mpsc::channel(16);
+        registry.relay("s2".to_string(), rx_handler, tx_client);
+
+        for i in 0..5 {
+            let _ = tx_handler
+                .send(Ok(Event::default().data(format!("chunk-{}", i))))
+                .await;
+        }
+        drop(tx_handler);
+        let _ = drain(rx_client).await;
+
+        assert!(registry.resume("other:1").is_none());
+        assert!(registry.resume("s2:0").is_none());
+        assert!(registry.resume("s2:9").is_none());
+        assert!(registry.resume("s2:2").is_some());
+    }
+}
@@ -15,6 +15,56 @@ pub struct StreamHandler;
 
 const MAX_STREAM_LINES: usize = 100_000;
 
+/// Unredacted projected requests of the turns still streaming, keyed by turn id.
+///
+/// The `projected` bundle blob is redacted, so retries that re-send the turn's request
+/// read it from here instead; see [`ProjectedRequests::hold`].
+#[derive(Debug, Default)]
+pub struct ProjectedRequests {
+    requests: std::sync::Mutex<HashMap<String, std::sync::Arc<serde_json::Value>>>,
+}
+
+/// Keeps a turn's projected request available for retries until dropped.
+#[derive(Debug)]
+pub struct ProjectedRequestGuard {
+    requests: std::sync::Arc<ProjectedRequests>,
+    tid: String,
+}
+
+impl ProjectedRequests {
+    pub fn hold(
+        self: &std::sync::Arc<Self>,
+        tid: &str,
+        request: serde_json::Value,
+    ) -> ProjectedRequestGuard {
+        self.lock()
+            .insert(tid.to_string(), std::sync::Arc::new(request));
+        ProjectedRequestGuard {
+            requests: self.clone(),
+            tid: tid.to_string(),
+        }
+    }
+
+    pub fn get(&self, tid: &str) -> Option<std::sync::Arc<serde_json::Value>> {
+        self.lock().get(tid).cloned()
+    }
+
+    fn lock(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<String, std::sync::Arc<serde_json::Value>>> {
+        match self.requests.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl Drop for ProjectedRequestGuard {
+    fn drop(&mut self) {
+        self.requests.lock().remove(&self.tid);
+    }
+}
+
 impl StreamHandler {
     #[allow(clippy::too_many_arguments)]
     async fn finalize_and_log_turn(
@@ -26,12 +76,15 @@ impl StreamHandler {
         start_time: std::time::Instant,
         started_at_ms: u64,
         tid: &str,
+        state: &AppState,
+        extra_issues: Vec<crate::debug_bundle::Issue>,
     ) {
         let finalized_turn_val = match serde_json::to_value(finalized_turn) {
             Ok(v) => v,
             Err(_) => serde_json::Value::Null,
         };
         crate::debug_utils::capture_debug_snapshot(
+            &state.args.debug_capture_dir,
             "final",
             model_id,
             conversation_id,
@@ -41,7 +94,7 @@ impl StreamHandler {
         .await;
 
         // Update bundle summaries
-        let bundle_manager = crate::debug_bundle::BundleManager::new("debug_capture");
+        let bundle_manager = state.bundles();
 
         // Build stages from what we know
         let final_blob_ref = Some(crate::debug_bundle::BlobRef {
@@ -89,6 +142,9 @@ impl StreamHandler {
             .build_span_summary(conversation_id, tid, request_id)
             .await;
 
+        let mut issues = bundle_manager.detect_issues(finalized_turn, &[], &cursor_tags);
+        issues.extend(extra_issues);
+
         let detail = crate::debug_bundle::TurnDetail {
             turn_id: tid.to_string(),
             request_id: request_id.to_string(),
@@ -105,7 +161,7 @@ impl StreamHandler {
             tool_calls,
             tool_results,
             cursor_tags: cursor_tags.clone(),
-            issues: bundle_manager.detect_issues(finalized_turn, &[], &cursor_tags),
+            issues,
             trace_id: None, // Trace ID would be extracted from span_summary if needed
             span_summary,
             user_query: None, // Will be preserved from initial write via merge
@@ -113,9 +169,13 @@ impl StreamHandler {
             conversation_id_source: ConversationIdSource::Unknown, // Source preserved in merge
             user_query_tags: None, // Will be preserved from initial write via merge
         };
-        let _ = bundle_manager
+        let issues = match bundle_manager
             .merge_and_write_turn(conversation_id, tid, &detail)
-            .await;
+            .await
+        {
+            Ok(merged) => merged.issues,
+            Err(_) => detail.issues.clone(),
+        };
 
         // Update conversation summary (but don't re-write turn.json, merge_and_write_turn already did that)
         let summary_update = crate::debug_bundle::TurnSummary {
@@ -125,7 +185,7 @@ impl StreamHandler {
             flavor: "streaming".to_string(),
             started_at_ms: detail.started_at_ms,
             ended_at_ms: detail.ended_at_ms,
-            issues: crate::debug_bundle::BundleManager::sum_issues_public(&detail.issues),
+            issues: crate::debug_bundle::BundleManager::sum_issues_public(&issues),
             role: Some("Assistant".to_string()),
             conversation_id_source: ConversationIdSource::Unknown,
         };
@@ -163,15 +223,22 @@ impl StreamHandler {
         pricing: std::sync::Arc<std::collections::HashMap<String, CostModel>>,
         tx_tui: tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
         start_time: std::time::Instant,
-        _disable_rescue: bool,
-        tools_were_advertised: bool,
+        disable_rescue: bool,
+        tool_arg_retry: bool,
+        tool_schemas: std::sync::Arc<crate::tool_schema::ToolSchemaRegistry>,
         state: std::sync::Arc<AppState>,
         tid: String,
+        mut secret_restorer: Option<crate::secret_scrub::PlaceholderRestorer>,
+        carried_issues: Vec<crate::debug_bundle::Issue>,
     ) where
         R: Stream<Item = std::result::Result<Bytes, std::io::Error>> + Unpin + Send,
     {
         tracing::info!("stream.start: Established upstream connection, beginning read loop");
 
+        let tools_were_advertised = !tool_schemas.is_empty();
+        // With --tool-arg-retry, tool calls reach the client only once their arguments validate.
+        let hold_tool_calls = tools_were_advertised && tool_arg_retry;
+
         let mut accumulator = TurnAccumulator::new();
         let mut tool_index_map = HashMap::<u32, String>::new();
         let mut metrics = crate::logging::StreamMetric::new();
@@ -180,10 +247,26 @@ impl StreamHandler {
         let mut buffered_pulses = Vec::new();
         let mut first_upstream_line_at: Option<std::time::Instant> = None;
         let mut first_client_send_at: Option<std::time::Instant> = None;
+        let mut first_token_reported = false;
         let mut last_activity_at = std::time::Instant::now();
         let mut end_reason = "upstream_eof";
         let mut content_scrubber = crate::hardening::CursorTagScrubber::new();
         let mut reasoning_scrubber = crate::hardening::CursorTagScrubber::new();
+        let mut rescuer = if tools_were_advertised && !disable_rescue {
+            Some(crate::rescue::StreamRescuer::new())
+        } else {
+            None
+        };
+        let upstream_capture = if state.args.capture_sse {
+            Some(state.bundles().start_sse_capture(
+                &conversation_id,
+                &tid,
+                "upstream_sse",
+                start_time,
+            ))
+        } else {
+            None
+        };
 
         while let Some(line_result) = lines_stream.next().await {
             let now = std::time::Instant::now();
@@ -231,6 +314,9 @@ impl StreamHandler {
 
             let should_break = match line_result {
                 Ok(line) => {
+                    if let Some(capture) = upstream_capture.as_ref().filter(|_| !line.is_empty()) {
+                        capture.record(&line);
+                    }
                     if let Some(data) = line
                         .strip_prefix("data: ")
                         .or_else(|| line.strip_prefix("data:"))
@@ -256,11 +342,14 @@ impl StreamHandler {
                                 &request_id,
                                 &tx_tui,
                                 tools_were_advertised,
+                                hold_tool_calls,
                                 &mut buffered_pulses,
                                 state.clone(),
                                 model_id.clone(),
                                 &mut content_scrubber,
                                 &mut reasoning_scrubber,
+                                &mut rescuer,
+                                &mut secret_restorer,
                             )
                             .await
                         }
@@ -271,6 +360,16 @@ impl StreamHandler {
                 Err(e) => Some(Self::handle_line_error(e, &tx).await),
             };
 
+            if !first_token_reported && metrics.has_output() {
+                first_token_reported = true;
+                let _ = tx_tui.send(crate::tui::TuiEvent::RequestDetail {
+                    id: request_id.clone(),
+                    detail: crate::tui::RequestDetail::FirstToken {
+                        ttft_ms: start_time.elapsed().as_millis() as u64,
+                    },
+                });
+            }
+
             if let Some(is_error) = should_break {
                 if is_error {
                     end_reason = "upstream_error";
@@ -301,6 +400,90 @@ impl StreamHandler {
             .unwrap_or(0)
             .saturating_sub(start_time.elapsed().as_millis() as u64);
 
+        drop(upstream_capture);
+
+        // A block that never closed (no finish_reason either) is released as plain text
+        let withheld = match rescuer.as_mut() {
+            Some(r) => r.finish(),
+            None => String::new(),
+        };
+        let mut carried_issues = carried_issues;
+        if !withheld.is_empty() && has_seen_tool_call {
+            // Text after tool calls is suppressed like any other content, but not silently
+            tracing::warn!(
+                "[RESCUE-STREAM] Dropped {} bytes of unterminated markup after tool calls",
+                withheld.len()
+            );
+            carried_issues.push(crate::debug_bundle::Issue {
+                kind: "RescueWithheldDropped".to_string(),
+                severity: "warning".to_string(),
+                message: format!(
+                    "{} bytes of unterminated tool-call markup were withheld and dropped after tool calls",
+                    withheld.len()
+                ),
+                context: serde_json::json!({ "text": withheld }),
+            });
+        } else if !withheld.is_empty() {
+            accumulator.text_buffer.push_str(&withheld);
+            let chunk = serde_json::json!({
+                "id": request_id,
+                "model": model_id,
+                "choices": [{ "delta": { "content": withheld }, "finish_reason": null }],
+            });
+            let _ = tx
+                .send(Ok(
+                    axum::response::sse::Event::default().data(chunk.to_string())
+                ))
+                .await;
+        }
+        // Arguments still held for a placeholder when the stream ended without a finish_reason
+        let tails = match secret_restorer.as_mut() {
+            Some(restorer) => restorer.finish(),
+            None => Vec::new(),
+        };
+        if !tails.is_empty() {
+            let pulse = ProviderPulse {
+                id: request_id.clone(),
+                model: model_id.clone(),
+                choices: vec![ProviderPulseChoice {
+                    delta: PulseDelta {
+                        content: None,
+                        role: None,
+                        tool_calls: Some(
+                            tails
+                                .into_iter()
+                                .map(|(index, tail)| Self::tool_arguments_delta(index, tail))
+                                .collect(),
+                        ),
+                        extra: serde_json::Map::new(),
+                    },
+                    finish_reason: None,
+                }],
+                usage: None,
+            };
+            Self::process_pulse(
+                &pulse,
+                &conversation_id,
+                &mut tool_index_map,
+                &mut accumulator,
+            )
+            .await;
+            if !hold_tool_calls {
+                if let Ok(json) = serde_json::to_string(&pulse) {
+                    let _ = tx
+                        .send(Ok(axum::response::sse::Event::default().data(json)))
+                        .await;
+                }
+            }
+        }
+        let rescued = match rescuer.as_ref() {
+            Some(r) => r.rescued().to_vec(),
+            None => Vec::new(),
+        };
+        let retry_restorer = secret_restorer
+            .as_ref()
+            .map(crate::secret_scrub::PlaceholderRestorer::fresh);
+
         Self::finish_stream(
             &accumulator,
             &model_id,
@@ -313,11 +496,16 @@ impl StreamHandler {
             &metrics,
             start_time,
             started_at_ms,
-            tools_were_advertised,
+            &tool_schemas,
+            &tool_index_map,
+            hold_tool_calls,
             has_seen_tool_call,
+            &rescued,
             state,
             &buffered_pulses,
             &tid,
+            retry_restorer,
+            carried_issues,
         )
         .await;
     }
@@ -335,24 +523,38 @@ impl StreamHandler {
         metrics: &crate::logging::StreamMetric,
         start_time: std::time::Instant,
         started_at_ms: u64,
-        tools_were_advertised: bool,
+        tool_schemas: &std::sync::Arc<crate::tool_schema::ToolSchemaRegistry>,
+        tool_index_map: &HashMap<u32, String>,
+        hold_tool_calls: bool,
         has_seen_tool_call: bool,
-        state: std::sync::Arc<AppState>, // Used for empty-stream retry
+        rescued: &[crate::rescue::RescuedCall],
+        state: std::sync::Arc<AppState>, // Used for empty-stream and tool-argument retries
         _buffered_pulses: &[ProviderPulse], // Kept for potential future diff-like retry logic
         tid: &str,
+        retry_restorer: Option<crate::secret_scrub::PlaceholderRestorer>,
+        carried_issues: Vec<crate::debug_bundle::Issue>,
     ) {
         if let Some(usage) = &accumulator.usage {
-            Self::compute_and_send_cost(model_id, request_id, usage, pricing, tx_tui);
+            Self::compute_and_send_cost(
+                model_id,
+                request_id,
+                usage,
+                pricing,
+                tx_tui,
+                &state.kernel,
+            )
+            .await;
         }
 
         if !accumulator.signatures.is_empty() {
             Self::persist_signatures(accumulator, conversation_id, db).await;
         }
 
-        let mut finalized_turn = accumulator.clone().finalize();
+        let tools_were_advertised = !tool_schemas.is_empty();
+        let (mut finalized_turn, arg_reports) = accumulator.clone().finalize_checked(tool_schemas);
 
         // Phase 2: Save final turn to bundle (RAW/UNSANITIZED for forensics)
-        let bundle_manager = crate::debug_bundle::BundleManager::new("debug_capture");
+        let bundle_manager = state.bundles();
         if let Ok(finalized_json) = serde_json::to_value(&finalized_turn) {
             let _ = bundle_manager
                 .write_blob(
@@ -533,20 +735,22 @@ impl StreamHandler {
                 ),
                 timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
             });
+            let _ = tx_tui.send(crate::tui::TuiEvent::hop(
+                request_id,
+                crate::tui::HopKind::StreamRetry,
+                model_id,
+                "empty stream; stop sequences stripped",
+            ));
 
-            if let Err(e) = Self::retry_with_projected_request(
-                state.clone(),
-                conversation_id.to_string(),
-                tid.to_string(),
-                tx,
-            )
-            .await
+            if let Err(e) =
+                Self::retry_with_projected_request(state.clone(), tid.to_string(), tx).await
             {
                 tracing::error!("[⚙️ ] Empty-stream retry failed: {}", e);
                 let error_msg = format!(
                     "Model {} returned an empty response (retry failed). Please retry your request.",
                     model_id
                 );
+                Self::report_stream_error(&state, request_id, model_id, &error_msg).await;
                 let _ = tx
                     .send(Err(ParallaxError::Upstream(
                         axum::http::StatusCode::BAD_GATEWAY,
@@ -557,6 +761,117 @@ impl StreamHandler {
             return;
         }
 
+        // Arguments that still break the advertised schema after coercion
+        let mut tool_arg_issues: Vec<crate::debug_bundle::Issue> =
+            arg_reports.iter().flat_map(|r| r.issues()).collect();
+        // Issues of the attempt a corrective retry replaced
+        tool_arg_issues.extend(carried_issues);
+        if !rescued.is_empty() {
+            let names: Vec<&str> = rescued.iter().map(|c| c.name.as_str()).collect();
+            tracing::info!(
+                "[⚙️ ] Rescued {} tool call(s) leaked as text: {}",
+                rescued.len(),
+                names.join(", ")
+            );
+            let _ = tx_tui.send(crate::tui::TuiEvent::LogMessage {
+                level: "INFO".to_string(),
+                target: "parallax::streaming".to_string(),
+                message: format!(
+                    "Rescued {} tool call(s) leaked as text: {}",
+                    rescued.len(),
+                    names.join(", ")
+                ),
+                timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+            });
+            tool_arg_issues.extend(rescued.iter().map(|c| c.issue()));
+        }
+        let invalid_calls: Vec<&crate::tool_schema::ToolArgReport> =
+            arg_reports.iter().filter(|r| !r.check.is_valid()).collect();
+        if !invalid_calls.is_empty() {
+            let summary = invalid_calls
+                .iter()
+                .map(|r| {
+                    let violations: Vec<String> =
+                        r.check.violations.iter().map(|v| v.to_string()).collect();
+                    format!("{}: {}", r.tool_name, violations.join("; "))
+                })
+                .collect::<Vec<_>>()
+                .join(" | ");
+            tracing::warn!(
+                "[⚙️ ] Tool call arguments do not match the advertised schema: {}",
+                summary
+            );
+            let _ = tx_tui.send(crate::tui::TuiEvent::LogMessage {
+                level: "WARN".to_string(),
+                target: "parallax::streaming".to_string(),
+                message: format!(
+                    "Tool call arguments do not match the advertised schema: {}",
+                    summary
+                ),
+                timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
+            });
+        }
+
+        if hold_tool_calls && has_seen_tool_call {
+            let calls = Self::ordered_tool_calls(&finalized_turn, tool_index_map);
+            if !invalid_calls.is_empty() {
+                let _ = tx_tui.send(crate::tui::TuiEvent::hop(
+                    request_id,
+                    crate::tui::HopKind::StreamRetry,
+                    model_id,
+                    "invalid tool arguments; corrective retry",
+                ));
+                // The rejected attempt stays in the bundle; the retry writes the final turn.
+                if let Ok(rejected) = serde_json::to_value(&finalized_turn) {
+                    let _ = bundle_manager
+                        .write_blob(
+                            conversation_id,
+                            tid,
+                            "final_rejected",
+                            rejected.to_string().as_bytes(),
+                        )
+                        .await;
+                }
+                let mut carried = tool_arg_issues.clone();
+                carried.push(crate::debug_bundle::Issue {
+                    kind: "ToolArgsRetried".to_string(),
+                    severity: "info".to_string(),
+                    message: format!(
+                        "Retried once with feedback for {} invalid tool call(s)",
+                        invalid_calls.len()
+                    ),
+                    context: serde_json::json!({
+                        "tool_ids": invalid_calls.iter().map(|r| r.tool_call_id.clone()).collect::<Vec<_>>(),
+                    }),
+                });
+                match Self::retry_with_tool_arg_feedback(
+                    state.clone(),
+                    conversation_id,
+                    request_id,
+                    model_id,
+                    tid,
+                    &calls,
+                    &arg_reports,
+                    tool_schemas.clone(),
+                    retry_restorer,
+                    carried,
+                    tx,
+                )
+                .await
+                {
+                    Ok(()) => {
+                        metrics.log_summary();
+                        return;
+                    }
+                    Err(e) => tracing::error!(
+                        "[⚙️ ] Tool-argument retry failed, forwarding the calls as-is: {}",
+                        e
+                    ),
+                }
+            }
+            Self::release_held_tool_calls(&calls, model_id, request_id, tx).await;
+        }
+
         Self::finalize_and_log_turn(
             &finalized_turn,
             accumulator.usage.as_ref(),
@@ -566,6 +881,8 @@ impl StreamHandler {
             start_time,
             started_at_ms,
             tid,
+            &state,
+            tool_arg_issues,
         )
         .await;
 
@@ -625,11 +942,14 @@ impl StreamHandler {
         request_id: &str,
         tx_tui: &tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
         tools_were_advertised: bool,
+        hold_tool_calls: bool,
         buffered_pulses: &mut Vec<ProviderPulse>,
         state: std::sync::Arc<AppState>,
         model_id: String,
         content_scrubber: &mut crate::hardening::CursorTagScrubber,
         reasoning_scrubber: &mut crate::hardening::CursorTagScrubber,
+        rescuer: &mut Option<crate::rescue::StreamRescuer>,
+        secret_restorer: &mut Option<crate::secret_scrub::PlaceholderRestorer>,
     ) -> Option<bool> {
         match crate::types::parse_provider_line(data) {
             LineEvent::Pulse(pulse) => {
@@ -644,26 +964,33 @@ impl StreamHandler {
                     request_id,
                     tx_tui,
                     tools_were_advertised,
+                    hold_tool_calls,
                     buffered_pulses,
                     content_scrubber,
                     reasoning_scrubber,
+                    rescuer,
+                    secret_restorer,
                 )
                 .await
             }
             crate::types::LineEvent::Error(err) => {
-                let _ = state
-                    .tx_kernel
-                    .send(crate::kernel::KernelCommand::UpdateHealth { success: false })
-                    .await;
-                let _ = state
-                    .tx_kernel
-                    .send(crate::kernel::KernelCommand::RecordCircuitFailure)
-                    .await;
+                state.kernel.update_health(false).await;
+                if Self::is_retryable_error(&err) {
+                    state
+                        .kernel
+                        .record_circuit_failure(crate::constants::UPSTREAM_OPENROUTER, &model_id)
+                        .await;
+                }
+                // Anything already streamed to the client rules out a retry.
+                let forwarded_to_client = *has_seen_tool_call
+                    || !accumulator.text_buffer.is_empty()
+                    || !accumulator.thought_buffer.is_empty()
+                    || !accumulator.tool_calls.is_empty();
                 Self::handle_provider_error(
                     data,
                     &err,
                     tx,
-                    *has_seen_tool_call,
+                    forwarded_to_client,
                     state,
                     conversation_id.to_string(),
                     model_id,
@@ -685,7 +1012,7 @@ impl StreamHandler {
         data: &str,
         err: &crate::types::ProviderError,
         tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
-        has_seen_tool_call: bool,
+        forwarded_to_client: bool,
         state: std::sync::Arc<AppState>,
         conversation_id: String,
         model_id: String,
@@ -693,6 +1020,7 @@ impl StreamHandler {
         tx_tui: tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
     ) {
         let is_retryable = Self::is_retryable_error(err);
+        let retry_hint = crate::hardening::retry_after_from_provider_error(&err.error);
 
         // Extract provider.status / provider.body if present (preserved via flatten extra)
         let provider_status = err
@@ -718,7 +1046,8 @@ impl StreamHandler {
             cid = %crate::str_utils::prefix_chars(&conversation_id, 8),
             model = %model_id,
             retryable = %is_retryable,
-            seen_tool_call = %has_seen_tool_call,
+            forwarded_to_client = %forwarded_to_client,
+            retry_after = ?retry_hint,
             code = ?err.error.code,
             provider_status = ?provider_status,
             msg = %err.error.message,
@@ -728,13 +1057,25 @@ impl StreamHandler {
         );
 
         // Classification & Retry Logic
-        if is_retryable && !has_seen_tool_call {
+        let retry_policy = crate::hardening::RetryPolicy::new(state.args.max_retries, 100)
+            .with_budget(std::time::Duration::from_secs(state.args.retry_budget_secs));
+        let retry_delay = if is_retryable {
+            retry_policy.stream_retry_delay(forwarded_to_client, retry_hint)
+        } else {
+            None
+        };
+        if let Some(delay) = retry_delay {
+            if !delay.is_zero() {
+                tracing::info!("[⚙️ ] Waiting {:?} before retrying stream", delay);
+                tokio::time::sleep(delay).await;
+            }
             if Self::handle_gemini_fallback(
                 &err_json,
                 &err.error.message,
                 &model_id,
                 &state,
                 &conversation_id,
+                &request_id,
                 tx,
                 &tx_tui,
             )
@@ -748,6 +1089,7 @@ impl StreamHandler {
                 &state,
                 &conversation_id,
                 &model_id,
+                &request_id,
                 tx,
                 tx_tui,
             )
@@ -755,6 +1097,7 @@ impl StreamHandler {
             return;
         }
 
+        Self::report_stream_error(&state, &request_id, &model_id, &err.error.message).await;
         if tx
             .send(Ok(axum::response::sse::Event::default().data(data)))
             .await
@@ -764,6 +1107,28 @@ impl StreamHandler {
         }
     }
 
+    /// Tells the dashboard (and the notifier) that `request_id` ended on an upstream error.
+    async fn report_stream_error(
+        state: &AppState,
+        request_id: &str,
+        model_id: &str,
+        message: &str,
+    ) {
+        let _ = state.tx_tui.send(crate::tui::TuiEvent::StreamError {
+            id: request_id.to_string(),
+            model: model_id.to_string(),
+            message: message.to_string(),
+        });
+        state
+            .kernel
+            .report(crate::kernel::KernelEvent::StreamFailed {
+                id: request_id.to_string(),
+                model: model_id.to_string(),
+                message: message.to_string(),
+            })
+            .await;
+    }
+
     fn is_retryable_error(err: &crate::types::ProviderError) -> bool {
         match err.error.code {
             Some(429) | Some(500) | Some(502) | Some(503) | Some(504) | Some(520) => true,
@@ -775,12 +1140,14 @@ impl StreamHandler {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn handle_gemini_fallback(
         _err_str: &str,
         error_message: &str,
         model_id: &str,
         state: &std::sync::Arc<AppState>,
         conversation_id: &str,
+        request_id: &str,
         tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
         tx_tui: &tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
     ) -> bool {
@@ -796,11 +1163,18 @@ impl StreamHandler {
                 timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
             });
 
-            if let Err(e) =
-                Self::fallback_to_flash(state.clone(), conversation_id.to_string(), tx, tx_tui)
-                    .await
+            if let Err(e) = Self::fallback_to_flash(
+                state.clone(),
+                conversation_id.to_string(),
+                request_id,
+                error_message,
+                tx,
+                tx_tui,
+            )
+            .await
             {
                 tracing::error!("[⚙️ ] Fallback failed: {}", e);
+                Self::report_stream_error(state, request_id, model_id, &e.to_string()).await;
                 let _ = tx.send(Err(e.inner)).await;
             }
             return true;
@@ -813,6 +1187,7 @@ impl StreamHandler {
         state: &std::sync::Arc<AppState>,
         conversation_id: &str,
         model_id: &str,
+        request_id: &str,
         tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
         tx_tui: tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
     ) {
@@ -822,17 +1197,24 @@ impl StreamHandler {
             message: format!("Retryable stream error: {}; retrying once.", error_message),
             timestamp: chrono::Local::now().format("%H:%M:%S").to_string(),
         });
+        let _ = tx_tui.send(crate::tui::TuiEvent::hop(
+            request_id,
+            crate::tui::HopKind::StreamRetry,
+            model_id,
+            error_message,
+        ));
 
         if let Err(e) = Self::retry_stream(
             state.clone(),
             conversation_id.to_string(),
             model_id.to_string(),
             tx,
-            tx_tui,
+            tx_tui.clone(),
         )
         .await
         {
             tracing::error!("[⚙️ ] Stream retry failed: {}", e);
+            Self::report_stream_error(state, request_id, model_id, &e.to_string()).await;
             let _ = tx.send(Err(e.inner)).await;
         }
     }
@@ -850,77 +1232,144 @@ impl StreamHandler {
     async fn fallback_to_flash(
         state: std::sync::Arc<AppState>,
         conversation_id: String,
+        request_id: &str,
+        error_message: &str,
         tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
         tx_tui: &tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
     ) -> Result<()> {
         // Find a suitable Flash model. We'll use a heuristic or common name.
         let fallback_model = "google/gemini-3-flash-preview-0814".to_string(); // Example
+        let _ = tx_tui.send(crate::tui::TuiEvent::hop(
+            request_id,
+            crate::tui::HopKind::Fallback,
+            &fallback_model,
+            error_message,
+        ));
         Self::execute_retry_or_fallback(state, conversation_id, fallback_model, tx, tx_tui.clone())
             .await
     }
 
     async fn retry_with_projected_request(
         state: std::sync::Arc<AppState>,
-        conversation_id: String,
         tid: String,
         tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
     ) -> crate::types::Result<()> {
-        // Load the exact projected upstream request we sent for this turn.
-        // This is robust: it preserves the full message history/tools/config that led to the failure.
-        let projected_path = std::path::Path::new("debug_capture")
-            .join("conversations")
-            .join(&conversation_id)
-            .join("turns")
-            .join(&tid)
-            .join("blobs")
-            .join("projected.json");
-
-        let projected_str = tokio::fs::read_to_string(&projected_path)
-            .await
-            .map_err(|e| {
-                ParallaxError::Io(std::io::Error::other(format!(
-                    "failed to read projected request at {}: {}",
-                    projected_path.display(),
-                    e
-                )))
-            })?;
-
-        let mut outgoing_request = serde_json::from_str::<crate::specs::openai::OpenAiRequest>(
-            &projected_str,
-        )
-        .map_err(|e| {
-            ParallaxError::Internal(
-                format!("failed to parse projected request JSON: {}", e),
-                tracing_error::SpanTrace::capture(),
-            )
-        })?;
-
         // Recovery tweak: remove stop sequences (they are a common cause of 0-token completions
         // in tool-heavy transcripts, e.g. "User:"/"Observation:").
-        outgoing_request.stop = None;
-        outgoing_request.stream = Some(true);
-
-        let response = state
-            .client
-            .post("https://openrouter.ai/api/v1/chat/completions")
-            .header("Authorization", format!("Bearer {}", state.openrouter_key))
-            .json(&outgoing_request)
-            .send()
-            .await
-            .map_err(ParallaxError::Network)?;
+        Self::replay_projected_request(state, &tid, tx, |request| {
+            request.stop = None;
+        })
+        .await
+    }
 
-        if !response.status().is_success() {
-            let err_body = match response.text().await {
-                Ok(t) => t,
-                Err(_) => "Unknown error (failed to read response text)".to_string(),
+    /// Answers the model's invalid tool calls with errors listing the schema violations and
+    /// replays the turn, so the model can call them again with valid arguments.
+    ///
+    /// The retry runs through [`Self::handle_stream`] like any other response (without a
+    /// further retry), which records its cost and writes the turn's final record.
+    #[allow(clippy::too_many_arguments)]
+    async fn retry_with_tool_arg_feedback(
+        state: std::sync::Arc<AppState>,
+        conversation_id: &str,
+        request_id: &str,
+        model_id: &str,
+        tid: &str,
+        calls: &[(&str, &str, &serde_json::Value)],
+        reports: &[crate::tool_schema::ToolArgReport],
+        tool_schemas: std::sync::Arc<crate::tool_schema::ToolSchemaRegistry>,
+        secret_restorer: Option<crate::secret_scrub::PlaceholderRestorer>,
+        carried_issues: Vec<crate::debug_bundle::Issue>,
+        tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
+    ) -> crate::types::Result<()> {
+        let mut tool_calls = Vec::new();
+        let mut feedback = Vec::new();
+        for (id, name, arguments) in calls {
+            tool_calls.push(crate::specs::openai::OpenAiToolCall {
+                id: id.to_string(),
+                r#type: "function".to_string(),
+                function: crate::specs::openai::OpenAiFunctionCall {
+                    name: name.to_string(),
+                    arguments: arguments.to_string(),
+                },
+                thought_signature: None,
+                extra_content: None,
+            });
+            let violations: Vec<String> = reports
+                .iter()
+                .filter(|r| r.tool_call_id == *id)
+                .flat_map(|r| r.check.violations.iter().map(|v| v.to_string()))
+                .collect();
+            let content = if violations.is_empty() {
+                "Not executed: another tool call in this turn had invalid arguments. \
+                 Call it again if it is still needed."
+                    .to_string()
+            } else {
+                format!(
+                    "Error: invalid arguments for '{}':\n- {}\n\nCall the tool again with arguments that match its schema.",
+                    name,
+                    violations.join("\n- ")
+                )
             };
-            return Err(ParallaxError::Upstream(
-                axum::http::StatusCode::BAD_GATEWAY,
-                format!("Empty-stream retry failed: {}", err_body),
-            )
-            .into());
+            feedback.push(crate::specs::openai::OpenAiMessage::Tool {
+                content,
+                tool_call_id: id.to_string(),
+                name: name.to_string(),
+                cache_control: None,
+            });
         }
 
+        let response = Self::send_projected_request(&state, tid, move |request| {
+            request
+                .messages
+                .push(crate::specs::openai::OpenAiMessage::Assistant {
+                    content: None,
+                    reasoning: None,
+                    tool_calls,
+                });
+            request.messages.extend(feedback);
+        })
+        .await?;
+
+        let bytes_stream = response
+            .bytes_stream()
+            .map(|r| r.map_err(std::io::Error::other));
+        let lines_stream = FramedRead::new(
+            tokio_util::io::StreamReader::new(bytes_stream),
+            LinesCodec::new_with_max_length(1024 * 1024),
+        );
+        // Boxed: handle_stream is what called us.
+        Box::pin(Self::handle_stream(
+            lines_stream,
+            state.db.clone(),
+            conversation_id.to_string(),
+            request_id.to_string(),
+            tx.clone(),
+            model_id.to_string(),
+            state.pricing.clone(),
+            state.tx_tui.clone(),
+            std::time::Instant::now(),
+            state.disable_rescue,
+            false,
+            tool_schemas,
+            state.clone(),
+            tid.to_string(),
+            secret_restorer,
+            carried_issues,
+        ))
+        .await;
+        Ok(())
+    }
+
+    /// Re-sends the projected upstream request of this turn, adjusted, and streams the
+    /// response straight to the client.
+    async fn replay_projected_request(
+        state: std::sync::Arc<AppState>,
+        tid: &str,
+        tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
+        adjust: impl FnOnce(&mut crate::specs::openai::OpenAiRequest),
+    ) -> crate::types::Result<()> {
+        let response = Self::send_projected_request(&state, tid, adjust).await?;
+
         let bytes_stream = response
             .bytes_stream()
             .map(|r| r.map_err(std::io::Error::other));
@@ -953,6 +1402,129 @@ impl StreamHandler {
         Ok(())
     }
 
+    /// Re-sends the projected upstream request of this turn, adjusted, and returns the
+    /// (successful) streaming response.
+    async fn send_projected_request(
+        state: &AppState,
+        tid: &str,
+        adjust: impl FnOnce(&mut crate::specs::openai::OpenAiRequest),
+    ) -> crate::types::Result<reqwest::Response> {
+        // Load the exact projected upstream request we sent for this turn.
+        // This is robust: it preserves the full message history/tools/config that led to the failure.
+        // The `projected` blob is redacted, so this reads the unredacted copy held for the turn.
+        let projected = match state.projected_requests.get(tid) {
+            Some(v) => v,
+            None => {
+                return Err(ParallaxError::Io(std::io::Error::other(format!(
+                    "projected request for turn {} is no longer held",
+                    tid
+                )))
+                .into())
+            }
+        };
+
+        let mut outgoing_request =
+            serde_json::from_value::<crate::specs::openai::OpenAiRequest>((*projected).clone())
+                .map_err(|e| {
+                    ParallaxError::Internal(
+                        format!("failed to parse projected request JSON: {}", e),
+                        tracing_error::SpanTrace::capture(),
+                    )
+                })?;
+
+        adjust(&mut outgoing_request);
+        outgoing_request.stream = Some(true);
+
+        let response = state
+            .client
+            .post(state.args.chat_completions_url())
+            .header("Authorization", format!("Bearer {}", state.openrouter_key))
+            .json(&outgoing_request)
+            .send()
+            .await
+            .map_err(ParallaxError::Network)?;
+
+        if !response.status().is_success() {
+            let err_body = match response.text().await {
+                Ok(t) => t,
+                Err(_) => "Unknown error (failed to read response text)".to_string(),
+            };
+            return Err(ParallaxError::Upstream(
+                axum::http::StatusCode::BAD_GATEWAY,
+                format!("Retry of the projected request failed: {}", err_body),
+            )
+            .into());
+        }
+
+        Ok(response)
+    }
+
+    /// Tool calls of the finalized turn in the order they were streamed.
+    fn ordered_tool_calls<'a>(
+        turn: &'a TurnRecord,
+        tool_index_map: &HashMap<u32, String>,
+    ) -> Vec<(&'a str, &'a str, &'a serde_json::Value)> {
+        let mut order: Vec<(&u32, &String)> = tool_index_map.iter().collect();
+        order.sort();
+        let position = |id: &str| match order.iter().position(|(_, known)| known.as_str() == id) {
+            Some(i) => i,
+            None => order.len(),
+        };
+        let mut calls: Vec<(&str, &str, &serde_json::Value)> = turn
+            .content
+            .iter()
+            .filter_map(|part| match part {
+                MessagePart::ToolCall {
+                    id,
+                    name,
+                    arguments,
+                    ..
+                } => Some((id.as_str(), name.as_str(), arguments)),
+                _ => None,
+            })
+            .collect();
+        calls.sort_by_key(|(id, _, _)| position(id));
+        calls
+    }
+
+    /// Sends held tool calls, with their checked arguments, followed by the tool-call finish.
+    async fn release_held_tool_calls(
+        calls: &[(&str, &str, &serde_json::Value)],
+        model_id: &str,
+        request_id: &str,
+        tx: &mpsc::Sender<std::result::Result<axum::response::sse::Event, ParallaxError>>,
+    ) {
+        let tool_calls: Vec<serde_json::Value> = calls
+            .iter()
+            .enumerate()
+            .map(|(index, (id, name, arguments))| {
+                serde_json::json!({
+                    "index": index,
+                    "id": id,
+                    "type": "function",
+                    "function": { "name": name, "arguments": arguments.to_string() },
+                })
+            })
+            .collect();
+        let chunk = serde_json::json!({
+            "id": request_id,
+            "model": model_id,
+            "choices": [{
+                "delta": { "role": "assistant", "tool_calls": tool_calls },
+                "finish_reason": "tool_calls",
+            }],
+        });
+        if tx
+            .send(Ok(
+                axum::response::sse::Event::default().data(chunk.to_string())
+            ))
+            .await
+            .is_err()
+        {
+            tracing::trace!("Client disconnected before held tool calls were released");
+        }
+    }
+
     async fn execute_retry_or_fallback(
         state: std::sync::Arc<AppState>,
         conversation_id: String,
@@ -986,14 +1558,16 @@ impl StreamHandler {
             &state.db,
             None,
             &state.pricing,
+            None, // The retry context carries no history to scrub
         )
         .await;
 
+        crate::sanitizer_rules::rules().patch_tools(outgoing_request.tools.as_mut());
         outgoing_request.stream = Some(true);
 
         let response = state
             .client
-            .post("https://openrouter.ai/api/v1/chat/completions")
+            .post(state.args.chat_completions_url())
             .header("Authorization", format!("Bearer {}", state.openrouter_key))
             .json(&outgoing_request)
             .send()
@@ -1066,9 +1640,12 @@ impl StreamHandler {
         request_id: &str,
         tx_tui: &tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
         tools_were_advertised: bool,
+        hold_tool_calls: bool,
         buffered_pulses: &mut Vec<ProviderPulse>,
         content_scrubber: &mut crate::hardening::CursorTagScrubber,
         reasoning_scrubber: &mut crate::hardening::CursorTagScrubber,
+        rescuer: &mut Option<crate::rescue::StreamRescuer>,
+        secret_restorer: &mut Option<crate::secret_scrub::PlaceholderRestorer>,
     ) -> Option<bool> {
         metrics.record_chunk(&pulse);
 
@@ -1082,12 +1659,44 @@ impl StreamHandler {
             }
         }
 
+        // Leaked tool-call markup becomes real tool_calls deltas (off with --disable-rescue)
+        if let Some(rescuer) = rescuer.as_mut() {
+            Self::rescue_leaked_tool_calls(&mut pulse, rescuer, tool_index_map);
+        }
+
         // Detect transition: we only want to scrub once (first time tool calls show up)
         let had_seen_tools_before = *has_seen_tool_call;
         let this_pulse_has_tools = pulse.choices.iter().any(|c| c.delta.tool_calls.is_some());
 
         Self::sanitize_tool_calls(&mut pulse, has_seen_tool_call);
 
+        // Put scrubbed secrets back into tool-call arguments (--restore-secrets)
+        if let Some(restorer) = secret_restorer.as_mut() {
+            for choice in &mut pulse.choices {
+                for tool_call in choice.delta.tool_calls.iter_mut().flatten() {
+                    let index = tool_call.index;
+                    if let Some(arguments) = tool_call
+                        .function
+                        .as_mut()
+                        .and_then(|f| f.arguments.as_mut())
+                    {
+                        *arguments = restorer.restore_chunk(index, arguments);
+                    }
+                }
+                // Nothing follows the finish, so tails held for a placeholder go out with it
+                if choice.finish_reason.is_some() {
+                    let tails = restorer.finish();
+                    if !tails.is_empty() {
+                        choice.delta.tool_calls.get_or_insert_with(Vec::new).extend(
+                            tails
+                                .into_iter()
+                                .map(|(index, tail)| Self::tool_arguments_delta(index, tail)),
+                        );
+                    }
+                }
+            }
+        }
+
         if !had_seen_tools_before && this_pulse_has_tools {
             // We already buffered/accumulated earlier deltas for this turn.
             // Scrub only explicit protocol leak patterns so we don't preserve "Assistant:" and
@@ -1099,7 +1708,9 @@ impl StreamHandler {
         }
 
         tracing::trace!("[☁️  -> ⚙️ ] Pulse: {:?}", pulse);
+        let known_indices: Vec<u32> = tool_index_map.keys().copied().collect();
         Self::process_pulse(&pulse, conversation_id, tool_index_map, accumulator).await;
+        Self::fill_missing_tool_call_ids(&mut pulse, &known_indices, tool_index_map);
 
         // IMPORTANT: We always send pulses to the client immediately to prevent timeouts.
         // Previously we buffered until tool calls appeared, but this caused Cursor to timeout
@@ -1111,8 +1722,22 @@ impl StreamHandler {
             buffered_pulses.push(pulse.clone());
         }
 
+        // Held tool calls (and the finish that follows them) are released in `finish_stream`.
+        let client_pulse = if hold_tool_calls {
+            let mut held = pulse.clone();
+            for choice in &mut held.choices {
+                choice.delta.tool_calls = None;
+                if *has_seen_tool_call {
+                    choice.finish_reason = None;
+                }
+            }
+            std::borrow::Cow::Owned(held)
+        } else {
+            std::borrow::Cow::Borrowed(&pulse)
+        };
+
         // Re-serialize the sanitized pulse
-        if let Ok(sanitized_json) = serde_json::to_string(&pulse) {
+        if let Ok(sanitized_json) = serde_json::to_string(&*client_pulse) {
             if tx
                 .send(Ok(
                     axum::response::sse::Event::default().data(sanitized_json)
@@ -1159,14 +1784,30 @@ impl StreamHandler {
                 });
             }
 
+            for tool_call in choice.delta.tool_calls.iter().flatten() {
+                let (name, arguments) = match tool_call.function.as_ref() {
+                    Some(f) => (
+                        f.name.clone().filter(|n| !n.is_empty()),
+                        f.arguments.clone().unwrap_or_default(),
+                    ),
+                    None => (None, String::new()),
+                };
+                let _ = tx_tui.send(crate::tui::TuiEvent::RequestDetail {
+                    id: request_id.to_string(),
+                    detail: crate::tui::RequestDetail::ToolCall {
+                        index: tool_call.index,
+                        call_id: tool_call.id.clone(),
+                        name,
+                        arguments,
+                    },
+                });
+            }
+
             if let Some(thought) = choice.delta.extract_reasoning() {
-                if !thought.is_empty() {
-                    let _ = tx_tui.send(crate::tui::TuiEvent::StreamUpdate {
-                        id: request_id.to_string(),
-                        content_delta: thought,
-                        tool_call: None,
-                    });
-                }
+                let _ = tx_tui.send(crate::tui::TuiEvent::RequestDetail {
+                    id: request_id.to_string(),
+                    detail: crate::tui::RequestDetail::Reasoning(thought),
+                });
             }
         }
 
@@ -1223,9 +1864,11 @@ impl StreamHandler {
             &state.db,
             intent,
             &state.pricing,
+            None, // The retry context carries no history to scrub
         )
         .await;
 
+        crate::sanitizer_rules::rules().patch_tools(outgoing_request.tools.as_mut());
         outgoing_request.stream = Some(true);
 
         // Record retry attempt in flight recorder if possible
@@ -1238,7 +1881,7 @@ impl StreamHandler {
         // Execute retry
         let response = state
             .client
-            .post("https://openrouter.ai/api/v1/chat/completions")
+            .post(state.args.chat_completions_url())
             .header("Authorization", format!("Bearer {}", state.openrouter_key))
             .json(&outgoing_request)
             .send()
@@ -1285,6 +1928,62 @@ impl StreamHandler {
         Ok(())
     }
 
+    /// Withholds text that may be leaked tool-call markup and appends the calls it completes as
+    /// `tool_calls` deltas, indexed after any calls already seen.
+    fn rescue_leaked_tool_calls(
+        pulse: &mut ProviderPulse,
+        rescuer: &mut crate::rescue::StreamRescuer,
+        tool_index_map: &HashMap<u32, String>,
+    ) {
+        for choice in &mut pulse.choices {
+            let out = match choice.delta.content.as_deref() {
+                Some(content) => rescuer.push(content),
+                None => crate::rescue::RescueOutput::default(),
+            };
+            let mut text = out.text;
+            // Once tool calls exist content is suppressed, so the stream end reports what is
+            // still withheld instead
+            let calls_seen = !tool_index_map.is_empty()
+                || choice.delta.tool_calls.is_some()
+                || !out.calls.is_empty();
+            if choice.finish_reason.is_some() && !calls_seen {
+                text.push_str(&rescuer.finish());
+            }
+            if choice.delta.content.is_some() || !text.is_empty() {
+                choice.delta.content = Some(text);
+            }
+            if out.calls.is_empty() {
+                continue;
+            }
+            let deltas = choice.delta.tool_calls.get_or_insert_with(Vec::new);
+            let first_index = tool_index_map
+                .keys()
+                .chain(deltas.iter().map(|d| &d.index))
+                .max()
+                .map_or(0, |i| i + 1);
+            for (index, call) in (first_index..).zip(&out.calls) {
+                tracing::info!(
+                    "[RESCUE-STREAM] Converted leaked XML to ToolCall: {}",
+                    call.name
+                );
+                deltas.push(call.tool_call_delta(index));
+            }
+        }
+    }
+
+    /// A delta carrying only more arguments for an already announced tool call.
+    fn tool_arguments_delta(index: u32, arguments: String) -> ProviderToolCallDelta {
+        ProviderToolCallDelta {
+            index,
+            id: None,
+            function: Some(RawFunction {
+                name: None,
+                arguments: Some(arguments),
+            }),
+            extra: serde_json::Map::new(),
+        }
+    }
+
     fn sanitize_tool_calls(pulse: &mut ProviderPulse, has_seen_tool_call: &mut bool) {
         if *has_seen_tool_call || pulse.choices.iter().any(|c| c.delta.tool_calls.is_some()) {
             *has_seen_tool_call = true;
@@ -1309,16 +2008,18 @@ impl StreamHandler {
         }
     }
 
-    fn compute_and_send_cost(
+    pub(crate) async fn compute_and_send_cost(
         model_id: &str,
         request_id: &str,
         usage: &Usage,
         pricing: &HashMap<String, CostModel>,
         tx_tui: &tokio::sync::broadcast::Sender<crate::tui::TuiEvent>,
+        kernel: &crate::kernel::KernelHandle,
     ) {
         let model_pricing = pricing.get(model_id).cloned();
         match crate::main_helper::calculate_cost(model_id, usage, pricing) {
             Ok(breakdown) => {
+                kernel.record_spend(model_id, breakdown.actual_cost).await;
                 let _ = tx_tui.send(crate::tui::TuiEvent::CostUpdate {
                     id: request_id.to_string(),
                     model: model_id.to_string(),
@@ -1350,6 +2051,32 @@ impl StreamHandler {
         }
     }
 
+    /// Gives the first fragment of each tool call an id when the provider omitted it, so the
+    /// client sees the same id the accumulator records.
+    fn fill_missing_tool_call_ids(
+        pulse: &mut ProviderPulse,
+        known_indices: &[u32],
+        tool_index_map: &HashMap<u32, String>,
+    ) {
+        let mut announced = known_indices.to_vec();
+        for choice in &mut pulse.choices {
+            if let Some(tool_deltas) = choice.delta.tool_calls.as_mut() {
+                for td in tool_deltas {
+                    if announced.contains(&td.index) {
+                        continue;
+                    }
+                    announced.push(td.index);
+                    if td.id.is_none() {
+                        td.id = Some(match tool_index_map.get(&td.index) {
+                            Some(id) => id.clone(),
+                            None => format!("tool_index_{}", td.index),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
     fn push_tool_call_pulse_parts(
         content: &mut Vec<PulsePart>,
         tool_deltas: &[ProviderToolCallDelta],
@@ -1471,7 +2198,20 @@ impl StreamHandler {
         }
 
         let mut content = Vec::new();
-        let choice = &pulse.choices[0];
+        let choice = match pulse.choices.first() {
+            Some(c) => c,
+            None => {
+                // Usage-only trailer chunks arrive with an empty `choices` array.
+                if pulse.usage.is_some() {
+                    accumulator.push(InternalPulse {
+                        content,
+                        finish_reason: None,
+                        usage: pulse.usage.clone(),
+                    });
+                }
+                return;
+            }
+        };
 
         // 1. Tool Calls
         if let Some(ref tool_deltas) = choice.delta.tool_calls {
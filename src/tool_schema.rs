@@ -2,7 +2,12 @@
 //!
 //! Provides utilities to analyze tool definitions and determine which parameters are required
 //! vs optional. This helps classify empty tool arguments appropriately during finalization.
+//!
+//! The registry also checks finalized tool-call arguments against the advertised JSON Schema
+//! (types, required, enums), applying the coercions models commonly need: stringified numbers
+//! and booleans, a single value where an array is expected, and near-miss parameter names.
 
+use serde::Serialize;
 use serde_json::Value;
 use std::collections::HashMap;
 
@@ -13,6 +18,8 @@ pub struct ToolSchema {
     pub required_params: Vec<String>,
     pub optional_params: Vec<String>,
     pub has_required_params: bool,
+    /// The advertised JSON Schema for the arguments object.
+    pub parameters: Value,
 }
 
 impl ToolSchema {
@@ -21,8 +28,14 @@ impl ToolSchema {
         let function = tool.get("function")?;
         let name = function.get("name")?.as_str()?.to_string();
 
-        let parameters = function.get("parameters")?;
-        let properties = parameters.get("properties")?.as_object()?;
+        let parameters = match function.get("parameters") {
+            Some(p) => p.clone(),
+            None => Value::Null,
+        };
+        let properties = match parameters.get("properties").and_then(|v| v.as_object()) {
+            Some(p) => p.clone(),
+            None => serde_json::Map::new(),
+        };
         let required = match parameters.get("required").and_then(|v| v.as_array()) {
             Some(arr) => arr
                 .iter()
@@ -43,9 +56,19 @@ impl ToolSchema {
             required_params: required.clone(),
             optional_params,
             has_required_params: !required.is_empty(),
+            parameters,
         })
     }
 
+    /// Validate `args` against the parameter schema, coercing them in place where possible.
+    pub fn check(&self, args: &mut Value) -> ArgCheck {
+        let mut check = ArgCheck::default();
+        if self.parameters.is_object() {
+            check_value(&self.parameters, args, "", &mut check);
+        }
+        check
+    }
+
     /// Check if this tool should have parameters
     pub fn should_have_params(&self) -> bool {
         self.has_required_params || !self.optional_params.is_empty()
@@ -80,6 +103,33 @@ impl ToolSchemaRegistry {
         registry
     }
 
+    /// Build registry from the tools of an outgoing OpenAI request
+    pub fn from_openai_tools(tools: &[crate::specs::openai::OpenAiTool]) -> Self {
+        let mut registry = Self::new();
+        for tool in tools {
+            let definition = serde_json::json!({
+                "function": {
+                    "name": tool.function.name,
+                    "parameters": tool.function.parameters,
+                }
+            });
+            if let Some(schema) = ToolSchema::from_tool_definition(&definition) {
+                registry.schemas.insert(schema.name.clone(), schema);
+            }
+        }
+        registry
+    }
+
+    /// True when no tools were advertised
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty()
+    }
+
+    /// Check a tool call's arguments; `None` when the tool was not advertised
+    pub fn check(&self, name: &str, args: &mut Value) -> Option<ArgCheck> {
+        self.get(name).map(|schema| schema.check(args))
+    }
+
     /// Get schema for a tool by name
     pub fn get(&self, name: &str) -> Option<&ToolSchema> {
         self.schemas.get(name)
@@ -108,6 +158,356 @@ impl Default for ToolSchemaRegistry {
     }
 }
 
+/// What checking one tool call's arguments found.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArgCheck {
+    pub coercions: Vec<Coercion>,
+    pub violations: Vec<Violation>,
+}
+
+impl ArgCheck {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.coercions.is_empty() && self.violations.is_empty()
+    }
+}
+
+/// What happened to one finalized tool call: schema coercions and violations, and the
+/// sanitizer rules that rewrote its arguments.
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolArgReport {
+    pub tool_call_id: String,
+    pub tool_name: String,
+    pub check: ArgCheck,
+    pub rules_applied: Vec<crate::sanitizer_rules::RuleApplication>,
+}
+
+impl ToolArgReport {
+    /// One debug-bundle issue per violation, coercion and sanitizer rule application.
+    pub fn issues(&self) -> Vec<crate::debug_bundle::Issue> {
+        let context = |detail: serde_json::Value| {
+            serde_json::json!({
+                "tool_id": self.tool_call_id,
+                "tool_name": self.tool_name,
+                "detail": detail,
+            })
+        };
+        let invalid = self
+            .check
+            .violations
+            .iter()
+            .map(|v| crate::debug_bundle::Issue {
+                kind: "ToolArgsInvalid".to_string(),
+                severity: "error".to_string(),
+                message: format!("Tool '{}': {}", self.tool_name, v),
+                context: context(serde_json::json!(v)),
+            });
+        let repaired = self
+            .check
+            .coercions
+            .iter()
+            .map(|c| crate::debug_bundle::Issue {
+                kind: "ToolArgsRepaired".to_string(),
+                severity: "info".to_string(),
+                message: format!("Tool '{}': {}", self.tool_name, c),
+                context: context(serde_json::json!(c)),
+            });
+        let sanitized = self
+            .rules_applied
+            .iter()
+            .map(|a| a.issue("SanitizerRuleApplied"));
+        invalid.chain(repaired).chain(sanitized).collect()
+    }
+}
+
+/// A repair applied to the arguments so they match the schema.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Coercion {
+    StringToNumber { path: String },
+    StringToBoolean { path: String },
+    WrapInArray { path: String },
+    RenamedKey { from: String, to: String },
+}
+
+impl std::fmt::Display for Coercion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Coercion::StringToNumber { path } => write!(f, "`{}` parsed from a string", path),
+            Coercion::StringToBoolean { path } => {
+                write!(f, "`{}` parsed as a boolean from a string", path)
+            }
+            Coercion::WrapInArray { path } => write!(f, "`{}` wrapped in an array", path),
+            Coercion::RenamedKey { from, to } => write!(f, "`{}` renamed to `{}`", from, to),
+        }
+    }
+}
+
+/// A schema rule the arguments break even after coercion.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum Violation {
+    Unparseable {
+        reason: String,
+    },
+    Missing {
+        path: String,
+    },
+    Type {
+        path: String,
+        expected: String,
+        found: String,
+    },
+    Enum {
+        path: String,
+        allowed: Vec<Value>,
+        found: Value,
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::Unparseable { reason } => {
+                write!(f, "arguments are not valid JSON: {}", reason)
+            }
+            Violation::Missing { path } => write!(f, "missing required parameter `{}`", path),
+            Violation::Type {
+                path,
+                expected,
+                found,
+            } => {
+                write!(
+                    f,
+                    "`{}` should be {}, got {}",
+                    display_path(path),
+                    expected,
+                    found
+                )
+            }
+            Violation::Enum {
+                path,
+                allowed,
+                found,
+            } => {
+                let allowed: Vec<String> = allowed.iter().map(|v| v.to_string()).collect();
+                write!(
+                    f,
+                    "`{}` must be one of {}, got {}",
+                    display_path(path),
+                    allowed.join(", "),
+                    found
+                )
+            }
+        }
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "arguments"
+    } else {
+        path
+    }
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+fn json_type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_type(value: &Value, ty: &str) -> bool {
+    match ty {
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => match value.as_f64() {
+            Some(n) => value.is_i64() || value.is_u64() || n.fract() == 0.0,
+            None => false,
+        },
+        "boolean" => value.is_boolean(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+/// Lowercased alphanumerics, so `filePath`, `file_path` and `FILE-PATH` compare equal.
+fn normalize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn parse_number(text: &str, integer: bool) -> Option<Value> {
+    let text = text.trim();
+    if let Ok(n) = text.parse::<i64>() {
+        return Some(Value::from(n));
+    }
+    let n = text.parse::<f64>().ok().filter(|n| n.is_finite())?;
+    if integer && n.fract() != 0.0 {
+        return None;
+    }
+    serde_json::Number::from_f64(n).map(Value::Number)
+}
+
+/// Try to turn `value` into one of `types`, returning the coercion applied.
+fn coerce(value: &mut Value, types: &[&str], path: &str) -> Option<Coercion> {
+    for ty in types {
+        let replacement = match (*ty, &*value) {
+            ("number", Value::String(s)) => parse_number(s, false).map(|v| {
+                (
+                    v,
+                    Coercion::StringToNumber {
+                        path: path.to_string(),
+                    },
+                )
+            }),
+            ("integer", Value::String(s)) => parse_number(s, true).map(|v| {
+                (
+                    v,
+                    Coercion::StringToNumber {
+                        path: path.to_string(),
+                    },
+                )
+            }),
+            ("boolean", Value::String(s)) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" => Some((
+                    Value::Bool(true),
+                    Coercion::StringToBoolean {
+                        path: path.to_string(),
+                    },
+                )),
+                "false" => Some((
+                    Value::Bool(false),
+                    Coercion::StringToBoolean {
+                        path: path.to_string(),
+                    },
+                )),
+                _ => None,
+            },
+            ("array", v) if !v.is_array() && !v.is_null() => Some((
+                Value::Array(vec![v.clone()]),
+                Coercion::WrapInArray {
+                    path: path.to_string(),
+                },
+            )),
+            _ => None,
+        };
+        if let Some((replacement, coercion)) = replacement {
+            *value = replacement;
+            return Some(coercion);
+        }
+    }
+    None
+}
+
+fn check_value(schema: &Value, value: &mut Value, path: &str, out: &mut ArgCheck) {
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(ts)) => ts.iter().filter_map(|t| t.as_str()).collect(),
+        _ => Vec::new(),
+    };
+
+    if !types.is_empty() && !types.iter().any(|t| matches_type(value, t)) {
+        match coerce(value, &types, path) {
+            Some(coercion) => out.coercions.push(coercion),
+            None => {
+                out.violations.push(Violation::Type {
+                    path: path.to_string(),
+                    expected: types.join(" or "),
+                    found: json_type_name(value).to_string(),
+                });
+                return;
+            }
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(|e| e.as_array()) {
+        if !allowed.contains(value) {
+            out.violations.push(Violation::Enum {
+                path: path.to_string(),
+                allowed: allowed.clone(),
+                found: value.clone(),
+            });
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            let properties = match schema.get("properties").and_then(|p| p.as_object()) {
+                Some(p) => p,
+                None => return,
+            };
+
+            // Rename unknown keys that only differ from a missing property by case or separators.
+            let unknown: Vec<String> = map
+                .keys()
+                .filter(|k| !properties.contains_key(*k))
+                .cloned()
+                .collect();
+            for key in unknown {
+                let normalized = normalize_key(&key);
+                let target = properties
+                    .keys()
+                    .find(|p| !map.contains_key(*p) && normalize_key(p) == normalized);
+                if let Some(target) = target {
+                    if let Some(v) = map.remove(&key) {
+                        map.insert(target.clone(), v);
+                        out.coercions.push(Coercion::RenamedKey {
+                            from: join_path(path, &key),
+                            to: join_path(path, target),
+                        });
+                    }
+                }
+            }
+
+            if let Some(required) = schema.get("required").and_then(|r| r.as_array()) {
+                for name in required.iter().filter_map(|r| r.as_str()) {
+                    if !map.contains_key(name) {
+                        out.violations.push(Violation::Missing {
+                            path: join_path(path, name),
+                        });
+                    }
+                }
+            }
+
+            for (key, child) in map.iter_mut() {
+                if let Some(child_schema) = properties.get(key) {
+                    check_value(child_schema, child, &join_path(path, key), out);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items").filter(|i| i.is_object()) {
+                for (i, item) in items.iter_mut().enumerate() {
+                    check_value(item_schema, item, &format!("{}[{}]", path, i), out);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -199,4 +599,77 @@ mod tests {
         assert!(registry.has_required_params("grep"));
         assert!(!registry.has_required_params("list_mcp_resources"));
     }
+
+    fn edit_tool() -> ToolSchemaRegistry {
+        ToolSchemaRegistry::from_tools(&[json!({
+            "type": "function",
+            "function": {
+                "name": "read_file",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "target_file": {"type": "string"},
+                        "offset": {"type": "integer"},
+                        "should_read_entire_file": {"type": "boolean"},
+                        "globs": {"type": "array", "items": {"type": "string"}},
+                        "mode": {"type": "string", "enum": ["text", "binary"]}
+                    },
+                    "required": ["target_file"]
+                }
+            }
+        })])
+    }
+
+    #[test]
+    fn test_check_coerces_common_model_mistakes() {
+        let registry = edit_tool();
+        let mut args = json!({
+            "targetFile": "src/main.rs",
+            "offset": "12",
+            "should_read_entire_file": "false",
+            "globs": "*.rs"
+        });
+        let check = match registry.check("read_file", &mut args) {
+            Some(c) => c,
+            None => panic!("read_file should be registered"),
+        };
+
+        assert!(
+            check.is_valid(),
+            "unexpected violations: {:?}",
+            check.violations
+        );
+        assert_eq!(
+            args,
+            json!({
+                "target_file": "src/main.rs",
+                "offset": 12,
+                "should_read_entire_file": false,
+                "globs": ["*.rs"]
+            })
+        );
+        assert_eq!(check.coercions.len(), 4);
+        assert!(check.coercions.contains(&Coercion::RenamedKey {
+            from: "targetFile".to_string(),
+            to: "target_file".to_string()
+        }));
+    }
+
+    #[test]
+    fn test_check_reports_each_violation() {
+        let registry = edit_tool();
+        let mut args = json!({"offset": "twelve", "mode": "hex"});
+        let check = match registry.check("read_file", &mut args) {
+            Some(c) => c,
+            None => panic!("read_file should be registered"),
+        };
+
+        let messages: Vec<String> = check.violations.iter().map(|v| v.to_string()).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert!(messages.contains(&"missing required parameter `target_file`".to_string()));
+        assert!(messages.contains(&"`offset` should be integer, got string".to_string()));
+        assert!(messages
+            .contains(&"`mode` must be one of \"text\", \"binary\", got \"hex\"".to_string()));
+        assert!(registry.check("unknown_tool", &mut json!({})).is_none());
+    }
 }
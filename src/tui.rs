@@ -1,5 +1,6 @@
 #![allow(clippy::manual_unwrap_or, clippy::manual_unwrap_or_default)]
 use crate::str_utils;
+use crate::tui_filter::{Filter, HighlightRule, RequestFacts};
 use crate::types::*;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEventKind},
@@ -9,14 +10,16 @@ use crossterm::{
 use ratatui::{
     prelude::*,
     widgets::{
-        Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table, Wrap,
+        block, Block, BorderType, Borders, Cell, List, ListItem, ListState, Paragraph, Row, Table,
+        Wrap,
     },
 };
 use std::collections::{HashMap, VecDeque};
 use std::{io, time::Duration};
 use tokio::sync::broadcast;
 
-#[derive(Clone, Debug)]
+/// Dashboard events; serialized as JSON for `/debug/events`.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub enum TuiEvent {
     RequestStarted {
         id: String,
@@ -58,15 +61,116 @@ pub enum TuiEvent {
         uptime_secs: u64,
         active_connections: usize,
     },
+    CircuitStateUpdate {
+        upstream: String,
+        model: String,
+        state: String,
+        consecutive_failures: u32,
+    },
     UpstreamHealthUpdate {
         consecutive_failures: u32,
         total_requests: u64,
         failed_requests: u64,
         degraded: bool,
     },
+    /// Sent first on every `/debug/events` connection: the catch-up that follows replaces
+    /// whatever a remote dashboard showed before.
+    Resync,
+    /// What the StreamFocus detail pane shows beyond the streamed text.
+    RequestDetail { id: String, detail: RequestDetail },
+    /// A stream ended on an upstream error that no retry or fallback recovered.
+    StreamError {
+        id: String,
+        model: String,
+        message: String,
+    },
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+impl TuiEvent {
+    /// A [`RequestDetail::Hop`] for request `id`.
+    pub fn hop(id: &str, kind: HopKind, model: &str, reason: impl Into<String>) -> Self {
+        TuiEvent::RequestDetail {
+            id: id.to_string(),
+            detail: RequestDetail::Hop {
+                kind,
+                model: model.to_string(),
+                reason: reason.into(),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum RequestDetail {
+    /// A reasoning ("thinking") delta.
+    Reasoning(String),
+    /// A fragment of tool call `index`; the id and name usually only come with the first one.
+    ToolCall {
+        index: u32,
+        call_id: Option<String>,
+        name: Option<String>,
+        arguments: String,
+    },
+    /// Time from the request arriving to the first streamed text, reasoning or tool call.
+    FirstToken { ttft_ms: u64 },
+    /// The request moved to another upstream attempt.
+    Hop {
+        kind: HopKind,
+        model: String,
+        reason: String,
+    },
+}
+
+impl RequestDetail {
+    /// Folds `next` into `self` when both are fragments of the same stream; used to keep
+    /// catch-up backlogs short.
+    pub fn absorb(&mut self, next: &RequestDetail) -> bool {
+        match (self, next) {
+            (RequestDetail::Reasoning(text), RequestDetail::Reasoning(more)) => {
+                text.push_str(more);
+                true
+            }
+            (
+                RequestDetail::ToolCall {
+                    index,
+                    call_id,
+                    name,
+                    arguments,
+                },
+                RequestDetail::ToolCall {
+                    index: next_index,
+                    call_id: next_call_id,
+                    name: next_name,
+                    arguments: more,
+                },
+            ) if index == next_index => {
+                if call_id.is_none() {
+                    call_id.clone_from(next_call_id);
+                }
+                if name.is_none() {
+                    name.clone_from(next_name);
+                }
+                arguments.push_str(more);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum HopKind {
+    /// The upstream request failed before streaming and was re-sent.
+    Retry,
+    /// The stream failed or came back empty and was re-issued.
+    StreamRetry,
+    /// Another model took over (open circuit breaker or provider fallback).
+    Fallback,
+    /// A hedged request won the race against the primary.
+    Hedge,
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
 pub enum Intent {
     Plan,
     Agent,
@@ -80,6 +184,7 @@ pub enum ActiveTab {
     StreamFocus,
     Console,
     Summary,
+    Conversations,
 }
 
 #[allow(dead_code)]
@@ -99,6 +204,25 @@ struct RequestRecord {
     active_tool: Option<String>,
     last_update: std::time::Instant,
     recorded_in_graphs: bool,
+    detail: crate::tui_detail::RequestDetailState,
+}
+
+impl RequestRecord {
+    fn facts(&self) -> RequestFacts<'_> {
+        RequestFacts {
+            model: &self.model,
+            intent: self.intent,
+            status: self.status,
+            cid: &self.cid.0,
+            content: &self.content,
+            active_tool: self.active_tool.as_deref(),
+            cost_usd: self.actual_cost.map(|c| c.0),
+            latency_ms: match self.latency {
+                Some(l) => l.0,
+                None => self.timestamp.elapsed().as_millis(),
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -137,8 +261,21 @@ pub struct AppState {
     tick: u64,
     should_quit: bool,
     upstream_health: Option<UpstreamHealthDisplay>,
+    circuits: std::collections::BTreeMap<String, CircuitDisplay>,
     matrix_effect: MatrixEffect,
     graph_state: GraphState,
+    filter: Filter,
+    /// Text being typed at the `/` prompt; `None` when the prompt is closed.
+    filter_prompt: Option<String>,
+    filter_error: Option<String>,
+    highlights: Vec<HighlightRule>,
+    /// Events held back while the view is frozen.
+    paused: Option<VecDeque<TuiEvent>>,
+}
+
+struct CircuitDisplay {
+    state: String,
+    consecutive_failures: u32,
 }
 
 struct UpstreamHealthDisplay {
@@ -203,11 +340,88 @@ impl AppState {
             tick: 0,
             should_quit: false,
             upstream_health: None,
+            circuits: std::collections::BTreeMap::new(),
             matrix_effect: MatrixEffect::new(80, 2), // Initial width and default level
             graph_state: GraphState::new(),
+            filter: Filter::default(),
+            filter_prompt: None,
+            filter_error: None,
+            highlights: Vec::new(),
+            paused: None,
         }
     }
 
+    /// Applies `event`, or queues it while the view is paused.
+    fn receive(&mut self, event: TuiEvent) {
+        match &mut self.paused {
+            Some(queue) if queue.len() < crate::constants::TUI_PAUSE_MAX_EVENTS => {
+                queue.push_back(event);
+            }
+            Some(_) => {
+                self.toggle_pause();
+                self.handle_event(event);
+                self.handle_log_message(
+                    chrono::Local::now().format("%H:%M:%S").to_string(),
+                    "WARN".to_string(),
+                    "parallax::tui".to_string(),
+                    "Pause buffer full; resumed live updates".to_string(),
+                );
+            }
+            None => self.handle_event(event),
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        match self.paused.take() {
+            Some(queue) => {
+                for event in queue {
+                    self.handle_event(event);
+                }
+            }
+            None => self.paused = Some(VecDeque::new()),
+        }
+    }
+
+    fn open_filter_prompt(&mut self) {
+        self.filter_prompt = Some(self.filter.as_str().to_string());
+        self.filter_error = None;
+    }
+
+    /// Feeds a key to the `/` prompt; returns false when the prompt is closed.
+    fn handle_prompt_key(&mut self, code: KeyCode) -> bool {
+        let input = match &mut self.filter_prompt {
+            Some(input) => input,
+            None => return false,
+        };
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.filter_prompt = None,
+            KeyCode::Enter => match input.parse::<Filter>() {
+                Ok(filter) => {
+                    self.filter = filter;
+                    self.filter_prompt = None;
+                    self.list_state.select(None);
+                }
+                Err(e) => self.filter_error = Some(e),
+            },
+            _ => {}
+        }
+        true
+    }
+
+    /// Requests shown in the FlightDeck grid: the newest twelve that pass the filter.
+    fn visible_requests(&self) -> Vec<&RequestRecord> {
+        self.requests
+            .iter()
+            .rev()
+            .filter(|r| self.filter.matches_request(&r.facts()))
+            .take(12)
+            .collect()
+    }
+
     fn reset_session_stats(&mut self) {
         self.model_stats.clear();
         self.session_cost = CostUsd(0.0);
@@ -218,6 +432,12 @@ impl AppState {
 
     fn handle_event(&mut self, event: TuiEvent) {
         match event {
+            TuiEvent::CircuitStateUpdate {
+                upstream,
+                model,
+                state,
+                consecutive_failures,
+            } => self.handle_circuit_state_update(upstream, model, state, consecutive_failures),
             TuiEvent::UpstreamHealthUpdate {
                 consecutive_failures,
                 total_requests,
@@ -287,6 +507,58 @@ impl AppState {
             } => {
                 self.server_uptime = uptime_secs;
             }
+            TuiEvent::Resync => self.resync(),
+            TuiEvent::RequestDetail { id, detail } => {
+                if let Some(req) = self.requests.iter_mut().find(|r| r.id.0 == id) {
+                    req.detail.apply(detail);
+                    req.last_update = std::time::Instant::now();
+                }
+            }
+            TuiEvent::StreamError { id, model, message } => self.handle_log_message(
+                chrono::Local::now().format("%H:%M:%S").to_string(),
+                "ERROR".to_string(),
+                "stream".to_string(),
+                format!(
+                    "[{}] stream failed on {}: {}",
+                    crate::str_utils::prefix_chars(&id, 8),
+                    model,
+                    message
+                ),
+            ),
+        }
+    }
+
+    fn resync(&mut self) {
+        self.requests.clear();
+        self.list_state = ListState::default();
+        self.active_request_id = None;
+        self.logs.clear();
+        self.active_connections = 0;
+        self.upstream_health = None;
+        self.circuits.clear();
+        self.graph_state = GraphState::new();
+        self.reset_session_stats();
+    }
+
+    fn handle_circuit_state_update(
+        &mut self,
+        upstream: String,
+        model: String,
+        state: String,
+        consecutive_failures: u32,
+    ) {
+        let key = format!("{}/{}", upstream, model);
+        // Only non-closed breakers are interesting in the health view.
+        if state == "closed" {
+            self.circuits.remove(&key);
+        } else {
+            self.circuits.insert(
+                key,
+                CircuitDisplay {
+                    state,
+                    consecutive_failures,
+                },
+            );
         }
     }
 
@@ -325,7 +597,16 @@ impl AppState {
             req.method = method;
             req.last_update = std::time::Instant::now();
             req.active_tool = None;
-            req.status = None; // Reset status for the new request
+            // Reset per-request state so the slot and detail pane describe the new request
+            req.status = None;
+            req.content.clear();
+            req.latency = None;
+            req.usage = None;
+            req.actual_cost = None;
+            req.potential_cost_no_cache = None;
+            req.timestamp = std::time::Instant::now();
+            req.recorded_in_graphs = false;
+            req.detail = Default::default();
         } else {
             self.requests.push_back(RequestRecord {
                 id: id.clone(),
@@ -343,6 +624,7 @@ impl AppState {
                 active_tool: None,
                 last_update: std::time::Instant::now(),
                 recorded_in_graphs: false,
+                detail: Default::default(),
             });
         }
 
@@ -421,9 +703,23 @@ impl AppState {
         request_cost: f64,
     ) {
         if let Some(req) = self.requests.iter_mut().find(|r| r.id == id) {
+            let cached_tokens = match usage.prompt_tokens_details.as_ref() {
+                Some(details) => details.cached_tokens.unwrap_or_default(),
+                None => 0,
+            };
             req.usage = Some(usage.clone());
             req.actual_cost = Some(actual_cost);
             req.potential_cost_no_cache = Some(potential_cost_no_cache);
+            req.detail.cost = Some(crate::main_helper::CostBreakdown {
+                actual_cost: actual_cost.0,
+                potential_cost_no_cache: potential_cost_no_cache.0,
+                prompt_cost,
+                completion_cost,
+                cache_read_cost,
+                request_cost,
+                cached_tokens,
+                uncached_prompt_tokens: usage.prompt_tokens.saturating_sub(cached_tokens),
+            });
         }
         self.session_cost.0 += actual_cost.0;
         self.model_costs
@@ -456,7 +752,7 @@ impl AppState {
         if self.active_tab == ActiveTab::FlightDeck {
             let i = match self.list_state.selected() {
                 Some(i) => {
-                    if i + 1 < self.requests.len().min(12) {
+                    if i + 1 < self.visible_requests().len() {
                         i + 1
                     } else {
                         i
@@ -482,7 +778,7 @@ impl AppState {
         if self.active_tab == ActiveTab::FlightDeck {
             let i = match self.list_state.selected() {
                 Some(i) => {
-                    if i < 6 && i + 6 < self.requests.len().min(12) {
+                    if i < 6 && i + 6 < self.visible_requests().len() {
                         i + 6
                     } else {
                         i
@@ -513,8 +809,8 @@ impl AppState {
     fn focus_selected(&mut self) {
         if self.active_tab == ActiveTab::FlightDeck {
             if let Some(i) = self.list_state.selected() {
-                if let Some(req) = self.requests.get(i) {
-                    self.active_request_id = Some(req.id.clone());
+                if let Some(id) = self.visible_requests().get(i).map(|r| r.id.clone()) {
+                    self.active_request_id = Some(id);
                     self.active_tab = ActiveTab::StreamFocus;
                 }
             }
@@ -549,6 +845,7 @@ impl AppState {
 pub struct App {
     rx: broadcast::Receiver<TuiEvent>,
     state: AppState,
+    browser: Option<crate::conversation_browser::ConversationBrowser>,
 }
 
 impl App {
@@ -556,9 +853,25 @@ impl App {
         Self {
             rx,
             state: AppState::new(),
+            browser: None,
         }
     }
 
+    /// Highlights requests matching any of `rules` (`--tui-highlight`).
+    pub fn with_highlights(mut self, rules: Vec<HighlightRule>) -> Self {
+        self.state.highlights = rules;
+        self
+    }
+
+    /// Enables the conversation browser tab over the captured debug bundles.
+    pub fn with_browser(
+        mut self,
+        browser: crate::conversation_browser::ConversationBrowser,
+    ) -> Self {
+        self.browser = Some(browser);
+        self
+    }
+
     pub async fn run(mut self) -> io::Result<()> {
         let original_hook = std::panic::take_hook();
         std::panic::set_hook(Box::new(move |panic_info| {
@@ -580,14 +893,30 @@ impl App {
             if crossterm::event::poll(Duration::from_millis(10))? {
                 match event::read()? {
                     Event::Key(key) if key.kind == KeyEventKind::Press => {
+                        let consumed = self.state.handle_prompt_key(key.code)
+                            || match (&mut self.browser, self.state.active_tab) {
+                                (Some(browser), ActiveTab::Conversations) => {
+                                    browser.handle_key(key.code)
+                                }
+                                _ => false,
+                            };
                         match key.code {
+                            _ if consumed => {}
                             KeyCode::Char('q') => self.state.should_quit = true,
                             KeyCode::Char('r') => self.state.matrix_effect.cycle_level(),
                             KeyCode::Char('1') => self.state.active_tab = ActiveTab::FlightDeck,
                             KeyCode::Char('2') => self.state.active_tab = ActiveTab::StreamFocus,
                             KeyCode::Char('3') => self.state.active_tab = ActiveTab::Console,
                             KeyCode::Char('4') => self.state.active_tab = ActiveTab::Summary,
+                            KeyCode::Char('5') => {
+                                self.state.active_tab = ActiveTab::Conversations;
+                                if let Some(browser) = &mut self.browser {
+                                    browser.activate();
+                                }
+                            }
                             KeyCode::Char('X') => self.state.reset_session_stats(),
+                            KeyCode::Char('/') => self.state.open_filter_prompt(),
+                            KeyCode::Char('p') => self.state.toggle_pause(),
 
                             // Vim keybindings for grid navigation
                             KeyCode::Char('k') | KeyCode::Up => self.state.select_up(),
@@ -612,7 +941,10 @@ impl App {
             self.state.matrix_effect.update(terminal.size()?.height);
 
             while let Ok(event) = self.rx.try_recv() {
-                self.state.handle_event(event);
+                self.state.receive(event);
+            }
+            if let Some(browser) = &mut self.browser {
+                browser.poll();
             }
 
             // Check for completed graph metrics
@@ -653,6 +985,10 @@ impl App {
             ActiveTab::StreamFocus => self.render_stream_focus(f, chunks[1]),
             ActiveTab::Console => self.render_console(f, chunks[1]),
             ActiveTab::Summary => self.render_session_summary(f, chunks[1]),
+            ActiveTab::Conversations => match &mut self.browser {
+                Some(browser) => browser.render(f, chunks[1]),
+                None => self.render_browser_unavailable(f, chunks[1]),
+            },
         }
 
         self.render_footer(f, chunks[2]);
@@ -733,6 +1069,31 @@ impl App {
             ));
         }
 
+        if !self.state.circuits.is_empty() {
+            let circuit_text = if is_compact {
+                format!(" | CB: {} ", self.state.circuits.len())
+            } else {
+                let tripped: Vec<String> = self
+                    .state
+                    .circuits
+                    .iter()
+                    .map(|(key, c)| {
+                        format!(
+                            "{} {} ({})",
+                            key,
+                            c.state.to_uppercase(),
+                            c.consecutive_failures
+                        )
+                    })
+                    .collect();
+                format!(" | CIRCUITS: {} ", tripped.join(", "))
+            };
+            header_spans.push(Span::styled(
+                circuit_text,
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+
         let header = Paragraph::new(Line::from(header_spans))
             .style(Style::default().bg(Color::White).fg(Color::Black));
         f.render_widget(header, area);
@@ -768,6 +1129,14 @@ impl App {
             self.render_tab_item("[3] SYSTEM", "[3]", "LOGS", ActiveTab::Console, is_compact),
             Span::raw("  "),
             self.render_tab_item("[4] SUMMARY", "[4]", "SUMM", ActiveTab::Summary, is_compact),
+            Span::raw("  "),
+            self.render_tab_item(
+                "[5] CONVERSATIONS",
+                "[5]",
+                "CONV",
+                ActiveTab::Conversations,
+                is_compact,
+            ),
         ];
         f.render_widget(Paragraph::new(Line::from(tabs)), area);
     }
@@ -889,11 +1258,47 @@ impl App {
     fn render_footer(&self, f: &mut Frame, area: Rect) {
         let is_compact = area.width < 85;
         let footer_text = if is_compact {
-            " [Q] Quit | [X] Reset | [1-4] Tabs | [Ent] Focus | [Esc] Back "
+            " [Q] Quit | [X] Reset | [1-5] Tabs | [/] Filter | [P] Pause | [Esc] Back "
         } else {
-            " [Q] Quit application | [X] Reset Stats | [1-4] Switch Tabs | [Enter] Focus Stream | [Esc] Back "
+            " [Q] Quit application | [X] Reset Stats | [1-5] Switch Tabs | [Enter] Focus Stream | [/] Filter | [P] Pause | [Esc] Back "
         };
-        let footer = Paragraph::new(footer_text).block(
+
+        let mut spans = Vec::new();
+        if let Some(input) = &self.state.filter_prompt {
+            spans.push(Span::styled(
+                format!(" /{}█ ", input),
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            ));
+            match &self.state.filter_error {
+                Some(error) => {
+                    spans.push(Span::styled(error.clone(), Style::default().fg(Color::Red)))
+                }
+                None => spans.push(Span::styled(
+                    "model: intent: status: cid: level: text | [Enter] Apply | [Esc] Cancel",
+                    Style::default().fg(Color::DarkGray),
+                )),
+            }
+        } else {
+            if let Some(queue) = &self.state.paused {
+                spans.push(Span::styled(
+                    format!(" PAUSED ({} queued) ", queue.len()),
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Yellow)
+                        .add_modifier(Modifier::BOLD),
+                ));
+            }
+            if !self.state.filter.is_empty() {
+                spans.push(Span::styled(
+                    format!(" FILTER: {} ", self.state.filter.as_str()),
+                    Style::default().fg(Color::Black).bg(Color::Cyan),
+                ));
+            }
+            spans.push(Span::raw(footer_text));
+        }
+        let footer = Paragraph::new(Line::from(spans)).block(
             Block::default()
                 .borders(Borders::TOP)
                 .border_type(BorderType::Plain),
@@ -932,14 +1337,8 @@ impl App {
         // Fill the 12 slots based on the conversations we have.
         // If we have fewer than 12, they fill from the first slot.
         // If we have more than 12, we show the 12 most recent ones.
-        let num_to_take = self.state.requests.len().min(12);
-        let display_reqs: Vec<_> = self
-            .state
-            .requests
-            .iter()
-            .rev() // Get newest first
-            .take(num_to_take)
-            .collect();
+        // Newest first, narrowed by the `/` filter.
+        let display_reqs = self.state.visible_requests();
 
         for i in 0..12 {
             let slot_area = slots[i];
@@ -963,6 +1362,24 @@ impl App {
                     BorderType::Rounded
                 };
 
+                let facts = req.facts();
+                let highlights: Vec<String> = self
+                    .state
+                    .highlights
+                    .iter()
+                    .filter(|rule| rule.matches(&facts))
+                    .map(|rule| rule.to_string())
+                    .collect();
+                let border_style = if is_selected {
+                    Style::default().fg(Color::White)
+                } else if !highlights.is_empty() {
+                    Style::default()
+                        .fg(Color::LightRed)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default().fg(border_color)
+                };
+
                 let last_update_ms = req.last_update.elapsed().as_millis();
                 let is_streaming = req.status.is_none();
 
@@ -1035,11 +1452,7 @@ impl App {
                 let block = Block::default()
                     .borders(Borders::ALL)
                     .border_type(border_type)
-                    .border_style(if is_selected {
-                        Style::default().fg(Color::White)
-                    } else {
-                        Style::default().fg(border_color)
-                    })
+                    .border_style(border_style)
                     .bg(Color::Black)
                     .title_alignment(Alignment::Left)
                     .title(Line::from(vec![
@@ -1052,6 +1465,20 @@ impl App {
                             Style::default().add_modifier(Modifier::BOLD),
                         ),
                     ]));
+                let block = if highlights.is_empty() {
+                    block
+                } else {
+                    block.title(
+                        block::Title::from(Span::styled(
+                            format!(" ⚑ {} ", highlights.join(" ")),
+                            Style::default()
+                                .fg(Color::Black)
+                                .bg(Color::LightRed)
+                                .add_modifier(Modifier::BOLD),
+                        ))
+                        .alignment(Alignment::Right),
+                    )
+                };
 
                 let inner = block.inner(slot_area);
                 f.render_widget(block, slot_area);
@@ -1132,7 +1559,11 @@ impl App {
                     })
                     .bg(Color::Black)
                     .title(Span::styled(
-                        " EMPTY SLOT ",
+                        if self.state.filter.is_empty() {
+                            " EMPTY SLOT "
+                        } else {
+                            " NO MATCH "
+                        },
                         Style::default().fg(Color::DarkGray),
                     ));
                 f.render_widget(block, slot_area);
@@ -1190,18 +1621,18 @@ impl App {
             f.render_widget(header1_p, layout[0]);
 
             // Row 2: Stats
-            let tps = if let Some(latency) = req.latency {
-                let tokens = match req.usage.as_ref() {
-                    Some(u) => u.completion_tokens,
-                    None => 0,
-                };
-                if latency.0 > 0 {
-                    (tokens as f64 / (latency.0 as f64 / 1000.0)) as u32
-                } else {
-                    0
-                }
-            } else {
-                0
+            let tps = match req.detail.throughput(
+                req.usage.as_ref().map(|u| u.completion_tokens),
+                &req.content,
+                req.facts().latency_ms,
+            ) {
+                Some(t) if t.estimated => format!("~{:.0}", t.tokens_per_sec),
+                Some(t) => format!("{:.0}", t.tokens_per_sec),
+                None => "—".to_string(),
+            };
+            let ttft = match req.detail.ttft_ms {
+                Some(ms) => format!("{}ms", ms),
+                None => "—".to_string(),
             };
 
             let cache_pct = match req.usage.as_ref() {
@@ -1245,9 +1676,10 @@ impl App {
 
             let stats_text = if is_compact {
                 format!(
-                    " MDL: {} | LAT: {}ms | TPS: {} | CST: {:.3}¢ ({:.3}¢)\n INP: {} | OUT: {} | CCH: {}% ",
+                    " MDL: {} | LAT: {}ms | TTFT: {} | TPS: {} | CST: {:.3}¢ ({:.3}¢)\n INP: {} | OUT: {} | CCH: {}% ",
                     req.model,
                     latency_val,
+                    ttft,
                     tps,
                     actual_cost * 100.0,
                     savings * 100.0,
@@ -1257,9 +1689,10 @@ impl App {
                 )
             } else {
                 format!(
-                    " MODEL: {} | LATENCY: {}ms | TPS: {} | COST: {:.3}¢ (SAVED: {:.3}¢) | INPUT: {} | OUTPUT: {} | CACHE: {}% ",
+                    " MODEL: {} | LATENCY: {}ms | TTFT: {} | TPS: {} | COST: {:.3}¢ (SAVED: {:.3}¢) | INPUT: {} | OUTPUT: {} | CACHE: {}% ",
                     req.model,
                     latency_val,
+                    ttft,
                     tps,
                     actual_cost * 100.0,
                     savings * 100.0,
@@ -1273,50 +1706,8 @@ impl App {
                 .block(Block::default().borders(Borders::ALL).bg(Color::Black));
             f.render_widget(stats_p, layout[1]);
 
-            // Chat Content
-            let mut chat_content = Vec::new();
-            chat_content.push(Line::from(vec![
-                Span::styled(
-                    "> [USER] ",
-                    Style::default()
-                        .fg(Color::Yellow)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                Span::raw("(Captured snippet here...)"),
-            ]));
-            chat_content.push(Line::from(""));
-            chat_content.push(Line::from(
-                "─".repeat(area.width.saturating_sub(2) as usize),
-            ));
-            chat_content.push(Line::from(""));
-            chat_content.push(Line::from(vec![
-                Span::styled(
-                    "> [ASSISTANT] ",
-                    Style::default()
-                        .fg(Color::Green)
-                        .add_modifier(Modifier::BOLD),
-                ),
-                if req.status.is_none() {
-                    Span::styled("(Streaming...) ▊", Style::default().fg(Color::Green))
-                } else {
-                    Span::raw("")
-                },
-            ]));
-
-            // Add actual response content
-            for line in req.content.lines() {
-                chat_content.push(Line::from(format!("  {}", line)));
-            }
-
-            let p = Paragraph::new(chat_content)
-                .wrap(Wrap { trim: false })
-                .block(
-                    Block::default()
-                        .borders(Borders::LEFT | Borders::RIGHT)
-                        .bg(Color::Black),
-                );
-
-            f.render_widget(p, layout[2]);
+            req.detail
+                .render(f, layout[2], &req.content, req.status.is_none());
         } else {
             let p = Paragraph::new(
                 "No stream selected. Select a request in Flight Deck and press Enter.",
@@ -1332,13 +1723,17 @@ impl App {
     }
 
     fn render_console(&self, f: &mut Frame, area: Rect) {
-        let logs_to_show: Vec<ListItem> = self
+        let mut matching: Vec<&String> = self
             .state
             .logs
             .iter()
             .rev()
+            .filter(|line| self.state.filter.matches_log(line))
             .take(area.height.saturating_sub(2) as usize)
-            .rev()
+            .collect();
+        matching.reverse();
+        let logs_to_show: Vec<ListItem> = matching
+            .into_iter()
             .map(|line| {
                 let style = if line.contains("[ERROR]") {
                     Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
@@ -1355,10 +1750,15 @@ impl App {
             })
             .collect();
 
+        let title = if self.state.filter.is_empty() {
+            " SYSTEM LOGS ".to_string()
+        } else {
+            format!(" SYSTEM LOGS [{}] ", self.state.filter.as_str())
+        };
         let list = List::new(logs_to_show).block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(" SYSTEM LOGS ")
+                .title(title)
                 .border_style(Style::default().fg(Color::DarkGray))
                 .bg(Color::Black),
         );
@@ -1366,6 +1766,19 @@ impl App {
         f.render_widget(list, area);
     }
 
+    fn render_browser_unavailable(&self, f: &mut Frame, area: Rect) {
+        let message = Paragraph::new(" No debug bundles are available to this dashboard.")
+            .style(Style::default().fg(Color::DarkGray))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" CONVERSATIONS ")
+                    .border_style(Style::default().fg(Color::DarkGray))
+                    .bg(Color::Black),
+            );
+        f.render_widget(message, area);
+    }
+
     fn render_session_summary(&self, f: &mut Frame, area: Rect) {
         let is_compact = area.width < 100;
 
@@ -2052,3 +2465,74 @@ mod graph_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+
+    fn started(id: &str, cid: &str, model: &str) -> TuiEvent {
+        TuiEvent::RequestStarted {
+            id: id.to_string(),
+            cid: cid.to_string(),
+            method: "POST".to_string(),
+            model: model.to_string(),
+            intent: Some(Intent::Agent),
+        }
+    }
+
+    fn type_filter(state: &mut AppState, text: &str) {
+        state.open_filter_prompt();
+        for c in text.chars() {
+            assert!(state.handle_prompt_key(KeyCode::Char(c)));
+        }
+        assert!(state.handle_prompt_key(KeyCode::Enter));
+    }
+
+    #[test]
+    fn test_filter_prompt_narrows_the_grid() {
+        let mut state = AppState::new();
+        state.receive(started("r1", "alpha-1", "openai/gpt-4o"));
+        state.receive(started("r2", "beta-2", "anthropic/claude-sonnet-4"));
+        state.receive(TuiEvent::RequestFinished {
+            id: "r2".to_string(),
+            status: 502,
+            latency_ms: 900,
+        });
+        assert_eq!(state.visible_requests().len(), 2);
+
+        type_filter(&mut state, "status:5xx");
+        let visible: Vec<&str> = state
+            .visible_requests()
+            .iter()
+            .map(|r| r.cid.0.as_str())
+            .collect();
+        assert_eq!(visible, vec!["beta-2"]);
+
+        // A bad filter keeps the prompt open and the old filter in place.
+        type_filter(&mut state, "x intent:yolo");
+        assert!(state.filter_prompt.is_some());
+        assert!(state.filter_error.is_some());
+        assert!(state.handle_prompt_key(KeyCode::Esc));
+        assert!(!state.handle_prompt_key(KeyCode::Esc));
+        assert_eq!(state.filter.as_str(), "status:5xx");
+
+        state.filter = Filter::default();
+        type_filter(&mut state, "cid:al");
+        state.list_state.select(Some(0));
+        state.focus_selected();
+        assert_eq!(state.active_request_id, Some(RequestId("r1".to_string())));
+    }
+
+    #[test]
+    fn test_pause_holds_events_until_resumed() {
+        let mut state = AppState::new();
+        state.toggle_pause();
+        state.receive(started("r1", "alpha-1", "openai/gpt-4o"));
+        assert!(state.requests.is_empty());
+        assert_eq!(state.paused.as_ref().map(VecDeque::len), Some(1));
+
+        state.toggle_pause();
+        assert!(state.paused.is_none());
+        assert_eq!(state.requests.len(), 1);
+    }
+}
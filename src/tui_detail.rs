@@ -0,0 +1,450 @@
+//! StreamFocus detail pane.
+//!
+//! [`RequestDetailState`] folds a request's [`RequestDetail`] events into what the pane shows
+//! next to the streamed text: reasoning, each tool call's arguments (pretty-printed once they
+//! parse as JSON), time-to-first-token, retry/fallback hops and the final cost breakdown.
+
+use crate::main_helper::CostBreakdown;
+use crate::tui::{HopKind, RequestDetail};
+use ratatui::{
+    prelude::*,
+    widgets::{Block, Borders, Paragraph, Wrap},
+};
+
+/// Characters per token used to estimate throughput before usage arrives.
+const CHARS_PER_TOKEN: f64 = 4.0;
+
+#[derive(Default)]
+pub struct RequestDetailState {
+    pub reasoning: String,
+    pub tool_calls: Vec<ToolCallView>,
+    pub ttft_ms: Option<u64>,
+    pub hops: Vec<HopView>,
+    pub cost: Option<CostBreakdown>,
+}
+
+pub struct ToolCallView {
+    pub index: u32,
+    pub call_id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+impl ToolCallView {
+    /// The arguments pretty-printed, once they form a complete JSON value.
+    pub fn pretty_arguments(&self) -> Option<String> {
+        match serde_json::from_str::<serde_json::Value>(&self.arguments) {
+            Ok(value) => serde_json::to_string_pretty(&value).ok(),
+            Err(_) => None,
+        }
+    }
+}
+
+pub struct HopView {
+    pub kind: HopKind,
+    pub model: String,
+    pub reason: String,
+}
+
+/// Tokens per second after the first token, and whether it is estimated from streamed text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throughput {
+    pub tokens_per_sec: f64,
+    pub estimated: bool,
+}
+
+impl RequestDetailState {
+    pub fn apply(&mut self, detail: RequestDetail) {
+        match detail {
+            RequestDetail::Reasoning(text) => self.reasoning.push_str(&text),
+            RequestDetail::ToolCall {
+                index,
+                call_id,
+                name,
+                arguments,
+            } => match self.tool_calls.iter_mut().find(|c| c.index == index) {
+                Some(call) => {
+                    if call.call_id.is_none() {
+                        call.call_id = call_id;
+                    }
+                    if call.name.is_none() {
+                        call.name = name;
+                    }
+                    call.arguments.push_str(&arguments);
+                }
+                None => self.tool_calls.push(ToolCallView {
+                    index,
+                    call_id,
+                    name,
+                    arguments,
+                }),
+            },
+            RequestDetail::FirstToken { ttft_ms } => self.ttft_ms = Some(ttft_ms),
+            RequestDetail::Hop {
+                kind,
+                model,
+                reason,
+            } => self.hops.push(HopView {
+                kind,
+                model,
+                reason,
+            }),
+        }
+    }
+
+    /// Generation speed over the time since the first token. Uses `completion_tokens` when the
+    /// usage is known, otherwise estimates tokens from everything streamed so far.
+    pub fn throughput(
+        &self,
+        completion_tokens: Option<u32>,
+        content: &str,
+        elapsed_ms: u128,
+    ) -> Option<Throughput> {
+        let generating_ms = elapsed_ms.saturating_sub(self.ttft_ms.unwrap_or(0) as u128);
+        if generating_ms == 0 {
+            return None;
+        }
+        let (tokens, estimated) = match completion_tokens {
+            Some(tokens) => (tokens as f64, false),
+            None => {
+                let streamed = content.len()
+                    + self.reasoning.len()
+                    + self
+                        .tool_calls
+                        .iter()
+                        .map(|c| c.arguments.len())
+                        .sum::<usize>();
+                (streamed as f64 / CHARS_PER_TOKEN, true)
+            }
+        };
+        if tokens <= 0.0 {
+            return None;
+        }
+        Some(Throughput {
+            tokens_per_sec: tokens * 1000.0 / generating_ms as f64,
+            estimated,
+        })
+    }
+
+    /// Streamed text on the left (or top, when narrow); tool calls and the timeline beside it.
+    pub fn render(&self, f: &mut Frame, area: Rect, content: &str, streaming: bool) {
+        let panes = if area.width >= 100 {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(58), Constraint::Percentage(42)])
+                .split(area)
+        } else {
+            Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(area)
+        };
+
+        self.render_stream(f, panes[0], content, streaming);
+
+        let timeline = self.timeline_lines();
+        let side = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length((timeline.len() as u16 + 2).min(panes[1].height / 2)),
+            ])
+            .split(panes[1]);
+        self.render_tool_calls(f, side[0], streaming);
+        f.render_widget(
+            Paragraph::new(timeline)
+                .wrap(Wrap { trim: false })
+                .block(panel(" TIMELINE ")),
+            side[1],
+        );
+    }
+
+    fn render_stream(&self, f: &mut Frame, area: Rect, content: &str, streaming: bool) {
+        let mut lines = Vec::new();
+        if !self.reasoning.is_empty() {
+            lines.push(heading("▸ REASONING", Color::Magenta));
+            for line in self.reasoning.lines() {
+                lines.push(Line::from(Span::styled(
+                    format!("  {}", line),
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::ITALIC),
+                )));
+            }
+            lines.push(Line::from(""));
+        }
+        lines.push(heading("▸ ASSISTANT", Color::Green));
+        for line in content.lines() {
+            lines.push(Line::from(format!("  {}", line)));
+        }
+        if streaming {
+            lines.push(Line::from(Span::styled(
+                "  ▊",
+                Style::default().fg(Color::Green),
+            )));
+        }
+
+        let block = panel(" STREAM ");
+        let inner = block.inner(area);
+        f.render_widget(
+            Paragraph::new(tail(lines, inner.height, inner.width))
+                .wrap(Wrap { trim: false })
+                .block(block),
+            area,
+        );
+    }
+
+    fn render_tool_calls(&self, f: &mut Frame, area: Rect, streaming: bool) {
+        let mut lines = Vec::new();
+        for call in &self.tool_calls {
+            let mut header = vec![Span::styled(
+                format!(
+                    "#{} {}",
+                    call.index,
+                    match call.name.as_deref() {
+                        Some(name) => name,
+                        None => "?",
+                    }
+                ),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )];
+            if let Some(id) = &call.call_id {
+                header.push(Span::styled(
+                    format!("  {}", id),
+                    Style::default().fg(Color::DarkGray),
+                ));
+            }
+            match call.pretty_arguments() {
+                Some(pretty) => {
+                    lines.push(Line::from(header));
+                    for line in pretty.lines() {
+                        lines.push(Line::from(format!("  {}", line)));
+                    }
+                }
+                None => {
+                    header.push(Span::styled(
+                        if streaming {
+                            "  streaming…"
+                        } else {
+                            "  incomplete JSON"
+                        },
+                        Style::default().fg(Color::Yellow),
+                    ));
+                    lines.push(Line::from(header));
+                    lines.push(Line::from(format!("  {}", call.arguments)));
+                }
+            }
+        }
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "  no tool calls",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+
+        let block = panel(&format!(" TOOL CALLS ({}) ", self.tool_calls.len()));
+        let inner = block.inner(area);
+        f.render_widget(
+            Paragraph::new(tail(lines, inner.height, inner.width))
+                .wrap(Wrap { trim: false })
+                .block(block),
+            area,
+        );
+    }
+
+    fn timeline_lines(&self) -> Vec<Line<'static>> {
+        let label = |text: &str| Span::styled(format!("{:<11}", text), Style::default().bold());
+        let mut lines = vec![Line::from(vec![
+            label("TTFT"),
+            Span::raw(match self.ttft_ms {
+                Some(ms) => format!("{}ms", ms),
+                None => "—".to_string(),
+            }),
+        ])];
+        for hop in &self.hops {
+            let (name, color) = match hop.kind {
+                HopKind::Retry => ("RETRY", Color::Yellow),
+                HopKind::StreamRetry => ("RE-STREAM", Color::Yellow),
+                HopKind::Fallback => ("FALLBACK", Color::LightRed),
+                HopKind::Hedge => ("HEDGE", Color::Cyan),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:<11}", name), Style::default().fg(color).bold()),
+                Span::raw(format!("{} ", hop.model)),
+                Span::styled(hop.reason.clone(), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+        if let Some(cost) = &self.cost {
+            let cents = |usd: f64| format!("{:.3}¢", usd * 100.0);
+            lines.push(Line::from(vec![
+                label("COST"),
+                Span::styled(
+                    cents(cost.actual_cost),
+                    Style::default().fg(Color::Green).bold(),
+                ),
+                Span::styled(
+                    format!(
+                        "  saved {}",
+                        cents(cost.potential_cost_no_cache - cost.actual_cost)
+                    ),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]));
+            lines.push(Line::from(format!(
+                "{:<11}prompt {} · completion {} · cache read {} · request {}",
+                "",
+                cents(cost.prompt_cost),
+                cents(cost.completion_cost),
+                cents(cost.cache_read_cost),
+                cents(cost.request_cost),
+            )));
+            lines.push(Line::from(format!(
+                "{:<11}{} cached / {} uncached prompt tokens",
+                "", cost.cached_tokens, cost.uncached_prompt_tokens
+            )));
+        }
+        lines
+    }
+}
+
+fn panel(title: &str) -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .title(title.to_string())
+        .border_style(Style::default().fg(Color::DarkGray))
+        .bg(Color::Black)
+}
+
+fn heading(text: &'static str, color: Color) -> Line<'static> {
+    Line::from(Span::styled(
+        text,
+        Style::default().fg(color).add_modifier(Modifier::BOLD),
+    ))
+}
+
+/// The last lines that fit `height` rows once wrapped at `width`, so a live stream stays
+/// scrolled to its newest output.
+fn tail(lines: Vec<Line<'_>>, height: u16, width: u16) -> Vec<Line<'_>> {
+    let width = width.max(1) as usize;
+    let mut rows = 0;
+    let mut start = lines.len();
+    while start > 0 {
+        let needed = lines[start - 1].width().max(1).div_ceil(width);
+        if rows + needed > height as usize {
+            break;
+        }
+        rows += needed;
+        start -= 1;
+    }
+    lines.into_iter().skip(start).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_fragment(index: u32, name: Option<&str>, arguments: &str) -> RequestDetail {
+        RequestDetail::ToolCall {
+            index,
+            call_id: name.map(|_| format!("call_{}", index)),
+            name: name.map(str::to_string),
+            arguments: arguments.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tool_call_arguments_pretty_print_once_complete() {
+        let mut state = RequestDetailState::default();
+        state.apply(tool_fragment(0, Some("read_file"), "{\"path\": "));
+        state.apply(tool_fragment(1, Some("grep"), "{\"q\":\"x\"}"));
+        assert_eq!(state.tool_calls[0].pretty_arguments(), None);
+
+        state.apply(tool_fragment(0, None, "\"src/lib.rs\"}"));
+        assert_eq!(state.tool_calls.len(), 2);
+        assert_eq!(state.tool_calls[0].name.as_deref(), Some("read_file"));
+        assert_eq!(state.tool_calls[0].call_id.as_deref(), Some("call_0"));
+        assert_eq!(
+            state.tool_calls[0].pretty_arguments().as_deref(),
+            Some("{\n  \"path\": \"src/lib.rs\"\n}")
+        );
+    }
+
+    #[test]
+    fn test_throughput_counts_from_first_token() {
+        let mut state = RequestDetailState::default();
+        assert_eq!(state.throughput(Some(10), "", 0), None);
+
+        state.apply(RequestDetail::FirstToken { ttft_ms: 1_000 });
+        assert_eq!(
+            state.throughput(Some(100), "", 3_000),
+            Some(Throughput {
+                tokens_per_sec: 50.0,
+                estimated: false
+            })
+        );
+        state.apply(RequestDetail::Reasoning("abcd".repeat(5)));
+        assert_eq!(
+            state.throughput(None, &"x".repeat(20), 2_000),
+            Some(Throughput {
+                tokens_per_sec: 10.0,
+                estimated: true
+            })
+        );
+        assert_eq!(state.throughput(None, "", 500), None);
+    }
+
+    #[test]
+    fn test_render_shows_calls_and_timeline() {
+        let mut state = RequestDetailState::default();
+        state.apply(RequestDetail::Reasoning("check the file".to_string()));
+        state.apply(tool_fragment(0, Some("read_file"), "{\"path\":\"a.rs\"}"));
+        state.apply(RequestDetail::FirstToken { ttft_ms: 850 });
+        state.apply(RequestDetail::Hop {
+            kind: HopKind::Fallback,
+            model: "openai/gpt-4o-mini".to_string(),
+            reason: "circuit open for openai/gpt-4o".to_string(),
+        });
+
+        let mut terminal = match Terminal::new(backend::TestBackend::new(120, 30)) {
+            Ok(t) => t,
+            Err(e) => panic!("terminal: {}", e),
+        };
+        if let Err(e) = terminal.draw(|f| state.render(f, f.size(), "Reading it now", true)) {
+            panic!("draw: {}", e);
+        }
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for expected in [
+            "check the file",
+            "Reading it now",
+            "TOOL CALLS (1)",
+            "#0 read_file",
+            "\"path\": \"a.rs\"",
+            "850ms",
+            "FALLBACK",
+        ] {
+            assert!(screen.contains(expected), "missing {:?}", expected);
+        }
+    }
+
+    #[test]
+    fn test_tail_keeps_the_newest_wrapped_rows() {
+        let lines = vec![
+            Line::from("old"),
+            Line::from("x".repeat(25)),
+            Line::from("new"),
+        ];
+        let kept: Vec<String> = tail(lines, 4, 10)
+            .iter()
+            .map(|l| l.spans.iter().map(|s| s.content.to_string()).collect())
+            .collect();
+        assert_eq!(kept, vec!["x".repeat(25), "new".to_string()]);
+    }
+}
@@ -0,0 +1,448 @@
+//! Dashboard filters and highlight rules.
+//!
+//! A [`Filter`] is what the TUI's `/` prompt parses: space-separated terms that must all match.
+//! `model:`, `intent:`, `status:` and `cid:` narrow the FlightDeck request list; bare words are
+//! free text matched against both requests and console log lines. `level:` narrows the console.
+//!
+//! [`HighlightRule`]s (`--tui-highlight cost>0.5,latency>60s`) mark requests that cross a
+//! threshold whatever the current filter is. They are saved to the `--tui-state` file and
+//! reused by later runs that do not pass the flag.
+
+use crate::tui::Intent;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The parts of a dashboard request that filters and highlight rules look at.
+pub struct RequestFacts<'a> {
+    pub model: &'a str,
+    pub intent: Option<Intent>,
+    /// `None` while the request is still streaming.
+    pub status: Option<u16>,
+    pub cid: &'a str,
+    pub content: &'a str,
+    pub active_tool: Option<&'a str>,
+    pub cost_usd: Option<f64>,
+    /// Final latency, or the time spent so far while streaming.
+    pub latency_ms: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum StatusClass {
+    Streaming,
+    /// `2xx`, `4xx`, `5xx`: the hundreds digit.
+    Class(u16),
+    Exact(u16),
+    /// Finished with anything but a 2xx.
+    Error,
+}
+
+impl StatusClass {
+    fn matches(&self, status: Option<u16>) -> bool {
+        match (self, status) {
+            (StatusClass::Streaming, None) => true,
+            (StatusClass::Class(class), Some(s)) => s / 100 == *class,
+            (StatusClass::Exact(expected), Some(s)) => s == *expected,
+            (StatusClass::Error, Some(s)) => s / 100 != 2,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Term {
+    Model(String),
+    Intent(Option<Intent>),
+    Status(StatusClass),
+    CidPrefix(String),
+    Level(String),
+    Text(String),
+}
+
+/// A parsed `/` filter; empty matches everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    terms: Vec<Term>,
+    source: String,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The text the filter was parsed from, for re-editing and display.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    pub fn matches_request(&self, request: &RequestFacts) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Model(model) => contains_ignore_case(request.model, model),
+            Term::Intent(intent) => request.intent == *intent,
+            Term::Status(class) => class.matches(request.status),
+            Term::CidPrefix(prefix) => request.cid.starts_with(prefix.as_str()),
+            Term::Level(_) => true,
+            Term::Text(text) => {
+                contains_ignore_case(request.model, text)
+                    || contains_ignore_case(request.cid, text)
+                    || contains_ignore_case(request.content, text)
+                    || request
+                        .active_tool
+                        .is_some_and(|tool| contains_ignore_case(tool, text))
+            }
+        })
+    }
+
+    /// Console lines only answer to free text and `level:`; request terms leave them alone.
+    pub fn matches_log(&self, line: &str) -> bool {
+        self.terms.iter().all(|term| match term {
+            Term::Text(text) => contains_ignore_case(line, text),
+            Term::Level(level) => log_level_at_least(line, level),
+            _ => true,
+        })
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut terms = Vec::new();
+        for word in s.split_whitespace() {
+            let term = match word.split_once(':') {
+                Some(("model", value)) if !value.is_empty() => Term::Model(value.to_string()),
+                Some(("cid", value)) if !value.is_empty() => Term::CidPrefix(value.to_string()),
+                Some(("intent", value)) => {
+                    Term::Intent(match value.to_ascii_lowercase().as_str() {
+                        "ask" => Some(Intent::Ask),
+                        "plan" => Some(Intent::Plan),
+                        "agent" => Some(Intent::Agent),
+                        "debug" => Some(Intent::Debug),
+                        "auto" | "none" => None,
+                        other => {
+                            return Err(format!(
+                                "unknown intent '{}' (ask, plan, agent, debug, auto)",
+                                other
+                            ))
+                        }
+                    })
+                }
+                Some(("status", value)) => Term::Status(parse_status(value)?),
+                Some(("level", value)) => match value.to_ascii_uppercase().as_str() {
+                    level @ ("ERROR" | "WARN" | "INFO" | "DEBUG" | "TRACE") => {
+                        Term::Level(level.to_string())
+                    }
+                    other => return Err(format!("unknown log level '{}'", other)),
+                },
+                Some((key @ ("model" | "cid"), _)) => {
+                    return Err(format!("'{}:' needs a value", key))
+                }
+                _ => Term::Text(word.to_string()),
+            };
+            terms.push(term);
+        }
+        Ok(Self {
+            terms,
+            source: s.trim().to_string(),
+        })
+    }
+}
+
+fn parse_status(value: &str) -> std::result::Result<StatusClass, String> {
+    let value = value.to_ascii_lowercase();
+    match value.as_str() {
+        "streaming" | "live" => return Ok(StatusClass::Streaming),
+        "ok" => return Ok(StatusClass::Class(2)),
+        "err" | "error" => return Ok(StatusClass::Error),
+        _ => {}
+    }
+    if let Some(class) = value.strip_suffix("xx") {
+        if let Ok(class @ 1..=5) = class.parse::<u16>() {
+            return Ok(StatusClass::Class(class));
+        }
+    }
+    match value.parse::<u16>() {
+        Ok(code @ 100..=599) => Ok(StatusClass::Exact(code)),
+        _ => Err(format!(
+            "unknown status '{}' (2xx, 4xx, 5xx, 429, ok, err, streaming)",
+            value
+        )),
+    }
+}
+
+fn contains_ignore_case(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// Whether a formatted console line (`12:00:00 [WARN] target: message`) is at `level` or worse.
+fn log_level_at_least(line: &str, level: &str) -> bool {
+    const SEVERITY: [&str; 5] = ["TRACE", "DEBUG", "INFO", "WARN", "ERROR"];
+    let rank = |l: &str| SEVERITY.iter().position(|s| *s == l);
+    let line_level = SEVERITY
+        .iter()
+        .find(|l| line.contains(&format!("[{}]", l)))
+        .copied();
+    match (line_level.and_then(rank), rank(level)) {
+        (Some(line_rank), Some(min_rank)) => line_rank >= min_rank,
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Metric {
+    CostUsd,
+    LatencyMs,
+}
+
+/// Marks requests past a threshold, e.g. `cost>0.5` (dollars) or `latency>60s`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HighlightRule {
+    metric: Metric,
+    above: bool,
+    threshold: f64,
+    source: String,
+}
+
+impl HighlightRule {
+    pub fn matches(&self, request: &RequestFacts) -> bool {
+        let value = match self.metric {
+            Metric::CostUsd => match request.cost_usd {
+                Some(cost) => cost,
+                None => return false,
+            },
+            Metric::LatencyMs => request.latency_ms as f64,
+        };
+        if self.above {
+            value > self.threshold
+        } else {
+            value < self.threshold
+        }
+    }
+}
+
+impl fmt::Display for HighlightRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl FromStr for HighlightRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let rule = s.trim();
+        let (key, above, value) = match (rule.split_once('>'), rule.split_once('<')) {
+            (Some((key, value)), None) => (key, true, value),
+            (None, Some((key, value))) => (key, false, value),
+            _ => {
+                return Err(format!(
+                    "expected metric>value or metric<value, got '{}'",
+                    s
+                ))
+            }
+        };
+        let value = value.trim();
+        let (metric, threshold) = match key.trim() {
+            "cost" => (
+                Metric::CostUsd,
+                value
+                    .trim_start_matches('$')
+                    .parse::<f64>()
+                    .map_err(|e| format!("invalid cost '{}': {}", value, e))?,
+            ),
+            "latency" => (Metric::LatencyMs, parse_duration_ms(value)?),
+            other => {
+                return Err(format!(
+                    "unknown highlight metric '{}' (cost, latency)",
+                    other
+                ))
+            }
+        };
+        if !threshold.is_finite() {
+            return Err(format!("invalid threshold '{}'", value));
+        }
+        Ok(Self {
+            metric,
+            above,
+            threshold,
+            source: rule.to_string(),
+        })
+    }
+}
+
+/// Dashboard settings kept between runs in the `--tui-state` file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedTuiState {
+    #[serde(default)]
+    highlights: Vec<String>,
+}
+
+/// The highlight rules for this run: the flag's rules, saved for later runs, or the saved ones
+/// when the flag is not given. A missing or unreadable state file means no saved rules.
+pub fn resolve_highlights(state_file: &Path, from_flag: &[HighlightRule]) -> Vec<HighlightRule> {
+    if !from_flag.is_empty() {
+        let saved = SavedTuiState {
+            highlights: from_flag.iter().map(|r| r.to_string()).collect(),
+        };
+        let written = match serde_json::to_vec_pretty(&saved) {
+            Ok(bytes) => std::fs::write(state_file, bytes).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = written {
+            tracing::warn!(
+                "Failed to save highlight rules to {}: {}",
+                state_file.display(),
+                e
+            );
+        }
+        return from_flag.to_vec();
+    }
+
+    let saved: SavedTuiState = match std::fs::read(state_file) {
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(saved) => saved,
+            Err(e) => {
+                tracing::warn!("Ignoring unreadable {}: {}", state_file.display(), e);
+                return Vec::new();
+            }
+        },
+        Err(_) => return Vec::new(),
+    };
+    saved
+        .highlights
+        .iter()
+        .filter_map(|rule| match rule.parse::<HighlightRule>() {
+            Ok(rule) => Some(rule),
+            Err(e) => {
+                tracing::warn!("Ignoring saved highlight rule '{}': {}", rule, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// `1500ms`, `60s`, `2m`; a bare number is seconds.
+fn parse_duration_ms(value: &str) -> std::result::Result<f64, String> {
+    let (number, scale) = if let Some(n) = value.strip_suffix("ms") {
+        (n, 1.0)
+    } else if let Some(n) = value.strip_suffix('s') {
+        (n, 1000.0)
+    } else if let Some(n) = value.strip_suffix('m') {
+        (n, 60_000.0)
+    } else {
+        (value, 1000.0)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(n) => Ok(n * scale),
+        Err(e) => Err(format!("invalid latency '{}': {}", value, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(model: &'a str, cid: &'a str, status: Option<u16>) -> RequestFacts<'a> {
+        RequestFacts {
+            model,
+            intent: Some(Intent::Agent),
+            status,
+            cid,
+            content: "Reading the config loader",
+            active_tool: Some("read_file"),
+            cost_usd: Some(0.12),
+            latency_ms: 4_000,
+        }
+    }
+
+    fn parse(s: &str) -> Filter {
+        match s.parse::<Filter>() {
+            Ok(f) => f,
+            Err(e) => panic!("{}: {}", s, e),
+        }
+    }
+
+    #[test]
+    fn test_filter_terms_must_all_match() {
+        let req = request("anthropic/claude-sonnet-4", "c0ffee-12", Some(503));
+        assert!(parse("").matches_request(&req));
+        assert!(parse("model:claude status:5xx").matches_request(&req));
+        assert!(parse("intent:agent cid:c0f").matches_request(&req));
+        assert!(parse("status:503 read_file").matches_request(&req));
+        assert!(parse("CONFIG").matches_request(&req));
+        assert!(!parse("model:gpt").matches_request(&req));
+        assert!(!parse("status:ok").matches_request(&req));
+        assert!(!parse("cid:12").matches_request(&req));
+        assert!(!parse("intent:auto").matches_request(&req));
+        assert!(!parse("status:streaming").matches_request(&req));
+        assert!(parse("status:streaming").matches_request(&request("m", "c", None)));
+    }
+
+    #[test]
+    fn test_filter_on_logs_uses_text_and_level() {
+        let warn = "12:00:01 [WARN] parallax::server: upstream retry for c0ffee";
+        let info = "12:00:02 [INFO] parallax::server: request finished";
+        let filter = parse("model:claude retry");
+        assert!(filter.matches_log(warn));
+        assert!(!filter.matches_log(info));
+        assert!(parse("level:warn").matches_log(warn));
+        assert!(!parse("level:warn").matches_log(info));
+        assert!(parse("level:info").matches_log(warn));
+    }
+
+    #[test]
+    fn test_filter_rejects_unknown_values() {
+        assert!("intent:yolo".parse::<Filter>().is_err());
+        assert!("status:7xx".parse::<Filter>().is_err());
+        assert!("model:".parse::<Filter>().is_err());
+        assert!("level:loud".parse::<Filter>().is_err());
+        assert_eq!(parse("  model:gpt  ").as_str(), "model:gpt");
+    }
+
+    #[test]
+    fn test_highlight_rules() {
+        let rule = |s: &str| match s.parse::<HighlightRule>() {
+            Ok(r) => r,
+            Err(e) => panic!("{}: {}", s, e),
+        };
+        let mut req = request("m", "c", Some(200));
+        assert!(rule("cost>0.1").matches(&req));
+        assert!(!rule("cost>$0.50").matches(&req));
+        assert!(rule("latency>3s").matches(&req));
+        assert!(!rule("latency>4000ms").matches(&req));
+        assert!(rule("latency<1m").matches(&req));
+        req.cost_usd = None;
+        assert!(!rule("cost>0").matches(&req));
+        assert_eq!(rule(" latency>60s ").to_string(), "latency>60s");
+        assert!("tokens>5".parse::<HighlightRule>().is_err());
+        assert!("cost=5".parse::<HighlightRule>().is_err());
+        assert!("latency>soon".parse::<HighlightRule>().is_err());
+    }
+
+    #[test]
+    fn test_highlight_rules_are_saved_and_reused() {
+        let dir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let state_file = dir.path().join("tui_state.json");
+        assert!(resolve_highlights(&state_file, &[]).is_empty());
+
+        let rules: Vec<HighlightRule> = ["cost>0.5", "latency>60s"]
+            .iter()
+            .map(|s| match s.parse() {
+                Ok(r) => r,
+                Err(e) => panic!("{}: {}", s, e),
+            })
+            .collect();
+        assert_eq!(resolve_highlights(&state_file, &rules), rules);
+        assert_eq!(resolve_highlights(&state_file, &[]), rules);
+
+        // A later flag replaces the saved rules.
+        let latency_only = rules[1..].to_vec();
+        assert_eq!(resolve_highlights(&state_file, &latency_only), latency_only);
+        assert_eq!(resolve_highlights(&state_file, &[]), latency_only);
+    }
+}
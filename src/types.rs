@@ -139,6 +139,12 @@ pub enum ParallaxError {
     #[error("Internal error: {0}")]
     Internal(String, SpanTrace),
 
+    #[error("Circuit breaker open: {0}")]
+    CircuitOpen(String),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
     #[allow(dead_code)]
     #[error("Protocol error: {0}")]
     Protocol(String),
@@ -183,6 +189,16 @@ impl axum::response::IntoResponse for ObservedError {
                 m.clone(),
                 "INTERNAL_ERROR",
             ),
+            ParallaxError::CircuitOpen(m) => (
+                axum::http::StatusCode::SERVICE_UNAVAILABLE,
+                m.clone(),
+                "CIRCUIT_OPEN",
+            ),
+            ParallaxError::Config(m) => (
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                m.clone(),
+                "CONFIG_ERROR",
+            ),
             ParallaxError::Protocol(m) => (
                 axum::http::StatusCode::BAD_REQUEST,
                 m.clone(),
@@ -205,6 +221,15 @@ impl axum::response::IntoResponse for ObservedError {
 pub struct ObservedError {
     pub inner: ParallaxError,
     pub span_trace: SpanTrace,
+    /// Server-provided retry hint (`Retry-After`, rate-limit reset), if any.
+    pub retry_after: Option<std::time::Duration>,
+}
+
+impl ObservedError {
+    pub fn with_retry_after(mut self, retry_after: Option<std::time::Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
 }
 
 impl std::fmt::Display for ObservedError {
@@ -227,6 +252,7 @@ where
         Self {
             inner: error.into(),
             span_trace: SpanTrace::capture(),
+            retry_after: None,
         }
     }
 }
@@ -738,7 +764,18 @@ impl TurnAccumulator {
         }
     }
     pub fn finalize(self) -> TurnRecord {
+        self.finalize_checked(&crate::tool_schema::ToolSchemaRegistry::new())
+            .0
+    }
+
+    /// Finalize the turn, checking each tool call's arguments against the advertised schemas.
+    /// Arguments are coerced in place; calls that needed attention are reported.
+    pub fn finalize_checked(
+        self,
+        schemas: &crate::tool_schema::ToolSchemaRegistry,
+    ) -> (TurnRecord, Vec<crate::tool_schema::ToolArgReport>) {
         let mut content = Vec::new();
+        let mut reports = Vec::new();
         if !self.text_buffer.is_empty() {
             content.push(MessagePart::Text {
                 content: self.text_buffer,
@@ -752,48 +789,91 @@ impl TurnAccumulator {
         }
 
         for (id, buf) in self.tool_calls {
-            let finalized_tool_call = Self::finalize_tool_call(id, buf);
+            let (finalized_tool_call, report) = Self::finalize_tool_call(id, buf, schemas);
             content.push(finalized_tool_call);
+            reports.extend(report);
         }
 
         let role = match self.role {
             Some(r) => r,
             None => Role::Assistant,
         };
-        TurnRecord {
-            role,
-            content,
-            tool_call_id: None,
-        }
+        (
+            TurnRecord {
+                role,
+                content,
+                tool_call_id: None,
+            },
+            reports,
+        )
     }
 
-    fn finalize_tool_call(id: String, buf: ToolCallBuffer) -> MessagePart {
-        let mut args_json =
-            match crate::json_repair::repair_tool_call_arguments(&buf.name, &buf.arguments) {
-                Ok(v) => v,
-                Err(repair_err) => {
-                    tracing::warn!(
+    fn finalize_tool_call(
+        id: String,
+        buf: ToolCallBuffer,
+        schemas: &crate::tool_schema::ToolSchemaRegistry,
+    ) -> (MessagePart, Option<crate::tool_schema::ToolArgReport>) {
+        let mut unparseable = None;
+        let mut rules_applied = Vec::new();
+        let mut args_json = match crate::json_repair::repair_tool_call_arguments_with_rule(
+            &buf.name,
+            &buf.arguments,
+        ) {
+            Ok((v, wrapped)) => {
+                rules_applied.extend(wrapped);
+                v
+            }
+            Err(repair_err) => {
+                tracing::warn!(
                     "[FINALIZE] Tool call '{}' (id={}) has invalid arguments even after repair: {}",
                     buf.name,
                     id,
                     repair_err
                 );
-                    // Return empty object as fallback rather than failing the entire operation
-                    serde_json::json!({})
-                }
-            };
+                unparseable = Some(repair_err);
+                // Return empty object as fallback rather than failing the entire operation
+                serde_json::json!({})
+            }
+        };
 
-        // Hardening Hook: Sanitize tool arguments (e.g. fix mutually exclusive flags)
-        crate::hardening::sanitize_tool_call(&buf.name, &mut args_json);
+        // Hardening Hook: sanitizer rules (e.g. fix mutually exclusive flags)
+        rules_applied.extend(crate::hardening::sanitize_tool_call(
+            &buf.name,
+            &mut args_json,
+        ));
 
-        MessagePart::ToolCall {
-            id,
-            name: buf.name,
-            arguments: args_json,
-            signature: None,
-            metadata: buf.metadata,
-            cache_control: None,
-        }
+        let check = match unparseable {
+            Some(reason) => crate::tool_schema::ArgCheck {
+                coercions: Vec::new(),
+                violations: vec![crate::tool_schema::Violation::Unparseable { reason }],
+            },
+            None => match schemas.check(&buf.name, &mut args_json) {
+                Some(check) => check,
+                None => crate::tool_schema::ArgCheck::default(),
+            },
+        };
+        let report = if check.is_clean() && rules_applied.is_empty() {
+            None
+        } else {
+            Some(crate::tool_schema::ToolArgReport {
+                tool_call_id: id.clone(),
+                tool_name: buf.name.clone(),
+                check,
+                rules_applied,
+            })
+        };
+
+        (
+            MessagePart::ToolCall {
+                id,
+                name: buf.name,
+                arguments: args_json,
+                signature: None,
+                metadata: buf.metadata,
+                cache_control: None,
+            },
+            report,
+        )
     }
 }
 
@@ -0,0 +1,61 @@
+//! Replays every fixture in `tests/cassettes/` (see `parallax cassette`) through
+//! lift → project → stream handling against a mock upstream and diffs the results.
+
+mod common;
+
+use common::{MockResponse, TestProxy};
+use parallax::cassette::{diff, Cassette};
+use std::path::Path;
+
+#[tokio::test]
+async fn test_replay_all_cassettes() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/cassettes");
+    let cassettes = match Cassette::load_dir(&dir).await {
+        Ok(c) => c,
+        Err(e) => panic!("failed to load cassettes: {}", e),
+    };
+    assert!(!cassettes.is_empty(), "no cassettes in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for (path, cassette) in &cassettes {
+        let proxy =
+            TestProxy::start(vec![MockResponse::lines(cassette.upstream_sse.clone())]).await;
+        let cid = &cassette.source.conversation_id;
+        let transcript = proxy.chat(cid, cassette.ingress.clone()).await;
+
+        let mut problems = Vec::new();
+        if transcript.status != 200 {
+            problems.push(format!("client status {}", transcript.status));
+        }
+
+        match proxy.upstream.requests().first() {
+            Some(projected) => problems.extend(
+                diff(&cassette.expected.projected, projected)
+                    .into_iter()
+                    .map(|d| format!("projected {}", d)),
+            ),
+            None => problems.push("no request reached the upstream".to_string()),
+        }
+
+        match proxy.blob(cid, "final").await {
+            Some(final_turn) => problems.extend(
+                diff(&cassette.expected.final_turn, &final_turn)
+                    .into_iter()
+                    .map(|d| format!("final {}", d)),
+            ),
+            None => problems.push("final blob was not written".to_string()),
+        }
+
+        if !problems.is_empty() {
+            failures.push(format!("{}:\n  {}", path.display(), problems.join("\n  ")));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} cassettes diverged:\n{}",
+        failures.len(),
+        cassettes.len(),
+        failures.join("\n")
+    );
+}
@@ -0,0 +1,362 @@
+//! In-process mock of the OpenRouter chat completions API.
+//!
+//! Each inbound request pops the next scripted [`MockResponse`]; once the script runs out
+//! the last entry is repeated. Received request bodies are recorded for assertions.
+
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures_util::StreamExt;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// One scripted upstream reply.
+#[derive(Clone, Debug)]
+pub enum MockResponse {
+    /// A `text/event-stream` body built from raw `data:` payloads.
+    Sse {
+        events: Vec<String>,
+        /// Append `data: [DONE]`; `false` simulates an EOF without the end marker.
+        done: bool,
+        /// Re-chunk the body into pieces of this many bytes, splitting lines mid-way.
+        chunk_size: Option<usize>,
+        /// Pause between chunks.
+        chunk_delay: Duration,
+    },
+    /// Verbatim SSE lines (e.g. a cassette's `upstream_sse`), each followed by a blank line.
+    Lines { lines: Vec<String> },
+    /// A plain non-2xx (or 2xx JSON) reply.
+    Status {
+        status: u16,
+        body: serde_json::Value,
+    },
+}
+
+impl MockResponse {
+    pub fn sse(events: Vec<String>) -> Self {
+        MockResponse::Sse {
+            events,
+            done: true,
+            chunk_size: None,
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// Same stream, but ends at EOF without `data: [DONE]`.
+    pub fn without_done(self) -> Self {
+        match self {
+            MockResponse::Sse {
+                events,
+                chunk_size,
+                chunk_delay,
+                ..
+            } => MockResponse::Sse {
+                events,
+                done: false,
+                chunk_size,
+                chunk_delay,
+            },
+            other => other,
+        }
+    }
+
+    /// Splits the body into `size`-byte network chunks.
+    pub fn chunked(self, size: usize) -> Self {
+        match self {
+            MockResponse::Sse {
+                events,
+                done,
+                chunk_delay,
+                ..
+            } => MockResponse::Sse {
+                events,
+                done,
+                chunk_size: Some(size.max(1)),
+                chunk_delay,
+            },
+            other => other,
+        }
+    }
+
+    /// Pauses `delay` before each network chunk (so a stream stays in flight for a while).
+    pub fn with_chunk_delay(self, delay: Duration) -> Self {
+        match self {
+            MockResponse::Sse {
+                events,
+                done,
+                chunk_size,
+                ..
+            } => MockResponse::Sse {
+                events,
+                done,
+                chunk_size,
+                chunk_delay: delay,
+            },
+            other => other,
+        }
+    }
+
+    pub fn lines(lines: Vec<String>) -> Self {
+        MockResponse::Lines { lines }
+    }
+
+    pub fn status(status: u16, body: serde_json::Value) -> Self {
+        MockResponse::Status { status, body }
+    }
+
+    fn body_bytes(events: &[String], done: bool) -> Vec<u8> {
+        let mut body = String::new();
+        for event in events {
+            body.push_str("data: ");
+            body.push_str(event);
+            body.push_str("\n\n");
+        }
+        if done {
+            body.push_str("data: [DONE]\n\n");
+        }
+        body.into_bytes()
+    }
+}
+
+/// Builders for canned provider chunks.
+pub mod chunks {
+    use serde_json::json;
+
+    pub fn content(text: &str) -> String {
+        json!({
+            "id": "gen-mock",
+            "model": "mock/model",
+            "choices": [{"index": 0, "delta": {"role": "assistant", "content": text}}]
+        })
+        .to_string()
+    }
+
+    /// A tool call delta; `id`/`name` are typically only present on the first fragment.
+    pub fn tool_call(index: u32, id: Option<&str>, name: Option<&str>, arguments: &str) -> String {
+        let mut function = json!({ "arguments": arguments });
+        if let Some(name) = name {
+            function["name"] = json!(name);
+        }
+        let mut call = json!({ "index": index, "type": "function", "function": function });
+        if let Some(id) = id {
+            call["id"] = json!(id);
+        }
+        json!({
+            "id": "gen-mock",
+            "model": "mock/model",
+            "choices": [{"index": 0, "delta": {"tool_calls": [call]}}]
+        })
+        .to_string()
+    }
+
+    pub fn finish(reason: &str) -> String {
+        json!({
+            "id": "gen-mock",
+            "model": "mock/model",
+            "choices": [{"index": 0, "delta": {}, "finish_reason": reason}]
+        })
+        .to_string()
+    }
+
+    pub fn usage(prompt: u32, completion: u32) -> String {
+        json!({
+            "id": "gen-mock",
+            "model": "mock/model",
+            "choices": [],
+            "usage": {
+                "prompt_tokens": prompt,
+                "completion_tokens": completion,
+                "total_tokens": prompt + completion
+            }
+        })
+        .to_string()
+    }
+
+    /// An in-band OpenRouter `ProviderError` line.
+    pub fn provider_error(code: u16, message: &str) -> String {
+        json!({ "error": { "code": code, "message": message } }).to_string()
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    script: Mutex<VecDeque<MockResponse>>,
+    last: Mutex<Option<MockResponse>>,
+    requests: Mutex<Vec<serde_json::Value>>,
+    /// SSE bodies still being written (dropped once the client hangs up or the body ends).
+    open_streams: AtomicUsize,
+    /// Network chunks written across all SSE bodies.
+    chunks_sent: AtomicUsize,
+}
+
+/// Counts an SSE body as open until the server drops it.
+struct OpenStream(Arc<MockState>);
+
+impl OpenStream {
+    fn new(state: Arc<MockState>) -> Self {
+        state.open_streams.fetch_add(1, Ordering::SeqCst);
+        Self(state)
+    }
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        self.0.open_streams.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct MockUpstream {
+    pub base_url: String,
+    state: Arc<MockState>,
+    _server: tokio::task::JoinHandle<()>,
+}
+
+impl MockUpstream {
+    pub async fn start(script: Vec<MockResponse>) -> Self {
+        let state = Arc::new(MockState {
+            script: Mutex::new(script.into_iter().collect()),
+            ..Default::default()
+        });
+        let app = Router::new()
+            .route("/api/v1/chat/completions", post(chat_completions))
+            .route("/api/v1/models", get(models))
+            .with_state(state.clone());
+
+        let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+            Ok(l) => l,
+            Err(e) => panic!("mock upstream bind failed: {}", e),
+        };
+        let addr = match listener.local_addr() {
+            Ok(a) => a,
+            Err(e) => panic!("mock upstream has no local addr: {}", e),
+        };
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Self {
+            base_url: format!("http://{}/api/v1", addr),
+            state,
+            _server: server,
+        }
+    }
+
+    /// Request bodies received so far, in arrival order.
+    pub fn requests(&self) -> Vec<serde_json::Value> {
+        match self.state.requests.lock() {
+            Ok(r) => r.clone(),
+            Err(p) => p.into_inner().clone(),
+        }
+    }
+
+    /// SSE bodies the mock is still streaming.
+    pub fn open_streams(&self) -> usize {
+        self.state.open_streams.load(Ordering::SeqCst)
+    }
+
+    /// Network chunks written so far across all SSE bodies.
+    pub fn chunks_sent(&self) -> usize {
+        self.state.chunks_sent.load(Ordering::SeqCst)
+    }
+}
+
+/// One priced model, so a proxy binary started against the mock finishes its pricing fetch.
+async fn models() -> Json<serde_json::Value> {
+    Json(serde_json::json!({
+        "data": [{
+            "id": "openai/gpt-4o",
+            "context_length": 128000,
+            "pricing": { "prompt": "0.0000025", "completion": "0.00001" }
+        }]
+    }))
+}
+
+async fn chat_completions(
+    State(state): State<Arc<MockState>>,
+    Json(body): Json<serde_json::Value>,
+) -> Response {
+    if let Ok(mut requests) = state.requests.lock() {
+        requests.push(body);
+    }
+
+    let next = {
+        let popped = match state.script.lock() {
+            Ok(mut s) => s.pop_front(),
+            Err(_) => None,
+        };
+        let mut last = match state.last.lock() {
+            Ok(l) => l,
+            Err(p) => p.into_inner(),
+        };
+        match popped {
+            Some(r) => {
+                *last = Some(r.clone());
+                Some(r)
+            }
+            None => last.clone(),
+        }
+    };
+
+    match next {
+        None => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(
+                serde_json::json!({ "error": { "message": "mock script is empty", "code": 500 } }),
+            ),
+        )
+            .into_response(),
+        Some(MockResponse::Status { status, body }) => {
+            let status = match StatusCode::from_u16(status) {
+                Ok(s) => s,
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            };
+            (status, Json(body)).into_response()
+        }
+        Some(MockResponse::Lines { lines }) => {
+            let mut body = String::new();
+            for line in lines {
+                body.push_str(&line);
+                body.push_str("\n\n");
+            }
+            Response::builder()
+                .status(StatusCode::OK)
+                .header("content-type", "text/event-stream")
+                .body(Body::from(body))
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+        Some(MockResponse::Sse {
+            events,
+            done,
+            chunk_size,
+            chunk_delay,
+        }) => {
+            let bytes = MockResponse::body_bytes(&events, done);
+            let size = match chunk_size {
+                Some(s) => s,
+                None => bytes.len().max(1),
+            };
+            let pieces: Vec<Bytes> = bytes.chunks(size).map(Bytes::copy_from_slice).collect();
+            let open = Arc::new(OpenStream::new(state.clone()));
+            let stream = futures_util::stream::iter(pieces).then(move |piece| {
+                let open = open.clone();
+                async move {
+                    if !chunk_delay.is_zero() {
+                        tokio::time::sleep(chunk_delay).await;
+                    }
+                    open.0.chunks_sent.fetch_add(1, Ordering::SeqCst);
+                    Ok::<_, std::io::Error>(piece)
+                }
+            });
+            Response::builder()
+                .status(StatusCode::OK)
+                .header("content-type", "text/event-stream")
+                .body(Body::from_stream(stream))
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! Shared integration-test harness: boots the proxy in-process against a [`MockUpstream`].
+#![allow(dead_code, unused_imports)]
+
+pub mod mock_upstream;
+
+pub use mock_upstream::{chunks, MockResponse, MockUpstream};
+
+use clap::Parser;
+use parallax::{AppState, Args};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub struct TestProxy {
+    pub base_url: String,
+    pub state: Arc<AppState>,
+    pub upstream: MockUpstream,
+    workdir: tempfile::TempDir,
+    _server: tokio::task::JoinHandle<()>,
+}
+
+/// Client-visible result of one chat completion call.
+#[derive(Debug)]
+pub struct SseTranscript {
+    pub status: u16,
+    /// `data:` payloads in order, including `[DONE]`.
+    pub data: Vec<String>,
+    pub raw: String,
+}
+
+impl SseTranscript {
+    /// Parsed JSON payloads (skips `[DONE]` and non-JSON data).
+    pub fn json(&self) -> Vec<serde_json::Value> {
+        self.data
+            .iter()
+            .filter_map(|d| serde_json::from_str(d).ok())
+            .collect()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.data.last().map(|d| d == "[DONE]").unwrap_or(false)
+    }
+
+    /// Concatenated `delta.content` across all chunks.
+    pub fn content(&self) -> String {
+        self.json()
+            .iter()
+            .filter_map(|c| {
+                c.pointer("/choices/0/delta/content")?
+                    .as_str()
+                    .map(String::from)
+            })
+            .collect()
+    }
+
+    /// All `delta.tool_calls` entries across all chunks.
+    pub fn tool_call_deltas(&self) -> Vec<serde_json::Value> {
+        self.json()
+            .iter()
+            .filter_map(|c| {
+                c.pointer("/choices/0/delta/tool_calls")?
+                    .as_array()
+                    .cloned()
+            })
+            .flatten()
+            .collect()
+    }
+}
+
+impl TestProxy {
+    /// Starts a mock upstream with `script` and a proxy pointed at it.
+    pub async fn start(script: Vec<MockResponse>) -> Self {
+        Self::start_with_args(script, &[]).await
+    }
+
+    pub async fn start_with_args(script: Vec<MockResponse>, extra_args: &[&str]) -> Self {
+        Self::start_with_pricing(script, extra_args, std::collections::HashMap::new()).await
+    }
+
+    /// Like [`TestProxy::start_with_args`], with a price list so turns are costed.
+    pub async fn start_with_pricing(
+        script: Vec<MockResponse>,
+        extra_args: &[&str],
+        pricing: std::collections::HashMap<String, parallax::types::CostModel>,
+    ) -> Self {
+        let upstream = MockUpstream::start(script).await;
+        let workdir = match tempfile::tempdir() {
+            Ok(d) => d,
+            Err(e) => panic!("tempdir: {}", e),
+        };
+        let db_path = workdir.path().join("parallax.db");
+        let capture_dir = workdir.path().join("debug_capture");
+
+        let mut argv: Vec<String> = vec![
+            "parallax".into(),
+            "--upstream-url".into(),
+            upstream.base_url.clone(),
+            "--database".into(),
+            db_path.to_string_lossy().into_owned(),
+            "--debug-capture-dir".into(),
+            capture_dir.to_string_lossy().into_owned(),
+        ];
+        argv.extend(extra_args.iter().map(|s| s.to_string()));
+        let args = Arc::new(Args::parse_from(argv));
+
+        let db = match parallax::db::init_db(&db_path).await {
+            Ok(db) => db,
+            Err(e) => panic!("init_db: {}", e),
+        };
+        let (tx_tui, _) = tokio::sync::broadcast::channel(1024);
+        let state = Arc::new(AppState::new(
+            args,
+            reqwest::Client::new(),
+            "sk-test".to_string(),
+            db,
+            tx_tui,
+            pricing,
+        ));
+
+        let app = parallax::server::router(state.clone());
+        let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+            Ok(l) => l,
+            Err(e) => panic!("proxy bind failed: {}", e),
+        };
+        let addr = match listener.local_addr() {
+            Ok(a) => a,
+            Err(e) => panic!("proxy has no local addr: {}", e),
+        };
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(
+                listener,
+                app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .await;
+        });
+
+        Self {
+            base_url: format!("http://{}", addr),
+            state,
+            upstream,
+            workdir,
+            _server: server,
+        }
+    }
+
+    pub fn capture_dir(&self) -> PathBuf {
+        self.workdir.path().join("debug_capture")
+    }
+
+    /// Posts a streaming chat completion for `conversation_id` and collects the SSE reply.
+    pub async fn chat(&self, conversation_id: &str, body: serde_json::Value) -> SseTranscript {
+        let response = match reqwest::Client::new()
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .header("x-conversation-id", conversation_id)
+            .json(&body)
+            .send()
+            .await
+        {
+            Ok(r) => r,
+            Err(e) => panic!("proxy request failed: {}", e),
+        };
+        let status = response.status().as_u16();
+        let raw = match response.text().await {
+            Ok(t) => t,
+            Err(e) => panic!("reading proxy response failed: {}", e),
+        };
+        let data = raw
+            .lines()
+            .filter_map(|l| l.strip_prefix("data:"))
+            .map(|d| d.trim().to_string())
+            .collect();
+        SseTranscript { status, data, raw }
+    }
+
+    /// Reads a blob from the (single) captured turn of `conversation_id`.
+    ///
+    /// Bundle files are finalized after the last SSE event, so this polls briefly.
+    pub async fn blob(&self, conversation_id: &str, blob_id: &str) -> Option<serde_json::Value> {
+        for _ in 0..50 {
+            if let Some(v) = self.read_blob(conversation_id, blob_id).await {
+                return Some(v);
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        None
+    }
+
+    /// Id of the first captured turn of `conversation_id`, whichever bundle store is in use.
+    pub async fn turn_id(&self, conversation_id: &str) -> Option<String> {
+        let turns = self
+            .state
+            .bundles()
+            .list_turns(conversation_id)
+            .await
+            .ok()?;
+        turns.into_iter().next()
+    }
+
+    async fn read_blob(&self, conversation_id: &str, blob_id: &str) -> Option<serde_json::Value> {
+        let bundles = self.state.bundles();
+        for tid in bundles.list_turns(conversation_id).await.ok()? {
+            match bundles
+                .read_blob_value(conversation_id, &tid, blob_id)
+                .await
+            {
+                // SSE captures come back as an array; wait until at least one line landed.
+                Ok(Some(serde_json::Value::Array(lines))) if lines.is_empty() => {}
+                Ok(Some(v)) => return Some(v),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// How long [`wait_for`] polls before giving up; generous so loaded CI machines do not flake.
+pub const WAIT_FOR_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Polls `probe` until it yields a value, for state the proxy finishes in the background
+/// after a response (bundle summaries, captures, the search index, alerts).
+pub async fn wait_for<T, F, Fut>(what: &str, mut probe: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Option<T>>,
+{
+    let deadline = tokio::time::Instant::now() + WAIT_FOR_TIMEOUT;
+    loop {
+        if let Some(value) = probe().await {
+            return value;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            panic!(
+                "timed out after {:?} waiting for {}",
+                WAIT_FOR_TIMEOUT, what
+            );
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+}
+
+/// A minimal streaming request, optionally advertising one tool.
+pub fn chat_request(model: &str, with_tool: bool) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "model": model,
+        "stream": true,
+        "messages": [{ "role": "user", "content": "Say hello" }]
+    });
+    if with_tool {
+        body["tools"] = serde_json::json!([{
+            "type": "function",
+            "function": {
+                "name": "read_file",
+                "description": "Read a file",
+                "parameters": {
+                    "type": "object",
+                    "properties": { "path": { "type": "string" } },
+                    "required": ["path"]
+                }
+            }
+        }]);
+    }
+    body
+}
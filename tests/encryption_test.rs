@@ -0,0 +1,108 @@
+//! At-rest encryption. The keyring is process-wide, so these tests live in their own binary.
+
+mod common;
+
+use common::{chat_request, chunks, wait_for, MockResponse, TestProxy};
+use parallax::at_rest::{self, Key, Keyring};
+
+const MARKER: &str = "ZephyrQuokkaArgument";
+
+fn install_key() {
+    match Key::from_material("integration-test-key-material") {
+        Ok(key) => at_rest::install(Keyring::with_key(key)),
+        Err(e) => panic!("key: {}", e),
+    }
+}
+
+fn files_under(dir: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(e) => e,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+async fn assert_captured_sealed(store: &str) {
+    install_key();
+    let proxy = TestProxy::start_with_args(
+        vec![MockResponse::sse(vec![
+            chunks::tool_call(
+                0,
+                Some("call_1"),
+                Some("read_file"),
+                &format!("{{\"path\": \"{}.rs\"}}", MARKER),
+            ),
+            chunks::finish("tool_calls"),
+        ])],
+        &["--bundle-store", store, "--capture-sse"],
+    )
+    .await;
+
+    let mut body = chat_request("openai/gpt-4o", true);
+    body["messages"][0]["content"] = serde_json::json!(format!("Open {}", MARKER));
+    let transcript = proxy.chat("sealed", body).await;
+    assert!(transcript.is_done(), "raw: {}", transcript.raw);
+
+    // Reads through the store decrypt transparently.
+    let request = match proxy.blob("sealed", "ingress_raw").await {
+        Some(v) => v,
+        None => panic!("ingress_raw blob missing"),
+    };
+    assert!(
+        request.to_string().contains(MARKER),
+        "ingress_raw: {}",
+        request
+    );
+    assert!(proxy.blob("sealed", "final").await.is_some());
+
+    let files = files_under(&proxy.capture_dir());
+    assert!(!files.is_empty());
+    for path in files {
+        let bytes = match std::fs::read(&path) {
+            Ok(b) => b,
+            Err(e) => panic!("read {}: {}", path.display(), e),
+        };
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(!text.contains(MARKER), "plaintext in {}", path.display());
+    }
+
+    // The search index keeps names only, never captured text.
+    let url = format!("{}/debug/search?tool=read_file", proxy.base_url);
+    let hits = wait_for("a search hit", || async {
+        let hits: serde_json::Value = match reqwest::get(&url).await {
+            Ok(r) => match r.json().await {
+                Ok(v) => v,
+                Err(_) => return None,
+            },
+            Err(e) => panic!("search request: {}", e),
+        };
+        hits.as_array()
+            .is_some_and(|h| !h.is_empty())
+            .then_some(hits)
+    })
+    .await;
+    assert_eq!(hits[0]["conversation_id"], "sealed", "hits: {}", hits);
+    assert!(!hits.to_string().contains(MARKER), "hits: {}", hits);
+}
+
+#[tokio::test]
+async fn test_file_store_captures_are_encrypted_on_disk() {
+    assert_captured_sealed("files").await;
+}
+
+#[tokio::test]
+async fn test_cas_store_captures_are_encrypted_on_disk() {
+    assert_captured_sealed("cas").await;
+}
@@ -0,0 +1,182 @@
+//! `parallax serve`: runs the real binary without a TUI and stops it with signals.
+#![cfg(unix)]
+
+mod common;
+
+use common::{chat_request, chunks, MockResponse, MockUpstream};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::Duration;
+
+fn free_port() -> u16 {
+    match std::net::TcpListener::bind("127.0.0.1:0").and_then(|l| l.local_addr()) {
+        Ok(addr) => addr.port(),
+        Err(e) => panic!("no free port: {}", e),
+    }
+}
+
+fn spawn_serve(workdir: &Path, upstream: &MockUpstream, port: u16) -> Child {
+    let stdout = match std::fs::File::create(workdir.join("stdout.log")) {
+        Ok(f) => f,
+        Err(e) => panic!("stdout file: {}", e),
+    };
+    let port = port.to_string();
+    let spawned = Command::new(env!("CARGO_BIN_EXE_parallax"))
+        .args([
+            "--host",
+            "127.0.0.1",
+            "--port",
+            port.as_str(),
+            "--upstream-url",
+            upstream.base_url.as_str(),
+            "--database",
+            "parallax.db",
+            "--log-format",
+            "json",
+            "--shutdown-grace-secs",
+            "20",
+            "serve",
+        ])
+        .current_dir(workdir)
+        .env("OPENROUTER_API_KEY", "sk-test")
+        .env("RUST_LOG", "parallax=info")
+        .stdin(Stdio::null())
+        .stdout(stdout)
+        .stderr(Stdio::null())
+        .spawn();
+    match spawned {
+        Ok(c) => c,
+        Err(e) => panic!("spawn parallax: {}", e),
+    }
+}
+
+async fn wait_for_exit(child: &mut Child) -> ExitStatus {
+    for _ in 0..400 {
+        match child.try_wait() {
+            Ok(Some(status)) => return status,
+            Ok(None) => tokio::time::sleep(Duration::from_millis(50)).await,
+            Err(e) => panic!("wait: {}", e),
+        }
+    }
+    let _ = child.kill();
+    panic!("parallax did not exit");
+}
+
+fn stdout_of(workdir: &Path) -> String {
+    std::fs::read_to_string(workdir.join("stdout.log")).unwrap_or_default()
+}
+
+async fn wait_until_healthy(child: &mut Child, workdir: &Path, base_url: &str) {
+    for _ in 0..400 {
+        if let Ok(r) = reqwest::get(format!("{}/health", base_url)).await {
+            if r.status().is_success() {
+                return;
+            }
+        }
+        if let Ok(Some(status)) = child.try_wait() {
+            panic!(
+                "parallax exited during startup: {}\n{}",
+                status,
+                stdout_of(workdir)
+            );
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    let _ = child.kill();
+    panic!("parallax never became healthy\n{}", stdout_of(workdir));
+}
+
+fn send_signal(child: &Child, signal: &str) {
+    match Command::new("kill")
+        .args([signal, &child.id().to_string()])
+        .status()
+    {
+        Ok(s) if s.success() => {}
+        other => panic!("kill {}: {:?}", signal, other),
+    }
+}
+
+#[tokio::test]
+async fn test_serve_drains_in_flight_stream_on_sigterm() {
+    let words = ["Draining", " keeps", " this", " whole", " reply", " intact"];
+    let mut events: Vec<String> = words.iter().map(|w| chunks::content(w)).collect();
+    events.push(chunks::finish("stop"));
+    let upstream = MockUpstream::start(vec![MockResponse::sse(events)
+        .chunked(24)
+        .with_chunk_delay(Duration::from_millis(40))])
+    .await;
+    let workdir = match tempfile::tempdir() {
+        Ok(d) => d,
+        Err(e) => panic!("tempdir: {}", e),
+    };
+    let port = free_port();
+    let base_url = format!("http://127.0.0.1:{}", port);
+    let mut child = spawn_serve(workdir.path(), &upstream, port);
+    wait_until_healthy(&mut child, workdir.path(), &base_url).await;
+
+    let mut response = match reqwest::Client::new()
+        .post(format!("{}/v1/chat/completions", base_url))
+        .header("x-conversation-id", "headless")
+        .json(&chat_request("openai/gpt-4o", false))
+        .send()
+        .await
+    {
+        Ok(r) => r,
+        Err(e) => panic!("chat request: {}", e),
+    };
+    let mut raw = String::new();
+    match response.chunk().await {
+        Ok(Some(chunk)) => raw.push_str(&String::from_utf8_lossy(&chunk)),
+        other => panic!("first chunk: {:?}", other),
+    }
+
+    // The stream is now in flight; SIGTERM must let it finish.
+    send_signal(&child, "-TERM");
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => raw.push_str(&String::from_utf8_lossy(&chunk)),
+            Ok(None) => break,
+            Err(e) => panic!("stream cut during shutdown: {} (so far: {})", e, raw),
+        }
+    }
+    for word in words {
+        assert!(raw.contains(word), "missing {:?} in {}", word, raw);
+    }
+    assert!(raw.contains("data: [DONE]"), "raw: {}", raw);
+
+    let status = wait_for_exit(&mut child).await;
+    assert!(status.success(), "exit status: {}", status);
+
+    let stdout = stdout_of(workdir.path());
+    let lines: Vec<&str> = stdout.lines().filter(|l| !l.trim().is_empty()).collect();
+    assert!(!lines.is_empty());
+    for line in &lines {
+        if let Err(e) = serde_json::from_str::<serde_json::Value>(line) {
+            panic!("not a JSON log line ({}): {}", e, line);
+        }
+    }
+    assert!(stdout.contains("Received SIGTERM"), "stdout: {}", stdout);
+    assert!(stdout.contains("Parallax stopped"), "stdout: {}", stdout);
+}
+
+#[tokio::test]
+async fn test_serve_exits_non_zero_when_the_port_is_taken() {
+    let upstream = MockUpstream::start(vec![]).await;
+    let workdir = match tempfile::tempdir() {
+        Ok(d) => d,
+        Err(e) => panic!("tempdir: {}", e),
+    };
+    let taken = match std::net::TcpListener::bind("127.0.0.1:0") {
+        Ok(l) => l,
+        Err(e) => panic!("bind: {}", e),
+    };
+    let port = match taken.local_addr() {
+        Ok(a) => a.port(),
+        Err(e) => panic!("local addr: {}", e),
+    };
+
+    let mut child = spawn_serve(workdir.path(), &upstream, port);
+    let status = wait_for_exit(&mut child).await;
+    assert!(!status.success(), "exit status: {}", status);
+    drop(taken);
+}
@@ -1,4 +1,4 @@
-use parallax::db::{cleanup_old_data, init_db};
+use parallax::db::{cleanup_old_data, cleanup_older_than_extending, init_db};
 use tempfile::tempdir;
 
 #[tokio::test]
@@ -126,3 +126,46 @@ async fn test_retention_cleanup() {
 
     pool.close().await;
 }
+
+#[tokio::test]
+async fn test_retention_cleanup_keeps_extended_conversations_longer() {
+    let dir: tempfile::TempDir = match tempdir() {
+        Ok(d) => d,
+        Err(e) => panic!("Failed to create temp dir: {:?}", e),
+    };
+    let pool = match init_db(&dir.path().join("test_cleanup_extended.db")).await {
+        Ok(p) => p,
+        Err(e) => panic!("Failed to init DB: {:?}", e),
+    };
+
+    // Three days old: past the 24h limit, within the 7-day limit for conversations with issues
+    for (id, cid) in [("plain", "conv_plain"), ("issue", "conv_issue")] {
+        match sqlx::query("INSERT INTO tool_signatures (id, conversation_id, signature, created_at) VALUES (?, ?, ?, datetime('now', '-3 days'))")
+            .bind(id)
+            .bind(cid)
+            .bind("{}")
+            .execute(&pool)
+            .await {
+                Ok(_) => (),
+                Err(e) => panic!("Failed to insert data: {:?}", e),
+            };
+    }
+
+    let extended = vec!["conv_issue".to_string()];
+    match cleanup_older_than_extending(&pool, 24, &extended, 7 * 24).await {
+        Ok(rows) => assert_eq!(rows, 1),
+        Err(e) => panic!("Cleanup failed: {:?}", e),
+    };
+
+    let remaining: Vec<(String,)> =
+        match sqlx::query_as("SELECT conversation_id FROM tool_signatures")
+            .fetch_all(&pool)
+            .await
+        {
+            Ok(rows) => rows,
+            Err(e) => panic!("Failed to list signatures: {:?}", e),
+        };
+    assert_eq!(remaining, vec![("conv_issue".to_string(),)]);
+
+    pool.close().await;
+}
@@ -0,0 +1,121 @@
+//! Failure and budget alerts through the webhook and command sinks.
+
+mod common;
+
+use common::{chat_request, chunks, wait_for, MockResponse, TestProxy};
+use parallax::notify::{Alert, AlertKind};
+use std::sync::{Arc, Mutex};
+
+/// A local webhook receiver; returns its URL and the alerts it has received.
+async fn webhook_sink() -> (String, Arc<Mutex<Vec<Alert>>>) {
+    let received: Arc<Mutex<Vec<Alert>>> = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let app = parallax::notify::webhook_sink_router(move |body| {
+        match serde_json::from_value::<Alert>(body) {
+            Ok(alert) => match sink.lock() {
+                Ok(mut alerts) => alerts.push(alert),
+                Err(p) => p.into_inner().push(alert),
+            },
+            Err(e) => panic!("webhook body is not an alert: {}", e),
+        }
+    });
+    let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+        Ok(l) => l,
+        Err(e) => panic!("sink bind failed: {}", e),
+    };
+    let addr = match listener.local_addr() {
+        Ok(a) => a,
+        Err(e) => panic!("sink has no local addr: {}", e),
+    };
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    (format!("http://{}/hook", addr), received)
+}
+
+fn alerts_of(received: &Arc<Mutex<Vec<Alert>>>, kind: AlertKind) -> Vec<Alert> {
+    let alerts = match received.lock() {
+        Ok(a) => a.clone(),
+        Err(p) => p.into_inner().clone(),
+    };
+    alerts.into_iter().filter(|a| a.kind == kind).collect()
+}
+
+#[tokio::test]
+async fn test_burst_of_stream_errors_sends_one_webhook() {
+    let (url, received) = webhook_sink().await;
+    let proxy = TestProxy::start_with_args(
+        vec![MockResponse::sse(vec![chunks::provider_error(
+            400,
+            "context length exceeded",
+        )])],
+        &["--notify-webhook", &url, "--notify-debounce-ms", "1500"],
+    )
+    .await;
+    let notifier = parallax::notify::spawn(proxy.state.clone()).await;
+    assert!(notifier.is_some());
+
+    for i in 0..3 {
+        proxy
+            .chat(&format!("burst-{}", i), chat_request("openai/gpt-4o", true))
+            .await;
+    }
+    assert!(alerts_of(&received, AlertKind::StreamError).is_empty());
+
+    let alerts = wait_for("a stream error alert", || async {
+        let alerts = alerts_of(&received, AlertKind::StreamError);
+        (!alerts.is_empty()).then_some(alerts)
+    })
+    .await;
+    match alerts.as_slice() {
+        [alert] => {
+            assert_eq!(alert.source, "parallax");
+            assert_eq!(alert.count, 3);
+            assert_eq!(alert.title, "Stream failed on openai/gpt-4o");
+            assert_eq!(alert.message, "context length exceeded");
+        }
+        other => panic!("expected one aggregated alert, got {:?}", other),
+    }
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_budget_threshold_runs_the_command() {
+    let out = match tempfile::tempdir() {
+        Ok(d) => d,
+        Err(e) => panic!("tempdir: {}", e),
+    };
+    let payload = out.path().join("alert.json");
+    let command = format!(
+        "cat > '{}' && test \"$PARALLAX_ALERT_KIND\" = budget",
+        payload.display()
+    );
+    let proxy = TestProxy::start_with_args(
+        vec![],
+        &[
+            "--notify-command",
+            &command,
+            "--notify-budget-usd",
+            "1.0,0.5",
+            "--notify-debounce-ms",
+            "50",
+        ],
+    )
+    .await;
+    let _notifier = parallax::notify::spawn(proxy.state.clone()).await;
+
+    for cost in [0.3, 0.3] {
+        proxy.state.kernel.record_spend("openai/gpt-4o", cost).await;
+    }
+
+    let alert = wait_for("the alert command's payload", || async {
+        match std::fs::read_to_string(&payload) {
+            Ok(text) => serde_json::from_str::<Alert>(&text).ok(),
+            Err(_) => None,
+        }
+    })
+    .await;
+    assert_eq!(alert.kind, AlertKind::Budget);
+    assert_eq!(alert.title, "Spend passed $0.50");
+    assert_eq!(alert.count, 1);
+}
@@ -0,0 +1,114 @@
+//! Detail events behind the TUI's StreamFocus pane.
+
+mod common;
+
+use common::{chat_request, chunks, MockResponse, TestProxy};
+use parallax::tui::{HopKind, RequestDetail, TuiEvent};
+use serde_json::json;
+
+fn reasoning(text: &str) -> String {
+    json!({
+        "id": "gen-mock",
+        "model": "mock/model",
+        "choices": [{"index": 0, "delta": {"role": "assistant", "reasoning": text}}]
+    })
+    .to_string()
+}
+
+/// Every detail event sent so far, fragments folded the way the dashboard backlog does.
+fn details(rx: &mut tokio::sync::broadcast::Receiver<TuiEvent>) -> Vec<RequestDetail> {
+    let mut folded: Vec<RequestDetail> = Vec::new();
+    while let Ok(event) = rx.try_recv() {
+        if let TuiEvent::RequestDetail { detail, .. } = event {
+            let absorbed = match folded.last_mut() {
+                Some(last) => last.absorb(&detail),
+                None => false,
+            };
+            if !absorbed {
+                folded.push(detail);
+            }
+        }
+    }
+    folded
+}
+
+#[tokio::test]
+async fn test_stream_reports_reasoning_tool_arguments_and_first_token() {
+    let proxy = TestProxy::start(vec![MockResponse::sse(vec![
+        reasoning("Need the "),
+        reasoning("manifest."),
+        chunks::tool_call(0, Some("call_1"), Some("read_file"), "{\"path\": "),
+        chunks::tool_call(0, None, None, "\"Cargo.toml\"}"),
+        chunks::finish("tool_calls"),
+        chunks::usage(120, 30),
+    ])])
+    .await;
+    let mut rx = proxy.state.tx_tui.subscribe();
+
+    let transcript = proxy
+        .chat("detail", chat_request("openai/gpt-4o", true))
+        .await;
+    assert!(transcript.is_done(), "raw: {}", transcript.raw);
+
+    let details = details(&mut rx);
+    assert!(
+        matches!(details.first(), Some(RequestDetail::Reasoning(_))),
+        "{:?}",
+        details
+    );
+    let reasoning: String = details
+        .iter()
+        .filter_map(|d| match d {
+            RequestDetail::Reasoning(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(reasoning, "Need the manifest.");
+    assert!(
+        details.contains(&RequestDetail::ToolCall {
+            index: 0,
+            call_id: Some("call_1".to_string()),
+            name: Some("read_file".to_string()),
+            arguments: "{\"path\": \"Cargo.toml\"}".to_string(),
+        }),
+        "{:?}",
+        details
+    );
+    assert_eq!(
+        details
+            .iter()
+            .filter(|d| matches!(d, RequestDetail::FirstToken { .. }))
+            .count(),
+        1,
+        "{:?}",
+        details
+    );
+}
+
+#[tokio::test]
+async fn test_upstream_retry_is_reported_as_a_hop() {
+    let proxy = TestProxy::start(vec![
+        MockResponse::status(503, json!({"error": {"message": "overloaded"}})),
+        MockResponse::sse(vec![chunks::content("Hello"), chunks::finish("stop")]),
+    ])
+    .await;
+    let mut rx = proxy.state.tx_tui.subscribe();
+
+    let transcript = proxy
+        .chat("detail-retry", chat_request("openai/gpt-4o", false))
+        .await;
+    assert_eq!(transcript.content(), "Hello");
+    assert_eq!(proxy.upstream.requests().len(), 2);
+
+    let hops: Vec<RequestDetail> = details(&mut rx)
+        .into_iter()
+        .filter(|d| matches!(d, RequestDetail::Hop { .. }))
+        .collect();
+    match hops.as_slice() {
+        [RequestDetail::Hop { kind, model, .. }] => {
+            assert_eq!(*kind, HopKind::Retry);
+            assert_eq!(model, "openai/gpt-4o");
+        }
+        other => panic!("expected one retry hop, got {:?}", other),
+    }
+}
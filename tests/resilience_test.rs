@@ -51,6 +51,29 @@ async fn test_retry_policy_eventual_success() {
     assert_eq!(attempts, 3);
 }
 
+#[tokio::test]
+async fn test_retry_policy_respects_budget() {
+    let policy = RetryPolicy::new(5, 1).with_budget(Duration::from_millis(50));
+    let mut attempts = 0;
+
+    let result: parallax::types::Result<i32> = policy
+        .execute_with_retry(|| {
+            attempts += 1;
+            Box::pin(async move {
+                Err(ObservedError::from(ParallaxError::Upstream(
+                    axum::http::StatusCode::TOO_MANY_REQUESTS,
+                    "rate limited".to_string(),
+                ))
+                .with_retry_after(Some(Duration::from_secs(10))))
+            })
+        })
+        .await;
+
+    assert!(result.is_err());
+    // The server asked for 10s, which exceeds the 50ms budget: no retry is attempted.
+    assert_eq!(attempts, 1);
+}
+
 #[tokio::test]
 async fn test_circuit_breaker_trips() {
     let cb = CircuitBreaker::new(2, Duration::from_secs(1));
@@ -76,3 +99,102 @@ async fn test_circuit_breaker_recovery() {
     // Should be half-open and allow one request
     assert!(cb.check().await.is_ok());
 }
+
+#[tokio::test]
+async fn test_circuit_breaker_half_open_admits_single_probe() {
+    let cb = CircuitBreaker::new(1, Duration::from_millis(20));
+
+    cb.record_failure().await;
+    tokio::time::sleep(Duration::from_millis(40)).await;
+
+    assert!(cb.check().await.is_ok());
+    assert!(cb.check().await.is_err());
+
+    // A failed probe re-opens the breaker immediately.
+    cb.record_failure().await;
+    assert_eq!(cb.state().await, CircuitState::Open);
+    assert!(cb.check().await.is_err());
+
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    assert!(cb.check().await.is_ok());
+    cb.record_success().await;
+    assert_eq!(cb.state().await, CircuitState::Closed);
+    assert!(cb.check().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_circuit_registry_isolates_models() {
+    let registry = CircuitBreakerRegistry::new(2, Duration::from_secs(30));
+
+    registry.record_failure("openrouter", "bad/model").await;
+    registry.record_failure("openrouter", "bad/model").await;
+
+    match registry.check("openrouter", "bad/model").await {
+        Err(e) => assert!(matches!(e.inner, ParallaxError::CircuitOpen(_))),
+        Ok(_) => panic!("bad/model should be open"),
+    }
+    assert!(registry.check("openrouter", "good/model").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_circuit_registry_returns_model_probe_when_wide_breaker_rejects() {
+    let registry = CircuitBreakerRegistry::new(1, Duration::from_millis(20));
+
+    registry.record_failure("openrouter", "m").await;
+    tokio::time::sleep(Duration::from_millis(40)).await;
+    registry.record_failure("openrouter", ANY_MODEL).await;
+
+    // The model breaker is half-open, but the wide breaker has just opened.
+    assert!(registry.check("openrouter", "m").await.is_err());
+    let breaker = registry.breaker("openrouter", "m").await;
+    assert_eq!(breaker.state().await, CircuitState::HalfOpen);
+    // The probe the rejected request took was given back.
+    assert!(matches!(breaker.check().await, Ok(Some(_))));
+}
+
+#[tokio::test]
+async fn test_circuit_probe_release_only_frees_the_owners_slot() {
+    let registry = CircuitBreakerRegistry::new(1, Duration::from_millis(20));
+
+    registry.record_failure("openrouter", "m").await;
+    tokio::time::sleep(Duration::from_millis(40)).await;
+
+    let first = match registry.check("openrouter", "m").await {
+        Ok(permit) => permit,
+        Err(e) => panic!("probe should be admitted: {}", e),
+    };
+    assert_eq!(first.probes.len(), 1);
+
+    // An uncounted failure from some other request does not free the slot.
+    registry
+        .record_fault("openrouter", "m", CircuitFault::NotCounted)
+        .await;
+    assert!(registry.check("openrouter", "m").await.is_err());
+
+    registry.release(&first).await;
+    let second = match registry.check("openrouter", "m").await {
+        Ok(permit) => permit,
+        Err(e) => panic!("released probe should be admitted again: {}", e),
+    };
+    // A stale permit cannot free the slot the second request now holds.
+    registry.release(&first).await;
+    assert!(registry.check("openrouter", "m").await.is_err());
+    registry.release(&second).await;
+    assert!(registry.check("openrouter", "m").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_circuit_registry_upstream_wide_breaker_gates_all_models() {
+    let registry = CircuitBreakerRegistry::new(1, Duration::from_secs(30));
+
+    registry.record_failure("openrouter", ANY_MODEL).await;
+
+    assert!(registry.upstream_open("openrouter").await);
+    assert!(registry.check("openrouter", "any/model").await.is_err());
+    assert!(registry.check("other", "any/model").await.is_ok());
+
+    let snapshot = registry.snapshot().await;
+    assert!(snapshot
+        .iter()
+        .any(|c| c.model == ANY_MODEL && c.state == CircuitState::Open));
+}
@@ -0,0 +1,131 @@
+//! The TUI conversation browser over real captured bundles.
+
+mod common;
+
+use common::{chat_request, chunks, MockResponse, TestProxy};
+use crossterm::event::KeyCode;
+use parallax::conversation_browser::{BrowserView, ConversationBrowser};
+use ratatui::{backend::TestBackend, Terminal};
+
+fn tool_reply() -> MockResponse {
+    MockResponse::sse(vec![
+        chunks::tool_call(
+            0,
+            Some("call_1"),
+            Some("read_file"),
+            "{\"path\": \"src/lib.rs\"}",
+        ),
+        chunks::finish("tool_calls"),
+    ])
+}
+
+/// Polls the browser until `done` holds.
+async fn settle(browser: &mut ConversationBrowser, done: impl Fn(&ConversationBrowser) -> bool) {
+    for _ in 0..250 {
+        browser.poll();
+        if done(browser) {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!(
+        "browser never settled: view {:?}, status {:?}",
+        browser.view(),
+        browser.status()
+    );
+}
+
+fn screen(browser: &mut ConversationBrowser) -> String {
+    let mut terminal = match Terminal::new(TestBackend::new(140, 40)) {
+        Ok(t) => t,
+        Err(e) => panic!("terminal: {}", e),
+    };
+    if let Err(e) = terminal.draw(|f| browser.render(f, f.size())) {
+        panic!("draw: {}", e);
+    }
+    terminal
+        .backend()
+        .buffer()
+        .content
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_browser_drills_into_turns_and_exports_and_replays() {
+    let proxy = TestProxy::start(vec![tool_reply(), tool_reply()]).await;
+    let transcript = proxy
+        .chat("browse", chat_request("openai/gpt-4o", true))
+        .await;
+    assert!(transcript.is_done(), "raw: {}", transcript.raw);
+    assert!(proxy.blob("browse", "final").await.is_some());
+
+    let exports = match tempfile::tempdir() {
+        Ok(d) => d,
+        Err(e) => panic!("tempdir: {}", e),
+    };
+    let mut browser = ConversationBrowser::new(
+        proxy.state.bundles(),
+        proxy.base_url.clone(),
+        exports.path().to_path_buf(),
+    );
+    browser.activate();
+    settle(&mut browser, |b| !b.conversations().is_empty()).await;
+    assert!(screen(&mut browser).contains("browse"));
+
+    // Conversation -> turns -> turn detail.
+    assert!(browser.handle_key(KeyCode::Enter));
+    assert_eq!(browser.view(), BrowserView::Turns);
+    assert!(browser.handle_key(KeyCode::Enter));
+    settle(&mut browser, |b| b.view() == BrowserView::Turn).await;
+    let turn = screen(&mut browser);
+    assert!(turn.contains("TOOL CALLS (1)"), "{}", turn);
+    assert!(turn.contains("read_file"), "{}", turn);
+    assert!(turn.contains("ingress_raw"), "{}", turn);
+
+    // The selected stage opens pretty-printed in the viewer.
+    assert!(browser.handle_key(KeyCode::Enter));
+    settle(&mut browser, |b| b.view() == BrowserView::Blob).await;
+    let blob = screen(&mut browser);
+    assert!(blob.contains('{'), "{}", blob);
+    assert!(browser.handle_key(KeyCode::Esc));
+    assert_eq!(browser.view(), BrowserView::Turn);
+
+    assert!(browser.handle_key(KeyCode::Char('e')));
+    settle(&mut browser, |b| {
+        b.status().is_some_and(|s| s.starts_with("Exported"))
+    })
+    .await;
+    let zips: Vec<_> = match std::fs::read_dir(exports.path()) {
+        Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+        Err(e) => panic!("exports: {}", e),
+    };
+    assert_eq!(zips.len(), 1, "{:?}", zips);
+    match std::fs::read(&zips[0]) {
+        Ok(bytes) => assert!(bytes.starts_with(b"PK"), "not a zip"),
+        Err(e) => panic!("read export: {}", e),
+    }
+
+    // Replay re-sends the captured request as its own conversation.
+    assert!(browser.handle_key(KeyCode::Char('p')));
+    settle(&mut browser, |b| {
+        b.status().is_some_and(|s| s.contains("HTTP 200"))
+    })
+    .await;
+    assert_eq!(proxy.upstream.requests().len(), 2);
+    settle(&mut browser, |b| {
+        b.conversations()
+            .iter()
+            .any(|c| c.conversation_id == "browse-replay")
+    })
+    .await;
+
+    assert!(browser.handle_key(KeyCode::Esc));
+    assert!(browser.handle_key(KeyCode::Esc));
+    assert_eq!(browser.view(), BrowserView::Conversations);
+    assert!(
+        !browser.handle_key(KeyCode::Esc),
+        "top level leaves Esc to the TUI"
+    );
+}